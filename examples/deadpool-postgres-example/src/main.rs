@@ -99,7 +99,8 @@ struct GetDepartmentStats {
 #[tokio::main]
 async fn main() -> Result<()> {
     // Enable SQL tracing if needed
-    // std::env::set_var("PARSQL_TRACE", "1");
+    // use parsql::deadpool_postgres::observer::{set_observer, PrintObserver};
+    // set_observer(PrintObserver);
 
     // Create connection pool
     let pool = create_pool().await?;
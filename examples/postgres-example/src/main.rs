@@ -84,7 +84,8 @@ struct GetActiveUsersWithPosts {
 
 fn main() -> Result<()> {
     // Enable SQL tracing if needed
-    // std::env::set_var("PARSQL_TRACE", "1");
+    // use parsql::postgres::observer::{set_observer, PrintObserver};
+    // set_observer(PrintObserver);
 
     // Connect to PostgreSQL
     // Note: Update connection string with your PostgreSQL credentials
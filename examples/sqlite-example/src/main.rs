@@ -87,7 +87,8 @@ struct GetUserWithPostCount {
 
 fn main() -> Result<()> {
     // Enable SQL tracing if needed
-    // std::env::set_var("PARSQL_TRACE", "1");
+    // use parsql::sqlite::observer::{set_observer, PrintObserver};
+    // set_observer(PrintObserver);
 
     // Create an in-memory SQLite database
     let mut conn = Connection::open_in_memory()?;
@@ -74,7 +74,8 @@ struct InsertPost {
 #[tokio::main]
 async fn main() -> Result<()> {
     // Enable SQL tracing if needed
-    // std::env::set_var("PARSQL_TRACE", "1");
+    // use parsql::tokio_postgres::observer::{set_observer, PrintObserver};
+    // set_observer(PrintObserver);
 
     // Connect to PostgreSQL
     let (client, connection) = tokio_postgres::connect(
@@ -0,0 +1,129 @@
+//! SQLite backup command implementations
+//!
+//! Backups use the online backup API of `parsql-sqlite`, so they can be taken while the
+//! application keeps using the database.
+
+use crate::utils::{self, DatabaseType, Progress};
+use crate::BackupCommands;
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use parsql_sqlite::backup::{self, BackupOptions};
+use rusqlite::Connection;
+use std::fs;
+use std::path::Path;
+
+pub fn handle_command(
+    command: BackupCommands,
+    database_url: Option<&str>,
+    verbose: bool,
+) -> Result<()> {
+    match command {
+        BackupCommands::Create {
+            output,
+            database_url: cmd_url,
+            vacuum,
+            pages_per_step,
+            force,
+        } => {
+            let db_path = sqlite_path(cmd_url.as_deref().or(database_url))?;
+            if verbose {
+                utils::print_info(&format!("Database: {}", db_path));
+            }
+
+            if Path::new(&output).exists() {
+                if !force {
+                    anyhow::bail!("{} already exists; pass --force to overwrite it", output);
+                }
+                fs::remove_file(&output).context("Failed to remove the existing backup file")?;
+            }
+
+            let conn = Connection::open(&db_path).context("Failed to open SQLite database")?;
+            if vacuum {
+                let progress = Progress::new("Writing snapshot");
+                backup::vacuum_into(&conn, &output).context("VACUUM INTO failed")?;
+                progress.finish();
+            } else {
+                let options = BackupOptions::new().pages_per_step(pages_per_step);
+                let pages = copy_pages("Backing up", |progress| {
+                    backup::backup_to(&conn, &output, &options, progress)
+                })?;
+                if verbose {
+                    utils::print_info(&format!("Copied {} pages", pages));
+                }
+            }
+
+            utils::print_success(&format!("Backed up {} to {}", db_path, output));
+        }
+
+        BackupCommands::Restore {
+            input,
+            database_url: cmd_url,
+            pages_per_step,
+            force,
+        } => {
+            let db_path = sqlite_path(cmd_url.as_deref().or(database_url))?;
+            if !Path::new(&input).exists() {
+                anyhow::bail!("Backup file {} does not exist", input);
+            }
+            if !force {
+                anyhow::bail!(
+                    "Restoring replaces the contents of {}; pass --force to continue",
+                    db_path
+                );
+            }
+
+            let mut conn = Connection::open(&db_path).context("Failed to open SQLite database")?;
+            let options = BackupOptions::new().pages_per_step(pages_per_step);
+            let pages = copy_pages("Restoring", |progress| {
+                backup::restore_from(&mut conn, &input, &options, progress)
+            })?;
+            if verbose {
+                utils::print_info(&format!("Copied {} pages", pages));
+            }
+
+            utils::print_success(&format!("Restored {} from {}", db_path, input));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the file path of a SQLite database URL.
+fn sqlite_path(url: Option<&str>) -> Result<String> {
+    let url =
+        url.context("Database URL not provided. Use --database-url or set DATABASE_URL env var")?;
+    match utils::parse_database_url(url)? {
+        DatabaseType::SQLite => Ok(url.strip_prefix("sqlite:").unwrap_or(url).to_string()),
+        DatabaseType::PostgreSQL => {
+            anyhow::bail!(
+                "Backups are only supported for SQLite databases; use pg_dump for PostgreSQL"
+            )
+        }
+    }
+}
+
+/// Runs a page-stepped copy with a progress bar and returns the number of pages copied.
+fn copy_pages<F>(message: &str, copy: F) -> Result<i32>
+where
+    F: FnOnce(&mut dyn FnMut(backup::Progress)) -> rusqlite::Result<()>,
+{
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len} pages")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar.set_message(message.to_string());
+
+    let mut pages = 0;
+    copy(&mut |progress| {
+        pages = progress.pagecount;
+        let done = progress.pagecount - progress.remaining;
+        bar.set_length(u64::try_from(progress.pagecount).unwrap_or(0));
+        bar.set_position(u64::try_from(done).unwrap_or(0));
+    })
+    .with_context(|| format!("{} failed", message))?;
+
+    bar.finish_and_clear();
+    Ok(pages)
+}
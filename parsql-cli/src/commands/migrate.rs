@@ -674,11 +674,11 @@ fn calculate_migration_checksum(migration: &FileMigration) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-struct FileMigration {
-    version: i64,
-    name: String,
+pub(crate) struct FileMigration {
+    pub(crate) version: i64,
+    pub(crate) name: String,
     migration_type: String,
-    up_sql: Option<String>,
+    pub(crate) up_sql: Option<String>,
     down_sql: Option<String>,
 }
 
@@ -710,7 +710,7 @@ impl Migration for FileMigration {
     }
 }
 
-fn load_migrations_from_directory(directory: &str) -> Result<Vec<FileMigration>> {
+pub(crate) fn load_migrations_from_directory(directory: &str) -> Result<Vec<FileMigration>> {
     let dir_path = Path::new(directory);
     
     if !dir_path.exists() {
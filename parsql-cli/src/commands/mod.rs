@@ -1,3 +1,5 @@
 //! Command modules
 
-pub mod migrate;
\ No newline at end of file
+pub mod backup;
+pub mod migrate;
+pub mod schema;
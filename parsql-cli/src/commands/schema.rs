@@ -0,0 +1,192 @@
+//! Schema snapshot command implementations
+//!
+//! The snapshot records the tables and columns of a database so that `parsql-macros`
+//! (with its `schema-check` feature) can validate derive attributes at compile time.
+
+use crate::commands::migrate::load_migrations_from_directory;
+use crate::config::Config;
+use crate::utils::{self, DatabaseType, Progress};
+use crate::SchemaCommands;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// A column as recorded in the snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSnapshot {
+    #[serde(rename = "type")]
+    pub sql_type: String,
+    pub nullable: bool,
+}
+
+/// Tables and columns of a database, in the format read by `parsql-macros`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    /// `postgres` or `sqlite`
+    pub dialect: String,
+    pub tables: BTreeMap<String, BTreeMap<String, ColumnSnapshot>>,
+}
+
+pub fn handle_command(
+    command: SchemaCommands,
+    database_url: Option<&str>,
+    config: &Config,
+    verbose: bool,
+) -> Result<()> {
+    match command {
+        SchemaCommands::Snapshot { output, database_url: cmd_url, from_migrations } => {
+            let snapshot = if from_migrations {
+                let progress = Progress::new("Replaying migrations");
+                let snapshot = snapshot_from_migrations(&config.migrations.directory)?;
+                progress.finish();
+                snapshot
+            } else {
+                let url = cmd_url
+                    .as_deref()
+                    .or(database_url)
+                    .context("Database URL not provided. Use --database-url, set DATABASE_URL or pass --from-migrations")?;
+
+                if verbose {
+                    utils::print_info(&format!("Database: {}", url));
+                }
+
+                let progress = Progress::new("Reading schema");
+                let snapshot = match utils::parse_database_url(url)? {
+                    DatabaseType::PostgreSQL => {
+                        snapshot_postgres(url, &config.migrations.table_name)?
+                    }
+                    DatabaseType::SQLite => {
+                        let db_path = url.strip_prefix("sqlite:").unwrap_or(url);
+                        let conn = rusqlite::Connection::open(db_path)
+                            .context("Failed to open SQLite database")?;
+                        snapshot_sqlite(&conn, &config.migrations.table_name)?
+                    }
+                };
+                progress.finish();
+                snapshot
+            };
+
+            write_snapshot(&snapshot, &output)?;
+            utils::print_success(&format!(
+                "Wrote schema snapshot with {} tables to {}",
+                snapshot.tables.len(),
+                output
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the snapshot as pretty-printed JSON
+pub fn write_snapshot(snapshot: &SchemaSnapshot, path: &str) -> Result<()> {
+    let json = serde_json::to_string_pretty(snapshot)?;
+    fs::write(path, json + "\n")
+        .with_context(|| format!("Failed to write schema snapshot to {}", path))
+}
+
+/// Builds a snapshot by applying every `*.up.sql` migration to an in-memory SQLite database
+#[cfg(feature = "sqlite")]
+pub fn snapshot_from_migrations(directory: &str) -> Result<SchemaSnapshot> {
+    let conn = rusqlite::Connection::open_in_memory()
+        .context("Failed to open in-memory SQLite database")?;
+
+    for migration in load_migrations_from_directory(directory)? {
+        if let Some(sql) = &migration.up_sql {
+            conn.execute_batch(sql).with_context(|| {
+                format!(
+                    "Failed to replay migration {} - {}",
+                    migration.version, migration.name
+                )
+            })?;
+        }
+    }
+
+    // No tracking table exists in the replayed database
+    snapshot_sqlite(&conn, "")
+}
+
+/// Reads tables and columns from a SQLite database, skipping the migration tracking table
+#[cfg(feature = "sqlite")]
+pub fn snapshot_sqlite(
+    conn: &rusqlite::Connection,
+    migrations_table: &str,
+) -> Result<SchemaSnapshot> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let table_names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tables = BTreeMap::new();
+    for table in table_names {
+        if table == migrations_table {
+            continue;
+        }
+
+        let mut stmt = conn.prepare("SELECT name, type, \"notnull\", pk FROM pragma_table_info(?)")?;
+        let columns = stmt
+            .query_map([&table], |row| {
+                let name: String = row.get(0)?;
+                let sql_type: String = row.get(1)?;
+                let not_null: bool = row.get(2)?;
+                let pk: i64 = row.get(3)?;
+                Ok((
+                    name,
+                    ColumnSnapshot {
+                        sql_type: sql_type.to_lowercase(),
+                        nullable: !not_null && pk == 0,
+                    },
+                ))
+            })?
+            .collect::<Result<BTreeMap<_, _>, _>>()?;
+
+        tables.insert(table, columns);
+    }
+
+    Ok(SchemaSnapshot {
+        dialect: "sqlite".to_string(),
+        tables,
+    })
+}
+
+/// Reads tables and columns of the current schema from PostgreSQL
+#[cfg(feature = "postgres")]
+fn snapshot_postgres(database_url: &str, migrations_table: &str) -> Result<SchemaSnapshot> {
+    use postgres::{Client, NoTls};
+
+    let mut client = Client::connect(database_url, NoTls)
+        .context("Failed to connect to PostgreSQL")?;
+
+    let rows = client
+        .query(
+            "SELECT table_name::text, column_name::text, udt_name::text, is_nullable = 'YES'
+             FROM information_schema.columns
+             WHERE table_schema = current_schema()
+             ORDER BY table_name, ordinal_position",
+            &[],
+        )
+        .context("Failed to read information_schema.columns")?;
+
+    let mut tables: BTreeMap<String, BTreeMap<String, ColumnSnapshot>> = BTreeMap::new();
+    for row in rows {
+        let table: String = row.get(0);
+        if table == migrations_table {
+            continue;
+        }
+        tables.entry(table).or_default().insert(
+            row.get(1),
+            ColumnSnapshot {
+                sql_type: row.get(2),
+                nullable: row.get(3),
+            },
+        );
+    }
+
+    Ok(SchemaSnapshot {
+        dialect: "postgres".to_string(),
+        tables,
+    })
+}
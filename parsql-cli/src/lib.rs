@@ -89,4 +89,69 @@ pub enum MigrateCommands {
         #[arg(long)]
         applied: bool,
     },
-}
\ No newline at end of file
+}
+
+#[derive(Subcommand)]
+pub enum SchemaCommands {
+    /// Write a schema snapshot for compile-time checks (`schema-check` feature of parsql-macros)
+    #[command(alias = "s")]
+    Snapshot {
+        /// Output file
+        #[arg(short, long, default_value = "parsql-schema.json")]
+        output: String,
+
+        /// Target database URL (overrides global --database-url)
+        #[arg(long)]
+        database_url: Option<String>,
+
+        /// Build the snapshot by replaying migrations into an in-memory SQLite database
+        /// instead of connecting to a database
+        #[arg(long)]
+        from_migrations: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    /// Back up a running SQLite database to a file
+    #[command(alias = "c")]
+    Create {
+        /// Backup file
+        output: String,
+
+        /// Target database URL (overrides global --database-url)
+        #[arg(long)]
+        database_url: Option<String>,
+
+        /// Write a compacted snapshot with `VACUUM INTO` instead of copying pages
+        #[arg(long)]
+        vacuum: bool,
+
+        /// Pages copied per step of an online backup
+        #[arg(long, default_value_t = 100)]
+        pages_per_step: i32,
+
+        /// Overwrite the backup file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Replace the contents of a SQLite database with a backup file
+    #[command(alias = "r")]
+    Restore {
+        /// Backup file
+        input: String,
+
+        /// Target database URL (overrides global --database-url)
+        #[arg(long)]
+        database_url: Option<String>,
+
+        /// Pages copied per step
+        #[arg(long, default_value_t = 100)]
+        pages_per_step: i32,
+
+        /// Confirm that the current contents of the database are replaced
+        #[arg(long)]
+        force: bool,
+    },
+}
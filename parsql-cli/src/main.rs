@@ -8,8 +8,8 @@ mod config;
 mod utils;
 mod ui;
 
-use commands::migrate;
-use parsql_cli::MigrateCommands;
+use commands::{backup, migrate, schema};
+use parsql_cli::{BackupCommands, MigrateCommands, SchemaCommands};
 
 /// Parsql database toolkit CLI
 #[derive(Parser)]
@@ -49,6 +49,18 @@ enum Commands {
         #[command(subcommand)]
         action: MigrateCommands,
     },
+
+    /// Schema snapshot commands
+    Schema {
+        #[command(subcommand)]
+        action: SchemaCommands,
+    },
+
+    /// SQLite backup and restore commands
+    Backup {
+        #[command(subcommand)]
+        action: BackupCommands,
+    },
     
     /// Initialize a new parsql project
     Init {
@@ -104,6 +116,16 @@ fn main() -> Result<()> {
             migrate::handle_command(action, &database_url, &config, cli.verbose)?;
         }
         
+        Commands::Schema { action } => {
+            let database_url = cli.database_url.or(config.database_url.clone());
+            schema::handle_command(action, database_url.as_deref(), &config, cli.verbose)?;
+        }
+
+        Commands::Backup { action } => {
+            let database_url = cli.database_url.or(config.database_url.clone());
+            backup::handle_command(action, database_url.as_deref(), cli.verbose)?;
+        }
+
         Commands::Init { path } => {
             init_project(&path)?;
         }
@@ -0,0 +1,133 @@
+//! Tests for SQLite backup and restore
+
+use anyhow::Result;
+use parsql_cli::{commands::backup, BackupCommands};
+use rusqlite::Connection;
+use tempfile::TempDir;
+
+fn create_database(dir: &TempDir) -> Result<String> {
+    let path = dir.path().join("app.db").to_str().unwrap().to_string();
+    let conn = Connection::open(&path)?;
+    conn.execute_batch(
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL);
+         WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500)
+         INSERT INTO notes (body) SELECT printf('note %d', i) FROM n;",
+    )?;
+    Ok(path)
+}
+
+fn count_notes(path: &str) -> Result<i64> {
+    let conn = Connection::open(path)?;
+    Ok(conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?)
+}
+
+fn create(database: &str, output: &str, vacuum: bool, force: bool) -> Result<()> {
+    backup::handle_command(
+        BackupCommands::Create {
+            output: output.to_string(),
+            database_url: Some(format!("sqlite:{}", database)),
+            vacuum,
+            pages_per_step: 2,
+            force,
+        },
+        None,
+        false,
+    )
+}
+
+#[test]
+fn test_backup_and_restore() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let database = create_database(&temp_dir)?;
+    let output = temp_dir
+        .path()
+        .join("backup.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    create(&database, &output, false, false)?;
+    assert_eq!(count_notes(&output)?, 500);
+
+    Connection::open(&database)?.execute("DELETE FROM notes", [])?;
+    assert_eq!(count_notes(&database)?, 0);
+
+    backup::handle_command(
+        BackupCommands::Restore {
+            input: output,
+            database_url: Some(format!("sqlite:{}", database)),
+            pages_per_step: 2,
+            force: true,
+        },
+        None,
+        false,
+    )?;
+    assert_eq!(count_notes(&database)?, 500);
+
+    Ok(())
+}
+
+#[test]
+fn test_vacuum_snapshot() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let database = create_database(&temp_dir)?;
+    let output = temp_dir
+        .path()
+        .join("snapshot.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    create(&database, &output, true, false)?;
+    assert_eq!(count_notes(&output)?, 500);
+
+    // An existing file is only replaced with --force
+    assert!(create(&database, &output, true, false).is_err());
+    create(&database, &output, true, true)?;
+    assert_eq!(count_notes(&output)?, 500);
+
+    Ok(())
+}
+
+#[test]
+fn test_restore_requires_force() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let database = create_database(&temp_dir)?;
+    let output = temp_dir
+        .path()
+        .join("backup.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+    create(&database, &output, false, false)?;
+
+    let result = backup::handle_command(
+        BackupCommands::Restore {
+            input: output,
+            database_url: Some(format!("sqlite:{}", database)),
+            pages_per_step: 100,
+            force: false,
+        },
+        None,
+        false,
+    );
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_backup_rejects_postgres() {
+    let result = backup::handle_command(
+        BackupCommands::Create {
+            output: "backup.db".to_string(),
+            database_url: Some("postgresql://localhost/app".to_string()),
+            vacuum: false,
+            pages_per_step: 100,
+            force: false,
+        },
+        None,
+        false,
+    );
+    assert!(result.is_err());
+}
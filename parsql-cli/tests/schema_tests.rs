@@ -0,0 +1,84 @@
+//! Tests for schema snapshot generation
+
+use parsql_cli::commands::schema::{snapshot_from_migrations, SchemaSnapshot};
+use parsql_cli::{commands::schema, config::Config, SchemaCommands};
+use std::fs;
+use anyhow::Result;
+use tempfile::TempDir;
+
+fn write_migrations(dir: &TempDir) -> Result<String> {
+    let migrations = dir.path().join("migrations");
+    fs::create_dir_all(&migrations)?;
+    fs::write(
+        migrations.join("20240101000000_create_users.up.sql"),
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, bio TEXT);",
+    )?;
+    fs::write(
+        migrations.join("20240102000000_add_posts.up.sql"),
+        "CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id BIGINT NOT NULL REFERENCES users(id));",
+    )?;
+    Ok(migrations.to_str().unwrap().to_string())
+}
+
+#[test]
+fn test_snapshot_from_migrations() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let directory = write_migrations(&temp_dir)?;
+
+    let snapshot = snapshot_from_migrations(&directory)?;
+
+    assert_eq!(snapshot.dialect, "sqlite");
+    assert_eq!(snapshot.tables.len(), 2);
+
+    let users = &snapshot.tables["users"];
+    assert_eq!(users["id"].sql_type, "integer");
+    assert!(!users["id"].nullable);
+    assert!(!users["email"].nullable);
+    assert!(users["bio"].nullable);
+    assert_eq!(snapshot.tables["posts"]["user_id"].sql_type, "bigint");
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_command_writes_json() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config = Config::default_with_directory(&write_migrations(&temp_dir)?);
+    let output = temp_dir.path().join("parsql-schema.json");
+
+    schema::handle_command(
+        SchemaCommands::Snapshot {
+            output: output.to_str().unwrap().to_string(),
+            database_url: None,
+            from_migrations: true,
+        },
+        None,
+        &config,
+        false,
+    )?;
+
+    let snapshot: SchemaSnapshot = serde_json::from_str(&fs::read_to_string(&output)?)?;
+    assert!(snapshot.tables.contains_key("posts"));
+
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&output)?)?;
+    assert_eq!(json["tables"]["users"]["email"]["type"], "text");
+    assert_eq!(json["tables"]["users"]["email"]["nullable"], false);
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_requires_database_url() {
+    let config = Config::default();
+    let result = schema::handle_command(
+        SchemaCommands::Snapshot {
+            output: "unused.json".to_string(),
+            database_url: None,
+            from_migrations: false,
+        },
+        None,
+        &config,
+        false,
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,200 @@
+//! Query composition.
+//!
+//! A [`Fragment`] is a piece of SQL together with the parameters bound to its placeholders.
+//! Placeholders are kept as references into the parameter list, so fragments can be
+//! combined freely and are renumbered when the final SQL is rendered. The backends
+//! re-export [`Fragment`] with their [`Dialect`] filled in.
+
+use crate::dialect::Dialect;
+
+/// A SQL fragment with its bound parameters.
+pub struct Fragment<'a, D: Dialect> {
+    /// Common table expressions rendered before the body
+    ctes: Vec<(String, Fragment<'a, D>)>,
+    /// SQL text between placeholders; always one more than `slots`
+    parts: Vec<String>,
+    /// Index into `params` for every placeholder, in order of appearance
+    slots: Vec<usize>,
+    params: Vec<&'a D::Param<'a>>,
+}
+
+impl<'a, D: Dialect> Fragment<'a, D> {
+    /// Creates a fragment from SQL and its parameters.
+    ///
+    /// Placeholders are written as `$1`, `$2`, ... and refer to `params` by position. Dialects
+    /// with [`Dialect::QUESTION_MARKS`] also accept `?1` and `?`; a bare `?` takes the number
+    /// after the largest one used so far.
+    /// Placeholders inside string literals are left untouched.
+    ///
+    /// # Panics
+    /// Panics if a placeholder refers to a parameter that was not given.
+    pub fn new(sql: impl AsRef<str>, params: Vec<&'a D::Param<'a>>) -> Self {
+        let mut parts = vec![String::new()];
+        let mut slots = Vec::new();
+        let mut in_string = false;
+        let mut last_index = 0;
+        let mut chars = sql.as_ref().chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                in_string = !in_string;
+            }
+            let question_mark = c == '?' && D::QUESTION_MARKS;
+            if in_string || (c != '$' && !question_mark) {
+                parts.last_mut().unwrap().push(c);
+                continue;
+            }
+
+            let mut digits = String::new();
+            while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(*d);
+                chars.next();
+            }
+
+            let index = match digits.parse::<usize>() {
+                Ok(number) if number > 0 => number,
+                _ if question_mark && digits.is_empty() => last_index + 1,
+                _ => {
+                    parts.last_mut().unwrap().push(c);
+                    parts.last_mut().unwrap().push_str(&digits);
+                    continue;
+                }
+            };
+            assert!(
+                index <= params.len(),
+                "Placeholder {}{} has no parameter; {} parameters were given",
+                c,
+                index,
+                params.len()
+            );
+
+            last_index = last_index.max(index);
+            slots.push(index - 1);
+            parts.push(String::new());
+        }
+
+        Fragment {
+            ctes: Vec::new(),
+            parts,
+            slots,
+            params,
+        }
+    }
+
+    /// Creates a fragment without parameters.
+    pub fn raw(sql: impl Into<String>) -> Self {
+        Fragment {
+            ctes: Vec::new(),
+            parts: vec![sql.into()],
+            slots: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Appends SQL text.
+    pub fn push(mut self, sql: &str) -> Self {
+        self.parts.last_mut().unwrap().push_str(sql);
+        self
+    }
+
+    /// Appends another fragment, merging its parameters and CTEs.
+    pub fn append(mut self, other: Fragment<'a, D>) -> Self {
+        let offset = self.params.len();
+        let mut parts = other.parts.into_iter();
+
+        self.parts
+            .last_mut()
+            .unwrap()
+            .push_str(&parts.next().unwrap_or_default());
+        for (slot, part) in other.slots.into_iter().zip(parts) {
+            self.slots.push(slot + offset);
+            self.parts.push(part);
+        }
+
+        self.params.extend(other.params);
+        self.ctes.extend(other.ctes);
+        self
+    }
+
+    /// Adds a common table expression: `WITH name AS (cte) <self>`.
+    pub fn with_cte(mut self, name: &str, cte: Fragment<'a, D>) -> Self {
+        let Fragment {
+            ctes,
+            parts,
+            slots,
+            params,
+        } = cte;
+        // CTEs of the CTE itself must be declared first
+        self.ctes.splice(0..0, ctes);
+        self.ctes.push((
+            name.to_string(),
+            Fragment {
+                ctes: Vec::new(),
+                parts,
+                slots,
+                params,
+            },
+        ));
+        self
+    }
+
+    /// Combines two queries: `(<self>) UNION ALL (<other>)`.
+    ///
+    /// Both members are parenthesized, so their own `ORDER BY` and `LIMIT` clauses stay local.
+    /// Dialects without [`Dialect::PARENTHESIZED_UNION`] wrap each member in a subquery
+    /// instead: `SELECT * FROM (<self>) UNION ALL SELECT * FROM (<other>)`.
+    pub fn union_all(self, other: Fragment<'a, D>) -> Self {
+        let (open, separator) = if D::PARENTHESIZED_UNION {
+            ("(", ") UNION ALL (")
+        } else {
+            ("SELECT * FROM (", ") UNION ALL SELECT * FROM (")
+        };
+        Fragment::raw(open)
+            .append(self)
+            .push(separator)
+            .append(other)
+            .push(")")
+    }
+
+    /// Keeps the rows of this query whose `column` is returned by `subquery`:
+    /// `SELECT * FROM (<self>) AS ... WHERE column IN (<subquery>)`.
+    pub fn in_subquery(self, column: &str, subquery: Fragment<'a, D>) -> Self {
+        Fragment::raw("SELECT * FROM (")
+            .append(self)
+            .push(&format!(") AS parsql_subquery WHERE {} IN (", column))
+            .append(subquery)
+            .push(")")
+    }
+
+    /// Renders the SQL with the dialect's numbered placeholders and returns the parameters
+    /// in order.
+    pub fn build(&self) -> (String, Vec<&'a D::Param<'a>>) {
+        let mut sql = String::new();
+        let mut params = Vec::new();
+        self.render(&mut sql, &mut params);
+        (sql, params)
+    }
+
+    fn render(&self, sql: &mut String, params: &mut Vec<&'a D::Param<'a>>) {
+        if !self.ctes.is_empty() {
+            sql.push_str("WITH ");
+            for (i, (name, cte)) in self.ctes.iter().enumerate() {
+                if i > 0 {
+                    sql.push_str(", ");
+                }
+                sql.push_str(&format!("{} AS (", name));
+                cte.render(sql, params);
+                sql.push(')');
+            }
+            sql.push(' ');
+        }
+
+        let offset = params.len();
+        sql.push_str(&self.parts[0]);
+        for (slot, part) in self.slots.iter().zip(&self.parts[1..]) {
+            sql.push_str(&D::placeholder(slot + offset + 1));
+            sql.push_str(part);
+        }
+        params.extend(self.params.iter().copied());
+    }
+}
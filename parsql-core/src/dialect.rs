@@ -0,0 +1,37 @@
+//! SQL dialects of the backends.
+//!
+//! [`Expr`](crate::expr::Expr), [`Fragment`](crate::compose::Fragment) and
+//! [`TenantContext`](crate::tenant::TenantContext) are written once against [`Dialect`].
+//! Every backend implements it for its driver's parameter type and re-exports these types
+//! with its dialect filled in, e.g. `parsql_postgres::Expr`.
+
+/// How a backend binds parameters and writes placeholders.
+pub trait Dialect: Sized + 'static {
+    /// A parameter as passed to the driver, e.g. `dyn ToSql + Sync + 'a`.
+    type Param<'a>: ?Sized + 'a;
+
+    /// A parameter value owned by an expression or a tenant context.
+    type Value: ?Sized;
+
+    /// Whether `Fragment::new` accepts `?N` and bare `?` placeholders besides `$N`.
+    const QUESTION_MARKS: bool = false;
+
+    /// Whether the members of a `UNION ALL` may be parenthesized; otherwise they are
+    /// wrapped in subqueries.
+    const PARENTHESIZED_UNION: bool = true;
+
+    /// Returns the placeholder of the `n`th parameter, counting from 1.
+    fn placeholder(n: usize) -> String;
+
+    /// Borrows an owned value as a driver parameter.
+    fn param(value: &Self::Value) -> &Self::Param<'_>;
+}
+
+/// Conversion of a Rust value into a parameter value of the dialect.
+pub trait Bind<V>: Dialect {
+    /// Boxes the value as a parameter.
+    fn bind(value: V) -> Box<Self::Value>;
+}
+
+/// Dialects with a case-insensitive `ILIKE` operator.
+pub trait ILike: Dialect {}
@@ -0,0 +1,318 @@
+//! Typed WHERE expressions.
+//!
+//! Column constants are generated by the `Columns` derive macro (`users::email`,
+//! `users::state`, ...). Comparing a column with a value produces an [`Expr`] that renders
+//! to a SQL fragment with the placeholders of the backend's [`Dialect`] and keeps the values
+//! as bound parameters. The backends re-export [`Column`] and [`Expr`] with their dialect
+//! filled in.
+
+use std::marker::PhantomData;
+use std::ops::Not;
+use std::sync::Arc;
+
+use crate::dialect::{Bind, Dialect, ILike};
+use crate::tenant::MissingTenant;
+use crate::traits::Columns;
+
+/// Conversion of a value into the type of a column.
+///
+/// Besides the column type itself, string slices are accepted for `String` columns and
+/// plain values for `Option<T>` columns. Unlike `Into`, integer literals keep inferring
+/// the column's integer type.
+pub trait IntoValue<T> {
+    /// Converts the value into the column type.
+    fn into_value(self) -> T;
+}
+
+impl<T> IntoValue<T> for T {
+    fn into_value(self) -> T {
+        self
+    }
+}
+
+impl<T> IntoValue<Option<T>> for T {
+    fn into_value(self) -> Option<T> {
+        Some(self)
+    }
+}
+
+impl IntoValue<String> for &str {
+    fn into_value(self) -> String {
+        self.to_string()
+    }
+}
+
+impl IntoValue<String> for &String {
+    fn into_value(self) -> String {
+        self.clone()
+    }
+}
+
+impl IntoValue<Option<String>> for &str {
+    fn into_value(self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+/// A column of a table whose values have the Rust type `T`.
+pub struct Column<T, D> {
+    name: &'static str,
+    _type: PhantomData<fn() -> (T, D)>,
+}
+
+impl<T, D> Clone for Column<T, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, D> Copy for Column<T, D> {}
+
+impl<T, D: Dialect> Column<T, D> {
+    /// Creates a column with the given name.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _type: PhantomData,
+        }
+    }
+
+    /// Returns the column name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// `column IS NULL`
+    pub fn is_null(self) -> Expr<D> {
+        Expr::Null {
+            column: self.name,
+            negated: false,
+        }
+    }
+
+    /// `column IS NOT NULL`
+    pub fn is_not_null(self) -> Expr<D> {
+        Expr::Null {
+            column: self.name,
+            negated: true,
+        }
+    }
+
+    fn compare<V>(self, op: &'static str, value: V) -> Expr<D>
+    where
+        D: Bind<V>,
+    {
+        Expr::Compare {
+            column: self.name,
+            op,
+            value: D::bind(value),
+        }
+    }
+}
+
+impl<T, D: Bind<String>> Column<T, D> {
+    /// `column LIKE pattern`
+    pub fn like(self, pattern: impl Into<String>) -> Expr<D> {
+        self.compare("LIKE", pattern.into())
+    }
+
+    /// `column NOT LIKE pattern`
+    pub fn not_like(self, pattern: impl Into<String>) -> Expr<D> {
+        self.compare("NOT LIKE", pattern.into())
+    }
+}
+
+impl<T, D: ILike + Bind<String>> Column<T, D> {
+    /// `column ILIKE pattern` (case-insensitive)
+    pub fn ilike(self, pattern: impl Into<String>) -> Expr<D> {
+        self.compare("ILIKE", pattern.into())
+    }
+}
+
+impl<T, D: Bind<T>> Column<T, D> {
+    /// `column = value`
+    pub fn eq(self, value: impl IntoValue<T>) -> Expr<D> {
+        self.compare("=", value.into_value())
+    }
+
+    /// `column <> value`
+    pub fn ne(self, value: impl IntoValue<T>) -> Expr<D> {
+        self.compare("<>", value.into_value())
+    }
+
+    /// `column > value`
+    pub fn gt(self, value: impl IntoValue<T>) -> Expr<D> {
+        self.compare(">", value.into_value())
+    }
+
+    /// `column >= value`
+    pub fn ge(self, value: impl IntoValue<T>) -> Expr<D> {
+        self.compare(">=", value.into_value())
+    }
+
+    /// `column < value`
+    pub fn lt(self, value: impl IntoValue<T>) -> Expr<D> {
+        self.compare("<", value.into_value())
+    }
+
+    /// `column <= value`
+    pub fn le(self, value: impl IntoValue<T>) -> Expr<D> {
+        self.compare("<=", value.into_value())
+    }
+
+    /// `column IN (values...)`; an empty list matches no rows.
+    pub fn in_list<V: IntoValue<T>>(self, values: impl IntoIterator<Item = V>) -> Expr<D> {
+        Expr::In {
+            column: self.name,
+            values: values
+                .into_iter()
+                .map(|value| D::bind(value.into_value()))
+                .collect(),
+        }
+    }
+}
+
+/// A WHERE condition with its bound parameters.
+pub enum Expr<D: Dialect> {
+    /// `column <op> value`
+    Compare {
+        column: &'static str,
+        op: &'static str,
+        value: Box<D::Value>,
+    },
+    /// `column IN (values...)`
+    In {
+        column: &'static str,
+        values: Vec<Box<D::Value>>,
+    },
+    /// `column IS [NOT] NULL`
+    Null { column: &'static str, negated: bool },
+    /// `column = tenant`, added by [`TenantContext::filter`]
+    ///
+    /// [`TenantContext::filter`]: crate::tenant::TenantContext::filter
+    Tenant {
+        column: &'static str,
+        tenant: Arc<D::Value>,
+    },
+    /// `(left AND right)`
+    And(Box<Expr<D>>, Box<Expr<D>>),
+    /// `(left OR right)`
+    Or(Box<Expr<D>>, Box<Expr<D>>),
+    /// `NOT (expr)`
+    Not(Box<Expr<D>>),
+}
+
+impl<D: Dialect> Expr<D> {
+    /// Combines two conditions with `AND`.
+    pub fn and(self, other: Expr<D>) -> Expr<D> {
+        Expr::And(Box::new(self), Box::new(other))
+    }
+
+    /// Combines two conditions with `OR`.
+    pub fn or(self, other: Expr<D>) -> Expr<D> {
+        Expr::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Renders the condition to a SQL fragment and the parameters bound to its placeholders.
+    pub fn to_sql(&self) -> (String, Vec<&D::Param<'_>>) {
+        let mut sql = String::new();
+        let mut params = Vec::new();
+        self.write(&mut sql, &mut params);
+        (sql, params)
+    }
+
+    /// Renders the query of `fetch_all_where`: the columns of `R` matching the condition,
+    /// leaving out soft-deleted rows.
+    ///
+    /// If `R` is `#[tenant_scoped]`, the condition must be restricted with
+    /// [`TenantContext::filter`]; a comparison on the tenant column written by hand does not
+    /// count, and neither does a tenant on one side of an `OR`.
+    ///
+    /// [`TenantContext::filter`]: crate::tenant::TenantContext::filter
+    pub fn select<R: Columns>(&self) -> Result<(String, Vec<&D::Param<'_>>), MissingTenant> {
+        if let Some(column) = R::tenant_column() {
+            if !self.has_tenant(column) {
+                return Err(MissingTenant {
+                    entity: std::any::type_name::<R>(),
+                });
+            }
+        }
+
+        let (condition, params) = self.to_sql();
+        let mut filters = vec![condition];
+        if let Some(column) = R::soft_delete_column() {
+            filters.push(format!("{}.{} IS NULL", R::table(), column));
+        }
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}",
+            R::columns().join(", "),
+            R::table(),
+            filters.join(" AND ")
+        );
+        Ok((sql, params))
+    }
+
+    /// Whether the condition only matches rows of the tenant bound to `column`: the tenant is
+    /// the whole condition or one side of an `AND`.
+    fn has_tenant(&self, column: &str) -> bool {
+        match self {
+            Expr::Tenant { column: name, .. } => *name == column,
+            Expr::And(left, right) => left.has_tenant(column) || right.has_tenant(column),
+            _ => false,
+        }
+    }
+
+    fn write<'a>(&'a self, sql: &mut String, params: &mut Vec<&'a D::Param<'a>>) {
+        match self {
+            Expr::Compare { column, op, value } => {
+                params.push(D::param(value));
+                sql.push_str(&format!("{} {} {}", column, op, D::placeholder(params.len())));
+            }
+            Expr::In { column, values } => {
+                if values.is_empty() {
+                    sql.push_str("1 = 0");
+                    return;
+                }
+                let placeholders = values
+                    .iter()
+                    .map(|value| {
+                        params.push(D::param(value));
+                        D::placeholder(params.len())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                sql.push_str(&format!("{} IN ({})", column, placeholders));
+            }
+            Expr::Null { column, negated } => {
+                let null = if *negated { "IS NOT NULL" } else { "IS NULL" };
+                sql.push_str(&format!("{} {}", column, null));
+            }
+            Expr::Tenant { column, tenant } => {
+                params.push(D::param(tenant));
+                sql.push_str(&format!("{} = {}", column, D::placeholder(params.len())));
+            }
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                let keyword = if matches!(self, Expr::And(..)) { "AND" } else { "OR" };
+                sql.push('(');
+                left.write(sql, params);
+                sql.push_str(&format!(" {} ", keyword));
+                right.write(sql, params);
+                sql.push(')');
+            }
+            Expr::Not(inner) => {
+                sql.push_str("NOT (");
+                inner.write(sql, params);
+                sql.push(')');
+            }
+        }
+    }
+}
+
+impl<D: Dialect> Not for Expr<D> {
+    type Output = Expr<D>;
+
+    /// `NOT (expr)`
+    fn not(self) -> Expr<D> {
+        Expr::Not(Box::new(self))
+    }
+}
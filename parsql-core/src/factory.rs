@@ -0,0 +1,54 @@
+//! Seed data factories shared by the database crates.
+//!
+//! `#[derive(Factory)]` implements [`Factory`], which builds the entities; every backend adds
+//! `create` and `create_many`, which insert them, in its own `FactoryExt` trait.
+
+/// A factory of `Insertable` entities; implemented by `#[derive(Factory)]`.
+pub trait Factory {
+    /// The entity built by the factory
+    type Entity;
+
+    /// Returns the next sequence number of the factory, starting at 1.
+    fn next_sequence() -> u64;
+
+    /// Builds the entity with sequence number `n`.
+    fn build_with(&self, n: u64) -> Self::Entity;
+
+    /// Builds the next entity.
+    fn build(&self) -> Self::Entity {
+        self.build_with(Self::next_sequence())
+    }
+
+    /// Builds `count` entities.
+    fn build_many(&self, count: usize) -> Vec<Self::Entity> {
+        (0..count).map(|_| self.build()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    struct Counter;
+
+    impl Factory for Counter {
+        type Entity = u64;
+
+        fn next_sequence() -> u64 {
+            static SEQUENCE: AtomicU64 = AtomicU64::new(1);
+            SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        }
+
+        fn build_with(&self, n: u64) -> u64 {
+            n * 10
+        }
+    }
+
+    #[test]
+    fn test_build_many_takes_consecutive_sequence_numbers() {
+        assert_eq!(Counter.build(), 10);
+        assert_eq!(Counter.build_many(3), vec![20, 30, 40]);
+        assert_eq!(Counter.build_with(7), 70);
+    }
+}
@@ -0,0 +1,13 @@
+//! Backend-agnostic types shared by the parsql database crates.
+
+pub mod compose;
+pub mod dialect;
+pub mod expr;
+pub mod factory;
+pub mod observer;
+pub mod retry;
+pub mod rls;
+pub mod search;
+pub mod stats;
+pub mod tenant;
+pub mod traits;
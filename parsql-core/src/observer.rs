@@ -0,0 +1,341 @@
+//! Query instrumentation shared by the backends.
+//!
+//! Every CRUD function reports the statement it executed to the [`QueryObserver`]
+//! registered with [`set_observer`]: the SQL, the number of parameters, optionally their
+//! values, the duration, the number of rows and the error, if any. The backends re-export
+//! this module's public items from their `observer` module; one observer receives the
+//! statements of all of them, told apart by [`QueryEvent::system`].
+//!
+//! Register [`PrintObserver`] to print every statement to stdout. With the `tracing`
+//! feature, [`TracingObserver`] emits a span per statement.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// The kind of statement executed by a CRUD function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Insert,
+    Update,
+    Delete,
+    Select,
+}
+
+impl QueryKind {
+    /// Returns the lowercase name of the kind, e.g. `"select"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryKind::Insert => "insert",
+            QueryKind::Update => "update",
+            QueryKind::Delete => "delete",
+            QueryKind::Select => "select",
+        }
+    }
+}
+
+/// The parameter values an observer receives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamCapture {
+    /// No values, only the parameter count.
+    #[default]
+    None,
+    /// Numbers, booleans and NULLs; every other value is replaced with `<redacted>`.
+    Redacted,
+    /// All values.
+    All,
+}
+
+/// A statement executed by a CRUD function.
+#[derive(Debug)]
+pub struct QueryEvent<'a> {
+    /// The database system, e.g. `"postgresql"` or `"sqlite"`.
+    pub system: &'static str,
+    /// The kind of statement.
+    pub kind: QueryKind,
+    /// The type name of the model or row type the statement was generated for.
+    pub entity: &'static str,
+    /// The SQL text.
+    pub sql: &'a str,
+    /// The number of bound parameters.
+    pub param_count: usize,
+    /// The parameter values, as requested by [`QueryObserver::param_capture`].
+    pub params: Option<&'a [String]>,
+    /// The time spent executing the statement and reading its rows.
+    pub duration: Duration,
+    /// The number of affected or returned rows, when the statement succeeded.
+    pub rows: Option<u64>,
+    /// The error, when the statement failed.
+    pub error: Option<&'a (dyn StdError + 'static)>,
+}
+
+/// Receives an event for every statement executed by the CRUD functions.
+pub trait QueryObserver: Send + Sync {
+    /// Called after a statement has finished.
+    fn on_query(&self, event: &QueryEvent<'_>);
+
+    /// The parameter values included in the events. Defaults to [`ParamCapture::None`].
+    fn param_capture(&self) -> ParamCapture {
+        ParamCapture::None
+    }
+}
+
+static OBSERVER: RwLock<Option<Arc<dyn QueryObserver>>> = RwLock::new(None);
+
+/// Registers the observer for all backends, pools and connections, replacing the previous
+/// one.
+pub fn set_observer<O: QueryObserver + 'static>(observer: O) {
+    if let Ok(mut current) = OBSERVER.write() {
+        *current = Some(Arc::new(observer));
+    }
+}
+
+/// Removes the registered observer.
+pub fn clear_observer() {
+    if let Ok(mut current) = OBSERVER.write() {
+        *current = None;
+    }
+}
+
+fn current_observer() -> Option<Arc<dyn QueryObserver>> {
+    OBSERVER.read().ok().and_then(|current| current.clone())
+}
+
+/// Prints every statement to stdout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintObserver;
+
+impl QueryObserver for PrintObserver {
+    fn on_query(&self, event: &QueryEvent<'_>) {
+        println!("[PARSQL-{}] Execute SQL: {}", event.system.to_uppercase(), event.sql);
+    }
+}
+
+/// Emits a span named after the query kind for every statement, with the SQL, entity,
+/// parameters, duration and row count as fields.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingObserver {
+    params: ParamCapture,
+}
+
+#[cfg(feature = "tracing")]
+impl TracingObserver {
+    /// Creates an observer that records no parameter values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the given parameter values in the spans.
+    pub fn with_params(mut self, params: ParamCapture) -> Self {
+        self.params = params;
+        self
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl QueryObserver for TracingObserver {
+    fn on_query(&self, event: &QueryEvent<'_>) {
+        macro_rules! query_span {
+            ($name:literal) => {
+                tracing::info_span!(
+                    $name,
+                    db.system = event.system,
+                    db.statement = event.sql,
+                    parsql.entity = event.entity,
+                    parsql.param_count = event.param_count,
+                    parsql.params = ?event.params,
+                    parsql.duration = ?event.duration,
+                    parsql.rows = event.rows,
+                )
+            };
+        }
+
+        let span = match event.kind {
+            QueryKind::Insert => query_span!("insert"),
+            QueryKind::Update => query_span!("update"),
+            QueryKind::Delete => query_span!("delete"),
+            QueryKind::Select => query_span!("select"),
+        };
+        let _entered = span.enter();
+        match event.error {
+            Some(error) => tracing::error!(error = %error, "query failed"),
+            None => tracing::debug!("query finished"),
+        }
+    }
+
+    fn param_capture(&self) -> ParamCapture {
+        self.params
+    }
+}
+
+/// A statement being executed; reports it to the registered observer when finished.
+///
+/// Used by the backends' CRUD functions.
+#[doc(hidden)]
+pub struct Observation<'a> {
+    observer: Option<Arc<dyn QueryObserver>>,
+    system: &'static str,
+    kind: QueryKind,
+    entity: &'static str,
+    sql: &'a str,
+    param_count: usize,
+    params: Option<Vec<String>>,
+    start: Instant,
+}
+
+impl<'a> Observation<'a> {
+    /// Starts observing a statement generated for the type named `entity`. `format` renders
+    /// a parameter value, redacted if its second argument is `true`; it is only called when
+    /// the observer captures parameters.
+    pub fn start<P>(
+        system: &'static str,
+        kind: QueryKind,
+        entity: &'static str,
+        sql: &'a str,
+        params: &[P],
+        format: impl Fn(&P, bool) -> String,
+    ) -> Self {
+        let observer = current_observer();
+        let capture = observer
+            .as_ref()
+            .map_or(ParamCapture::None, |observer| observer.param_capture());
+        let values = match capture {
+            ParamCapture::None => None,
+            ParamCapture::Redacted => Some(params.iter().map(|p| format(p, true)).collect()),
+            ParamCapture::All => Some(params.iter().map(|p| format(p, false)).collect()),
+        };
+
+        Observation {
+            observer,
+            system,
+            kind,
+            entity,
+            sql,
+            param_count: params.len(),
+            params: values,
+            start: Instant::now(),
+        }
+    }
+
+    /// Reports the result of the statement and passes it through. `rows` returns the number
+    /// of affected or returned rows of a successful result.
+    pub fn finish<T, E>(self, result: Result<T, E>, rows: impl FnOnce(&T) -> u64) -> Result<T, E>
+    where
+        E: StdError + 'static,
+    {
+        if let Some(observer) = &self.observer {
+            let event = QueryEvent {
+                system: self.system,
+                kind: self.kind,
+                entity: self.entity,
+                sql: self.sql,
+                param_count: self.param_count,
+                params: self.params.as_deref(),
+                duration: self.start.elapsed(),
+                rows: result.as_ref().ok().map(rows),
+                error: result
+                    .as_ref()
+                    .err()
+                    .map(|error| error as &(dyn StdError + 'static)),
+            };
+            observer.on_query(&event);
+        }
+        result
+    }
+}
+
+/// Formats a parameter with `{:?}`; when redacting, only numbers, booleans and NULL,
+/// optionally in `Some`, are kept and every other value becomes `<redacted>`.
+#[doc(hidden)]
+pub fn format_debug<P: fmt::Debug + ?Sized>(param: &P, redact: bool) -> String {
+    let value = format!("{:?}", param);
+    if redact && !is_plain_scalar(&value) {
+        "<redacted>".to_string()
+    } else {
+        value
+    }
+}
+
+/// Whether a debug-formatted parameter is a number, a boolean or NULL, optionally in `Some`.
+fn is_plain_scalar(value: &str) -> bool {
+    let value = value
+        .strip_prefix("Some(")
+        .and_then(|inner| inner.strip_suffix(')'))
+        .unwrap_or(value);
+    matches!(value, "None" | "true" | "false") || value.parse::<f64>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted_params_keep_only_numbers_booleans_and_null() {
+        assert_eq!(format_debug(&42i64, true), "42");
+        assert_eq!(format_debug(&1.5f64, true), "1.5");
+        assert_eq!(format_debug(&true, true), "true");
+        assert_eq!(format_debug(&Some(7i32), true), "Some(7)");
+        assert_eq!(format_debug(&Option::<i32>::None, true), "None");
+        assert_eq!(format_debug(&"secret", true), "<redacted>");
+        assert_eq!(format_debug(&"123", true), "<redacted>");
+        assert_eq!(format_debug(&vec![1u8, 2], true), "<redacted>");
+        assert_eq!(format_debug(&Some("secret"), true), "<redacted>");
+    }
+
+    #[test]
+    fn test_all_params_are_formatted() {
+        assert_eq!(format_debug(&"secret", false), "\"secret\"");
+    }
+
+    /// Records the events, with their rows and error text.
+    struct Recorder(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl QueryObserver for Recorder {
+        fn on_query(&self, event: &QueryEvent<'_>) {
+            let error = event.error.map(|error| error.to_string());
+            self.0.lock().unwrap().push(format!(
+                "{} {} {} {:?} {:?} {:?}",
+                event.system,
+                event.kind.as_str(),
+                event.sql,
+                event.params,
+                event.rows,
+                error
+            ));
+        }
+
+        fn param_capture(&self) -> ParamCapture {
+            ParamCapture::Redacted
+        }
+    }
+
+    #[test]
+    fn test_registered_observer_receives_finished_statements() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        set_observer(Recorder(Arc::clone(&events)));
+
+        let params: [&dyn fmt::Debug; 2] = [&7, &"secret"];
+        let format = |param: &&dyn fmt::Debug, redact| format_debug(*param, redact);
+        let observation =
+            Observation::start("sqlite", QueryKind::Select, "User", "q1", &params, format);
+        let _ = observation.finish(Ok::<_, fmt::Error>(vec![1, 2]), |rows| rows.len() as u64);
+        let observation =
+            Observation::start("sqlite", QueryKind::Delete, "User", "q2", &params, format);
+        let _ = observation.finish(Err::<u64, _>(fmt::Error), |rows| *rows);
+        clear_observer();
+        let observation =
+            Observation::start("sqlite", QueryKind::Select, "User", "q3", &params, format);
+        let _ = observation.finish(Ok::<_, fmt::Error>(0), |rows| *rows);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "sqlite select q1 Some([\"7\", \"<redacted>\"]) Some(2) None",
+                "sqlite delete q2 Some([\"7\", \"<redacted>\"]) None \
+                 Some(\"an error occurred when formatting an argument\")",
+            ]
+        );
+    }
+}
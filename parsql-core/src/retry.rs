@@ -0,0 +1,317 @@
+//! Backoff shared by the retry helpers of the async PostgreSQL crates.
+//!
+//! [`RetryPolicy`] decides how often and how long to wait before a failed call runs again;
+//! the backends decide which errors are worth retrying and how to sleep.
+
+use std::collections::hash_map::RandomState;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// SQLSTATEs of a connection that was lost or of a server that is shutting down or starting.
+pub const RETRYABLE_STATES: &[&str] = &[
+    // admin_shutdown, crash_shutdown, cannot_connect_now
+    "57P01", "57P02", "57P03",
+    // connection_exception, connection_does_not_exist, connection_failure
+    "08000", "08003", "08006",
+    // sqlclient_unable_to_establish_sqlconnection,
+    // sqlserver_rejected_establishment_of_sqlconnection
+    "08001", "08004",
+];
+
+/// Returns whether a SQLSTATE is one of the [`RETRYABLE_STATES`].
+pub fn is_retryable_state(code: &str) -> bool {
+    RETRYABLE_STATES.contains(&code)
+}
+
+/// Finds the first error of type `E` in the source chain of `error`, e.g. the database
+/// error inside an `UpdateError`.
+pub fn find_source<'a, E>(error: &'a (dyn StdError + 'static)) -> Option<&'a E>
+where
+    E: StdError + 'static,
+{
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(found) = error.downcast_ref::<E>() {
+            return Some(found);
+        }
+        current = error.source();
+    }
+    None
+}
+
+/// How often and how long to wait before retrying a failed call.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy of 3 retries, starting at 50ms and capped at 2s, with jitter.
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(2),
+            jitter: true,
+        }
+    }
+
+    /// Sets the number of retries after the first attempt.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry; it doubles with every retry.
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the longest delay between two attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Enables or disables the random jitter, which spreads the retries of concurrent calls.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns the delay before the given retry, counted from 0.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+
+        // "Equal jitter": a random delay between half and all of the backoff
+        let half = delay.as_nanos() / 2;
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(retry);
+        let offset = u128::from(hasher.finish()) % (half + 1);
+        Duration::from_nanos(u64::try_from(half + offset).unwrap_or(u64::MAX))
+    }
+
+    /// Returns the delay before the given retry, or `None` once the retries are used up.
+    pub fn retry_delay(&self, retry: u32) -> Option<Duration> {
+        (retry < self.max_retries).then(|| self.delay(retry))
+    }
+
+    /// Runs `op`, running it again after `sleep` while it fails with an error for which
+    /// `is_retryable` holds.
+    pub async fn run<T, E, F, Fut, S, SFut>(
+        &self,
+        mut op: F,
+        is_retryable: impl Fn(&E) -> bool,
+        mut sleep: S,
+    ) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        S: FnMut(Duration) -> SFut,
+        SFut: Future<Output = ()>,
+    {
+        let mut retry = 0;
+        loop {
+            match op().await {
+                Err(err) if is_retryable(&err) => match self.retry_delay(retry) {
+                    Some(delay) => {
+                        sleep(delay).await;
+                        retry += 1;
+                    }
+                    None => return Err(err),
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::fmt;
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Lost,
+        Constraint,
+    }
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl StdError for TestError {}
+
+    #[derive(Debug)]
+    struct Wrapper(TestError);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "wrapped")
+        }
+    }
+
+    impl StdError for Wrapper {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::new()
+            .base_delay(Duration::from_millis(10))
+            .max_delay(Duration::from_millis(50))
+            .jitter(false);
+
+        let delays: Vec<_> = (0..5).map(|retry| policy.delay(retry)).collect();
+        assert_eq!(
+            delays,
+            [10, 20, 40, 50, 50].map(Duration::from_millis).to_vec()
+        );
+        // The exponent saturates instead of overflowing
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_jitter_stays_between_half_and_full_backoff() {
+        let policy = RetryPolicy::new()
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(300));
+
+        for retry in 0..6 {
+            let full = Duration::from_millis(100)
+                .saturating_mul(2_u32.pow(retry))
+                .min(Duration::from_millis(300));
+            for _ in 0..50 {
+                let delay = policy.delay(retry);
+                assert!(delay >= full / 2 && delay <= full, "{delay:?} for {full:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_is_none_after_max_retries() {
+        let policy = RetryPolicy::new().max_retries(2).jitter(false);
+
+        assert!(policy.retry_delay(0).is_some());
+        assert!(policy.retry_delay(1).is_some());
+        assert_eq!(policy.retry_delay(2), None);
+        assert_eq!(RetryPolicy::new().max_retries(0).retry_delay(0), None);
+    }
+
+    #[test]
+    fn test_retryable_states() {
+        for code in ["57P01", "57P02", "57P03", "08000", "08001", "08003", "08004", "08006"] {
+            assert!(is_retryable_state(code), "{code}");
+        }
+        // unique_violation, foreign_key_violation, syntax_error, query_canceled
+        for code in ["23505", "23503", "42601", "57014"] {
+            assert!(!is_retryable_state(code), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_find_source_walks_the_chain() {
+        let error = Wrapper(TestError::Lost);
+
+        assert_eq!(find_source::<TestError>(&error), Some(&TestError::Lost));
+        assert!(find_source::<Wrapper>(&TestError::Constraint).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_after_max_retries() {
+        let policy = RetryPolicy::new()
+            .max_retries(3)
+            .base_delay(Duration::from_millis(10))
+            .jitter(false);
+        let attempts = Cell::new(0);
+        let sleeps = RefCell::new(Vec::new());
+
+        let result: Result<(), _> = policy
+            .run(
+                || async {
+                    attempts.set(attempts.get() + 1);
+                    Err(TestError::Lost)
+                },
+                |err| *err == TestError::Lost,
+                |delay| {
+                    sleeps.borrow_mut().push(delay);
+                    async {}
+                },
+            )
+            .await;
+
+        assert_eq!(result, Err(TestError::Lost));
+        assert_eq!(attempts.get(), 4);
+        assert_eq!(
+            sleeps.into_inner(),
+            [10, 20, 40].map(Duration::from_millis).to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_does_not_retry_other_errors() {
+        let policy = RetryPolicy::new();
+        let attempts = Cell::new(0);
+
+        let result: Result<(), _> = policy
+            .run(
+                || async {
+                    attempts.set(attempts.get() + 1);
+                    Err(TestError::Constraint)
+                },
+                |err| *err == TestError::Lost,
+                |_| async { unreachable!("no retry expected") },
+            )
+            .await;
+
+        assert_eq!(result, Err(TestError::Constraint));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_returns_the_first_success() {
+        let policy = RetryPolicy::new().base_delay(Duration::ZERO);
+        let attempts = Cell::new(0);
+
+        let result = policy
+            .run(
+                || async {
+                    attempts.set(attempts.get() + 1);
+                    if attempts.get() < 3 {
+                        Err(TestError::Lost)
+                    } else {
+                        Ok(attempts.get())
+                    }
+                },
+                |err| *err == TestError::Lost,
+                |_| async {},
+            )
+            .await;
+
+        assert_eq!(result, Ok(3));
+    }
+}
@@ -0,0 +1,100 @@
+//! Session settings for PostgreSQL row-level security.
+//!
+//! [`SessionSettings`] collects the role and the session variables RLS policies read, and
+//! renders the statements that apply them to a transaction. The PostgreSQL backends re-export
+//! it from their `rls` module and run these statements with their driver.
+
+/// Role and session variables applied to a transaction.
+#[derive(Debug, Clone, Default)]
+pub struct SessionSettings {
+    role: Option<String>,
+    variables: Vec<(String, String)>,
+}
+
+impl SessionSettings {
+    /// Creates empty settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switches to the given role for the transaction (`SET LOCAL ROLE`).
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    /// Sets a session variable for the transaction, e.g. `app.user_id`.
+    pub fn set(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.variables.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Returns the statements that apply the settings, in order, with their parameters.
+    ///
+    /// Variables are bound as parameters of `set_config`; only the role name is part of the
+    /// SQL text, quoted with [`quote_identifier`]. Both are local to the transaction.
+    pub fn statements(&self) -> Vec<(String, Vec<&str>)> {
+        let mut statements: Vec<(String, Vec<&str>)> = self
+            .variables
+            .iter()
+            .map(|(name, value)| {
+                let sql = "SELECT set_config($1, $2, true)".to_string();
+                (sql, vec![name.as_str(), value.as_str()])
+            })
+            .collect();
+        if let Some(role) = &self.role {
+            statements.push((format!("SET LOCAL ROLE {}", quote_identifier(role)), Vec::new()));
+        }
+        statements
+    }
+}
+
+/// Quotes an identifier so that it is used verbatim.
+///
+/// Embedded double quotes are doubled. An empty identifier becomes `""`, which PostgreSQL
+/// rejects as a zero-length identifier.
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("app_user"), "\"app_user\"");
+        assert_eq!(quote_identifier("App User"), "\"App User\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+        assert_eq!(
+            quote_identifier("x\"; DROP TABLE users; --"),
+            "\"x\"\"; DROP TABLE users; --\""
+        );
+        assert_eq!(quote_identifier(""), "\"\"");
+    }
+
+    #[test]
+    fn test_statements_bind_variables_and_quote_the_role() {
+        let settings = SessionSettings::new()
+            .role("app\"user")
+            .set("app.user_id", 42)
+            .set("app.tenant", "acme'; --");
+
+        assert_eq!(
+            settings.statements(),
+            vec![
+                ("SELECT set_config($1, $2, true)".to_string(), vec!["app.user_id", "42"]),
+                ("SELECT set_config($1, $2, true)".to_string(), vec!["app.tenant", "acme'; --"]),
+                ("SET LOCAL ROLE \"app\"\"user\"".to_string(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_settings_have_no_statements() {
+        assert!(SessionSettings::new().statements().is_empty());
+
+        let settings = SessionSettings::new().set("app.user_id", 1);
+        assert_eq!(settings.statements().len(), 1);
+    }
+}
@@ -0,0 +1,113 @@
+//! Statements for PostgreSQL full-text search, shared by the PostgreSQL crates.
+//!
+//! The search itself is generated by `#[text_search]`; these statements store and index the
+//! searched document.
+
+/// Returns the statements adding a generated `tsvector` column over `columns` of `table`,
+/// kept up to date by PostgreSQL, and a GIN index on it.
+///
+/// The statements are run one by one, e.g. in the `up` of a migration. Generated columns
+/// need PostgreSQL 12 or later.
+pub fn vector_column_statements(
+    table: &str,
+    vector_column: &str,
+    config: &str,
+    columns: &[&str],
+) -> Vec<String> {
+    vec![
+        format!(
+            "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS {vector} tsvector \
+             GENERATED ALWAYS AS ({document}) STORED",
+            table = table,
+            vector = vector_column,
+            document = document(config, columns)
+        ),
+        format!(
+            "CREATE INDEX IF NOT EXISTS {table}_{vector}_idx ON {table} USING GIN ({vector})",
+            table = table,
+            vector = vector_column
+        ),
+    ]
+}
+
+/// Returns the statements removing what [`vector_column_statements`] created, e.g. for the
+/// `down` of a migration.
+pub fn drop_vector_column_statements(table: &str, vector_column: &str) -> Vec<String> {
+    vec![
+        format!("DROP INDEX IF EXISTS {}_{}_idx", table, vector_column),
+        format!(
+            "ALTER TABLE {} DROP COLUMN IF EXISTS {}",
+            table, vector_column
+        ),
+    ]
+}
+
+/// Returns a statement creating a GIN expression index for searches declared with
+/// `columns = "..."`; `config` and `columns` must match the `#[text_search]` attribute.
+pub fn index_statement(table: &str, index: &str, config: &str, columns: &[&str]) -> String {
+    format!(
+        "CREATE INDEX IF NOT EXISTS {} ON {} USING GIN (({}))",
+        index,
+        table,
+        document(config, columns)
+    )
+}
+
+/// The document searched by `#[text_search(columns = "...")]`.
+fn document(config: &str, columns: &[&str]) -> String {
+    format!(
+        "to_tsvector('{}', {})",
+        config.replace('\'', "''"),
+        columns
+            .iter()
+            .map(|column| format!("coalesce({}, '')", column))
+            .collect::<Vec<_>>()
+            .join(" || ' ' || ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_column_statements() {
+        let statements =
+            vector_column_statements("articles", "search_vector", "english", &["title", "body"]);
+        assert_eq!(
+            statements,
+            [
+                "ALTER TABLE articles ADD COLUMN IF NOT EXISTS search_vector tsvector \
+                 GENERATED ALWAYS AS (to_tsvector('english', coalesce(title, '') || ' ' || \
+                 coalesce(body, ''))) STORED",
+                "CREATE INDEX IF NOT EXISTS articles_search_vector_idx ON articles \
+                 USING GIN (search_vector)",
+            ]
+        );
+        assert_eq!(
+            drop_vector_column_statements("articles", "search_vector"),
+            [
+                "DROP INDEX IF EXISTS articles_search_vector_idx",
+                "ALTER TABLE articles DROP COLUMN IF EXISTS search_vector",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_statement_matches_the_searched_document() {
+        assert_eq!(
+            index_statement("articles", "articles_fts", "english", &["title"]),
+            "CREATE INDEX IF NOT EXISTS articles_fts ON articles \
+             USING GIN ((to_tsvector('english', coalesce(title, ''))))"
+        );
+    }
+
+    #[test]
+    fn test_config_is_quoted() {
+        assert_eq!(
+            index_statement("t", "t_idx", "it's", &["a"]),
+            "CREATE INDEX IF NOT EXISTS t_idx ON t \
+             USING GIN ((to_tsvector('it''s', coalesce(a, ''))))"
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Per-query statistics and slow-query log.
+//!
+//! [`QueryStats`] is a [`QueryObserver`] that aggregates the statements executed by the CRUD
+//! functions, keyed by the Rust type they were generated for or by their SQL text. It
+//! records the number of calls, errors and rows, and the p50/p95/p99 latency over the most
+//! recent calls. Statements slower than the configured threshold are passed to a slow-query
+//! handler; by default it emits a `tracing` warning with the `tracing` feature and does
+//! nothing otherwise.
+//!
+//! The backends re-export this module as `stats`; a registry is registered with the
+//! backend's `observer::set_observer`.
+//!
+//! ```rust
+//! use parsql_core::stats::QueryStats;
+//! use std::time::Duration;
+//!
+//! let stats = QueryStats::new().slow_query_threshold(Duration::from_millis(200));
+//! // e.g. parsql_sqlite::observer::set_observer(stats.clone());
+//!
+//! // ... run queries ...
+//!
+//! for stat in stats.snapshot() {
+//!     println!("{}: {} calls, p99 {:?}", stat.key, stat.count, stat.p99);
+//! }
+//! let metrics = stats.to_prometheus();
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::observer::{QueryEvent, QueryObserver};
+
+/// Number of recent latencies kept per key for the percentiles.
+const LATENCY_WINDOW: usize = 1024;
+
+/// What the statistics are grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsKey {
+    /// The type name of the model or row type.
+    #[default]
+    Entity,
+    /// The SQL text.
+    Sql,
+}
+
+/// Statistics of one query, as returned by [`QueryStats::snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryStat {
+    /// The type name or SQL text the statistics are grouped by.
+    pub key: String,
+    /// Number of calls.
+    pub count: u64,
+    /// Number of failed calls.
+    pub errors: u64,
+    /// Total number of affected or returned rows.
+    pub rows: u64,
+    /// Total time spent in the calls.
+    pub total: Duration,
+    /// Median latency of the recent calls.
+    pub p50: Duration,
+    /// 95th percentile latency of the recent calls.
+    pub p95: Duration,
+    /// 99th percentile latency of the recent calls.
+    pub p99: Duration,
+}
+
+#[derive(Default)]
+struct Entry {
+    count: u64,
+    errors: u64,
+    rows: u64,
+    total: Duration,
+    latencies: VecDeque<Duration>,
+}
+
+type SlowQueryHandler = dyn Fn(&QueryEvent<'_>) + Send + Sync;
+
+/// In-process statistics registry; register a clone with the backend's
+/// `observer::set_observer`.
+///
+/// Clones share the same statistics.
+#[derive(Clone)]
+pub struct QueryStats {
+    key: StatsKey,
+    slow_query_threshold: Option<Duration>,
+    on_slow_query: Arc<SlowQueryHandler>,
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl Default for QueryStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryStats {
+    /// Creates an empty registry keyed by type name, without a slow-query threshold.
+    pub fn new() -> Self {
+        QueryStats {
+            key: StatsKey::Entity,
+            slow_query_threshold: None,
+            on_slow_query: Arc::new(log_slow_query),
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Groups the statistics by type name or SQL text.
+    pub fn key_by(mut self, key: StatsKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Passes the statements that take at least `threshold` to the slow-query handler.
+    pub fn slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Replaces the default slow-query handler, which emits a `tracing` warning with the
+    /// `tracing` feature and does nothing otherwise.
+    pub fn on_slow_query<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&QueryEvent<'_>) + Send + Sync + 'static,
+    {
+        self.on_slow_query = Arc::new(handler);
+        self
+    }
+
+    /// Returns the statistics of every query, sorted by total time, slowest first.
+    pub fn snapshot(&self) -> Vec<QueryStat> {
+        let entries = self.entries();
+        let mut stats: Vec<QueryStat> = entries
+            .iter()
+            .map(|(key, entry)| {
+                let mut latencies: Vec<Duration> = entry.latencies.iter().copied().collect();
+                latencies.sort_unstable();
+                QueryStat {
+                    key: key.clone(),
+                    count: entry.count,
+                    errors: entry.errors,
+                    rows: entry.rows,
+                    total: entry.total,
+                    p50: percentile(&latencies, 500),
+                    p95: percentile(&latencies, 950),
+                    p99: percentile(&latencies, 990),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.key.cmp(&b.key)));
+        stats
+    }
+
+    /// Exports the statistics in the Prometheus text format.
+    pub fn to_prometheus(&self) -> String {
+        let stats = self.snapshot();
+        let mut out = String::new();
+
+        out.push_str("# HELP parsql_query_duration_seconds Latency of parsql queries.\n");
+        out.push_str("# TYPE parsql_query_duration_seconds summary\n");
+        for stat in &stats {
+            let label = escape_label(&stat.key);
+            for (quantile, value) in [("0.5", stat.p50), ("0.95", stat.p95), ("0.99", stat.p99)] {
+                let _ = writeln!(
+                    out,
+                    "parsql_query_duration_seconds{{query=\"{}\",quantile=\"{}\"}} {}",
+                    label,
+                    quantile,
+                    value.as_secs_f64()
+                );
+            }
+            let _ = writeln!(
+                out,
+                "parsql_query_duration_seconds_sum{{query=\"{}\"}} {}",
+                label,
+                stat.total.as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "parsql_query_duration_seconds_count{{query=\"{}\"}} {}",
+                label, stat.count
+            );
+        }
+
+        write_counter(
+            &mut out,
+            "parsql_query_errors_total",
+            "Failed parsql queries.",
+            &stats,
+            |stat| stat.errors,
+        );
+        write_counter(
+            &mut out,
+            "parsql_query_rows_total",
+            "Rows affected or returned by parsql queries.",
+            &stats,
+            |stat| stat.rows,
+        );
+
+        out
+    }
+
+    /// Removes all statistics.
+    pub fn reset(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        // The statistics stay usable after a panic in another thread
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl QueryObserver for QueryStats {
+    fn on_query(&self, event: &QueryEvent<'_>) {
+        let key = match self.key {
+            StatsKey::Entity => event.entity,
+            StatsKey::Sql => event.sql,
+        };
+
+        {
+            let mut entries = self.entries();
+            let entry = match entries.get_mut(key) {
+                Some(entry) => entry,
+                None => entries.entry(key.to_string()).or_default(),
+            };
+            entry.count += 1;
+            entry.total += event.duration;
+            if event.error.is_some() {
+                entry.errors += 1;
+            }
+            entry.rows += event.rows.unwrap_or(0);
+            if entry.latencies.len() == LATENCY_WINDOW {
+                entry.latencies.pop_front();
+            }
+            entry.latencies.push_back(event.duration);
+        }
+
+        if let Some(threshold) = self.slow_query_threshold {
+            if event.duration >= threshold {
+                (self.on_slow_query)(event);
+            }
+        }
+    }
+}
+
+/// The default slow-query handler.
+fn log_slow_query(_event: &QueryEvent<'_>) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        db.system = _event.system,
+        db.statement = _event.sql,
+        parsql.entity = _event.entity,
+        parsql.duration = ?_event.duration,
+        "slow query"
+    );
+}
+
+/// Nearest-rank percentile of sorted latencies; `per_mille` is e.g. 950 for p95.
+fn percentile(sorted: &[Duration], per_mille: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() * per_mille).div_ceil(1000);
+    sorted[rank.saturating_sub(1)]
+}
+
+fn write_counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    stats: &[QueryStat],
+    value: impl Fn(&QueryStat) -> u64,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for stat in stats {
+        let _ = writeln!(
+            out,
+            "{}{{query=\"{}\"}} {}",
+            name,
+            escape_label(&stat.key),
+            value(stat)
+        );
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observer::QueryKind;
+
+    fn event<'a>(sql: &'a str, millis: u64, rows: Option<u64>) -> QueryEvent<'a> {
+        QueryEvent {
+            system: "postgresql",
+            kind: QueryKind::Select,
+            entity: "app::User",
+            sql,
+            param_count: 0,
+            params: None,
+            duration: Duration::from_millis(millis),
+            rows,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_percentile_of_no_samples_is_zero() {
+        assert_eq!(percentile(&[], 500), Duration::ZERO);
+        assert_eq!(percentile(&[], 990), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_percentile_of_one_sample_is_that_sample() {
+        let sample = [Duration::from_millis(7)];
+        assert_eq!(percentile(&sample, 500), sample[0]);
+        assert_eq!(percentile(&sample, 990), sample[0]);
+    }
+
+    #[test]
+    fn test_percentile_uses_nearest_rank() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 500), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 950), Duration::from_millis(95));
+        assert_eq!(percentile(&sorted, 990), Duration::from_millis(99));
+    }
+
+    #[test]
+    fn test_snapshot_aggregates_by_key() {
+        let stats = QueryStats::new();
+        stats.on_query(&event("SELECT 1", 10, Some(3)));
+        let error = std::fmt::Error;
+        stats.on_query(&QueryEvent {
+            error: Some(&error),
+            ..event("SELECT 1", 30, None)
+        });
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        let stat = &snapshot[0];
+        assert_eq!(stat.key, "app::User");
+        assert_eq!((stat.count, stat.errors, stat.rows), (2, 1, 3));
+        assert_eq!(stat.total, Duration::from_millis(40));
+        assert_eq!(stat.p50, Duration::from_millis(10));
+        assert_eq!(stat.p99, Duration::from_millis(30));
+
+        stats.reset();
+        assert!(stats.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_prometheus_export() {
+        let stats = QueryStats::new();
+        stats.on_query(&event("SELECT 1", 10, Some(3)));
+        stats.on_query(&event("SELECT 1", 30, Some(1)));
+
+        assert_eq!(
+            stats.to_prometheus(),
+            "# HELP parsql_query_duration_seconds Latency of parsql queries.\n\
+             # TYPE parsql_query_duration_seconds summary\n\
+             parsql_query_duration_seconds{query=\"app::User\",quantile=\"0.5\"} 0.01\n\
+             parsql_query_duration_seconds{query=\"app::User\",quantile=\"0.95\"} 0.03\n\
+             parsql_query_duration_seconds{query=\"app::User\",quantile=\"0.99\"} 0.03\n\
+             parsql_query_duration_seconds_sum{query=\"app::User\"} 0.04\n\
+             parsql_query_duration_seconds_count{query=\"app::User\"} 2\n\
+             # HELP parsql_query_errors_total Failed parsql queries.\n\
+             # TYPE parsql_query_errors_total counter\n\
+             parsql_query_errors_total{query=\"app::User\"} 0\n\
+             # HELP parsql_query_rows_total Rows affected or returned by parsql queries.\n\
+             # TYPE parsql_query_rows_total counter\n\
+             parsql_query_rows_total{query=\"app::User\"} 4\n"
+        );
+    }
+
+    #[test]
+    fn test_prometheus_labels_are_escaped() {
+        let stats = QueryStats::new().key_by(StatsKey::Sql);
+        stats.on_query(&event("SELECT \"a\\b\"\nFROM t", 1, Some(0)));
+
+        assert!(stats
+            .to_prometheus()
+            .contains("parsql_query_rows_total{query=\"SELECT \\\"a\\\\b\\\"\\nFROM t\"} 0\n"));
+    }
+
+    #[test]
+    fn test_slow_queries_are_reported() {
+        let slow = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&slow);
+        let stats = QueryStats::new()
+            .slow_query_threshold(Duration::from_millis(20))
+            .on_slow_query(move |event| log.lock().unwrap().push(event.sql.to_string()));
+
+        stats.on_query(&event("SELECT fast", 5, Some(1)));
+        stats.on_query(&event("SELECT slow", 20, Some(1)));
+
+        assert_eq!(*slow.lock().unwrap(), vec!["SELECT slow".to_string()]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Multi-tenant row scoping.
+//!
+//! A [`TenantContext`] holds the tenant of the current request. It binds tenant-scoped models
+//! with [`TenantContext::scope`] and restricts `fetch_all_where` conditions with
+//! [`TenantContext::filter`]. The backends re-export both types with their [`Dialect`] filled
+//! in and implement their model traits for [`Scoped`].
+//!
+//! `fetch_all_where` on a tenant-scoped model fails with [`MissingTenant`] unless its
+//! condition was restricted with [`TenantContext::filter`].
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::dialect::{Bind, Dialect};
+use crate::expr::Expr;
+use crate::traits::{Columns, TenantScoped};
+
+/// The tenant of the current request.
+///
+/// Cloning is cheap; the tenant value is shared between clones.
+pub struct TenantContext<D: Dialect> {
+    tenant_id: Arc<D::Value>,
+}
+
+impl<D: Dialect> Clone for TenantContext<D> {
+    fn clone(&self) -> Self {
+        TenantContext {
+            tenant_id: Arc::clone(&self.tenant_id),
+        }
+    }
+}
+
+impl<D: Dialect> TenantContext<D> {
+    /// Creates a context for the given tenant.
+    pub fn new<V>(tenant_id: V) -> Self
+    where
+        D: Bind<V>,
+    {
+        TenantContext {
+            tenant_id: Arc::from(D::bind(tenant_id)),
+        }
+    }
+
+    /// Binds a tenant-scoped model to this tenant.
+    pub fn scope<T: TenantScoped>(&self, entity: T) -> Scoped<T, D> {
+        Scoped {
+            entity,
+            tenant_id: Arc::clone(&self.tenant_id),
+        }
+    }
+
+    /// Restricts a `fetch_all_where` condition on `R` to this tenant.
+    ///
+    /// Returns `expr` unchanged if `R` is not `#[tenant_scoped]`.
+    pub fn filter<R: Columns>(&self, expr: Expr<D>) -> Expr<D> {
+        match R::tenant_column() {
+            Some(column) => expr.and(Expr::Tenant {
+                column,
+                tenant: Arc::clone(&self.tenant_id),
+            }),
+            None => expr,
+        }
+    }
+}
+
+/// A tenant-scoped model together with its tenant, created by [`TenantContext::scope`].
+///
+/// Generates the same SQL as the wrapped model and adds the tenant value to its parameters.
+pub struct Scoped<T, D: Dialect> {
+    entity: T,
+    tenant_id: Arc<D::Value>,
+}
+
+impl<T, D: Dialect> Scoped<T, D> {
+    /// Returns the wrapped model.
+    pub fn into_inner(self) -> T {
+        self.entity
+    }
+
+    /// Returns a reference to the wrapped model.
+    pub fn entity(&self) -> &T {
+        &self.entity
+    }
+
+    /// Inserts the tenant value into the parameters of the wrapped model, at the position of
+    /// its tenant placeholder.
+    pub fn with_tenant<'a>(&'a self, mut params: Vec<&'a D::Param<'a>>) -> Vec<&'a D::Param<'a>>
+    where
+        T: TenantScoped,
+    {
+        let index = T::tenant_param_index().min(params.len());
+        params.insert(index, D::param(&self.tenant_id));
+        params
+    }
+}
+
+/// Error returned by `fetch_all_where` when a `#[tenant_scoped]` model is queried without a
+/// [`TenantContext::filter`] condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingTenant {
+    /// Type name of the model
+    pub entity: &'static str,
+}
+
+impl fmt::Display for MissingTenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Missing tenant: {} is tenant scoped and the condition was not restricted with \
+             TenantContext::filter",
+            self.entity
+        )
+    }
+}
+
+impl std::error::Error for MissingTenant {}
@@ -0,0 +1,35 @@
+//! Model traits that do not depend on the driver.
+//!
+//! The backends re-export these from their `traits` module, next to the driver-specific
+//! traits implemented by the same derive macros.
+
+/// Trait for models restricted to the rows of a single tenant.
+/// This trait is implemented by the derive macros when `#[tenant_scoped]` is present;
+/// the tenant value is supplied with `TenantContext::scope`.
+pub trait TenantScoped {
+    /// Returns the tenant column.
+    fn tenant_column() -> &'static str;
+
+    /// Returns the position of the tenant value among the parameters of the generated SQL.
+    fn tenant_param_index() -> usize;
+}
+
+/// Trait for describing the table a model is read from.
+/// This trait is implemented by the derive macro `Columns`.
+pub trait Columns {
+    /// Returns the table name.
+    fn table() -> &'static str;
+
+    /// Returns the column names, in field order.
+    fn columns() -> &'static [&'static str];
+
+    /// Returns the `#[soft_delete]` column; rows where it is set are skipped.
+    fn soft_delete_column() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the `#[tenant_scoped]` column; rows of other tenants are skipped.
+    fn tenant_column() -> Option<&'static str> {
+        None
+    }
+}
@@ -1,21 +1,18 @@
-use deadpool_postgres::{Pool, PoolError};
+use deadpool_postgres::Pool;
 use postgres::types::FromSqlOwned;
 //use postgres::types::FromSql;
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use crate::compose::Fragment;
+use crate::error::{QueryError, UpdateError, WhereError};
+use crate::expr::Expr;
+use crate::observer::{Observation, QueryKind};
+use crate::timeout::Checkout;
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+    Versioned,
+};
+use std::collections::HashSet;
 use tokio_postgres::{types::FromSql, Error, Row};
 
-// Daha basit bir yaklaşım: PoolError'dan genel bir Error oluştur
-fn pool_err_to_io_err(e: PoolError) -> Error {
-    // Bu özel fonksiyon tokio_postgres'in sağladığı timeout hatasını döndürür
-    // Güzel bir çözüm değil, ama çalışır bir örnek için kullanılabilir
-    let err = Error::__private_api_timeout();
-
-    // Debug süreci için stderr'e hatayı yazdıralım
-    eprintln!("Pool bağlantı hatası: {}", e);
-
-    err
-}
-
 /// # insert
 ///
 /// Deadpool bağlantı havuzunu kullanarak veritabanına yeni bir kayıt ekler.
@@ -25,7 +22,7 @@ fn pool_err_to_io_err(e: PoolError) -> Error {
 /// - `entity`: Eklenecek veri nesnesi (SqlQuery ve SqlParams trait'lerini uygulamalıdır)
 ///
 /// ## Dönüş Değeri
-/// - `Result<i64, Error>`: Başarılı olursa, eklenen kayıt ID'sini döndürür; başarısız olursa, Error döndürür
+/// - `Result<i64, QueryError>`: Başarılı olursa, eklenen kayıt ID'sini döndürür; başarısız olursa, QueryError döndürür
 ///
 /// ## Yapı Tanımı
 /// Bu fonksiyonla kullanılan yapılar aşağıdaki derive makrolarıyla işaretlenmelidir:
@@ -59,7 +56,7 @@ fn pool_err_to_io_err(e: PoolError) -> Error {
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -81,7 +78,7 @@ fn pool_err_to_io_err(e: PoolError) -> Error {
 //     pool: &Pool,
 //     entity: T,
 // ) -> Result<P, Error> {
-//     let client = pool.get().await.map_err(pool_err_to_io_err)?;
+//     let client = Checkout::get(pool).await?;
 //     let sql = T::query();
 
 //     if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
@@ -93,21 +90,18 @@ fn pool_err_to_io_err(e: PoolError) -> Error {
 //     row.try_get::<_, P>(0)
 // }
 
-pub async fn insert<T, P>(pool: &Pool, entity: T) -> Result<P, Error>
+pub async fn insert<T, P>(pool: &Pool, entity: T) -> Result<P, QueryError>
 where
     T: SqlCommand + SqlParams,
     P: FromSqlOwned + Send + Sync,
 {
-    let client = pool.get().await.map_err(pool_err_to_io_err)?;
+    let client = Checkout::get(pool).await?;
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = client.query_one(&sql, &params).await?;
-    row.try_get::<_, P>(0)
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+    let row = observation.finish(client.query_one(&sql, &params).await, |_| 1)?;
+    Ok(row.try_get::<_, P>(0)?)
 }
 
 /// # update
@@ -119,7 +113,7 @@ where
 /// - `entity`: Güncelleme bilgilerini içeren veri nesnesi (SqlQuery ve UpdateParams trait'lerini uygulamalıdır)
 ///
 /// ## Dönüş Değeri
-/// - `Result<bool, Error>`: Başarılı olursa, true döndürür; başarısız olursa, Error döndürür
+/// - `Result<bool, QueryError>`: Başarılı olursa, true döndürür; başarısız olursa, QueryError döndürür
 ///
 /// ## Yapı Tanımı
 /// Bu fonksiyonla kullanılan yapılar aşağıdaki derive makrolarıyla işaretlenmelidir:
@@ -146,7 +140,7 @@ where
 /// ## Kullanım Örneği
 /// ```rust,no_run
 /// use deadpool_postgres::{Config, Runtime, Pool};
-/// use tokio_postgres::{NoTls, Error};
+/// use tokio_postgres::NoTls;
 /// use parsql::tokio_postgres::pool_crud_ops::update;
 ///
 /// #[derive(Updateable, UpdateParams)]
@@ -161,7 +155,7 @@ where
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -180,19 +174,69 @@ where
 ///     Ok(())
 /// }
 /// ```
-pub async fn update<T: SqlCommand + UpdateParams>(pool: &Pool, entity: T) -> Result<bool, Error> {
-    let client = pool.get().await.map_err(pool_err_to_io_err)?;
+pub async fn update<T: SqlCommand + UpdateParams>(
+    pool: &Pool,
+    entity: T,
+) -> Result<bool, QueryError> {
+    let client = Checkout::get(pool).await?;
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    match client.execute(&sql, &params).await {
-        Ok(_) => Ok(true),
-        Err(e) => Err(e),
-    }
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    observation.finish(client.execute(&sql, &params).await, |rows| *rows)?;
+    Ok(true)
+}
+
+/// # update_versioned
+///
+/// `#[version_column("...")]` kullanan bir modelin kaydını iyimser kilitleme ile günceller.
+///
+/// Üretilen SQL, kayıtla birlikte okunan sürümü eşleştirir ve artırır. Hiçbir satır
+/// eşleşmezse kayıt okunduktan sonra değiştirilmiş ya da silinmiştir ve güncelleme
+/// `UpdateError::StaleObject` hatası ile döner.
+///
+/// ## Parametreler
+/// - `pool`: Deadpool bağlantı havuzu
+/// - `entity`: Güncelleme bilgilerini içeren veri nesnesi (Versioned ve UpdateParams trait'lerini uygulamalıdır)
+///
+/// ## Dönüş Değeri
+/// - `Result<(), QueryError<UpdateError>>`: Başarısız olursa `UpdateError::StaleObject`, veritabanı hatası
+///   ya da havuz hatası döndürür
+///
+/// ## Kullanım Örneği
+/// ```rust,no_run
+/// use deadpool_postgres::{Config, Runtime};
+/// use tokio_postgres::NoTls;
+/// use parsql_deadpool_postgres::traits::*;
+/// use parsql_deadpool_postgres::*;
+///
+/// #[derive(Updateable, UpdateParams)]
+/// #[table("accounts")]
+/// #[update("balance")]
+/// #[where_clause("id = $")]
+/// #[version_column("version")]
+/// pub struct UpdateAccount {
+///     pub id: i64,
+///     pub balance: i64,
+///     pub version: i64,
+/// }
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+/// let account = UpdateAccount { id: 1, balance: 50, version: 3 };
+///
+/// match update_versioned(&pool, account).await {
+///     Err(QueryError::Query(err)) if err.is_stale() => println!("Reload the account and try again"),
+///     result => result?,
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn update_versioned<T>(pool: &Pool, entity: T) -> Result<(), QueryError<UpdateError>>
+where
+    T: Versioned + UpdateParams + Send + Sync,
+{
+    CrudOps::update_versioned(pool, entity).await
 }
 
 /// # delete
@@ -204,7 +248,7 @@ pub async fn update<T: SqlCommand + UpdateParams>(pool: &Pool, entity: T) -> Res
 /// - `entity`: Silme bilgilerini içeren veri nesnesi (SqlQuery ve SqlParams trait'lerini uygulamalıdır)
 ///
 /// ## Dönüş Değeri
-/// - `Result<u64, Error>`: Başarılı olursa, silinen kayıt sayısını döndürür; başarısız olursa, Error döndürür
+/// - `Result<u64, QueryError>`: Başarılı olursa, silinen kayıt sayısını döndürür; başarısız olursa, QueryError döndürür
 ///
 /// ## Yapı Tanımı
 /// Bu fonksiyonla kullanılan yapılar aşağıdaki derive makrolarıyla işaretlenmelidir:
@@ -238,7 +282,7 @@ pub async fn update<T: SqlCommand + UpdateParams>(pool: &Pool, entity: T) -> Res
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -252,16 +296,89 @@ pub async fn update<T: SqlCommand + UpdateParams>(pool: &Pool, entity: T) -> Res
 ///     Ok(())
 /// }
 /// ```
-pub async fn delete<T: SqlCommand + SqlParams>(pool: &Pool, entity: T) -> Result<u64, Error> {
-    let client = pool.get().await.map_err(pool_err_to_io_err)?;
+pub async fn delete<T: SqlCommand + SqlParams>(pool: &Pool, entity: T) -> Result<u64, QueryError> {
+    let client = Checkout::get(pool).await?;
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-    }
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    Ok(observation.finish(client.execute(&sql, &params).await, |rows| *rows)?)
+}
+
+/// # restore
+///
+/// Soft-delete ile silinmiş kayıtları, silinme işaretini temizleyerek geri getirir.
+///
+/// ## Parametreler
+/// - `pool`: Deadpool bağlantı havuzu
+/// - `entity`: Silme koşullarını içeren veri nesnesi (SoftDelete ve SqlParams trait'lerini uygulamalıdır)
+///
+/// ## Dönüş Değeri
+/// - `Result<u64, QueryError>`: Başarılı olursa, geri getirilen kayıt sayısını döndürür; başarısız olursa, QueryError döndürür
+///
+/// ## Kullanım Örneği
+/// ```rust,no_run
+/// use parsql_deadpool_postgres::traits::*;
+/// use parsql_deadpool_postgres::*;
+///
+/// #[derive(Deletable, SqlParams)]
+/// #[table("users")]
+/// #[where_clause("id = $")]
+/// #[soft_delete(column = "deleted_at")]
+/// pub struct DeleteUser {
+///     pub id: i32,
+/// }
+///
+/// async fn restore_user(pool: &Pool) -> Result<u64, QueryError> {
+///     restore(pool, DeleteUser { id: 6 }).await
+/// }
+/// # fn main() {}
+/// ```
+pub async fn restore<T: SoftDelete + SqlParams>(pool: &Pool, entity: T) -> Result<u64, QueryError> {
+    let client = Checkout::get(pool).await?;
+    let sql = T::restore_query();
+
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    Ok(observation.finish(client.execute(&sql, &params).await, |rows| *rows)?)
+}
+
+/// # force_delete
+///
+/// Soft-delete uygulanan bir tablodan kayıtları kalıcı olarak siler.
+///
+/// ## Parametreler
+/// - `pool`: Deadpool bağlantı havuzu
+/// - `entity`: Silme koşullarını içeren veri nesnesi (SoftDelete ve SqlParams trait'lerini uygulamalıdır)
+///
+/// ## Dönüş Değeri
+/// - `Result<u64, QueryError>`: Başarılı olursa, silinen kayıt sayısını döndürür; başarısız olursa, QueryError döndürür
+///
+/// ## Kullanım Örneği
+/// ```rust,no_run
+/// use parsql_deadpool_postgres::traits::*;
+/// use parsql_deadpool_postgres::*;
+///
+/// #[derive(Deletable, SqlParams)]
+/// #[table("users")]
+/// #[where_clause("id = $")]
+/// #[soft_delete(column = "deleted_at")]
+/// pub struct DeleteUser {
+///     pub id: i32,
+/// }
+///
+/// async fn force_delete_user(pool: &Pool) -> Result<u64, QueryError> {
+///     force_delete(pool, DeleteUser { id: 6 }).await
+/// }
+/// # fn main() {}
+/// ```
+pub async fn force_delete<T: SoftDelete + SqlParams>(pool: &Pool, entity: T) -> Result<u64, QueryError> {
+    let client = Checkout::get(pool).await?;
+    let sql = T::force_delete_query();
 
     let params = entity.params();
-    client.execute(&sql, &params).await
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    Ok(observation.finish(client.execute(&sql, &params).await, |rows| *rows)?)
 }
 
 /// # fetch
@@ -273,7 +390,7 @@ pub async fn delete<T: SqlCommand + SqlParams>(pool: &Pool, entity: T) -> Result
 /// - `params`: Sorgu parametrelerini içeren veri nesnesi (SqlQuery, FromRow ve SqlParams trait'lerini uygulamalıdır)
 ///
 /// ## Dönüş Değeri
-/// - `Result<T, Error>`: Başarılı olursa, alınan kaydı döndürür; başarısız olursa, Error döndürür
+/// - `Result<T, QueryError>`: Başarılı olursa, alınan kaydı döndürür; başarısız olursa, QueryError döndürür
 ///
 /// ## Yapı Tanımı
 /// Bu fonksiyonla kullanılan yapılar aşağıdaki derive makrolarıyla işaretlenmelidir:
@@ -324,7 +441,7 @@ pub async fn delete<T: SqlCommand + SqlParams>(pool: &Pool, entity: T) -> Result
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -338,21 +455,18 @@ pub async fn delete<T: SqlCommand + SqlParams>(pool: &Pool, entity: T) -> Result
 ///     Ok(())
 /// }
 /// ```
-pub async fn fetch<P, R>(pool: &Pool, params: &P) -> Result<R, Error>
+pub async fn fetch<P, R>(pool: &Pool, params: &P) -> Result<R, QueryError>
 where
     P: SqlQuery<R> + SqlParams,
     R: FromRow,
 {
-    let client = pool.get().await.map_err(pool_err_to_io_err)?;
+    let client = Checkout::get(pool).await?;
     let sql = P::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let row = client.query_one(&sql, &query_params).await?;
-    R::from_row(&row)
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let row = observation.finish(client.query_one(&sql, &query_params).await, |_| 1)?;
+    Ok(R::from_row(&row)?)
 }
 
 /// # fetch_all
@@ -364,7 +478,7 @@ where
 /// - `params`: Sorgu parametrelerini içeren veri nesnesi (SqlQuery, FromRow ve SqlParams trait'lerini uygulamalıdır)
 ///
 /// ## Dönüş Değeri
-/// - `Result<Vec<T>, Error>`: Başarılı olursa, alınan kayıtları içeren bir vektör döndürür; başarısız olursa, Error döndürür
+/// - `Result<Vec<T>, QueryError>`: Başarılı olursa, alınan kayıtları içeren bir vektör döndürür; başarısız olursa, QueryError döndürür
 ///
 /// ## Yapı Tanımı
 /// Bu fonksiyonla kullanılan yapılar aşağıdaki derive makrolarıyla işaretlenmelidir:
@@ -416,7 +530,7 @@ where
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -430,20 +544,20 @@ where
 ///     Ok(())
 /// }
 /// ```
-pub async fn fetch_all<P, R>(pool: &Pool, params: &P) -> Result<Vec<R>, Error>
+pub async fn fetch_all<P, R>(pool: &Pool, params: &P) -> Result<Vec<R>, QueryError>
 where
     P: SqlQuery<R> + SqlParams,
     R: FromRow,
 {
-    let client = pool.get().await.map_err(pool_err_to_io_err)?;
+    let client = Checkout::get(pool).await?;
     let sql = P::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let rows = client.query(&sql, &query_params).await?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let rows = observation.finish(
+        client.query(&sql, &query_params).await,
+        |rows| rows.len() as u64,
+    )?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -463,7 +577,7 @@ where
 /// - `to_model`: Satırı modele dönüştüren fonksiyon
 ///
 /// ## Dönüş Değeri
-/// - `Result<R, Error>`: Başarılı olursa, dönüştürülen modeli döndürür; başarısız olursa, Error döndürür
+/// - `Result<R, QueryError>`: Başarılı olursa, dönüştürülen modeli döndürür; başarısız olursa, QueryError döndürür
 ///
 /// ## Kullanım Örneği
 /// ```rust,no_run
@@ -501,7 +615,7 @@ where
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -519,20 +633,17 @@ pub async fn select<T: SqlQuery<T> + SqlParams, R, F>(
     pool: &Pool,
     entity: T,
     to_model: F,
-) -> Result<R, Error>
+) -> Result<R, QueryError>
 where
     F: Fn(&Row) -> Result<R, Error>,
 {
-    let client = pool.get().await.map_err(pool_err_to_io_err)?;
+    let client = Checkout::get(pool).await?;
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = client.query_one(&sql, &params).await?;
-    to_model(&row)
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let row = observation.finish(client.query_one(&sql, &params).await, |_| 1)?;
+    Ok(to_model(&row)?)
 }
 
 /// # select_all
@@ -545,7 +656,7 @@ where
 /// - `to_model`: Satırı modele dönüştüren fonksiyon
 ///
 /// ## Dönüş Değeri
-/// - `Result<Vec<R>, Error>`: Başarılı olursa, dönüştürülen modelleri içeren bir vektör döndürür; başarısız olursa, Error döndürür
+/// - `Result<Vec<R>, QueryError>`: Başarılı olursa, dönüştürülen modelleri içeren bir vektör döndürür; başarısız olursa, QueryError döndürür
 ///
 /// ## Kullanım Örneği
 /// ```rust,no_run
@@ -583,7 +694,7 @@ where
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -601,19 +712,16 @@ pub async fn select_all<T: SqlQuery<T> + SqlParams, R, F>(
     pool: &Pool,
     entity: T,
     to_model: F,
-) -> Result<Vec<R>, Error>
+) -> Result<Vec<R>, QueryError>
 where
     F: Fn(&Row) -> R,
 {
-    let client = pool.get().await.map_err(pool_err_to_io_err)?;
+    let client = Checkout::get(pool).await?;
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let rows = client.query(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params).await, |rows| rows.len() as u64)?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -623,6 +731,202 @@ where
     Ok(results)
 }
 
+/// # preload
+///
+/// Loads the records related to a list of records with a single `= ANY($1)` query
+/// and attaches them, avoiding one query per record (N+1).
+///
+/// ## Parameters
+/// - `pool`: Deadpool PostgreSQL connection pool
+/// - `records`: Records previously returned by `fetch_all` (must implement Relation<C>)
+///
+/// ## Return Value
+/// - `Result<(), QueryError>`: On success, the relation field of every record is filled; on failure, returns QueryError
+///
+/// ## Example Usage
+/// Relations are declared on the model with the `has_many` or `belongs_to` attributes
+/// of the `FromRow` derive macro; a nullable foreign key is an `Option` field:
+///
+/// ```rust
+/// use parsql_deadpool_postgres::traits::*;
+/// use parsql_deadpool_postgres::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("orders")]
+/// #[select("id, customer_id")]
+/// #[where_clause("customer_id = $")]
+/// #[result_type("Order")]
+/// pub struct OrdersByCustomer {
+///     pub customer_id: i64,
+/// }
+///
+/// #[derive(FromRow, Debug)]
+/// #[has_many(OrderItem, foreign_key = "order_id", field = "items")]
+/// pub struct Order {
+///     pub id: i64,
+///     pub customer_id: i64,
+///     pub items: Vec<OrderItem>,    // Filled by preload
+/// }
+///
+/// #[derive(FromRow, Clone, Debug)]
+/// pub struct OrderItem {
+///     pub id: i64,
+///     pub order_id: Option<i64>,
+///     pub product: String,
+/// }
+///
+/// async fn orders_with_items(pool: &Pool, customer_id: i64) -> Result<Vec<Order>, QueryError> {
+///     let mut orders: Vec<Order> = fetch_all(pool, &OrdersByCustomer { customer_id }).await?;
+///     preload::<Order, OrderItem>(pool, &mut orders).await?;
+///     Ok(orders)
+/// }
+/// # fn main() {}
+/// ```
+pub async fn preload<R, C>(pool: &Pool, records: &mut [R]) -> Result<(), QueryError>
+where
+    R: Relation<C>,
+    C: FromRow + Clone,
+{
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    // Each distinct key is sent only once; records without a key have no related rows
+    let mut seen = HashSet::new();
+    let keys: Vec<R::Key> = records
+        .iter()
+        .filter_map(|record| record.relation_key())
+        .filter(|key| seen.insert(key.clone()))
+        .collect();
+
+    let client = Checkout::get(pool).await?;
+    let sql = R::relation_query();
+
+    let observation = Observation::start::<C>(QueryKind::Select, &sql, &[&keys]);
+    let rows = observation.finish(client.query(&sql, &[&keys]).await, |rows| rows.len() as u64)?;
+
+    let mut related = Vec::with_capacity(rows.len());
+    for row in rows {
+        related.push(C::from_row(&row)?);
+    }
+
+    R::stitch(records, related);
+    Ok(())
+}
+
+/// # fetch_all_where
+///
+/// Retrieves the records of a table that match a typed WHERE expression.
+///
+/// ## Parameters
+/// - `pool`: Deadpool PostgreSQL connection pool
+/// - `expr`: The condition, built from the column constants generated by the `Columns` derive
+///
+/// ## Return Value
+/// - `Result<Vec<R>, QueryError<WhereError>>`: On success, returns the matching records; on failure, returns QueryError
+///
+/// Like the `Queryable` queries of the model, rows marked by `#[soft_delete]` are skipped, and
+/// a `#[tenant_scoped]` model needs the condition to be restricted with
+/// `TenantContext::filter`; otherwise the query is not run and fails with
+/// `WhereError::MissingTenant`.
+///
+/// ## Example Usage
+///
+/// ```rust
+/// use parsql_deadpool_postgres::traits::*;
+/// use parsql_deadpool_postgres::*;
+///
+/// #[derive(Columns, FromRow, Debug)]
+/// #[table("users")]
+/// #[soft_delete]
+/// pub struct User {
+///     pub id: i64,
+///     pub email: String,
+///     pub state: i16,
+/// }
+///
+/// async fn active_users(pool: &Pool) -> Result<Vec<User>, QueryError<WhereError>> {
+///     let expr = users::state.eq(1).and(users::email.ilike("%@example.com"));
+///     fetch_all_where::<User>(pool, expr).await
+/// }
+/// # fn main() {}
+/// ```
+pub async fn fetch_all_where<R>(pool: &Pool, expr: Expr) -> Result<Vec<R>, QueryError<WhereError>>
+where
+    R: Columns + FromRow,
+{
+    let (sql, params) = expr.select::<R>().map_err(WhereError::from)?;
+    let client = Checkout::get(pool).await?;
+
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params).await, |rows| rows.len() as u64)?;
+    Ok(rows.iter().map(R::from_row).collect::<Result<_, Error>>()?)
+}
+
+/// # fetch_all_fragment
+///
+/// Retrieves all records returned by a composed query.
+///
+/// ## Parameters
+/// - `pool`: Deadpool PostgreSQL connection pool
+/// - `fragment`: The query, usually built from `Queryable` structs with `SqlQuery::fragment`
+///
+/// ## Return Value
+/// - `Result<Vec<R>, Error>`: On success, returns the records; on failure, returns Error
+///
+/// ## Example Usage
+/// ```rust,no_run
+/// use deadpool_postgres::{Config, Runtime};
+/// use tokio_postgres::NoTls;
+/// use parsql_deadpool_postgres::traits::*;
+/// use parsql_deadpool_postgres::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("users")]
+/// #[select("id, name")]
+/// #[where_clause("state = $")]
+/// #[result_type("UserSummary")]
+/// struct ActiveUsers {
+///     state: i32,
+/// }
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("admins")]
+/// #[select("id, name")]
+/// #[where_clause("level >= $")]
+/// #[result_type("UserSummary")]
+/// struct Admins {
+///     level: i32,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct UserSummary {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+///
+/// let active = ActiveUsers { state: 1 };
+/// let admins = Admins { level: 3 };
+/// let query = active.fragment().union_all(admins.fragment());
+/// let users: Vec<UserSummary> = fetch_all_fragment(&pool, &query).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn fetch_all_fragment<R>(pool: &Pool, fragment: &Fragment<'_>) -> Result<Vec<R>, QueryError>
+where
+    R: FromRow,
+{
+    let client = Checkout::get(pool).await?;
+    let (sql, params) = fragment.build();
+
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params).await, |rows| rows.len() as u64)?;
+    Ok(rows.iter().map(R::from_row).collect::<Result<_, Error>>()?)
+}
+
 // Deprecated functions for backward compatibility
 #[deprecated(
     since = "0.2.0",
@@ -631,7 +935,7 @@ where
 pub async fn get<T: SqlQuery<T> + FromRow + SqlParams>(
     pool: &Pool,
     params: &T,
-) -> Result<T, Error> {
+) -> Result<T, QueryError> {
     fetch(pool, params).await
 }
 
@@ -642,90 +946,92 @@ pub async fn get<T: SqlQuery<T> + FromRow + SqlParams>(
 pub async fn get_all<T: SqlQuery<T> + FromRow + SqlParams>(
     pool: &Pool,
     params: &T,
-) -> Result<Vec<T>, Error> {
+) -> Result<Vec<T>, QueryError> {
     fetch_all(pool, params).await
 }
 
 /// CrudOps trait implementation for deadpool_postgres::Client
 #[async_trait::async_trait]
 impl CrudOps for Pool {
-    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, Error>
+    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let row = client.query_one(&sql, &params).await?;
-        row.try_get::<_, P>(0)
+        let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+        let row = observation.finish(client.query_one(&sql, &params).await, |_| 1)?;
+        Ok(row.try_get::<_, P>(0)?)
     }
 
-    async fn update<T>(&self, entity: T) -> Result<u64, Error>
+    async fn update<T>(&self, entity: T) -> Result<u64, QueryError>
     where
         T: SqlCommand + UpdateParams + Send + Sync,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        client.execute(&sql, &params).await
+        let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+        Ok(observation.finish(client.execute(&sql, &params).await, |rows| *rows)?)
     }
 
-    async fn delete<T>(&self, entity: T) -> Result<u64, Error>
+    async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        client.execute(&sql, &params).await
+        let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+        Ok(observation.finish(client.execute(&sql, &params).await, |rows| *rows)?)
+    }
+
+    async fn restore<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        restore(self, entity).await
+    }
+
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        force_delete(self, entity).await
     }
 
-    async fn fetch<P, R>(&self, params: &P) -> Result<R, Error>
+    async fn fetch<P, R>(&self, params: &P) -> Result<R, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync,
         R: FromRow + Send + Sync,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
         let sql = P::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let row = client.query_one(&sql, &query_params).await?;
-        R::from_row(&row)
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let row = observation.finish(client.query_one(&sql, &query_params).await, |_| 1)?;
+        Ok(R::from_row(&row)?)
     }
 
-    async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, Error>
+    async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync,
         R: FromRow + Send + Sync,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
         let sql = P::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let rows = client.query(&sql, &query_params).await?;
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let rows = observation.finish(
+            client.query(&sql, &query_params).await,
+            |rows| rows.len() as u64,
+        )?;
 
         let mut results = Vec::with_capacity(rows.len());
         for row in rows {
@@ -735,37 +1041,31 @@ impl CrudOps for Pool {
         Ok(results)
     }
 
-    async fn select<T, R, F>(&self, entity: T, to_model: F) -> Result<R, Error>
+    async fn select<T, R, F>(&self, entity: T, to_model: F) -> Result<R, QueryError>
     where
         T: SqlQuery<T> + SqlParams + Send + Sync,
         F: FnOnce(&Row) -> Result<R, Error> + Send + Sync,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let row = client.query_one(&sql, &params).await?;
-        to_model(&row)
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+        let row = observation.finish(client.query_one(&sql, &params).await, |_| 1)?;
+        Ok(to_model(&row)?)
     }
 
-    async fn select_all<T, R, F>(&self, entity: T, to_model: F) -> Result<Vec<R>, Error>
+    async fn select_all<T, R, F>(&self, entity: T, to_model: F) -> Result<Vec<R>, QueryError>
     where
         T: SqlQuery<T> + SqlParams + Send + Sync,
         F: Fn(&Row) -> R + Send + Sync,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let rows = client.query(&sql, &params).await?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+        let rows = observation.finish(client.query(&sql, &params).await, |rows| rows.len() as u64)?;
 
         let mut results = Vec::with_capacity(rows.len());
         for row in rows {
@@ -774,4 +1074,26 @@ impl CrudOps for Pool {
 
         Ok(results)
     }
+
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), QueryError>
+    where
+        R: Relation<C> + Send + Sync,
+        C: FromRow + Clone + Send + Sync,
+    {
+        preload(self, records).await
+    }
+
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, QueryError<WhereError>>
+    where
+        R: Columns + FromRow + Send + Sync,
+    {
+        fetch_all_where(self, expr).await
+    }
+
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, QueryError>
+    where
+        R: FromRow + Send + Sync,
+    {
+        fetch_all_fragment(self, fragment).await
+    }
 }
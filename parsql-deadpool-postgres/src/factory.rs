@@ -0,0 +1,101 @@
+//! Seed data factories for tests.
+//!
+//! `#[derive(Factory)]` on an `Insertable` struct generates `<Struct>Factory`, which
+//! implements [`Factory`]: every entity it builds gets the next sequence number of the
+//! factory, so defaults such as `name-1`, `name-2` stay unique, and `with_<field>` sets a
+//! field for all of them. [`FactoryExt`] inserts the entities with [`CrudOps::insert`], so
+//! `create` returns the `#[returning]` column, which associates the rows of another factory
+//! with it:
+//!
+//! ```rust,no_run
+//! use parsql_deadpool_postgres::traits::{CrudOps, SqlCommand, SqlParams};
+//! use parsql_deadpool_postgres::*;
+//! use deadpool_postgres::{Config, Runtime};
+//! use tokio_postgres::NoTls;
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("users")]
+//! #[returning("id")]
+//! #[factory(name = "UserFactory")]
+//! struct InsertUser {
+//!     name: String,
+//!     #[factory(sequence = "user{n}@example.com")]
+//!     email: String,
+//! }
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("posts")]
+//! #[returning("id")]
+//! #[factory(name = "PostFactory")]
+//! struct InsertPost {
+//!     user_id: i64,
+//!     title: String,
+//! }
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let pool = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+//! let user_id: i64 = UserFactory::new().with_name("Ada").create(&pool).await?;
+//! let post_ids: Vec<i64> = PostFactory::new()
+//!     .with_user_id(user_id)
+//!     .create_many(&pool, 3)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use futures_util::future::join_all;
+use tokio_postgres::types::FromSql;
+
+use crate::error::QueryError;
+use crate::traits::{CrudOps, SqlCommand, SqlParams};
+
+pub use parsql_core::factory::Factory;
+
+/// Inserts the entities of a [`Factory`]; implemented for every factory of `Insertable`
+/// entities.
+#[async_trait::async_trait]
+pub trait FactoryExt: Factory + Sync {
+    /// Inserts the next entity and returns its `#[returning]` column.
+    async fn create<C, P>(&self, conn: &C) -> Result<P, QueryError>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync;
+
+    /// Inserts `count` entities and returns their `#[returning]` columns, in order.
+    ///
+    /// The inserts are sent together: pipelined on one connection for a client or transaction,
+    /// spread over the connections of a pool. They are not wrapped in a transaction: the first
+    /// error is returned but the other rows stay in place, so pass a transaction to insert
+    /// them atomically.
+    async fn create_many<C, P>(&self, conn: &C, count: usize) -> Result<Vec<P>, QueryError>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync;
+}
+
+#[async_trait::async_trait]
+impl<F> FactoryExt for F
+where
+    F: Factory + Sync + ?Sized,
+    F::Entity: SqlCommand + SqlParams + Send + Sync,
+{
+    async fn create<C, P>(&self, conn: &C) -> Result<P, QueryError>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        conn.insert(self.build()).await
+    }
+
+    async fn create_many<C, P>(&self, conn: &C, count: usize) -> Result<Vec<P>, QueryError>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        let inserts = self
+            .build_many(count)
+            .into_iter()
+            .map(|entity| conn.insert(entity));
+        join_all(inserts).await.into_iter().collect()
+    }
+}
@@ -14,6 +14,42 @@ pub use transactional_ops as transactional;
 // CRUD işlemleri için modül
 mod crud_ops;
 
+// Güncelleme hataları (iyimser kilitleme) için modül
+pub mod error;
+
+// Tipli WHERE ifadeleri için modül
+pub mod expr;
+
+// Sorgu birleştirme (CTE, alt sorgu, UNION) için modül
+pub mod compose;
+
+// Çok kiracılı (multi-tenant) satır kapsamı için modül
+pub mod tenant;
+
+// Sorgu gözlemcileri (enstrümantasyon) için modül
+pub mod observer;
+
+// Sorgu istatistikleri ve yavaş sorgu günlüğü için modül
+pub use parsql_core::stats;
+
+// Satır düzeyinde güvenlik (RLS) oturum ayarları için modül
+pub mod rls;
+
+// Okuma replikalarına yönlendirme için modül
+pub mod routing;
+
+// Geçici bağlantı hatalarında yeniden deneme için modül
+pub mod retry;
+
+// Sorgu zaman aşımı ve iptali için modül
+pub mod timeout;
+
+// Tek bağlantıda ardışık (pipelined) toplu sorgular için modül
+pub mod batch;
+
+// Test verisi üreten fabrikalar için modül
+pub mod factory;
+
 // Pool extension işlemleri için modül
 pub mod pool_extensions;
 pub mod transaction_extensions;
@@ -22,15 +58,32 @@ pub mod transaction_extensions;
 pub use crud_ops::{
     insert,
     update,
+    update_versioned,
     delete,
+    restore,
+    force_delete,
     fetch,
     fetch_all,
     get,
     get_all,
     select,
-    select_all
+    select_all,
+    preload,
+    fetch_all_where,
+    fetch_all_fragment
 };
 
+pub use batch::Batch;
+pub use compose::Fragment;
+pub use error::{QueryError, UpdateError, WhereError};
+pub use factory::{Factory, FactoryExt};
+pub use observer::{set_observer, QueryEvent, QueryObserver};
+pub use stats::QueryStats;
+pub use routing::RoutedPool;
+pub use retry::{RetryPolicy, Retrying};
+pub use tenant::{Scoped, TenantContext};
+pub use expr::{Column, Expr, IntoValue};
+
 // Deadpool-postgres türlerini dışa aktar
 pub use deadpool_postgres::{Pool, Client as PoolClient, PoolError, Transaction};
 
@@ -1,5 +1,5 @@
 pub use crate::traits::SqlCommand;
 pub use parsql_macros::{
-    Deletable, FromRowPostgres as FromRow, Insertable, Queryable, SqlParams, UpdateParams,
-    Updateable,
+    query, Columns, Deletable, Factory, FromRowPostgres as FromRow, Insertable, Queryable,
+    SqlParams, UpdateParams, Updateable,
 };
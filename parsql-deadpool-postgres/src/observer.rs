@@ -0,0 +1,86 @@
+//! Query instrumentation.
+//!
+//! Every CRUD function reports the statement it executed to the registered
+//! [`QueryObserver`]: the SQL, the number of parameters, optionally their values, the
+//! duration, the number of rows and the error, if any.
+//!
+//! ```rust
+//! use parsql_deadpool_postgres::observer::{set_observer, ParamCapture, QueryEvent, QueryObserver};
+//!
+//! struct SlowQueries;
+//!
+//! impl QueryObserver for SlowQueries {
+//!     fn on_query(&self, event: &QueryEvent<'_>) {
+//!         if event.duration.as_millis() > 100 {
+//!             eprintln!("slow {}: {} {:?}", event.kind.as_str(), event.sql, event.params);
+//!         }
+//!     }
+//!
+//!     fn param_capture(&self) -> ParamCapture {
+//!         ParamCapture::Redacted
+//!     }
+//! }
+//!
+//! set_observer(SlowQueries);
+//! ```
+//!
+//! Register [`PrintObserver`] to print every statement to stdout. With the `tracing`
+//! feature, [`TracingObserver`] emits a span per statement.
+//!
+//! These types live in [`parsql_core::observer`]; the registered observer receives the
+//! statements of every parsql backend, with [`QueryEvent::system`] set to `"postgresql"`.
+
+use parsql_core::observer::format_debug;
+use postgres::types::ToSql;
+use std::error::Error as StdError;
+
+pub use parsql_core::observer::{
+    clear_observer, set_observer, ParamCapture, PrintObserver, QueryEvent, QueryKind,
+    QueryObserver,
+};
+#[cfg(feature = "tracing")]
+pub use parsql_core::observer::TracingObserver;
+
+/// A statement being executed; reports it to the observer when finished.
+pub(crate) struct Observation<'a>(parsql_core::observer::Observation<'a>);
+
+impl<'a> Observation<'a> {
+    /// Starts observing a statement generated for `E`.
+    pub(crate) fn start<E: ?Sized>(
+        kind: QueryKind,
+        sql: &'a str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Self {
+        Self::start_named(kind, std::any::type_name::<E>(), sql, params)
+    }
+
+    /// Starts observing a statement generated for the type named `entity`.
+    pub(crate) fn start_named(
+        kind: QueryKind,
+        entity: &'static str,
+        sql: &'a str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Self {
+        Observation(parsql_core::observer::Observation::start(
+            "postgresql",
+            kind,
+            entity,
+            sql,
+            params,
+            |param, redact| format_debug(*param, redact),
+        ))
+    }
+
+    /// Reports the result of the statement and passes it through. `rows` returns the number
+    /// of affected or returned rows of a successful result.
+    pub(crate) fn finish<T, E>(
+        self,
+        result: Result<T, E>,
+        rows: impl FnOnce(&T) -> u64,
+    ) -> Result<T, E>
+    where
+        E: StdError + 'static,
+    {
+        self.0.finish(result, rows)
+    }
+}
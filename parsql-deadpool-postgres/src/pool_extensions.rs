@@ -1,47 +1,36 @@
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
-use deadpool_postgres::{GenericClient, Pool};
+use crate::error::QueryError;
+use crate::observer::{Observation, QueryKind};
+use crate::timeout::Checkout;
+use crate::traits::{FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use deadpool_postgres::Pool;
 use postgres::types::FromSql;
-use std::sync::OnceLock;
-use tokio_postgres::{Error, Row};
-
-// Daha basit bir yaklaşım: PoolError'dan genel bir Error oluştur
-fn pool_err_to_io_err(e: deadpool_postgres::PoolError) -> Error {
-    // Bu özel fonksiyon tokio_postgres'in sağladığı timeout hatasını döndürür
-    // Güzel bir çözüm değil, ama çalışır bir örnek için kullanılabilir
-    let err = Error::__private_api_timeout();
-
-    // Debug süreci için stderr'e hatayı yazdıralım
-    eprintln!("Pool bağlantı hatası: {}", e);
-
-    err
-}
 
 /// Pool extension trait for additional query operations
 #[async_trait::async_trait]
 pub trait PoolExtensions {
     /// Inserts a new record into the database
-    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, Error>
+    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync + 'static;
 
     /// Updates an existing record in the database
-    async fn update<T>(&self, entity: T) -> Result<bool, Error>
+    async fn update<T>(&self, entity: T) -> Result<bool, QueryError>
     where
         T: SqlCommand + UpdateParams + Send + Sync + 'static;
 
     /// Deletes a record from the database
-    async fn delete<T>(&self, entity: T) -> Result<u64, Error>
+    async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync + 'static;
 
     /// Retrieves a single record from the database
-    async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
+    async fn fetch<P, R>(&self, params: P) -> Result<R, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
         R: FromRow + Send + Sync + 'static;
 
     /// Retrieves multiple records from the database
-    async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, Error>
+    async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
         R: FromRow + Send + Sync + 'static;
@@ -49,109 +38,77 @@ pub trait PoolExtensions {
 
 #[async_trait::async_trait]
 impl PoolExtensions for Pool {
-    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, Error>
+    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync + 'static,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
 
         let sql = T::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let row = client.query_one(&sql, &params).await?;
-        row.try_get::<_, P>(0)
+        let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+        let row = observation.finish(client.query_one(&sql, &params).await, |_| 1)?;
+        Ok(row.try_get::<_, P>(0)?)
     }
 
-    async fn update<T>(&self, entity: T) -> Result<bool, Error>
+    async fn update<T>(&self, entity: T) -> Result<bool, QueryError>
     where
         T: SqlCommand + UpdateParams + Send + Sync + 'static,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
 
         let sql = T::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let result = client.execute(&sql, &params).await?;
+        let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+        let result = observation.finish(client.execute(&sql, &params).await, |rows| *rows)?;
         Ok(result > 0)
     }
 
-    async fn delete<T>(&self, entity: T) -> Result<u64, Error>
+    async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync + 'static,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
 
         let sql = T::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        client.execute(&sql, &params).await
+        let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+        Ok(observation.finish(client.execute(&sql, &params).await, |rows| *rows)?)
     }
 
-    async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
+    async fn fetch<P, R>(&self, params: P) -> Result<R, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
         R: FromRow + Send + Sync + 'static,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
 
         let sql = P::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let row = client.query_one(&sql, &query_params).await?;
-        R::from_row(&row)
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let row = observation.finish(client.query_one(&sql, &query_params).await, |_| 1)?;
+        Ok(R::from_row(&row)?)
     }
 
-    async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, Error>
+    async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
         R: FromRow + Send + Sync + 'static,
     {
-        let client = self.get().await.map_err(pool_err_to_io_err)?;
+        let client = Checkout::get(self).await?;
 
         let sql = P::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let rows = client.query(&sql, &query_params).await?;
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let rows = observation.finish(
+            client.query(&sql, &query_params).await,
+            |rows| rows.len() as u64,
+        )?;
 
         let mut results = Vec::with_capacity(rows.len());
         for row in rows {
@@ -0,0 +1,332 @@
+//! Retrying reads on transient connection errors.
+//!
+//! During a failover every in-flight query fails with a closed connection or an
+//! `admin_shutdown` error, and new connections are refused for a moment. [`RetryPolicy`]
+//! retries such calls with exponential backoff, jitter and a cap. Only idempotent calls are
+//! retried: [`Retrying`] retries the reads of the pool it wraps and passes writes through,
+//! and [`run`] retries any call the caller marks as safe to repeat.
+//!
+//! ```rust,no_run
+//! # use deadpool_postgres::{Config, Runtime};
+//! # use tokio_postgres::NoTls;
+//! use parsql_deadpool_postgres::retry::{self, RetryPolicy, Retrying};
+//! use parsql_deadpool_postgres::traits::CrudOps;
+//! # use parsql_deadpool_postgres::traits::{
+//! #     FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+//! # };
+//! # use parsql_deadpool_postgres::*;
+//! # use std::time::Duration;
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams)]
+//! # #[table("users")]
+//! # #[where_clause("id = $")]
+//! # struct User { id: i64, name: String }
+//! #
+//! # #[derive(Updateable, UpdateParams)]
+//! # #[table("users")]
+//! # #[update("name")]
+//! # #[where_clause("id = $")]
+//! # struct RenameUser { id: i64, name: String }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! # let pool = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+//! let policy = RetryPolicy::new()
+//!     .max_retries(5)
+//!     .max_delay(Duration::from_secs(1));
+//! let db = Retrying::new(pool, policy.clone());
+//!
+//! // Retried on connection errors
+//! let user: User = db.fetch(&User { id: 1, name: String::new() }).await?;
+//!
+//! // Not retried: the update may have been applied before the connection dropped
+//! db.update(RenameUser { id: 1, name: "Ada".into() }).await?;
+//!
+//! // Retried, because the caller knows that repeating it is harmless
+//! retry::run(&policy, || db.inner().update(RenameUser { id: 1, name: "Ada".into() })).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::error::Error as StdError;
+use std::future::Future;
+
+use parsql_core::retry::{find_source, is_retryable_state};
+use postgres::types::FromSql;
+use tokio_postgres::{Error, Row};
+
+use crate::compose::Fragment;
+use crate::error::{QueryError, WhereError};
+use crate::expr::Expr;
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+};
+
+pub use parsql_core::retry::RetryPolicy;
+
+/// Returns whether an error is a transient connection error worth retrying.
+pub fn is_retryable(error: &Error) -> bool {
+    if error.is_closed() {
+        return true;
+    }
+    if let Some(code) = error.code() {
+        return is_retryable_state(code.code());
+    }
+    // Connection refused or reset while (re)connecting
+    error
+        .source()
+        .is_some_and(|source| source.is::<std::io::Error>())
+}
+
+/// Runs `op`, running it again with `policy` while it fails with a retryable error.
+///
+/// `op` must be safe to repeat: a write may have been applied before its connection
+/// was lost.
+pub async fn run<T, E, F, Fut>(policy: &RetryPolicy, op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: StdError + 'static,
+{
+    policy
+        .run(op, |err| is_retryable_chain(err), tokio::time::sleep)
+        .await
+}
+
+/// Finds the database error in the source chain of `error`, e.g. inside an `UpdateError`.
+fn is_retryable_chain(error: &(dyn StdError + 'static)) -> bool {
+    find_source::<Error>(error).is_some_and(is_retryable)
+}
+
+/// Wraps a `Pool` or [`RoutedPool`](crate::routing::RoutedPool) and retries its reads.
+///
+/// `fetch`, `fetch_all`, `preload` and `fetch_all_fragment` are retried. `select`,
+/// `select_all` and `fetch_all_where` take their arguments by value and run once, like the
+/// writes; use [`run`] for them.
+pub struct Retrying<C> {
+    inner: C,
+    policy: RetryPolicy,
+}
+
+impl<C> Retrying<C> {
+    /// Wraps `inner` with the given policy.
+    pub fn new(inner: C, policy: RetryPolicy) -> Self {
+        Retrying { inner, policy }
+    }
+
+    /// Returns the wrapped pool, whose calls are not retried.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the policy.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+}
+
+#[async_trait::async_trait]
+impl<C: CrudOps + Send + Sync> CrudOps for Retrying<C> {
+    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync,
+    {
+        self.inner.insert(entity).await
+    }
+
+    async fn update<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SqlCommand + UpdateParams + Send + Sync,
+    {
+        self.inner.update(entity).await
+    }
+
+    async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync,
+    {
+        self.inner.delete(entity).await
+    }
+
+    async fn restore<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        self.inner.restore(entity).await
+    }
+
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        self.inner.force_delete(entity).await
+    }
+
+    async fn fetch<P, R>(&self, params: &P) -> Result<R, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync,
+        R: FromRow + Send + Sync,
+    {
+        run(&self.policy, || self.inner.fetch(params)).await
+    }
+
+    async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync,
+        R: FromRow + Send + Sync,
+    {
+        run(&self.policy, || self.inner.fetch_all(params)).await
+    }
+
+    async fn select<T, R, F>(&self, entity: T, to_model: F) -> Result<R, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync,
+        F: FnOnce(&Row) -> Result<R, Error> + Send + Sync,
+    {
+        self.inner.select(entity, to_model).await
+    }
+
+    async fn select_all<T, R, F>(&self, entity: T, to_model: F) -> Result<Vec<R>, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync,
+        F: Fn(&Row) -> R + Send + Sync,
+    {
+        self.inner.select_all(entity, to_model).await
+    }
+
+    async fn preload<R, C2>(&self, records: &mut [R]) -> Result<(), QueryError>
+    where
+        R: Relation<C2> + Send + Sync,
+        C2: FromRow + Clone + Send + Sync,
+    {
+        let mut retry = 0;
+        loop {
+            match self.inner.preload(&mut *records).await {
+                Err(err) if is_retryable_chain(&err) => match self.policy.retry_delay(retry) {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        retry += 1;
+                    }
+                    None => return Err(err),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, QueryError<WhereError>>
+    where
+        R: Columns + FromRow + Send + Sync,
+    {
+        self.inner.fetch_all_where(expr).await
+    }
+
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, QueryError>
+    where
+        R: FromRow + Send + Sync,
+    {
+        run(&self.policy, || self.inner.fetch_all_fragment(fragment)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use deadpool_postgres::{Manager, Pool, Runtime};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use tokio_postgres::NoTls;
+
+    #[derive(Debug, Queryable, FromRow, SqlParams)]
+    #[table("users")]
+    #[where_clause("id = $")]
+    struct User {
+        id: i64,
+    }
+
+    /// A pool whose connections are refused.
+    fn unreachable() -> Pool {
+        let mut config = tokio_postgres::Config::new();
+        config.host("127.0.0.1").port(1).user("postgres");
+        Pool::builder(Manager::new(config, NoTls))
+            .max_size(1)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap()
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new().max_retries(2).base_delay(Duration::ZERO)
+    }
+
+    #[tokio::test]
+    async fn test_refused_connection_is_retried_up_to_max_retries() {
+        let pool = unreachable();
+        let attempts = AtomicU32::new(0);
+
+        let result = run(&policy(), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            pool.fetch::<_, User>(&User { id: 1 })
+        })
+        .await;
+
+        assert!(matches!(result, Err(QueryError::Pool(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_closed_pool_is_not_retried() {
+        let pool = unreachable();
+        pool.close();
+        let attempts = AtomicU32::new(0);
+
+        let result = run(&policy(), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            pool.fetch::<_, User>(&User { id: 1 })
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retrying_passes_the_last_error_through() {
+        let db = Retrying::new(unreachable(), policy());
+
+        let err = db.fetch_all::<_, User>(&User { id: 1 }).await.unwrap_err();
+        let source = find_source::<Error>(&err).expect("database error in the chain");
+        assert!(is_retryable(source));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_constraint_violation_is_not_retryable() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let config: tokio_postgres::Config = url.parse().unwrap();
+        let pool = Pool::builder(Manager::new(config, NoTls))
+            .max_size(1)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap();
+        let client = pool.get().await.unwrap();
+        client
+            .batch_execute("CREATE TEMP TABLE retry_keys (id int PRIMARY KEY)")
+            .await
+            .unwrap();
+        let attempts = AtomicU32::new(0);
+
+        let insert = "INSERT INTO retry_keys VALUES (1)";
+        client.execute(insert, &[]).await.unwrap();
+        let err = run(&policy(), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            client.execute(insert, &[])
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.code(), Some(&tokio_postgres::error::SqlState::UNIQUE_VIOLATION));
+        assert!(!is_retryable(&err));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
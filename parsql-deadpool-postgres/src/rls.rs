@@ -0,0 +1,141 @@
+//! Session settings for PostgreSQL row-level security.
+//!
+//! RLS policies usually read the current user from a session variable
+//! (`current_setting('app.user_id')`) or rely on the current role. [`SessionSettings`]
+//! collects these values and [`begin_with_session`] applies them at the start of a
+//! transaction with `SET LOCAL ROLE` and `set_config(name, value, true)`.
+//!
+//! Both are transaction-local: PostgreSQL resets them on commit or rollback, including the
+//! rollback sent when a `Transaction` is dropped, so a connection never returns to the
+//! `Pool` with the settings of a previous request.
+//!
+//! ```rust,no_run
+//! # use deadpool_postgres::{Config, Runtime};
+//! # use tokio_postgres::NoTls;
+//! use parsql_deadpool_postgres::rls::{with_session, SessionSettings};
+//! use parsql_deadpool_postgres::traits::TransactionOps;
+//! # use parsql_deadpool_postgres::traits::{FromRow, SqlParams, SqlQuery};
+//! # use parsql_deadpool_postgres::*;
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams, Debug, Clone)]
+//! # #[table("documents")]
+//! # #[where_clause("id > $")]
+//! # struct Document { id: i64, title: String }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! # let pool = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+//! let settings = SessionSettings::new()
+//!     .role("app_user")
+//!     .set("app.user_id", 42);
+//!
+//! let documents: Vec<Document> = with_session(&pool, &settings, |tx| {
+//!     Box::pin(async move {
+//!         let documents = tx.tx_fetch_all(&Document { id: 0, title: String::new() }).await?;
+//!         Ok::<_, Box<dyn std::error::Error + Send + Sync>>(documents)
+//!     })
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use deadpool_postgres::{Client, Pool, PoolError, Transaction};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Error;
+
+pub use parsql_core::rls::SessionSettings;
+
+/// Applies the session settings to an open transaction.
+pub async fn apply(transaction: &Transaction<'_>, settings: &SessionSettings) -> Result<(), Error> {
+    for (sql, params) in settings.statements() {
+        let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|param| param as _).collect();
+        transaction.execute(&sql, &params).await?;
+    }
+    Ok(())
+}
+
+/// Begins a transaction and applies the session settings to it.
+///
+/// # Return Value
+/// * `Result<Transaction<'_>, Error>` - On success, returns the transaction; on failure, returns Error
+pub async fn begin_with_session<'a>(
+    client: &'a mut Client,
+    settings: &SessionSettings,
+) -> Result<Transaction<'a>, Error> {
+    let transaction = client.transaction().await?;
+
+    apply(&transaction, settings).await?;
+    Ok(transaction)
+}
+
+/// Checks out a connection from the pool and runs `f` in a transaction with the session
+/// settings applied.
+///
+/// The transaction is committed when `f` succeeds and rolled back otherwise, before the
+/// connection is returned to the pool.
+///
+/// # Return Value
+/// * `Result<R, E>` - The value returned by `f`, or the first error
+pub async fn with_session<F, R, E>(
+    pool: &Pool,
+    settings: &SessionSettings,
+    f: F,
+) -> Result<R, E>
+where
+    F: for<'t> FnOnce(
+        &'t Transaction<'t>,
+    ) -> Pin<Box<dyn Future<Output = Result<R, E>> + Send + 't>>,
+    E: From<Error> + From<PoolError>,
+{
+    let mut client = pool.get().await?;
+    let transaction = begin_with_session(&mut client, settings).await?;
+    match f(&transaction).await {
+        Ok(value) => {
+            transaction.commit().await?;
+            Ok(value)
+        }
+        Err(err) => {
+            // The error of `f` is kept; a failed rollback means the connection is closed
+            let _ = transaction.rollback().await;
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deadpool_postgres::{Manager, Runtime};
+    use tokio_postgres::NoTls;
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_settings_do_not_outlive_the_session() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = Manager::new(url.parse().unwrap(), NoTls);
+        let pool = Pool::builder(manager)
+            .max_size(1)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap();
+        let read = "SELECT current_setting('app.user_id', true)";
+
+        let settings = SessionSettings::new().set("app.user_id", 42);
+        let user_id: Option<String> = with_session(&pool, &settings, |tx| {
+            Box::pin(async move {
+                let row = tx.query_one(read, &[]).await?;
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(row.get(0))
+            })
+        })
+        .await
+        .unwrap();
+        assert_eq!(user_id.as_deref(), Some("42"));
+
+        // The same connection is reused without the variable
+        let client = pool.get().await.unwrap();
+        let user_id: Option<String> = client.query_one(read, &[]).await.unwrap().get(0);
+        assert!(user_id.is_none_or(|value| value.is_empty()));
+    }
+}
@@ -0,0 +1,439 @@
+//! Read-replica routing.
+//!
+//! [`RoutedPool`] wraps the `Pool` of a primary server and the pools of its replicas. Reads
+//! (`fetch`, `fetch_all`, `select`, `select_all`, `preload`, ...) go to the replicas in
+//! round-robin order; writes (`insert`, `update`, `delete`, `restore`, `force_delete`) go to
+//! the primary. Transactions are started on [`RoutedPool::primary`], which is also the
+//! override for reads that must see the caller's own writes.
+//!
+//! A replica is marked unhealthy by the reads sent to it: when it cannot hand out a
+//! connection within its pool's `wait` timeout, or the connection is closed during the query,
+//! it is skipped for [`RoutedPool::retry_after`] (30 seconds by default) and the read is sent
+//! to the primary instead. `select` and `select_all`, which take their arguments by value,
+//! return the replica's error instead of being run again. SQL errors do not affect the health
+//! of a replica. Reads go to the primary while no replica is healthy.
+//!
+//! Give the replica pools a `wait` timeout (`Timeouts::wait`), so that an exhausted or
+//! unreachable replica is given up quickly.
+//!
+//! ```rust,no_run
+//! # use deadpool_postgres::{Config, Runtime};
+//! # use tokio_postgres::NoTls;
+//! use parsql_deadpool_postgres::routing::RoutedPool;
+//! use parsql_deadpool_postgres::traits::CrudOps;
+//! # use parsql_deadpool_postgres::traits::{FromRow, SqlCommand, SqlParams, SqlQuery};
+//! # use parsql_deadpool_postgres::*;
+//! #
+//! # #[derive(Insertable, SqlParams)]
+//! # #[table("users")]
+//! # struct InsertUser { name: String }
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams)]
+//! # #[table("users")]
+//! # #[where_clause("name = $")]
+//! # struct User { id: i64, name: String }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! # let primary = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+//! # let replica_1 = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+//! # let replica_2 = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+//! let pool = RoutedPool::new(primary, vec![replica_1, replica_2]);
+//!
+//! // Sent to the primary
+//! let id: i64 = pool.insert(InsertUser { name: "Ada".into() }).await?;
+//!
+//! // Sent to a replica
+//! let users: Vec<User> = pool.fetch_all(&User { id: 0, name: "Ada".into() }).await?;
+//!
+//! // Read-your-writes: the replicas may not have the new row yet
+//! let user: User = pool.primary().fetch(&User { id, name: "Ada".into() }).await?;
+//!
+//! // Transactions always run on the primary
+//! let mut client = pool.primary().get().await?;
+//! let tx = client.transaction().await?;
+//! # tx.commit().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use deadpool_postgres::Pool;
+use postgres::types::FromSql;
+use tokio_postgres::{Error, Row};
+
+use crate::compose::Fragment;
+use crate::error::{QueryError, WhereError};
+use crate::expr::Expr;
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+};
+
+/// Default time an unhealthy replica is skipped for.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+struct Replica {
+    pool: Pool,
+    down_until: Mutex<Option<Instant>>,
+}
+
+impl Replica {
+    fn down_until(&self) -> MutexGuard<'_, Option<Instant>> {
+        // The health state stays usable after a panic in another thread
+        self.down_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn is_available(&self, now: Instant) -> bool {
+        self.down_until().is_none_or(|until| now >= until)
+    }
+
+    fn skip_for(&self, retry_after: Duration) {
+        *self.down_until() = Some(Instant::now() + retry_after);
+    }
+}
+
+/// Whether a failed read shows that the server cannot be used, rather than a SQL error.
+fn is_unavailable(error: &QueryError) -> bool {
+    match error {
+        QueryError::Timeout(_) | QueryError::Pool(_) => true,
+        QueryError::Query(error) => error.is_closed(),
+    }
+}
+
+/// A primary `Pool` with read replicas; implements [`CrudOps`] like `Pool`.
+pub struct RoutedPool {
+    primary: Pool,
+    replicas: Vec<Replica>,
+    next: AtomicUsize,
+    retry_after: Duration,
+}
+
+impl RoutedPool {
+    /// Creates a routed pool. Without replicas every query goes to the primary.
+    pub fn new(primary: Pool, replicas: Vec<Pool>) -> Self {
+        RoutedPool {
+            primary,
+            replicas: replicas
+                .into_iter()
+                .map(|pool| Replica {
+                    pool,
+                    down_until: Mutex::new(None),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+            retry_after: DEFAULT_RETRY_AFTER,
+        }
+    }
+
+    /// Sets how long a replica that could not be reached is skipped.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Returns the primary pool, for transactions and reads that must see recent writes.
+    pub fn primary(&self) -> &Pool {
+        &self.primary
+    }
+
+    /// Returns the number of replicas that are not being skipped.
+    pub fn healthy_replicas(&self) -> usize {
+        let now = Instant::now();
+        self.replicas
+            .iter()
+            .filter(|replica| replica.is_available(now))
+            .count()
+    }
+
+    /// Returns the pool the next read is sent to: the next healthy replica in round-robin
+    /// order, or the primary while every replica is skipped.
+    ///
+    /// Reads run on the returned pool do not update the health of the replica.
+    pub fn reader(&self) -> &Pool {
+        self.next_replica().map_or(&self.primary, |replica| &replica.pool)
+    }
+
+    fn next_replica(&self) -> Option<&Replica> {
+        let count = self.replicas.len();
+        if count == 0 {
+            return None;
+        }
+
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        (0..count)
+            .map(|offset| &self.replicas[(start + offset) % count])
+            .find(|replica| replica.is_available(now))
+    }
+
+    /// Runs a read on the next healthy replica. If the replica cannot be used, it is skipped
+    /// for `retry_after` and the read is run again on the primary.
+    async fn read<'a, T, F, Fut>(&'a self, query: F) -> Result<T, QueryError>
+    where
+        F: Fn(&'a Pool) -> Fut,
+        Fut: Future<Output = Result<T, QueryError>>,
+    {
+        if let Some(replica) = self.next_replica() {
+            match query(&replica.pool).await {
+                Err(error) if is_unavailable(&error) => replica.skip_for(self.retry_after),
+                result => return result,
+            }
+        }
+        query(&self.primary).await
+    }
+
+    /// Runs a read that cannot be repeated on the next healthy replica. If the replica cannot
+    /// be used, it is skipped for `retry_after` and the error is returned.
+    async fn read_once<'a, T, F, Fut>(&'a self, query: F) -> Result<T, QueryError>
+    where
+        F: FnOnce(&'a Pool) -> Fut,
+        Fut: Future<Output = Result<T, QueryError>>,
+    {
+        let Some(replica) = self.next_replica() else {
+            return query(&self.primary).await;
+        };
+        let result = query(&replica.pool).await;
+        if result.as_ref().is_err_and(is_unavailable) {
+            replica.skip_for(self.retry_after);
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait]
+impl CrudOps for RoutedPool {
+    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync,
+    {
+        self.primary.insert(entity).await
+    }
+
+    async fn update<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SqlCommand + UpdateParams + Send + Sync,
+    {
+        self.primary.update(entity).await
+    }
+
+    async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync,
+    {
+        self.primary.delete(entity).await
+    }
+
+    async fn restore<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        self.primary.restore(entity).await
+    }
+
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        self.primary.force_delete(entity).await
+    }
+
+    async fn fetch<P, R>(&self, params: &P) -> Result<R, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync,
+        R: FromRow + Send + Sync,
+    {
+        self.read(|pool| pool.fetch(params)).await
+    }
+
+    async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync,
+        R: FromRow + Send + Sync,
+    {
+        self.read(|pool| pool.fetch_all(params)).await
+    }
+
+    async fn select<T, R, F>(&self, entity: T, to_model: F) -> Result<R, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync,
+        F: FnOnce(&Row) -> Result<R, Error> + Send + Sync,
+    {
+        self.read_once(|pool| pool.select(entity, to_model)).await
+    }
+
+    async fn select_all<T, R, F>(&self, entity: T, to_model: F) -> Result<Vec<R>, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync,
+        F: Fn(&Row) -> R + Send + Sync,
+    {
+        self.read_once(|pool| pool.select_all(entity, to_model)).await
+    }
+
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), QueryError>
+    where
+        R: Relation<C> + Send + Sync,
+        C: FromRow + Clone + Send + Sync,
+    {
+        // Runs the read like `read`; `records` cannot be captured by a repeatable closure
+        if let Some(replica) = self.next_replica() {
+            match replica.pool.preload(records).await {
+                Err(error) if is_unavailable(&error) => replica.skip_for(self.retry_after),
+                result => return result,
+            }
+        }
+        self.primary.preload(records).await
+    }
+
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, QueryError<WhereError>>
+    where
+        R: Columns + FromRow + Send + Sync,
+    {
+        // Rendered once, so that the read can be repeated on the primary
+        let (sql, params) = expr.select::<R>().map_err(WhereError::from)?;
+        let fragment = Fragment::new(sql, params);
+        Ok(self.read(|pool| pool.fetch_all_fragment(&fragment)).await?)
+    }
+
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, QueryError>
+    where
+        R: FromRow + Send + Sync,
+    {
+        self.read(|pool| pool.fetch_all_fragment(fragment)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use deadpool_postgres::{Manager, PoolError, Runtime};
+    use tokio_postgres::NoTls;
+
+    #[derive(Queryable, FromRow, SqlParams, Columns)]
+    #[table("users")]
+    #[where_clause("id > $")]
+    struct User {
+        id: i64,
+    }
+
+    fn pool(config: tokio_postgres::Config, max_size: usize) -> Pool {
+        Pool::builder(Manager::new(config, NoTls))
+            .max_size(max_size)
+            .runtime(Runtime::Tokio1)
+            .wait_timeout(Some(Duration::from_millis(20)))
+            .build()
+            .unwrap()
+    }
+
+    /// A pool that answers every checkout with `PoolError::Closed`.
+    fn closed() -> Pool {
+        let pool = pool(tokio_postgres::Config::new(), 1);
+        pool.close();
+        pool
+    }
+
+    /// A pool that never hands out a connection; checkouts time out.
+    fn empty() -> Pool {
+        pool(tokio_postgres::Config::new(), 0)
+    }
+
+    /// A pool of a server that refuses connections.
+    fn unreachable() -> Pool {
+        let mut config = tokio_postgres::Config::new();
+        config.host("127.0.0.1").port(1).user("postgres");
+        pool(config, 1)
+    }
+
+    async fn read(pool: &RoutedPool) -> Result<Vec<User>, QueryError> {
+        pool.fetch_all_fragment(&Fragment::raw("SELECT 1::bigint AS id")).await
+    }
+
+    fn is_closed(result: Result<Vec<User>, QueryError>) -> bool {
+        matches!(result, Err(QueryError::Pool(PoolError::Closed)))
+    }
+
+    #[tokio::test]
+    async fn test_reads_go_to_the_primary_without_replicas() {
+        let pool = RoutedPool::new(closed(), Vec::new());
+
+        assert!(std::ptr::eq(pool.reader(), pool.primary()));
+        assert!(is_closed(read(&pool).await));
+    }
+
+    #[tokio::test]
+    async fn test_replicas_are_used_in_turn() {
+        let pool = RoutedPool::new(closed(), vec![empty(), empty()]);
+
+        let first = pool.reader() as *const Pool;
+        let second = pool.reader() as *const Pool;
+        assert_ne!(first, second);
+        assert_eq!(pool.reader() as *const Pool, first);
+        assert!(!std::ptr::eq(first, pool.primary()));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_replica_falls_back_to_the_primary() {
+        // The closed primary tells its answer apart from the replica's
+        let pool = RoutedPool::new(closed(), vec![unreachable()]);
+
+        assert!(is_closed(read(&pool).await));
+        assert_eq!(pool.healthy_replicas(), 0);
+        assert!(std::ptr::eq(pool.reader(), pool.primary()));
+    }
+
+    #[tokio::test]
+    async fn test_replica_checkout_timeout_falls_back_to_the_primary() {
+        let pool = RoutedPool::new(closed(), vec![empty()]);
+
+        assert!(is_closed(read(&pool).await));
+        assert_eq!(pool.healthy_replicas(), 0);
+
+        let result = pool.fetch_all_where::<User>(users::id.gt(0)).await;
+        assert!(matches!(result, Err(QueryError::Pool(PoolError::Closed))));
+    }
+
+    #[tokio::test]
+    async fn test_skipped_replica_is_used_again_after_retry_after() {
+        let pool = RoutedPool::new(closed(), vec![unreachable()])
+            .retry_after(Duration::from_millis(50));
+
+        assert!(is_closed(read(&pool).await));
+        assert_eq!(pool.healthy_replicas(), 0);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(pool.healthy_replicas(), 1);
+        assert!(!std::ptr::eq(pool.reader(), pool.primary()));
+    }
+
+    #[tokio::test]
+    async fn test_reads_that_cannot_be_repeated_return_the_replica_error() {
+        let pool = RoutedPool::new(closed(), vec![empty()]);
+
+        let result = pool.select(User { id: 0 }, |row| User::from_row(row)).await;
+        assert!(matches!(result, Err(QueryError::Timeout(_))));
+        assert_eq!(pool.healthy_replicas(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_sql_errors_keep_the_replica_healthy() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let server = || pool(url.parse().unwrap(), 1);
+
+        let pool = RoutedPool::new(server(), vec![unreachable()]);
+        assert_eq!(read(&pool).await.unwrap().len(), 1);
+        assert_eq!(pool.healthy_replicas(), 0);
+
+        let pool = RoutedPool::new(closed(), vec![server()]);
+        let result = pool
+            .fetch_all_fragment::<User>(&Fragment::raw("SELECT * FROM no_such_table"))
+            .await;
+        assert!(matches!(result, Err(QueryError::Query(_))));
+        assert_eq!(pool.healthy_replicas(), 1);
+    }
+}
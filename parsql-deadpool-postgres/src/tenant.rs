@@ -0,0 +1,223 @@
+//! Multi-tenant row scoping.
+//!
+//! Models marked with `#[tenant_scoped(column = "tenant_id")]` get a `tenant_id = $n` predicate
+//! in their generated SELECT, UPDATE and DELETE statements, and the tenant column in their
+//! INSERT statement. The tenant value itself comes from a [`TenantContext`], which wraps a
+//! model with [`TenantContext::scope`] before it is passed to the CRUD functions:
+//!
+//! ```rust
+//! use parsql_deadpool_postgres::traits::*;
+//! use parsql_deadpool_postgres::*;
+//!
+//! #[derive(Queryable, FromRow, SqlParams)]
+//! #[table("projects")]
+//! #[where_clause("name = $")]
+//! #[tenant_scoped]
+//! struct Project {
+//!     id: i64,
+//!     name: String,
+//! }
+//!
+//! let acme = TenantContext::new(1_i64);
+//! let query = acme.scope(Project { id: 0, name: "Website".into() });
+//! assert_eq!(
+//!     <Scoped<Project> as SqlQuery<Project>>::query(),
+//!     "SELECT id, name FROM projects WHERE (name = $1) AND tenant_id = $2"
+//! );
+//! assert_eq!(query.params().len(), 2);
+//! // let projects: Vec<Project> = fetch_all(&pool, &query).await?;
+//! ```
+//!
+//! The tenant placeholder is part of the generated SQL, so running a scoped model without a
+//! context fails with a parameter count error instead of reading other tenants' rows.
+//! `fetch_all_where` on a tenant-scoped model fails with `WhereError::MissingTenant` unless its
+//! condition was restricted with [`TenantContext::filter`].
+
+use tokio_postgres::types::ToSql;
+
+use crate::expr::Postgres;
+use crate::traits::{
+    SoftDelete, SoftDeleteQuery, SqlCommand, SqlParams, SqlQuery, TenantScoped, UpdateParams,
+    Versioned,
+};
+
+/// The tenant of the current request.
+///
+/// Cloning is cheap; the tenant value is shared between clones.
+pub type TenantContext = parsql_core::tenant::TenantContext<Postgres>;
+
+/// A tenant-scoped model together with its tenant, created by [`TenantContext::scope`].
+///
+/// Generates the same SQL as the wrapped model and adds the tenant value to its parameters.
+pub type Scoped<T> = parsql_core::tenant::Scoped<T, Postgres>;
+
+impl<T, R> SqlQuery<R> for Scoped<T>
+where
+    T: SqlQuery<R> + TenantScoped,
+{
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: SqlCommand + TenantScoped> SqlCommand for Scoped<T> {
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: Versioned + TenantScoped> Versioned for Scoped<T> {
+    fn version_column() -> &'static str {
+        T::version_column()
+    }
+}
+
+impl<T: SoftDelete + TenantScoped> SoftDelete for Scoped<T> {
+    fn restore_query() -> String {
+        T::restore_query()
+    }
+
+    fn force_delete_query() -> String {
+        T::force_delete_query()
+    }
+}
+
+impl<T: SoftDeleteQuery + TenantScoped> SoftDeleteQuery for Scoped<T> {
+    fn query_with_deleted() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams + TenantScoped> SqlParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+impl<T: UpdateParams + TenantScoped> UpdateParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use deadpool_postgres::{Manager, Pool, Runtime};
+    use tokio_postgres::NoTls;
+
+    use crate::error::{QueryError, WhereError};
+    use crate::expr::Column;
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams)]
+    #[table("projects")]
+    #[returning("id")]
+    #[tenant_scoped]
+    struct InsertProject {
+        name: String,
+    }
+
+    #[derive(Queryable, FromRow, SqlParams, Columns, Debug)]
+    #[table("projects")]
+    #[where_clause("id > $")]
+    #[tenant_scoped]
+    struct Project {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("projects")]
+    #[update("name")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct RenameProject {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("projects")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct DeleteProject {
+        id: i64,
+    }
+
+    fn pool(config: tokio_postgres::Config) -> Pool {
+        Pool::builder(Manager::new(config, NoTls))
+            .max_size(1)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap()
+    }
+
+    fn names(projects: Vec<Project>) -> Vec<String> {
+        projects.into_iter().map(|project| project.name).collect()
+    }
+
+    fn is_missing_tenant(result: Result<Vec<Project>, QueryError<WhereError>>) -> bool {
+        matches!(result, Err(QueryError::Query(err)) if err.is_missing_tenant())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_where_without_a_context_fails() {
+        // The condition is checked before a connection is taken from the pool
+        let pool = pool(tokio_postgres::Config::new());
+        pool.close();
+
+        assert!(is_missing_tenant(fetch_all_where(&pool, projects::id.gt(0)).await));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_comparison_does_not_bypass_the_context() {
+        let pool = pool(tokio_postgres::Config::new());
+        pool.close();
+        let acme = TenantContext::new(7_i64);
+        let tenant_id = Column::<i64>::new("tenant_id");
+
+        assert!(is_missing_tenant(fetch_all_where(&pool, tenant_id.eq(8)).await));
+
+        let expr = acme.filter::<Project>(projects::id.gt(0)).or(tenant_id.eq(8));
+        assert!(is_missing_tenant(fetch_all_where(&pool, expr).await));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_scoped_writes_and_reads_stay_in_the_tenant() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = pool(url.parse().unwrap());
+        pool.get()
+            .await
+            .unwrap()
+            .batch_execute(
+                "CREATE TEMP TABLE projects \
+                 (id BIGSERIAL PRIMARY KEY, name TEXT, tenant_id BIGINT)",
+            )
+            .await
+            .unwrap();
+        let acme = TenantContext::new(1_i64);
+        let globex = TenantContext::new(2_i64);
+
+        let add = |tenant: &TenantContext, name: &str| {
+            insert::<_, i64>(&pool, tenant.scope(InsertProject { name: name.into() }))
+        };
+        let website = add(&acme, "Website").await.unwrap();
+        add(&globex, "Shop").await.unwrap();
+
+        let rename = |name: &str| RenameProject { id: website, name: name.into() };
+        update(&pool, globex.scope(rename("Hijacked"))).await.unwrap();
+        update(&pool, acme.scope(rename("Homepage"))).await.unwrap();
+        let remove = DeleteProject { id: website };
+        assert_eq!(delete(&pool, globex.scope(remove)).await.unwrap(), 0);
+
+        let all = Project { id: 0, name: String::new() };
+        let projects: Vec<Project> = fetch_all(&pool, &acme.scope(all)).await.unwrap();
+        assert_eq!(names(projects), vec!["Homepage"]);
+
+        let expr = globex.filter::<Project>(projects::id.gt(0));
+        let projects: Vec<Project> = fetch_all_where(&pool, expr).await.unwrap();
+        assert_eq!(names(projects), vec!["Shop"]);
+    }
+}
@@ -0,0 +1,431 @@
+//! Statement timeouts and server-side cancellation.
+//!
+//! A query that runs too long holds its pooled connection until the server finishes it.
+//! [`with_timeout`] limits a single call and [`Timed`] applies the same limit to every call
+//! made through a `Pool`, [`RoutedPool`](crate::routing::RoutedPool) or
+//! [`Retrying`](crate::retry::Retrying). Both return [`QueryError::Timeout`] when the limit
+//! is reached, as do the CRUD functions when no connection becomes available within the
+//! pool's own wait, create or recycle timeout.
+//!
+//! The server keeps running a query when its client stops waiting for it, so the CRUD
+//! functions of this crate cancel it whenever their future is dropped before the result
+//! arrives, whether because of a timeout, a `select!` or an aborted task: a cancel request
+//! is sent through the connection's `CancelToken` and the connection is removed from the
+//! pool instead of being handed to the next caller. The cancel request is sent without TLS.
+//!
+//! ```rust,no_run
+//! # use deadpool_postgres::{Config, Runtime};
+//! # use tokio_postgres::NoTls;
+//! use parsql_deadpool_postgres::error::QueryError;
+//! use parsql_deadpool_postgres::timeout::{with_timeout, Timed};
+//! use parsql_deadpool_postgres::traits::CrudOps;
+//! # use parsql_deadpool_postgres::traits::{FromRow, SqlParams, SqlQuery};
+//! # use parsql_deadpool_postgres::*;
+//! # use std::time::Duration;
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams)]
+//! # #[table("orders")]
+//! # #[where_clause("total > $")]
+//! # struct Order { id: i64, total: i64 }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! # let pool = Config::new().create_pool(Some(Runtime::Tokio1), NoTls)?;
+//! let query = Order { id: 0, total: 1000 };
+//!
+//! // One call
+//! match with_timeout(Duration::from_secs(2), pool.fetch_all::<_, Order>(&query)).await {
+//!     Ok(orders) => println!("{} orders", orders.len()),
+//!     Err(QueryError::Timeout(after)) => eprintln!("cancelled after {:?}", after),
+//!     Err(err) => return Err(err.into()),
+//! }
+//!
+//! // Every call
+//! let db = Timed::new(pool, Duration::from_secs(5));
+//! let orders: Vec<Order> = db.fetch_all(&query).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use deadpool_postgres::{Object, Pool};
+use postgres::types::FromSql;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Error, NoTls, Row};
+
+use crate::compose::Fragment;
+use crate::error::{CheckoutError, QueryError, UpdateError, WhereError};
+use crate::expr::Expr;
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+    Versioned,
+};
+
+/// Runs a pool operation, cancelling it when it does not finish within `limit`.
+pub async fn with_timeout<T, E, F>(limit: Duration, query: F) -> Result<T, QueryError<E>>
+where
+    F: Future<Output = Result<T, QueryError<E>>>,
+{
+    // Dropping the query on timeout cancels it on the server, see `Checkout`
+    match tokio::time::timeout(limit, query).await {
+        Ok(result) => result,
+        Err(_) => Err(QueryError::Timeout(limit)),
+    }
+}
+
+/// Wraps a `Pool` or another [`CrudOps`] implementation and limits the duration of every
+/// call, including the wait for a connection.
+///
+/// Provides the methods of [`CrudOps`], returning [`QueryError`].
+pub struct Timed<C> {
+    inner: C,
+    limit: Duration,
+}
+
+impl<C> Timed<C> {
+    /// Wraps `inner` with the given limit.
+    pub fn new(inner: C, limit: Duration) -> Self {
+        Timed { inner, limit }
+    }
+
+    /// Returns the wrapped pool, whose calls are not limited.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the limit.
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+}
+
+impl<C: CrudOps + Send + Sync> Timed<C> {
+    /// Inserts a new record.
+    pub async fn insert<T, P>(&self, entity: T) -> Result<P, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.insert(entity)).await
+    }
+
+    /// Updates an existing record.
+    pub async fn update<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SqlCommand + UpdateParams + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.update(entity)).await
+    }
+
+    /// Updates a record of a model with `#[version_column("...")]`, failing with
+    /// `UpdateError::StaleObject` when no row matched the expected version.
+    pub async fn update_versioned<T>(&self, entity: T) -> Result<(), QueryError<UpdateError>>
+    where
+        T: Versioned + UpdateParams + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.update_versioned(entity)).await
+    }
+
+    /// Deletes a record.
+    pub async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.delete(entity)).await
+    }
+
+    /// Restores soft-deleted records.
+    pub async fn restore<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.restore(entity)).await
+    }
+
+    /// Removes soft-deleted records permanently.
+    pub async fn force_delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.force_delete(entity)).await
+    }
+
+    /// Retrieves a single record.
+    pub async fn fetch<P, R>(&self, params: &P) -> Result<R, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync,
+        R: FromRow + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.fetch(params)).await
+    }
+
+    /// Retrieves multiple records.
+    pub async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync,
+        R: FromRow + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.fetch_all(params)).await
+    }
+
+    /// Retrieves a single record with a custom conversion function.
+    pub async fn select<T, R, F>(&self, entity: T, to_model: F) -> Result<R, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync,
+        F: FnOnce(&Row) -> Result<R, Error> + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.select(entity, to_model)).await
+    }
+
+    /// Retrieves multiple records with a custom conversion function.
+    pub async fn select_all<T, R, F>(&self, entity: T, to_model: F) -> Result<Vec<R>, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync,
+        F: Fn(&Row) -> R + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.select_all(entity, to_model)).await
+    }
+
+    /// Loads the related records of `records`.
+    pub async fn preload<R, C2>(&self, records: &mut [R]) -> Result<(), QueryError>
+    where
+        R: Relation<C2> + Send + Sync,
+        C2: FromRow + Clone + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.preload(records)).await
+    }
+
+    /// Retrieves the records matching a typed WHERE expression.
+    pub async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, QueryError<WhereError>>
+    where
+        R: Columns + FromRow + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.fetch_all_where(expr)).await
+    }
+
+    /// Retrieves the records returned by a composed query.
+    pub async fn fetch_all_fragment<R>(
+        &self,
+        fragment: &Fragment<'_>,
+    ) -> Result<Vec<R>, QueryError>
+    where
+        R: FromRow + Send + Sync,
+    {
+        with_timeout(self.limit, self.inner.fetch_all_fragment(fragment)).await
+    }
+}
+
+/// Takes a connection from `pool`, keeping the pool's timeouts for the error.
+pub(crate) async fn get_client(pool: &Pool) -> Result<Object, CheckoutError> {
+    pool.get().await.map_err(|error| CheckoutError {
+        error,
+        timeouts: pool.timeouts(),
+    })
+}
+
+/// A pooled connection that cancels its running statement when dropped.
+///
+/// Shadows the query methods of the client; a statement whose future is dropped before it
+/// finishes is cancelled on the server and the connection is discarded, so that the cancel
+/// request cannot hit a statement of the connection's next user.
+pub(crate) struct Checkout {
+    client: Option<Object>,
+    running: AtomicBool,
+}
+
+impl Checkout {
+    /// Takes a connection from `pool`.
+    pub(crate) async fn get(pool: &Pool) -> Result<Self, CheckoutError> {
+        get_client(pool).await.map(Checkout::new)
+    }
+
+    pub(crate) fn new(client: Object) -> Self {
+        Checkout {
+            client: Some(client),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) async fn query(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error> {
+        self.run(self.deref().query(sql, params)).await
+    }
+
+    pub(crate) async fn query_one(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Row, Error> {
+        self.run(self.deref().query_one(sql, params)).await
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, Error> {
+        self.run(self.deref().execute(sql, params)).await
+    }
+
+    /// Runs a statement, or several pipelined ones, that is cancelled if dropped.
+    pub(crate) async fn run<F: Future>(&self, statement: F) -> F::Output {
+        self.running.store(true, Ordering::Release);
+        let output = statement.await;
+        self.running.store(false, Ordering::Release);
+        output
+    }
+}
+
+impl Deref for Checkout {
+    type Target = Object;
+
+    fn deref(&self) -> &Object {
+        self.client.as_ref().expect("the client is only taken on drop")
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if !self.running.load(Ordering::Acquire) {
+            return;
+        }
+        let Some(client) = self.client.take() else {
+            return;
+        };
+        let client = Object::take(client);
+        // Without a runtime the connection is only closed; the server stops at its next write
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let token = client.cancel_token();
+        runtime.spawn(async move {
+            let _ = token.cancel_query(NoTls).await;
+            drop(client);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deadpool_postgres::{Manager, PoolError, Runtime};
+
+    struct One;
+
+    impl FromRow for One {
+        fn from_row(_row: &Row) -> Result<Self, Error> {
+            Ok(One)
+        }
+    }
+
+    /// A pool that never hands out a connection.
+    fn empty_pool(wait: Option<Duration>) -> Pool {
+        let manager = Manager::new(tokio_postgres::Config::new(), NoTls);
+        Pool::builder(manager)
+            .max_size(0)
+            .runtime(Runtime::Tokio1)
+            .wait_timeout(wait)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout_cancels_slow_query() {
+        let limit = Duration::from_millis(10);
+        let result: Result<(), QueryError> =
+            with_timeout(limit, std::future::pending::<Result<(), QueryError>>()).await;
+        assert!(matches!(result, Err(QueryError::Timeout(after)) if after == limit));
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout_passes_results_through() {
+        let limit = Duration::from_secs(1);
+        let result = with_timeout(limit, async { Ok::<_, QueryError>(7) }).await;
+        assert!(matches!(result, Ok(7)));
+
+        let result = with_timeout(limit, async {
+            Err::<(), QueryError>(QueryError::Pool(PoolError::Closed))
+        })
+        .await;
+        assert!(matches!(result, Err(QueryError::Pool(PoolError::Closed))));
+    }
+
+    #[tokio::test]
+    async fn test_timed_limits_the_wait_for_a_connection() {
+        let limit = Duration::from_millis(20);
+        let db = Timed::new(empty_pool(None), limit);
+
+        let result = db.fetch_all_fragment::<One>(&Fragment::raw("SELECT 1")).await;
+        assert!(matches!(result, Err(QueryError::Timeout(after)) if after == limit));
+    }
+
+    #[tokio::test]
+    async fn test_checkout_timeout_is_a_query_timeout() {
+        let wait = Duration::from_millis(20);
+        let pool = empty_pool(Some(wait));
+
+        let result = pool.fetch_all_fragment::<One>(&Fragment::raw("SELECT 1")).await;
+        assert!(matches!(result, Err(QueryError::Timeout(after)) if after == wait));
+    }
+
+    #[tokio::test]
+    async fn test_closed_pool_is_a_pool_error() {
+        let pool = empty_pool(None);
+        pool.close();
+
+        let result = pool.fetch_all_fragment::<One>(&Fragment::raw("SELECT 1")).await;
+        assert!(matches!(result, Err(QueryError::Pool(PoolError::Closed))));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_cancelled_statement_discards_the_connection() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = Manager::new(url.parse().unwrap(), NoTls);
+        let pool = Pool::builder(manager)
+            .max_size(1)
+            .runtime(Runtime::Tokio1)
+            .build()
+            .unwrap();
+
+        // A finished statement hands the connection back to the pool
+        let client = Checkout::get(&pool).await.unwrap();
+        client.execute("SELECT 1", &[]).await.unwrap();
+        drop(client);
+        assert_eq!(pool.status().size, 1);
+
+        // A statement dropped while running is cancelled and its connection discarded
+        let client = Checkout::get(&pool).await.unwrap();
+        let sleep = client.execute("SELECT pg_sleep(30)", &[]);
+        assert!(tokio::time::timeout(Duration::from_millis(200), sleep)
+            .await
+            .is_err());
+        drop(client);
+        assert_eq!(pool.status().size, 0);
+
+        let client = Checkout::get(&pool).await.unwrap();
+        let mut sleeping = 1;
+        for _ in 0..50 {
+            let row = client
+                .query_one(
+                    "SELECT count(*) FROM pg_stat_activity \
+                     WHERE query = 'SELECT pg_sleep(30)' AND state = 'active'",
+                    &[],
+                )
+                .await
+                .unwrap();
+            sleeping = row.get::<_, i64>(0);
+            if sleeping == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(sleeping, 0);
+    }
+}
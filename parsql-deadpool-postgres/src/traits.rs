@@ -1,14 +1,34 @@
 use async_trait::async_trait;
 use postgres::types::FromSql;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
 use tokio_postgres::types::ToSql;
 use tokio_postgres::{Error, Row};
 
+use crate::compose::Fragment;
+use crate::error::{check_version, QueryError, UpdateError, WhereError};
+use crate::expr::Expr;
+
+pub use parsql_core::traits::{Columns, TenantScoped};
+
 /// Trait for generating SQL queries (for SELECT operations).
 /// This trait is implemented by the derive macro `Queryable`.
 pub trait SqlQuery<R> {
     /// Returns the SQL query string.
     fn query() -> String;
+
+    /// Returns the query and its parameters as a composable fragment,
+    /// to be used as a CTE, a subquery or a member of a `UNION ALL`.
+    ///
+    /// The SQL is scanned for placeholders on every call, so build the fragment once
+    /// instead of inside a loop.
+    fn fragment(&self) -> Fragment<'_>
+    where
+        Self: SqlParams + Sized,
+    {
+        Fragment::new(<Self as SqlQuery<R>>::query(), self.params())
+    }
 }
 
 /// Trait for generating SQL commands (for INSERT/UPDATE/DELETE operations).
@@ -32,6 +52,51 @@ pub trait UpdateParams {
     fn params(&self) -> Vec<&(dyn ToSql + Sync)>;
 }
 
+/// Trait for update models with optimistic locking.
+/// This trait is implemented by the derive macro `Updateable` when `#[version_column("...")]`
+/// is present; only such models can be passed to `update_versioned`.
+pub trait Versioned: SqlCommand {
+    /// Returns the column incremented by every update.
+    fn version_column() -> &'static str;
+}
+
+/// Trait for commands of soft-deleted tables.
+/// This trait is implemented by the derive macro `Deletable` when `#[soft_delete]` is present.
+pub trait SoftDelete {
+    /// Returns the SQL command that clears the deletion marker.
+    fn restore_query() -> String;
+
+    /// Returns the SQL command that removes the record permanently.
+    fn force_delete_query() -> String;
+}
+
+/// Trait for queries that skip soft-deleted records.
+/// This trait is implemented by the derive macro `Queryable` when `#[soft_delete]` is present.
+pub trait SoftDeleteQuery {
+    /// Returns the SQL query without the soft-delete filter.
+    fn query_with_deleted() -> String;
+}
+
+/// Runs a soft-delete aware query including the soft-deleted records,
+/// e.g. `fetch_all(&pool, &WithDeleted(query))`.
+#[derive(Debug, Clone)]
+pub struct WithDeleted<T>(pub T);
+
+impl<T, R> SqlQuery<R> for WithDeleted<T>
+where
+    T: SqlQuery<R> + SoftDeleteQuery,
+{
+    fn query() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams> SqlParams for WithDeleted<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0.params()
+    }
+}
+
 /// Trait for converting database rows to Rust structs.
 /// This trait is implemented by the derive macro `FromRow`.
 pub trait FromRow {
@@ -47,48 +112,118 @@ pub trait FromRow {
         Self: Sized;
 }
 
+/// Trait for batch loading records related to a model, implemented by the `FromRow`
+/// derive macro for every `#[has_many(...)]` and `#[belongs_to(...)]` declaration.
+pub trait Relation<C> {
+    type Key: ToSql + Send + Sync + Eq + Hash + Clone;
+
+    fn relation_query() -> String;
+
+    fn relation_key(&self) -> Option<Self::Key>;
+
+    fn related_key(related: &C) -> Option<Self::Key>;
+
+    fn attach(&mut self, related: Vec<C>);
+
+    fn stitch(records: &mut [Self], related: Vec<C>)
+    where
+        Self: Sized,
+        C: Clone,
+    {
+        let mut grouped: HashMap<Self::Key, Vec<C>> = HashMap::new();
+        for item in related {
+            if let Some(key) = Self::related_key(&item) {
+                grouped.entry(key).or_default().push(item);
+            }
+        }
+
+        for record in records {
+            let related = record
+                .relation_key()
+                .and_then(|key| grouped.get(&key).cloned())
+                .unwrap_or_default();
+            record.attach(related);
+        }
+    }
+}
+
 /// CrudOps trait'i, Pool nesnesi için CRUD işlemlerini extension method olarak sağlar.
 /// Bu trait, Pool üzerinde doğrudan CRUD işlemlerini çağırmayı mümkün kılar.
 #[async_trait]
 pub trait CrudOps {
     /// Veritabanına yeni bir kayıt ekler.
-    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, Error>
+    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync;
 
     /// Veritabanındaki mevcut bir kaydı günceller.
-    async fn update<T>(&self, entity: T) -> Result<u64, Error>
+    async fn update<T>(&self, entity: T) -> Result<u64, QueryError>
     where
         T: SqlCommand + UpdateParams + Send + Sync;
 
+    /// `#[version_column("...")]` kullanan bir modelin kaydını günceller; hiçbir satır beklenen
+    /// sürümle eşleşmezse `UpdateError::StaleObject` döndürür.
+    async fn update_versioned<T>(&self, entity: T) -> Result<(), QueryError<UpdateError>>
+    where
+        T: Versioned + UpdateParams + Send + Sync,
+    {
+        let affected_rows = self.update(entity).await?;
+        Ok(check_version::<T>(affected_rows > 0)?)
+    }
+
     /// Veritabanından bir kaydı siler.
-    async fn delete<T>(&self, entity: T) -> Result<u64, Error>
+    async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
     where
         T: SqlCommand + SqlParams + Send + Sync;
 
+    /// Soft-delete ile silinmiş kayıtları geri getirir.
+    async fn restore<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync;
+
+    /// Soft-delete uygulanan bir tablodan kayıtları kalıcı olarak siler.
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync;
+
     /// Belirtilen kriterlere uygun tek bir kaydı getirir.
-    async fn fetch<P, R>(&self, params: &P) -> Result<R, Error>
+    async fn fetch<P, R>(&self, params: &P) -> Result<R, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync,
         R: FromRow + Send + Sync;
 
     /// Belirtilen kriterlere uygun tüm kayıtları getirir.
-    async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, Error>
+    async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, QueryError>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync,
         R: FromRow + Send + Sync;
 
     /// Belirtilen özel dönüşüm fonksiyonunu kullanarak tek bir kaydı getirir.
-    async fn select<T, R, F>(&self, entity: T, to_model: F) -> Result<R, Error>
+    async fn select<T, R, F>(&self, entity: T, to_model: F) -> Result<R, QueryError>
     where
         T: SqlQuery<T> + SqlParams + Send + Sync,
         F: FnOnce(&Row) -> Result<R, Error> + Send + Sync;
 
     /// Belirtilen özel dönüşüm fonksiyonunu kullanarak tüm kayıtları getirir.
-    async fn select_all<T, R, F>(&self, entity: T, to_model: F) -> Result<Vec<R>, Error>
+    async fn select_all<T, R, F>(&self, entity: T, to_model: F) -> Result<Vec<R>, QueryError>
     where
         T: SqlQuery<T> + SqlParams + Send + Sync,
         F: Fn(&Row) -> R + Send + Sync;
+
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), QueryError>
+    where
+        R: Relation<C> + Send + Sync,
+        C: FromRow + Clone + Send + Sync;
+
+    /// Tipli bir WHERE ifadesine uyan kayıtları getirir.
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, QueryError<WhereError>>
+    where
+        R: Columns + FromRow + Send + Sync;
+
+    /// Birleştirilmiş bir sorgunun döndürdüğü tüm kayıtları getirir.
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, QueryError>
+    where
+        R: FromRow + Send + Sync;
 }
 
 /// TransactionOps trait, Transaction için CRUD işlemlerini extension method olarak sağlar
@@ -106,6 +241,16 @@ pub trait TransactionOps {
     where
         T: SqlCommand + UpdateParams + SqlParams + Debug + Send + 'static;
 
+    /// Versioned update method, `#[version_column("...")]` kullanan bir kaydı günceller;
+    /// hiçbir satır beklenen sürümle eşleşmezse `UpdateError::StaleObject` döner
+    async fn tx_update_versioned<T>(&self, entity: T) -> Result<(), UpdateError>
+    where
+        T: Versioned + UpdateParams + SqlParams + Debug + Send + 'static,
+    {
+        let updated = self.tx_update(entity).await?;
+        check_version::<T>(updated)
+    }
+
     /// Delete method, bir kaydı silmek için kullanılır
     async fn tx_delete<T>(&self, entity: T) -> Result<u64, Error>
     where
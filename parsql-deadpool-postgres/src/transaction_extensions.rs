@@ -1,7 +1,9 @@
-use crate::traits::{FromRow, SqlCommand, SqlParams, SqlQuery, TransactionOps, UpdateParams};
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{
+    FromRow, SqlCommand, SqlParams, SqlQuery, TransactionOps, UpdateParams,
+};
 use deadpool_postgres::{GenericClient, Transaction};
 use std::fmt::Debug;
-use std::sync::OnceLock;
 use tokio_postgres::Row;
 use tokio_postgres::{types::FromSql, Error};
 
@@ -45,12 +47,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = entity.params();
-        let row = self.query_one(&sql, &query_params).await?;
+        let observation = Observation::start::<T>(QueryKind::Insert, &sql, &query_params);
+        let row = observation.finish(self.query_one(&sql, &query_params).await, |_| 1)?;
         row.try_get::<_, P>(0)
     }
 
@@ -60,12 +59,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = <T as UpdateParams>::params(&entity);
-        let result = self.execute(&sql, &query_params).await?;
+        let observation = Observation::start::<T>(QueryKind::Update, &sql, &query_params);
+        let result = observation.finish(self.execute(&sql, &query_params).await, |rows| *rows)?;
         Ok(result > 0)
     }
 
@@ -75,12 +71,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = entity.params();
-        self.execute(&sql, &query_params).await
+        let observation = Observation::start::<T>(QueryKind::Delete, &sql, &query_params);
+        observation.finish(self.execute(&sql, &query_params).await, |rows| *rows)
     }
 
     async fn tx_fetch<P, R>(&self, params: &P) -> Result<R, Error>
@@ -90,12 +83,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = P::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let row = self.query_one(&sql, &query_params).await?;
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let row = observation.finish(self.query_one(&sql, &query_params).await, |_| 1)?;
         R::from_row(&row)
     }
 
@@ -106,12 +96,12 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = P::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let rows = self.query(&sql, &query_params).await?;
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let rows = observation.finish(
+            self.query(&sql, &query_params).await,
+            |rows| rows.len() as u64,
+        )?;
 
         let mut results = Vec::with_capacity(rows.len());
         for row in rows {
@@ -129,12 +119,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = entity.params();
-        let row = self.query_one(&sql, &query_params).await?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &query_params);
+        let row = observation.finish(self.query_one(&sql, &query_params).await, |_| 1)?;
         to_model(&row)
     }
 
@@ -146,12 +133,12 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = entity.params();
-        let rows = self.query(&sql, &query_params).await?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &query_params);
+        let rows = observation.finish(
+            self.query(&sql, &query_params).await,
+            |rows| rows.len() as u64,
+        )?;
 
         let mut results = Vec::with_capacity(rows.len());
         for row in rows {
@@ -168,12 +155,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = entity.params();
-        self.execute(&sql, &query_params).await
+        let observation = Observation::start::<T>(QueryKind::Insert, &sql, &query_params);
+        observation.finish(self.execute(&sql, &query_params).await, |rows| *rows)
     }
 
     async fn update<T>(&self, entity: T) -> Result<u64, Error>
@@ -182,12 +166,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = <T as UpdateParams>::params(&entity);
-        self.execute(&sql, &query_params).await
+        let observation = Observation::start::<T>(QueryKind::Update, &sql, &query_params);
+        observation.finish(self.execute(&sql, &query_params).await, |rows| *rows)
     }
 
     async fn delete<T>(&self, entity: T) -> Result<u64, Error>
@@ -196,12 +177,9 @@ impl<'a> TransactionOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-TOKIO-POSTGRES-POOL] Execute SQL: {}", sql);
-        }
-
         let query_params = entity.params();
-        self.execute(&sql, &query_params).await
+        let observation = Observation::start::<T>(QueryKind::Delete, &sql, &query_params);
+        observation.finish(self.execute(&sql, &query_params).await, |rows| *rows)
     }
 
     async fn get<T>(&self, params: &T) -> Result<T, Error>
@@ -245,16 +223,9 @@ impl TransactionExtensions for Transaction<'_> {
     {
         let sql = T::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES-TX] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let row = self.query_one(&sql, &params).await?;
+        let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+        let row = observation.finish(self.query_one(&sql, &params).await, |_| 1)?;
         row.try_get::<_, P>(0)
     }
 
@@ -264,16 +235,9 @@ impl TransactionExtensions for Transaction<'_> {
     {
         let sql = T::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES-TX] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let result = self.execute(&sql, &params).await?;
+        let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+        let result = observation.finish(self.execute(&sql, &params).await, |rows| *rows)?;
         Ok(result > 0)
     }
 
@@ -283,16 +247,9 @@ impl TransactionExtensions for Transaction<'_> {
     {
         let sql = T::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES-TX] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        self.execute(&sql, &params).await
+        let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+        observation.finish(self.execute(&sql, &params).await, |rows| *rows)
     }
 
     async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
@@ -302,16 +259,9 @@ impl TransactionExtensions for Transaction<'_> {
     {
         let sql = P::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES-TX] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let row = self.query_one(&sql, &query_params).await?;
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let row = observation.finish(self.query_one(&sql, &query_params).await, |_| 1)?;
         R::from_row(&row)
     }
 
@@ -322,16 +272,12 @@ impl TransactionExtensions for Transaction<'_> {
     {
         let sql = P::query();
 
-        static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-        let is_trace_enabled =
-            *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-        if is_trace_enabled {
-            println!("[PARSQL-DEADPOOL-POSTGRES-TX] Execute SQL: {}", sql);
-        }
-
         let query_params = params.params();
-        let rows = self.query(&sql, &query_params).await?;
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+        let rows = observation.finish(
+            self.query(&sql, &query_params).await,
+            |rows| rows.len() as u64,
+        )?;
 
         let mut results = Vec::with_capacity(rows.len());
         for row in rows {
@@ -4,7 +4,8 @@ use tokio_postgres::Error;
 // Makrolar sadece dokümantasyon için kullanılıyor, gerçek kodda SqlQuery kullanılmalı
 // use parsql_macros::{Insertable, Updateable};
 
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
 
 /// # begin
 ///
@@ -42,11 +43,6 @@ use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdatePar
 /// ```
 pub async fn begin(client: &mut Client) -> Result<Transaction<'_>, Error> {
     let tx = client.transaction().await?;
-
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Begin Transaction");
-    }
-
     Ok(tx)
 }
 
@@ -103,7 +99,7 @@ pub async fn begin(client: &mut Client) -> Result<Transaction<'_>, Error> {
 ///
 /// ## Example Usage
 /// ```rust,no_run
-/// use tokio_postgres::{NoTls, Error};
+/// use tokio_postgres::NoTls;
 /// use deadpool_postgres::{Config, Runtime};
 /// use parsql::deadpool_postgres::transactional::tx_update;
 ///
@@ -118,7 +114,7 @@ pub async fn begin(client: &mut Client) -> Result<Transaction<'_>, Error> {
 /// }
 ///
 /// #[tokio::main]
-/// async fn main() -> Result<(), Error> {
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut cfg = Config::new();
 ///     cfg.host = Some("localhost".to_string());
 ///     cfg.dbname = Some("test".to_string());
@@ -141,18 +137,15 @@ pub async fn begin(client: &mut Client) -> Result<Transaction<'_>, Error> {
 ///     Ok(())
 /// }
 /// ```
-pub async fn tx_update<T: SqlCommand + SqlParams>(
+pub async fn tx_update<T: SqlCommand + UpdateParams>(
     transaction: Transaction<'_>,
     entity: T,
 ) -> Result<(Transaction<'_>, u64), Error> {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = transaction.execute(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    let result = observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)?;
     Ok((transaction, result))
 }
 
@@ -211,12 +204,9 @@ pub async fn tx_insert<T: SqlCommand + SqlParams>(
 ) -> Result<(Transaction<'_>, u64), Error> {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = transaction.execute(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+    let result = observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)?;
     Ok((transaction, result))
 }
 
@@ -236,12 +226,9 @@ pub async fn tx_delete<T: SqlCommand + SqlParams>(
 ) -> Result<(Transaction<'_>, u64), Error> {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = transaction.execute(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    let result = observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)?;
     Ok((transaction, result))
 }
 
@@ -264,12 +251,9 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let row = transaction.query_one(&sql, &query_params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &query_params);
+    let row = observation.finish(transaction.query_one(&sql, &query_params).await, |_| 1)?;
     let result = T::from_row(&row)?;
 
     Ok((transaction, result))
@@ -294,12 +278,12 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let rows = transaction.query(&sql, &query_params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &query_params);
+    let rows = observation.finish(
+        transaction.query(&sql, &query_params).await,
+        |rows| rows.len() as u64,
+    )?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -331,12 +315,9 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = transaction.query_one(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let row = observation.finish(transaction.query_one(&sql, &params).await, |_| 1)?;
     let result = to_model(&row)?;
 
     Ok((transaction, result))
@@ -364,12 +345,12 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let rows = transaction.query(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(
+        transaction.query(&sql, &params).await,
+        |rows| rows.len() as u64,
+    )?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
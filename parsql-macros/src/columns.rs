@@ -0,0 +1,169 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+
+use crate::relations::relation_field_names;
+use crate::soft_delete::soft_delete_column;
+use crate::tenant::tenant_column;
+
+/// Implements the Columns derive macro.
+///
+/// Generates a `Columns` implementation with the table and column names, and a module
+/// named after the table (or `#[columns_module("...")]`) with one typed `Column` constant
+/// per field, e.g. `users::email`.
+pub(crate) fn derive_columns_impl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_columns(&input).into()
+}
+
+fn expand_columns(input: &DeriveInput) -> TokenStream2 {
+    let struct_name = &input.ident;
+    let vis = &input.vis;
+
+    let table = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("table"))
+        .expect("Missing `#[table = \"...\"]` attribute")
+        .parse_args::<syn::LitStr>()
+        .expect("Expected a string literal for table name")
+        .value();
+
+    // An alias (`users u`) is not part of the table name
+    let table = table
+        .split_whitespace()
+        .next()
+        .expect("Table name must not be empty")
+        .to_string();
+
+    // Relation fields hold loaded records and are not columns
+    let relation_fields = relation_field_names(input);
+
+    let fields = if let Data::Struct(data) = &input.data {
+        if let Fields::Named(fields) = &data.fields {
+            fields
+                .named
+                .iter()
+                .filter(|f| !relation_fields.contains(&f.ident.as_ref().unwrap().to_string()))
+                .collect::<Vec<_>>()
+        } else {
+            panic!("Columns can only be derived for structs with named fields");
+        }
+    } else {
+        panic!("Columns can only be derived for structs");
+    };
+
+    let column_names: Vec<String> = fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap().to_string())
+        .collect();
+    let idents = fields.iter().map(|f| f.ident.as_ref().unwrap());
+    let types = fields.iter().map(|f| &f.ty);
+    let module = module_name(input, &table);
+
+    // `fetch_all_where` applies the same row filters as the model's `Queryable` queries
+    let soft_delete = soft_delete_column(input).map(|column| {
+        quote! {
+            fn soft_delete_column() -> Option<&'static str> {
+                Some(#column)
+            }
+        }
+    });
+    let tenant_scoped = tenant_column(input).map(|column| {
+        quote! {
+            fn tenant_column() -> Option<&'static str> {
+                Some(#column)
+            }
+        }
+    });
+
+    quote! {
+        impl Columns for #struct_name {
+            fn table() -> &'static str {
+                #table
+            }
+
+            fn columns() -> &'static [&'static str] {
+                &[#(#column_names),*]
+            }
+
+            #soft_delete
+            #tenant_scoped
+        }
+
+        #[allow(non_upper_case_globals, dead_code)]
+        #vis mod #module {
+            use super::*;
+
+            #(pub const #idents: Column<#types> = Column::new(#column_names);)*
+        }
+    }
+}
+
+/// The name of the generated module: `#[columns_module("...")]`, or the table name without
+/// its schema. Several structs of one table need `#[columns_module]` to derive `Columns` side
+/// by side.
+fn module_name(input: &DeriveInput, table: &str) -> Ident {
+    let name = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("columns_module"))
+        .map(|attr| {
+            attr.parse_args::<syn::LitStr>()
+                .expect("Expected a string literal for columns_module")
+                .value()
+        })
+        .unwrap_or_else(|| {
+            let name = table.rsplit('.').next().unwrap_or(table);
+            name.trim_matches('"').to_string()
+        });
+
+    // Keywords such as `type` become raw identifiers
+    syn::parse_str::<Ident>(&name).unwrap_or_else(|_| {
+        syn::parse_str::<Ident>(&format!("r#{}", name))
+            .unwrap_or_else(|_| panic!("`{}` is not a valid module name", name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(item: &str) -> String {
+        let input: DeriveInput = syn::parse_str(item).unwrap();
+        expand_columns(&input).to_string()
+    }
+
+    #[test]
+    fn test_module_name() {
+        let tokens = expand(r#"#[table("public.users")] pub struct UserRow { id: i64 }"#);
+        assert!(tokens.contains("pub mod users"));
+        assert!(tokens.contains("\"public.users\""));
+
+        let tokens = expand(r#"#[table("users u")] struct User { id: i64 }"#);
+        assert!(tokens.contains("mod users"));
+
+        let tokens =
+            expand(r#"#[table("users")] #[columns_module("user_row")] struct User { id: i64 }"#);
+        assert!(tokens.contains("mod user_row"));
+
+        let tokens = expand(r#"#[table("type")] struct Type { id: i64 }"#);
+        assert!(tokens.contains("mod r#type"));
+    }
+
+    #[test]
+    fn test_row_filters() {
+        let tokens = expand(r#"#[table("users")] struct User { id: i64 }"#);
+        assert!(!tokens.contains("soft_delete_column"));
+
+        let tokens = expand(
+            r#"#[table("users")] #[soft_delete] #[tenant_scoped(column = "org_id")] struct User { id: i64 }"#,
+        );
+        assert!(tokens.contains(
+            "fn soft_delete_column () -> Option < & 'static str > { Some (\"deleted_at\") }"
+        ));
+        assert!(tokens
+            .contains("fn tenant_column () -> Option < & 'static str > { Some (\"org_id\") }"));
+    }
+}
@@ -3,9 +3,11 @@ use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
 use crate::{
-    extract_fields_from_where_clause, log_message, number_where_clause_params, query_builder,
-    SqlParamCounter,
+    extract_fields_from_where_clause, NOW, number_where_clause_params,
+    query_builder, SqlParamCounter,
 };
+use crate::soft_delete::{soft_delete_column, with_null_check};
+use crate::tenant::{tenant_column, tenant_scoped_impl, with_tenant_check};
 
 pub(crate) fn derive_deletable_impl(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -35,29 +37,83 @@ pub(crate) fn derive_deletable_impl(input: TokenStream) -> TokenStream {
     // Her zaman 1'den başlar
     let mut param_counter = SqlParamCounter::new();
 
-    let adjusted_where_clause = where_clause
+    let mut adjusted_where_clause = where_clause
         .map(|clause| number_where_clause_params(&clause, &mut param_counter))
         .unwrap_or_else(|| "".to_string());
 
-    let mut builder = query_builder::SafeQueryBuilder::new();
+    // The tenant value is bound after the WHERE parameters
+    let tenant_scoped = tenant_column(&input).map(|column| {
+        let param_number = param_counter.next();
+        adjusted_where_clause = with_tenant_check(&adjusted_where_clause, &column, param_number);
+        tenant_scoped_impl(struct_name, &column, param_number)
+    });
 
-    builder.add_keyword("DELETE FROM");
-    builder.add_identifier(&table);
-    builder.add_keyword("WHERE");
-    builder.add_raw(&adjusted_where_clause); // SafeQueryBuilder will automatically add spaces
+    let delete_query = {
+        let mut builder = query_builder::SafeQueryBuilder::new();
+        builder.add_keyword("DELETE FROM");
+        builder.add_identifier(&table);
+        builder.add_keyword("WHERE");
+        builder.add_raw(&adjusted_where_clause); // SafeQueryBuilder will automatically add spaces
+        builder.build()
+    };
+
+    // `UPDATE table SET column = <value> WHERE ...` for soft-deleted tables
+    let update_marker = |column: &str, value: &str, negated: bool| {
+        let mut builder = query_builder::SafeQueryBuilder::new();
+        builder.add_keyword("UPDATE");
+        builder.add_identifier(&table);
+        builder.add_keyword("SET");
+        builder.add_raw(&format!("{} = {}", column, value));
+        builder.add_keyword("WHERE");
+        builder.add_raw(&with_null_check(&adjusted_where_clause, column, negated));
+        builder.build()
+    };
 
-    let safe_query = builder.build();
+    let expanded = match soft_delete_column(&input) {
+        Some(column) => {
+            let soft_delete_query = update_marker(&column, NOW, false);
+            let restore_query = update_marker(&column, "NULL", true);
 
-    // Log mesajlarını PARSQL_TRACE kontrolü ile yazdır
-    log_message(&format!("Generated DELETE SQL: {}", safe_query));
-    log_message(&format!("Total param count: {}", param_counter.count()));
+            quote! {
+                impl SqlCommand for #struct_name {
+                    fn query() -> String {
+                        #soft_delete_query.to_string()
+                    }
+                }
 
-    let expanded = quote! {
-        impl SqlCommand for #struct_name {
-            fn query() -> String {
-                #safe_query.to_string()
+                impl SoftDelete for #struct_name {
+                    fn restore_query() -> String {
+                        #restore_query.to_string()
+                    }
+
+                    fn force_delete_query() -> String {
+                        #delete_query.to_string()
+                    }
+                }
             }
         }
+        None => {
+
+            quote! {
+                impl SqlCommand for #struct_name {
+                    fn query() -> String {
+                        #delete_query.to_string()
+                    }
+                }
+            }
+        }
+    };
+
+    let expanded = quote! {
+        #expanded
+        #tenant_scoped
+    };
+
+    #[cfg(feature = "schema-check")]
+    let expanded = {
+        let checks =
+            crate::schema_check::validate(&input, crate::schema_check::DeriveKind::Deletable);
+        quote! { #expanded #checks }
     };
 
     TokenStream::from(expanded)
@@ -0,0 +1,275 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields, Type};
+
+use crate::utils::parse_options;
+
+/// Implements the Factory derive macro.
+///
+/// Generates `<Struct>Factory` (or the `#[factory(name = "...")]` name) with a `with_<field>`
+/// override per field, and implements the backend's `Factory` trait for it. Fields that are
+/// not overridden get their `#[factory(default = "...")]` expression or
+/// `#[factory(sequence = "...")]` string, or a default chosen from their type; `n`, the
+/// sequence number of the built entity, is in scope in both.
+pub(crate) fn derive_factory_impl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_factory(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_factory(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let struct_name = &input.ident;
+    let vis = &input.vis;
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "Factory cannot be derived for generic structs",
+        ));
+    }
+
+    let mut factory_name = format_ident!("{}Factory", struct_name);
+    if let Some(attr) = input.attrs.iter().find(|attr| attr.path().is_ident("factory")) {
+        for (_, name) in parse_options(attr, "factory", &["name"])? {
+            factory_name = name.parse()?;
+        }
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            fields => {
+                return Err(syn::Error::new(
+                    fields.span(),
+                    "Factory can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                struct_name.span(),
+                "Factory can only be derived for structs",
+            ))
+        }
+    };
+
+    let idents: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let setters: Vec<_> = idents
+        .iter()
+        .map(|ident| format_ident!("with_{}", ident))
+        .collect();
+    let setter_docs: Vec<_> = idents
+        .iter()
+        .map(|ident| format!("Sets `{}` of every entity built by this factory.", ident))
+        .collect();
+    let defaults: Vec<_> = fields
+        .iter()
+        .map(|field| field_default(field.ident.as_ref().unwrap(), &field.ty, &field.attrs))
+        .collect::<syn::Result<_>>()?;
+
+    let struct_doc = format!(
+        "Builds sequence-numbered `{}` values for tests; see `Factory`.",
+        struct_name
+    );
+
+    Ok(quote! {
+        #[doc = #struct_doc]
+        #[derive(Clone)]
+        #vis struct #factory_name {
+            #( #idents: ::core::option::Option<#types>, )*
+        }
+
+        impl #factory_name {
+            /// Creates a factory that builds entities with default field values.
+            #vis fn new() -> Self {
+                Self {
+                    #( #idents: ::core::option::Option::None, )*
+                }
+            }
+
+            #(
+                #[doc = #setter_docs]
+                #vis fn #setters(mut self, value: impl ::core::convert::Into<#types>) -> Self {
+                    self.#idents = ::core::option::Option::Some(value.into());
+                    self
+                }
+            )*
+        }
+
+        impl ::core::default::Default for #factory_name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Factory for #factory_name {
+            type Entity = #struct_name;
+
+            fn next_sequence() -> u64 {
+                static SEQUENCE: ::std::sync::atomic::AtomicU64 =
+                    ::std::sync::atomic::AtomicU64::new(1);
+                SEQUENCE.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed)
+            }
+
+            #[allow(unused_variables)]
+            fn build_with(&self, n: u64) -> #struct_name {
+                #struct_name {
+                    #(
+                        #idents: match &self.#idents {
+                            ::core::option::Option::Some(value) => ::core::clone::Clone::clone(value),
+                            ::core::option::Option::None => #defaults,
+                        },
+                    )*
+                }
+            }
+        }
+    })
+}
+
+/// The value of a field that is not overridden, for sequence number `n`.
+fn field_default(
+    ident: &syn::Ident,
+    ty: &Type,
+    attrs: &[syn::Attribute],
+) -> syn::Result<TokenStream2> {
+    if let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("factory")) {
+        let options = parse_options(attr, "factory", &["default", "sequence"])?;
+        return match options.as_slice() {
+            [(key, value)] if key == "default" => {
+                let expr: Expr = value.parse().map_err(|err| {
+                    syn::Error::new(
+                        value.span(),
+                        format!("Invalid factory default for `{}`: {}", ident, err),
+                    )
+                })?;
+                Ok(quote! { ::core::convert::Into::into(#expr) })
+            }
+            [(key, value)] if key == "sequence" => {
+                Ok(quote! { ::core::convert::Into::into(#value.replace("{n}", &n.to_string())) })
+            }
+            _ => Err(syn::Error::new(
+                attr.span(),
+                format!(
+                    "Expected `#[factory(default = \"...\")]` or \
+                     `#[factory(sequence = \"...\")]` on `{}`",
+                    ident
+                ),
+            )),
+        };
+    }
+
+    Ok(match type_name(ty).as_deref() {
+        // `email-1`, `email-2`, ... keeps unique columns unique
+        Some("String") => {
+            let prefix = ident.to_string();
+            quote! { ::std::format!("{}-{}", #prefix, n) }
+        }
+        Some(
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize",
+        ) => quote! {
+            <#ty as ::core::convert::TryFrom<u64>>::try_from(n).unwrap_or(<#ty>::MAX)
+        },
+        _ => quote! { ::core::default::Default::default() },
+    })
+}
+
+/// The last path segment of a type without generic arguments, e.g. `String`.
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last()?;
+            segment
+                .arguments
+                .is_none()
+                .then(|| segment.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(item: &str) -> String {
+        let input: DeriveInput = syn::parse_str(item).unwrap();
+        expand_factory(&input).unwrap().to_string()
+    }
+
+    fn expand_err(item: &str) -> String {
+        let input: DeriveInput = syn::parse_str(item).unwrap();
+        expand_factory(&input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_factory_name_and_setters() {
+        let tokens = expand(
+            r#"#[factory(name = "UserFactory")] pub struct InsertUser { name: String, age: i32 }"#,
+        );
+        assert!(tokens.contains("pub struct UserFactory"));
+        assert!(tokens.contains("pub fn with_name"));
+        assert!(tokens.contains("type Entity = InsertUser"));
+
+        let tokens = expand("struct InsertPost { user_id: i64 }");
+        assert!(tokens.contains("struct InsertPostFactory"));
+    }
+
+    #[test]
+    fn test_field_defaults() {
+        let ident = format_ident!("email");
+        let string: Type = syn::parse_str("String").unwrap();
+        let default = field_default(&ident, &string, &[]).unwrap().to_string();
+        assert!(default.contains("\"email\""));
+
+        let integer: Type = syn::parse_str("i32").unwrap();
+        let default = field_default(&ident, &integer, &[]).unwrap().to_string();
+        assert!(default.contains("try_from (n)"));
+
+        let option: Type = syn::parse_str("Option<String>").unwrap();
+        let default = field_default(&ident, &option, &[]).unwrap().to_string();
+        assert!(default.contains("Default :: default"));
+
+        let input: DeriveInput = syn::parse_str(
+            r#"struct InsertUser { #[factory(sequence = "user{n}@example.com")] email: String }"#,
+        )
+        .unwrap();
+        let Data::Struct(data) = &input.data else {
+            unreachable!()
+        };
+        let field = data.fields.iter().next().unwrap();
+        let default = field_default(&ident, &field.ty, &field.attrs).unwrap().to_string();
+        assert!(default.contains("\"user{n}@example.com\" . replace"));
+    }
+
+    #[test]
+    fn test_invalid_input_is_a_compile_error() {
+        assert_eq!(
+            expand_err("struct Wrapper<T> { value: T }"),
+            "Factory cannot be derived for generic structs"
+        );
+        assert_eq!(
+            expand_err("struct Pair(i32, i32);"),
+            "Factory can only be derived for structs with named fields"
+        );
+        assert_eq!(
+            expand_err("enum Kind { A, B }"),
+            "Factory can only be derived for structs"
+        );
+        assert_eq!(
+            expand_err(r#"#[factory(label = "UserFactory")] struct InsertUser { age: i32 }"#),
+            "Unknown factory option `label`; expected one of: name"
+        );
+        assert!(
+            expand_err(r#"struct InsertUser { #[factory(default = "1 +")] age: i32 }"#)
+                .starts_with("Invalid factory default for `age`")
+        );
+        assert!(expand_err(
+            r#"struct InsertUser { #[factory(default = "1", sequence = "{n}")] age: i32 }"#
+        )
+        .starts_with("Expected `#[factory(default"));
+    }
+}
@@ -0,0 +1,177 @@
+use syn::DeriveInput;
+
+use crate::utils::parse_options;
+
+/// Field bound to the search expression when `#[fts]` or `#[text_search]` has no `query`
+/// option.
+pub(crate) const DEFAULT_QUERY_FIELD: &str = "query";
+
+/// A parsed `#[fts(...)]` declaration of a SQLite FTS5 search.
+pub(crate) struct FtsDecl {
+    /// The FTS5 virtual table
+    pub table: String,
+    /// The indexed columns, in the order of the virtual table
+    pub columns: Vec<String>,
+    /// The field holding the match expression
+    pub query: String,
+    /// Columns returned as `<column>_snippet`
+    pub snippet: Vec<String>,
+    /// Columns returned as `<column>_highlight`
+    pub highlight: Vec<String>,
+    /// Markers placed around matched terms in snippets and highlights
+    pub open: String,
+    pub close: String,
+}
+
+/// Splits a comma separated column list.
+pub(crate) fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|column| column.trim().to_string())
+        .filter(|column| !column.is_empty())
+        .collect()
+}
+
+/// Returns the `#[fts(table = "...", columns = "...")]` declaration, if present.
+pub(crate) fn parse_fts(input: &DeriveInput) -> Option<FtsDecl> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("fts"))?;
+
+    let options = parse_options(
+        attr,
+        "fts",
+        &[
+            "table",
+            "columns",
+            "query",
+            "snippet",
+            "highlight",
+            "open",
+            "close",
+        ],
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.value())
+    };
+
+    let columns = split_list(&option("columns").expect("`#[fts]` requires `columns = \"...\"`"));
+    let snippet = option("snippet").map_or_else(Vec::new, |value| split_list(&value));
+    let highlight = option("highlight").map_or_else(Vec::new, |value| split_list(&value));
+    for column in snippet.iter().chain(&highlight) {
+        if !columns.contains(column) {
+            panic!("fts column `{}` is not listed in `columns`", column);
+        }
+    }
+
+    Some(FtsDecl {
+        table: option("table").expect("`#[fts]` requires `table = \"...\"`"),
+        columns,
+        query: option("query").unwrap_or_else(|| DEFAULT_QUERY_FIELD.to_string()),
+        snippet,
+        highlight,
+        open: option("open").unwrap_or_else(|| "<b>".to_string()),
+        close: option("close").unwrap_or_else(|| "</b>".to_string()),
+    })
+}
+
+/// Quotes a value as an SQL string literal.
+pub(crate) fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl FtsDecl {
+    /// `JOIN <fts> ON <fts>.rowid = <content>.rowid`
+    pub fn join(&self, content_table: &str) -> String {
+        format!(
+            "JOIN {fts} ON {fts}.rowid = {content}.rowid",
+            fts = self.table,
+            content = content_table
+        )
+    }
+
+    /// `<fts> MATCH $n`
+    pub fn match_clause(&self, param_number: usize) -> String {
+        format!("{} MATCH ${}", self.table, param_number)
+    }
+
+    /// The `rank`, snippet and highlight columns added to the select list.
+    pub fn output_columns(&self) -> Vec<String> {
+        let index = |column: &String| self.columns.iter().position(|c| c == column).unwrap();
+        let (open, close) = (literal(&self.open), literal(&self.close));
+
+        let mut outputs = vec![format!("bm25({}) AS rank", self.table)];
+        outputs.extend(self.snippet.iter().map(|column| {
+            format!(
+                "snippet({}, {}, {}, {}, '…', 16) AS {}_snippet",
+                self.table,
+                index(column),
+                open,
+                close,
+                column
+            )
+        }));
+        outputs.extend(self.highlight.iter().map(|column| {
+            format!(
+                "highlight({}, {}, {}, {}) AS {}_highlight",
+                self.table,
+                index(column),
+                open,
+                close,
+                column
+            )
+        }));
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fts() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[fts(table = "products_fts", columns = "title, body", snippet = "body", highlight = "title")]
+            struct SearchProducts {
+                query: String,
+            }
+        };
+
+        let fts = parse_fts(&input).unwrap();
+        assert_eq!(fts.table, "products_fts");
+        assert_eq!(fts.columns, vec!["title", "body"]);
+        assert_eq!(fts.query, "query");
+        assert_eq!(
+            fts.join("products"),
+            "JOIN products_fts ON products_fts.rowid = products.rowid"
+        );
+        assert_eq!(fts.match_clause(1), "products_fts MATCH $1");
+        assert_eq!(
+            fts.output_columns(),
+            vec![
+                "bm25(products_fts) AS rank",
+                "snippet(products_fts, 1, '<b>', '</b>', '…', 16) AS body_snippet",
+                "highlight(products_fts, 0, '<b>', '</b>') AS title_highlight",
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not listed in `columns`")]
+    fn test_parse_fts_unknown_snippet_column() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[fts(table = "products_fts", columns = "title", snippet = "body")]
+            struct SearchProducts {
+                query: String,
+            }
+        };
+        parse_fts(&input);
+    }
+}
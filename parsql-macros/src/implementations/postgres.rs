@@ -1,6 +1,8 @@
 use quote::quote;
 use syn::{Data, DeriveInput, Fields};
 
+use crate::relations::{generate_relations, relation_field_names};
+
 /// Implements the FromRow trait for PostgreSQL database
 /// 
 /// # Arguments
@@ -19,16 +21,33 @@ pub fn generate_from_row(ast: &DeriveInput) -> proc_macro2::TokenStream {
         _ => panic!("FromRow only supports structs"),
     };
 
-    let field_names = fields.iter().map(|f| &f.ident);
-    let field_names_str = fields.iter().map(|f| f.ident.as_ref().unwrap().to_string());
+    // Fields holding related records are filled in by `preload`, not from the row
+    let relation_names = relation_field_names(ast);
+    let (column_fields, relation_fields): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .partition(|ident| !relation_names.contains(&ident.to_string()));
+    let field_names_str = column_fields.iter().map(|ident| ident.to_string());
+
+    let relations = generate_relations(
+        ast,
+        |column| {
+            let predicate = format!("{} = ANY($1)", column);
+            quote! { #predicate }
+        },
+        false,
+    );
 
     quote! {
         impl FromRow for #name {
             fn from_row(row: &Row) -> Result<Self, Error> {
                 Ok(Self {
-                    #(#field_names: row.try_get(#field_names_str)?),*
+                    #(#column_fields: row.try_get(#field_names_str)?,)*
+                    #(#relation_fields: Default::default(),)*
                 })
             }
         }
+
+        #relations
     }
 }
@@ -2,6 +2,8 @@ use quote::quote;
 use syn::{Data, DeriveInput, Fields};
 use proc_macro2::TokenStream;
 
+use crate::relations::{generate_relations, relation_field_names};
+
 /// Implements the FromRow trait for SQLite database
 /// 
 /// # Arguments
@@ -20,20 +22,36 @@ pub fn generate_from_row(input: &DeriveInput) -> TokenStream {
         _ => panic!("Only structs are supported"),
     };
 
-    let field_names = fields.named.iter().map(|f| f.ident.as_ref().unwrap());
-    let field_strings = fields
+    // Fields holding related records are filled in by `preload`, not from the row
+    let relation_names = relation_field_names(input);
+    let (column_fields, relation_fields): (Vec<_>, Vec<_>) = fields
         .named
         .iter()
-        .map(|f| f.ident.as_ref().unwrap().to_string());
+        .map(|f| f.ident.as_ref().unwrap())
+        .partition(|ident| !relation_names.contains(&ident.to_string()));
+    let field_strings = column_fields.iter().map(|ident| ident.to_string());
+
+    let relations = generate_relations(
+        input,
+        |column| {
+            quote! {
+                format!("{} IN ({})", #column, vec!["?"; count].join(", "))
+            }
+        },
+        true,
+    );
 
     quote! {
         impl FromRow for #name {
             fn from_row(row: &Row) -> Result<Self, Error> {
                 Ok(Self {
-                    #(#field_names: row.get(#field_strings)?),*
+                    #(#column_fields: row.get(#field_strings)?,)*
+                    #(#relation_fields: Default::default(),)*
                 })
             }
         }
+
+        #relations
     }
 }
 
@@ -1,4 +1,8 @@
 use crate::query_builder;
+use crate::relations::relation_field_names;
+use crate::tenant::{tenant_column, tenant_scoped_impl};
+use crate::timestamps::timestamps;
+use crate::utils::NOW;
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
@@ -29,12 +33,16 @@ pub(crate) fn derive_insertable_impl(input: TokenStream) -> TokenStream {
                 .value()
         });
 
+    // Relation fields hold loaded records, not columns
+    let relation_fields = relation_field_names(&input);
+
     let fields = if let Data::Struct(data) = &input.data {
         if let Fields::Named(fields) = &data.fields {
             fields
                 .named
                 .iter()
                 .map(|f| f.ident.as_ref().unwrap().to_string())
+                .filter(|f| !relation_fields.contains(f))
                 .collect::<Vec<_>>()
         } else {
             panic!("Insertable can only be derived for structs with named fields");
@@ -43,10 +51,10 @@ pub(crate) fn derive_insertable_impl(input: TokenStream) -> TokenStream {
         panic!("Insertable can only be derived for structs");
     };
 
-    let column_names = fields.iter().map(|f| f.as_str()).collect::<Vec<_>>();
+    let mut column_names = fields.iter().map(|f| f.as_str()).collect::<Vec<_>>();
 
     // Create placeholders for SQL parameters
-    let placeholders: Vec<String> = if cfg!(any(
+    let mut placeholders: Vec<String> = if cfg!(any(
         feature = "postgres",
         feature = "tokio-postgres",
         feature = "deadpool-postgres"
@@ -58,6 +66,40 @@ pub(crate) fn derive_insertable_impl(input: TokenStream) -> TokenStream {
         (0..fields.len()).map(|_| "?".to_string()).collect()
     };
 
+    // Timestamp columns are filled with the database clock unless the struct sets them
+    let timestamps = timestamps(&input);
+    if let Some(timestamps) = &timestamps {
+        for column in [&timestamps.created, &timestamps.updated].into_iter().flatten() {
+            if !column_names.contains(&column.as_str()) {
+                column_names.push(column);
+                placeholders.push(NOW.to_string());
+            }
+        }
+    }
+
+    // The tenant column is filled from the tenant context, after the struct fields
+    let tenant_column = tenant_column(&input);
+    let tenant_scoped = tenant_column.as_ref().map(|column| {
+        if fields.contains(column) {
+            panic!(
+                "The tenant column `{}` is set from the tenant context and cannot be a field",
+                column
+            );
+        }
+        let param_number = fields.len() + 1;
+        column_names.push(column);
+        placeholders.push(if cfg!(any(
+            feature = "postgres",
+            feature = "tokio-postgres",
+            feature = "deadpool-postgres"
+        )) {
+            format!("${}", param_number)
+        } else {
+            "?".to_string()
+        });
+        tenant_scoped_impl(struct_name, column, param_number)
+    });
+
     let mut builder = query_builder::SafeQueryBuilder::new();
 
     builder.add_keyword("INSERT INTO");
@@ -78,17 +120,21 @@ pub(crate) fn derive_insertable_impl(input: TokenStream) -> TokenStream {
 
     let safe_query = builder.build();
 
-    // Log the generated SQL if tracing is enabled
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-MACROS] Generated INSERT SQL: {}", safe_query);
-    }
-
     let expanded = quote! {
         impl SqlCommand for #struct_name {
             fn query() -> String {
                 #safe_query.to_string()
             }
         }
+
+        #tenant_scoped
+    };
+
+    #[cfg(feature = "schema-check")]
+    let expanded = {
+        let checks =
+            crate::schema_check::validate(&input, crate::schema_check::DeriveKind::Insertable);
+        quote! { #expanded #checks }
     };
 
     TokenStream::from(expanded)
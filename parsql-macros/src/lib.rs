@@ -19,19 +19,38 @@
 //! - `SqlParams`: Generates parameter handling code
 //! - `UpdateParams`: Generates parameter handling code for UPDATE operations
 //! - `FromRow`: Generates code for converting database rows to Rust structs
+//!   and batch loading of relations declared with `has_many` / `belongs_to`
+//! - `Columns`: Generates typed column constants for the expression based WHERE DSL
+//! - `Factory`: Generates a factory building sequence-numbered test data for an `Insertable`
+//! - `query!`: Builds a query from raw SQL with captured, typed parameters
+//! - `#[test]`: Runs a PostgreSQL integration test in a transaction that is rolled back
+//!
+//! With the `schema-check` feature, `Queryable`, `Insertable`, `Updateable` and `Deletable`
+//! are validated at compile time against a schema snapshot created by `parsql schema snapshot`.
 
-use std::env;
 
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
+mod columns;
 mod deletable;
+mod factory;
 mod from_row;
+mod fts;
 mod insertable;
 mod numbering_test;
 mod query_builder;
+mod query_macro;
 mod queryable;
+mod relations;
+#[cfg(feature = "schema-check")]
+mod schema_check;
+mod soft_delete;
 mod sql_params;
+mod tenant;
+mod test_macro;
+mod text_search;
+mod timestamps;
 mod update_params;
 mod updateable;
 mod utils;
@@ -51,7 +70,15 @@ pub(crate) use utils::*;
 /// - `table`: The name of the table to update
 /// - `where_clause`: The WHERE clause for the UPDATE statement
 /// - `update`: The columns to update
-#[proc_macro_derive(Updateable, attributes(table, where_clause, update))]
+/// - `timestamps`: Sets the `updated` column to the database clock (optional)
+/// - `version_column`: Optimistic locking column, checked in WHERE and incremented; it must not
+///   be listed in `update`. Updates of versioned models fail with `UpdateError` (optional)
+/// - `tenant_scoped`: Restricts the update to the current tenant,
+///   e.g. `#[tenant_scoped(column = "tenant_id")]` (optional)
+#[proc_macro_derive(
+    Updateable,
+    attributes(table, where_clause, update, timestamps, version_column, tenant_scoped)
+)]
 pub fn derive_updateable(input: TokenStream) -> TokenStream {
     // Let's add special checks for secure parameter usage
     updateable::derive_updateable_impl(input)
@@ -62,7 +89,12 @@ pub fn derive_updateable(input: TokenStream) -> TokenStream {
 /// # Attributes
 /// - `table`: The name of the table to insert into
 /// - `returning`: The column to return after insert (optional)
-#[proc_macro_derive(Insertable, attributes(table, returning, sql_type))]
+/// - `timestamps`: Fills the `created`/`updated` columns with the database clock (optional)
+/// - `tenant_scoped`: Sets the tenant column from the tenant context (optional)
+#[proc_macro_derive(
+    Insertable,
+    attributes(table, returning, sql_type, timestamps, tenant_scoped)
+)]
 pub fn derive_insertable(input: TokenStream) -> TokenStream {
     insertable::derive_insertable_impl(input)
 }
@@ -81,6 +113,20 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
 /// - `offset`: OFFSET clause (optional)
 /// - `distinct`: Make query return distinct results (optional)
 /// - `result_type`: The type to return as query result (optional, defaults to self)
+/// - `soft_delete`: Skip soft-deleted records, e.g. `#[soft_delete(column = "deleted_at")]` (optional)
+/// - `tenant_scoped`: Only return rows of the current tenant,
+///   e.g. `#[tenant_scoped(column = "tenant_id")]` (optional)
+/// - `fts`: SQLite FTS5 search, e.g. `#[fts(table = "products_fts", columns = "title, body")]`
+///   (optional). Joins the FTS table, matches it against the `query` field (or the field named
+///   by `query = "..."`), selects `bm25()` as `rank` and orders by it. `snippet` and
+///   `highlight` list columns returned as `<column>_snippet` / `<column>_highlight`, marked with
+///   `open` and `close` (`<b>`, `</b>`)
+/// - `text_search`: PostgreSQL full-text search, e.g.
+///   `#[text_search(config = "english", columns = "title, body")]` (optional). Matches the
+///   columns, or a stored `tsvector` column given with `vector = "..."`, against the `query`
+///   field with `websearch_to_tsquery` (`syntax = "plain" | "phrase" | "raw"` for the other
+///   parsers), selects `ts_rank_cd()` as `rank` and orders by it. `headline` lists columns
+///   returned as `<column>_headline` through `ts_headline()`
 #[proc_macro_derive(
     Queryable,
     attributes(
@@ -94,7 +140,11 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
         limit,
         offset,
         distinct,
-        result_type
+        result_type,
+        soft_delete,
+        tenant_scoped,
+        fts,
+        text_search
     )
 )]
 pub fn derive_queryable(input: TokenStream) -> TokenStream {
@@ -106,11 +156,79 @@ pub fn derive_queryable(input: TokenStream) -> TokenStream {
 /// # Attributes
 /// - `table`: The name of the table to delete from
 /// - `where_clause`: The WHERE clause for the DELETE statement
-#[proc_macro_derive(Deletable, attributes(table, where_clause))]
+/// - `soft_delete`: Mark records as deleted instead of removing them,
+///   e.g. `#[soft_delete(column = "deleted_at")]` (optional)
+/// - `tenant_scoped`: Restricts the delete to the current tenant (optional)
+#[proc_macro_derive(Deletable, attributes(table, where_clause, soft_delete, tenant_scoped))]
 pub fn derive_deletable(input: TokenStream) -> TokenStream {
     deletable::derive_deletable_impl(input)
 }
 
+/// Derive macro for generating typed column constants.
+///
+/// Implements the `Columns` trait and generates a module named after the table with one
+/// `Column<T>` constant per field, used to build type-checked WHERE expressions:
+///
+/// ```rust,ignore
+/// #[derive(Columns, FromRow)]
+/// #[table("users")]
+/// pub struct User {
+///     pub id: i64,
+///     pub email: String,
+///     pub state: i16,
+/// }
+///
+/// let expr = users::state.eq(1).and(users::email.like("%@example.com"));
+/// let active: Vec<User> = fetch_all_where::<User>(&conn, expr)?;
+/// ```
+///
+/// # Attributes
+/// - `table`: The name of the table
+/// - `columns_module`: The name of the generated module, needed when several structs of one
+///   table derive `Columns` in the same module (optional)
+/// - `soft_delete`: `fetch_all_where` skips soft-deleted rows, as `Queryable` does (optional)
+/// - `tenant_scoped`: `fetch_all_where` only returns rows of the tenant bound with
+///   `TenantContext::filter` (optional)
+#[proc_macro_derive(Columns, attributes(table, columns_module, soft_delete, tenant_scoped))]
+pub fn derive_columns(input: TokenStream) -> TokenStream {
+    columns::derive_columns_impl(input)
+}
+
+/// Derive macro for generating seed data factories.
+///
+/// Generates `<Struct>Factory` with a `with_<field>` method per field and implements the
+/// `Factory` trait, whose `build` method builds sequence-numbered entities; the backend's
+/// `FactoryExt` adds `create` and `create_many`, which insert them with `CrudOps::insert`:
+///
+/// ```rust,ignore
+/// #[derive(Insertable, SqlParams, Factory)]
+/// #[table("users")]
+/// #[returning("id")]
+/// #[factory(name = "UserFactory")]
+/// pub struct InsertUser {
+///     pub name: String,
+///     #[factory(sequence = "user{n}@example.com")]
+///     pub email: String,
+///     #[factory(default = "n % 2 == 0")]
+///     pub active: bool,
+/// }
+///
+/// let user_id: i64 = UserFactory::new().with_email("ada@example.com").create(&conn)?;
+/// let post_ids: Vec<i64> = PostFactory::new().with_user_id(user_id).create_many(&conn, 3)?;
+/// ```
+///
+/// Fields that are not set get `<field>-<n>` for `String`, `n` for integers and
+/// `Default::default()` otherwise, where `n` is the sequence number of the built entity.
+///
+/// # Attributes
+/// - `factory(name = "...")` on the struct: The name of the factory
+/// - `factory(default = "...")` on a field: An expression for the field; `n` is in scope
+/// - `factory(sequence = "...")` on a field: A string with `{n}` replaced by the sequence number
+#[proc_macro_derive(Factory, attributes(factory))]
+pub fn derive_factory(input: TokenStream) -> TokenStream {
+    factory::derive_factory_impl(input)
+}
+
 /// Derive macro for generating SQL parameter handling code.
 ///
 /// # Attributes
@@ -125,7 +243,8 @@ pub fn derive_sql_params(input: TokenStream) -> TokenStream {
 /// # Attributes
 /// - `update`: The columns to update
 /// - `where_clause`: The WHERE clause containing parameter placeholders
-#[proc_macro_derive(UpdateParams, attributes(update, where_clause))]
+/// - `version_column`: Binds the expected version after the WHERE parameters (optional)
+#[proc_macro_derive(UpdateParams, attributes(update, where_clause, version_column))]
 pub fn derive_update_params(input: TokenStream) -> TokenStream {
     update_params::derive_update_params_impl(input)
 }
@@ -141,6 +260,16 @@ pub fn derive_update_params(input: TokenStream) -> TokenStream {
 /// - `deadpool-postgres`: Generate code for Deadpool PostgreSQL
 /// - `sqlite`: Generate code for SQLite
 ///
+/// # Attributes
+/// - `has_many`: Declares a one-to-many relation, e.g.
+///   `#[has_many(OrderItem, foreign_key = "order_id")]` (optional)
+/// - `belongs_to`: Declares a many-to-one relation, e.g.
+///   `#[belongs_to(Customer, foreign_key = "customer_id")]` (optional)
+///
+/// Both accept `table`, `foreign_key`, `references` (defaults to `id`) and `field`
+/// options. The relation field (`Vec<T>` for `has_many`, `Option<T>` for `belongs_to`)
+/// is not read from the row; it is filled by the backend's `preload` function.
+///
 /// # Usage
 /// ```rust
 /// #[derive(FromRow)]
@@ -150,14 +279,75 @@ pub fn derive_update_params(input: TokenStream) -> TokenStream {
 ///     email: String,
 /// }
 /// ```
-#[proc_macro_derive(FromRow)]
+#[proc_macro_derive(FromRow, attributes(has_many, belongs_to))]
 pub fn derive_from_row(input: TokenStream) -> TokenStream {
     from_row::expand_from_row(input)
 }
 
+/// Builds a query from raw SQL for cases the attribute DSL cannot express
+/// (CTEs, window functions, ...).
+///
+/// `{expr}` in the SQL captures a value from the surrounding scope as a bound parameter and
+/// is rewritten to the backend's placeholder (`$1` for PostgreSQL, `?1` for SQLite). A value
+/// captured more than once is bound once. Use `{{` and `}}` for literal braces.
+///
+/// The result borrows the captured values and implements `SqlQuery<R>` and `SqlParams`,
+/// so it can be passed to `fetch`, `fetch_all` and the `CrudOps` methods; rows are mapped
+/// through `R`'s `FromRow` implementation.
+///
+/// # Usage
+/// ```rust,ignore
+/// let min_total = 100.0;
+/// let customers: Vec<CustomerTotal> = fetch_all(&conn, &query!(
+///     "WITH totals AS (SELECT customer_id, SUM(total) AS total FROM orders GROUP BY customer_id)
+///      SELECT c.id, c.name, t.total FROM customers c JOIN totals t ON t.customer_id = c.id
+///      WHERE t.total > {min_total} AND c.region = {filter.region}",
+///     CustomerTotal
+/// ))?;
+/// ```
+#[proc_macro]
+pub fn query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as query_macro::QueryInput);
+    query_macro::expand_query(input).into()
+}
+
+/// Runs a PostgreSQL integration test inside a transaction that is rolled back when the test
+/// ends, even if it panics, so tests can run in parallel against one database.
+///
+/// The database is read from `DATABASE_URL`. A function takes the transaction as its only
+/// argument: `&mut parsql::postgres::Transaction<'_>` for a sync test, or
+/// `&tokio_postgres::Transaction<'_>` for an `async fn`, which is run on a single-threaded
+/// runtime. Other attributes such as `#[should_panic]` are kept.
+///
+/// Sync tests need the `postgres` feature of parsql and `async fn` tests the
+/// `tokio-postgres` feature; without it, the test fails to compile with an error naming it.
+///
+/// # Usage
+/// ```rust,ignore
+/// #[parsql::test]
+/// fn inserts_user(tx: &mut Transaction<'_>) {
+///     let id: i64 = tx.insert(InsertUser { name: "Ada".into() }).unwrap();
+///     assert!(id > 0);
+/// }
+///
+/// #[parsql::test]
+/// async fn finds_user(tx: &Transaction<'_>) {
+///     tx.insert::<_, i64>(InsertUser { name: "Ada".into() }).await.unwrap();
+///     let user = tx.fetch(GetUser { name: "Ada".into() }).await.unwrap();
+///     assert_eq!(user.name, "Ada");
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as syn::ItemFn);
+    test_macro::expand_test(args.into(), item, test_macro::Backends::enabled())
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
 // Geriye dönük uyumluluk için özel veritabanı makroları
 #[cfg(feature = "sqlite")]
-#[proc_macro_derive(FromRowSqlite)]
+#[proc_macro_derive(FromRowSqlite, attributes(has_many, belongs_to))]
 pub fn derive_from_row_sqlite(input: TokenStream) -> TokenStream {
     crate::implementations::sqlite::generate_from_row(&parse_macro_input!(input as DeriveInput))
         .into()
@@ -168,7 +358,7 @@ pub fn derive_from_row_sqlite(input: TokenStream) -> TokenStream {
     feature = "tokio-postgres",
     feature = "deadpool-postgres"
 ))]
-#[proc_macro_derive(FromRowPostgres)]
+#[proc_macro_derive(FromRowPostgres, attributes(has_many, belongs_to))]
 pub fn derive_from_row_postgres(input: TokenStream) -> TokenStream {
     crate::implementations::postgres::generate_from_row(&parse_macro_input!(input as DeriveInput))
         .into()
@@ -177,12 +367,3 @@ pub fn derive_from_row_postgres(input: TokenStream) -> TokenStream {
 // SqlParamCounter ve number_where_clause_params fonksiyonlarını sadece test için dışa aktarıyoruz
 #[cfg(test)]
 pub(crate) use utils::{number_where_clause_params, SqlParamCounter};
-
-/// Log mesajlarını yazdırmak için yardımcı fonksiyon
-pub(crate) fn log_message(message: &str) {
-    if let Ok(trace) = env::var("PARSQL_TRACE") {
-        if trace == "1" {
-            println!("{}", message);
-        }
-    }
-}
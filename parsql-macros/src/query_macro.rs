@@ -0,0 +1,202 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, LitStr, Token, Type};
+
+/// Input of `query!("SELECT ... WHERE id = {id}", ResultType)`.
+pub(crate) struct QueryInput {
+    sql: LitStr,
+    result_type: Type,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let sql: LitStr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let result_type: Type = input.parse()?;
+        // Allow a trailing comma
+        let _ = input.parse::<Token![,]>();
+        Ok(QueryInput { sql, result_type })
+    }
+}
+
+/// A SQL template with `{expr}` captures replaced by numbered placeholders.
+pub(crate) struct Template {
+    /// The SQL with placeholders
+    pub sql: String,
+    /// The captured expressions, in placeholder order
+    pub captures: Vec<String>,
+}
+
+/// Parses a SQL template. `{expr}` captures a value, `{{` and `}}` produce literal braces.
+/// A capture used more than once is bound once and its placeholder is reused.
+///
+/// # Arguments
+/// * `template` - The SQL template
+/// * `prefix` - Placeholder prefix: `$` for PostgreSQL (`$1`), `?` for SQLite (`?1`)
+pub(crate) fn parse_template(template: &str, prefix: char) -> Result<Template, String> {
+    let mut sql = String::new();
+    let mut captures: Vec<String> = Vec::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                sql.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                sql.push('}');
+            }
+            '{' => {
+                let mut capture = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => capture.push(c),
+                        None => return Err("unterminated `{` in query; use `{{` for a literal brace".to_string()),
+                    }
+                }
+                let capture = capture.trim().to_string();
+                if capture.is_empty() {
+                    return Err("empty `{}` in query; name the value to bind, e.g. `{id}`".to_string());
+                }
+                let index = match captures.iter().position(|c| *c == capture) {
+                    Some(index) => index,
+                    None => {
+                        captures.push(capture);
+                        captures.len() - 1
+                    }
+                };
+                sql.push(prefix);
+                sql.push_str(&(index + 1).to_string());
+            }
+            '}' => return Err("unmatched `}` in query; use `}}` for a literal brace".to_string()),
+            c => sql.push(c),
+        }
+    }
+
+    Ok(Template { sql, captures })
+}
+
+/// Implements the `query!` macro.
+///
+/// Expands to a value of an anonymous struct that borrows the captured values and
+/// implements `SqlQuery<R>` and `SqlParams`, so it can be passed to `fetch`/`fetch_all`.
+pub(crate) fn expand_query(input: QueryInput) -> TokenStream {
+    let prefix = if cfg!(any(
+        feature = "postgres",
+        feature = "tokio-postgres",
+        feature = "deadpool-postgres"
+    )) {
+        '$'
+    } else {
+        '?'
+    };
+
+    let template = match parse_template(&input.sql.value(), prefix) {
+        Ok(template) => template,
+        Err(message) => return syn::Error::new_spanned(&input.sql, message).to_compile_error(),
+    };
+
+    let mut exprs = Vec::new();
+    for capture in &template.captures {
+        match syn::parse_str::<Expr>(capture) {
+            // Re-span so that local variables resolve at the call site and errors point at the query
+            Ok(expr) => exprs.push(respan(quote! { #expr }, input.sql.span())),
+            Err(_) => {
+                return syn::Error::new_spanned(
+                    &input.sql,
+                    format!("`{{{}}}` is not a valid Rust expression", capture),
+                )
+                .to_compile_error()
+            }
+        }
+    }
+
+    let sql = &template.sql;
+    let result_type = &input.result_type;
+    let names: Vec<_> = (0..exprs.len()).map(|i| format_ident!("p{}", i)).collect();
+    let types: Vec<_> = (0..exprs.len()).map(|i| format_ident!("T{}", i)).collect();
+
+    quote! {
+        {
+            struct __ParsqlQuery<'a, #(#types),*> {
+                __marker: ::std::marker::PhantomData<&'a ()>,
+                #(#names: &'a #types,)*
+            }
+
+            impl<'a, #(#types),*> SqlQuery<#result_type> for __ParsqlQuery<'a, #(#types),*> {
+                fn query() -> String {
+                    #sql.to_string()
+                }
+            }
+
+            impl<'a, #(#types: ToSql + Sync),*> SqlParams for __ParsqlQuery<'a, #(#types),*> {
+                fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+                    vec![#(self.#names as &(dyn ToSql + Sync)),*]
+                }
+            }
+
+            __ParsqlQuery {
+                __marker: ::std::marker::PhantomData,
+                #(#names: &(#exprs),)*
+            }
+        }
+    }
+}
+
+fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut tree| {
+            if let proc_macro2::TokenTree::Group(group) = &tree {
+                let mut new_group =
+                    proc_macro2::Group::new(group.delimiter(), respan(group.stream(), span));
+                new_group.set_span(span);
+                tree = proc_macro2::TokenTree::Group(new_group);
+            } else {
+                tree.set_span(span);
+            }
+            tree
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholders() {
+        let template =
+            parse_template("SELECT * FROM users WHERE id = {id} AND age > {min}", '$').unwrap();
+        assert_eq!(template.sql, "SELECT * FROM users WHERE id = $1 AND age > $2");
+        assert_eq!(template.captures, vec!["id", "min"]);
+
+        let template = parse_template("SELECT * FROM users WHERE id = {id}", '?').unwrap();
+        assert_eq!(template.sql, "SELECT * FROM users WHERE id = ?1");
+    }
+
+    #[test]
+    fn test_repeated_capture_and_escapes() {
+        let template = parse_template(
+            "SELECT '{{1,2}}'::int[] WHERE a = {filter.id} OR b = { filter.id } OR c = {x}",
+            '$',
+        )
+        .unwrap();
+        assert_eq!(
+            template.sql,
+            "SELECT '{1,2}'::int[] WHERE a = $1 OR b = $1 OR c = $2"
+        );
+        assert_eq!(template.captures, vec!["filter.id", "x"]);
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(parse_template("WHERE id = {id", '$').is_err());
+        assert!(parse_template("WHERE id = {}", '$').is_err());
+        assert!(parse_template("WHERE id = id}", '$').is_err());
+    }
+}
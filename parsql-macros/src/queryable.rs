@@ -1,4 +1,8 @@
-use crate::{log_message, number_where_clause_params, query_builder, SqlParamCounter};
+use crate::relations::relation_field_names;
+use crate::soft_delete::{qualified_column, soft_delete_column, with_null_check};
+use crate::tenant::{tenant_column, tenant_scoped_impl, with_tenant_check};
+use crate::text_search::parse_search;
+use crate::{number_where_clause_params, query_builder, SqlParamCounter};
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
@@ -46,12 +50,16 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
                 .value()
         });
 
+    // Relation fields are loaded separately and are never selected as columns
+    let relation_fields = relation_field_names(&input);
+
     let fields = if let Data::Struct(data) = &input.data {
         if let Fields::Named(fields) = &data.fields {
             fields
                 .named
                 .iter()
                 .map(|f| f.ident.as_ref().unwrap().to_string())
+                .filter(|f| !relation_fields.contains(f))
                 .collect::<Vec<_>>()
         } else {
             panic!("Queryable can only be derived for structs with named fields");
@@ -60,7 +68,9 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
         panic!("Queryable can only be derived for structs");
     };
 
-    let joins: Vec<String> = input
+    let search = parse_search(&input);
+
+    let mut joins: Vec<String> = input
         .attrs
         .iter()
         .filter(|attr| attr.path().is_ident("join"))
@@ -71,17 +81,44 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
         })
         .collect();
 
+    // The FTS table is joined back to the content table before any user join
+    if let Some(join) = search.as_ref().and_then(|search| search.join(&table)) {
+        joins.insert(0, join);
+    }
+
     let tables = table.to_string();
 
     // SQL parametrelerinin numaralandırması için SqlParamCounter kullanıyoruz
     // Bu sayede tüm parametreler her zaman 1'den başlayacak ve tutarlı şekilde artacak
     let mut param_counter = SqlParamCounter::new();
 
+    // The search expression is always the first parameter
+    let search_param = search.as_ref().map(|_| param_counter.next());
+    let match_clause = search
+        .as_ref()
+        .zip(search_param)
+        .map(|(search, param_number)| search.predicate(&table, param_number));
+
     // WHERE cümlesini numaralandır
-    let adjusted_where_clause = where_clause
+    let mut adjusted_where_clause = where_clause
         .map(|clause| number_where_clause_params(&clause, &mut param_counter))
         .unwrap_or_else(|| "".to_string());
 
+    if let Some(match_clause) = match_clause {
+        adjusted_where_clause = if adjusted_where_clause.trim().is_empty() {
+            match_clause
+        } else {
+            format!("{} AND ({})", match_clause, adjusted_where_clause.trim())
+        };
+    }
+
+    // The tenant value is bound right after the WHERE parameters, before HAVING
+    let tenant_scoped = tenant_column(&input).map(|column| {
+        let param_number = param_counter.next();
+        adjusted_where_clause = with_tenant_check(&adjusted_where_clause, &column, param_number);
+        tenant_scoped_impl(struct_name, &column, param_number)
+    });
+
     // Get the optional select attribute
     let select = input
         .attrs
@@ -94,13 +131,21 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
         });
 
     // If select is not defined, use all fields
-    let select = select.unwrap_or_else(|| {
-        fields
-            .iter()
-            .map(|f| f.as_str())
-            .collect::<Vec<_>>()
-            .join(", ")
-    });
+    let select = match search.as_ref().zip(search_param) {
+        // Search structs hold parameters, so the content row is selected with the rank columns
+        Some((search, param_number)) => {
+            let mut columns = vec![select.unwrap_or_else(|| format!("{}.*", table))];
+            columns.extend(search.output_columns(&table, param_number));
+            columns.join(", ")
+        }
+        None => select.unwrap_or_else(|| {
+            fields
+                .iter()
+                .map(|f| f.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }),
+    };
 
     // Get the optional group_by attribute
     let group_by = input
@@ -131,7 +176,7 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
         .map(|clause| number_where_clause_params(clause, &mut param_counter))
         .unwrap_or_else(|| "".to_string());
 
-    // Get the optional order_by attribute
+    // Get the optional order_by attribute; searches are ordered by relevance by default
     let order_by = input
         .attrs
         .iter()
@@ -140,7 +185,8 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
             attr.parse_args::<syn::LitStr>()
                 .expect("Expected a string literal for order_by")
                 .value()
-        });
+        })
+        .or_else(|| search.as_ref().map(|search| search.order_by().to_string()));
 
     // Get the optional distinct attribute
     let distinct = input
@@ -160,93 +206,114 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
                 .map(|lit| lit.value())
         });
 
-    let mut builder = query_builder::SafeQueryBuilder::new();
+    // The same SELECT is built with and without the soft-delete filter
+    let build_query = |where_clause: &str| -> String {
+        let mut builder = query_builder::SafeQueryBuilder::new();
 
-    builder.add_keyword("SELECT");
+        builder.add_keyword("SELECT");
     
-    // Add DISTINCT if present
-    if distinct {
-        if let Some(columns) = &distinct_columns {
-            // DISTINCT ON (columns) - PostgreSQL specific
-            builder.add_keyword("DISTINCT ON");
-            builder.add_raw(&format!("({})", columns));
-        } else {
-            // Simple DISTINCT
-            builder.add_keyword("DISTINCT");
+        // Add DISTINCT if present
+        if distinct {
+            if let Some(columns) = &distinct_columns {
+                // DISTINCT ON (columns) - PostgreSQL specific
+                builder.add_keyword("DISTINCT ON");
+                builder.add_raw(&format!("({})", columns));
+            } else {
+                // Simple DISTINCT
+                builder.add_keyword("DISTINCT");
+            }
         }
-    }
     
-    builder.add_raw(&select);
-    builder.add_keyword("FROM");
-    builder.add_identifier(&tables);
-
-    // Add join expressions separately and place a space around each one
-    for join in joins {
-        builder.add_raw(&format!(" {} ", join.trim()));
-    }
+        builder.add_raw(&select);
+        builder.add_keyword("FROM");
+        builder.add_identifier(&tables);
 
-    if !adjusted_where_clause.is_empty() {
-        builder.add_keyword("WHERE");
-        builder.add_raw(&adjusted_where_clause);
-    }
+        // Add join expressions separately and place a space around each one
+        for join in &joins {
+            builder.add_raw(&format!(" {} ", join.trim()));
+        }
 
-    // Add GROUP BY clause
-    if let Some(group_by_clause) = group_by {
-        builder.add_keyword("GROUP BY");
-        builder.add_raw(&group_by_clause);
-    }
+        if !where_clause.is_empty() {
+            builder.add_keyword("WHERE");
+            builder.add_raw(where_clause);
+        }
 
-    // HAVING cümlesi
-    if let Some(_) = having {
-        builder.add_keyword("HAVING");
-        builder.add_raw(&adjusted_having_clause);
-    }
+        // Add GROUP BY clause
+        if let Some(group_by_clause) = &group_by {
+            builder.add_keyword("GROUP BY");
+            builder.add_raw(group_by_clause);
+        }
 
-    // Add ORDER BY clause
-    if let Some(order_by_clause) = order_by {
-        builder.add_keyword("ORDER BY");
-        builder.add_raw(&order_by_clause);
-    }
+        // HAVING cümlesi
+        if having.is_some() {
+            builder.add_keyword("HAVING");
+            builder.add_raw(&adjusted_having_clause);
+        }
 
-    // Add LIMIT clause
-    let limit = input
-        .attrs
-        .iter()
-        .find(|attr| attr.path().is_ident("limit"))
-        .map(|attr| {
-            attr.parse_args::<syn::LitInt>()
-                .expect("Expected an integer literal for limit")
-                .base10_parse::<u64>()
-                .expect("Failed to parse limit value as an integer")
-        });
+        // Add ORDER BY clause
+        if let Some(order_by_clause) = &order_by {
+            builder.add_keyword("ORDER BY");
+            builder.add_raw(order_by_clause);
+        }
 
-    if let Some(limit_value) = limit {
-        builder.add_keyword("LIMIT");
-        builder.add_raw(&limit_value.to_string());
-    }
+        // Add LIMIT clause
+        let limit = input
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("limit"))
+            .map(|attr| {
+                attr.parse_args::<syn::LitInt>()
+                    .expect("Expected an integer literal for limit")
+                    .base10_parse::<u64>()
+                    .expect("Failed to parse limit value as an integer")
+            });
+
+        if let Some(limit_value) = limit {
+            builder.add_keyword("LIMIT");
+            builder.add_raw(&limit_value.to_string());
+        }
 
-    // Add OFFSET clause
-    let offset = input
-        .attrs
-        .iter()
-        .find(|attr| attr.path().is_ident("offset"))
-        .map(|attr| {
-            attr.parse_args::<syn::LitInt>()
-                .expect("Expected an integer literal for offset")
-                .base10_parse::<u64>()
-                .expect("Failed to parse offset value as an integer")
-        });
+        // Add OFFSET clause
+        let offset = input
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("offset"))
+            .map(|attr| {
+                attr.parse_args::<syn::LitInt>()
+                    .expect("Expected an integer literal for offset")
+                    .base10_parse::<u64>()
+                    .expect("Failed to parse offset value as an integer")
+            });
+
+        if let Some(offset_value) = offset {
+            builder.add_keyword("OFFSET");
+            builder.add_raw(&offset_value.to_string());
+        }
 
-    if let Some(offset_value) = offset {
-        builder.add_keyword("OFFSET");
-        builder.add_raw(&offset_value.to_string());
-    }
+        builder.build()
+    };
 
-    let safe_query = builder.build();
+    // The marker is qualified, since joined tables may have a column with the same name
+    let soft_delete = soft_delete_column(&input);
+    let safe_query = match &soft_delete {
+        Some(column) => {
+            let column = qualified_column(&table, column);
+            build_query(&with_null_check(&adjusted_where_clause, &column, false))
+        }
+        None => build_query(&adjusted_where_clause),
+    };
 
-    // Log mesajlarını PARSQL_TRACE kontrolü ile yazdır
-    log_message(&format!("Generated SQL Query: {}", safe_query));
-    log_message(&format!("Total param count: {}", param_counter.count()));
+    // Soft-deleted records can still be queried through `WithDeleted`
+    let with_deleted = soft_delete.map(|_| {
+        let query_with_deleted = build_query(&adjusted_where_clause);
+        quote! {
+            impl SoftDeleteQuery for #struct_name {
+                fn query_with_deleted() -> String {
+                    #query_with_deleted.to_string()
+                }
+            }
+        }
+    });
 
     // Generate SqlQuery implementation with generic type parameter
     let expanded = quote! {
@@ -255,6 +322,16 @@ pub fn derive_queryable_impl(input: TokenStream) -> TokenStream {
                 #safe_query.to_string()
             }
         }
+
+        #with_deleted
+        #tenant_scoped
+    };
+
+    #[cfg(feature = "schema-check")]
+    let expanded = {
+        let checks =
+            crate::schema_check::validate(&input, crate::schema_check::DeriveKind::Queryable);
+        quote! { #expanded #checks }
     };
 
     TokenStream::from(expanded)
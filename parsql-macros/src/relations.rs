@@ -0,0 +1,322 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse::ParseStream, Attribute, Data, DeriveInput, Fields, Ident, LitStr, Path, Token, Type};
+
+use crate::utils::option_inner;
+
+/// The kind of a relation declared on a model.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum RelationKind {
+    /// `#[has_many(Child, foreign_key = "...")]`: the related rows point at this record.
+    HasMany,
+    /// `#[belongs_to(Parent, foreign_key = "...")]`: this record points at the related row.
+    BelongsTo,
+}
+
+/// A parsed `has_many` / `belongs_to` declaration.
+pub(crate) struct RelationDecl {
+    pub kind: RelationKind,
+    /// The related model type
+    pub target: Path,
+    /// The table the related rows are loaded from
+    pub table: String,
+    /// The column holding the reference
+    pub foreign_key: String,
+    /// The column being referenced (`id` unless overridden)
+    pub references: String,
+    /// The field of the model that receives the loaded rows
+    pub field: String,
+}
+
+/// Converts a `CamelCase` type name to `snake_case`.
+pub(crate) fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// Naive English pluralization used to derive default table names.
+pub(crate) fn pluralize(word: &str) -> String {
+    if word.ends_with('s') || word.ends_with('x') || word.ends_with("ch") || word.ends_with("sh") {
+        format!("{}es", word)
+    } else if word.ends_with('y')
+        && !word
+            .chars()
+            .rev()
+            .nth(1)
+            .map(|c| "aeiou".contains(c))
+            .unwrap_or(false)
+    {
+        format!("{}ies", &word[..word.len() - 1])
+    } else {
+        format!("{}s", word)
+    }
+}
+
+fn parse_relation(attr: &Attribute, kind: RelationKind, model: &Ident) -> RelationDecl {
+    let (target, options) = attr
+        .parse_args_with(|input: ParseStream| {
+            let target: Path = input.parse()?;
+            let mut options = Vec::new();
+            while input.parse::<Token![,]>().is_ok() {
+                if input.is_empty() {
+                    break;
+                }
+                let key: Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+                let value: LitStr = input.parse()?;
+                options.push((key.to_string(), value.value()));
+            }
+            Ok((target, options))
+        })
+        .expect("Expected `Type, key = \"value\", ...` in relation attribute");
+
+    let target_name = target
+        .segments
+        .last()
+        .expect("Relation target must be a type name")
+        .ident
+        .to_string();
+    let target_snake = to_snake_case(&target_name);
+
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    for (key, _) in &options {
+        if !["table", "foreign_key", "references", "field"].contains(&key.as_str()) {
+            panic!(
+                "Unknown relation option `{}`; expected one of: table, foreign_key, references, field",
+                key
+            );
+        }
+    }
+
+    let (default_foreign_key, default_field) = match kind {
+        RelationKind::HasMany => (
+            format!("{}_id", to_snake_case(&model.to_string())),
+            pluralize(&target_snake),
+        ),
+        RelationKind::BelongsTo => (format!("{}_id", target_snake), target_snake.clone()),
+    };
+
+    RelationDecl {
+        kind,
+        table: option("table").unwrap_or_else(|| pluralize(&target_snake)),
+        foreign_key: option("foreign_key").unwrap_or(default_foreign_key),
+        references: option("references").unwrap_or_else(|| "id".to_string()),
+        field: option("field").unwrap_or(default_field),
+        target,
+    }
+}
+
+/// Collects the `has_many` and `belongs_to` declarations of a model.
+pub(crate) fn parse_relations(input: &DeriveInput) -> Vec<RelationDecl> {
+    input
+        .attrs
+        .iter()
+        .filter_map(|attr| {
+            if attr.path().is_ident("has_many") {
+                Some(parse_relation(attr, RelationKind::HasMany, &input.ident))
+            } else if attr.path().is_ident("belongs_to") {
+                Some(parse_relation(attr, RelationKind::BelongsTo, &input.ident))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Returns the names of the fields that hold related records.
+/// These fields are not columns and must be left out of generated SQL and row mapping.
+pub(crate) fn relation_field_names(input: &DeriveInput) -> Vec<String> {
+    parse_relations(input)
+        .into_iter()
+        .map(|relation| relation.field)
+        .collect()
+}
+
+/// Returns the type of the field `name`, without `Option`: a nullable key column is looked
+/// up by its values, and a `NULL` key has no related rows.
+fn key_type<'a>(input: &'a DeriveInput, name: &str) -> &'a Type {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("Relations are only supported on structs with named fields"),
+        },
+        _ => panic!("Relations are only supported on structs"),
+    };
+
+    let ty = &fields
+        .iter()
+        .find(|f| f.ident.as_ref().map(|i| i == name).unwrap_or(false))
+        .unwrap_or_else(|| panic!("Field `{}` used by a relation was not found", name))
+        .ty;
+    option_inner(ty).unwrap_or(ty)
+}
+
+/// Generates `Relation` implementations for every relation declared on the model.
+///
+/// # Arguments
+/// * `input` - The model definition
+/// * `in_list` - Builds the key predicate from the column name; backends differ here
+///   (`= ANY($1)` for PostgreSQL, `IN (?, ...)` for SQLite)
+/// * `takes_count` - Whether `relation_query` receives the number of keys
+pub(crate) fn generate_relations(
+    input: &DeriveInput,
+    in_list: fn(&str) -> TokenStream,
+    takes_count: bool,
+) -> TokenStream {
+    let name = &input.ident;
+
+    let impls = parse_relations(input).into_iter().map(|relation| {
+        let target = &relation.target;
+        let field = format_ident!("{}", relation.field);
+        let references = format_ident!("{}", relation.references);
+        let foreign_key = format_ident!("{}", relation.foreign_key);
+
+        let (lookup_column, key_type, own_key, related_key, attach) = match relation.kind {
+            RelationKind::HasMany => (
+                relation.foreign_key.clone(),
+                key_type(input, &relation.references),
+                quote! { self.#references.clone() },
+                quote! { related.#foreign_key.clone() },
+                quote! { self.#field = related; },
+            ),
+            RelationKind::BelongsTo => (
+                relation.references.clone(),
+                key_type(input, &relation.foreign_key),
+                quote! { self.#foreign_key.clone() },
+                quote! { related.#references.clone() },
+                quote! { self.#field = related.into_iter().next(); },
+            ),
+        };
+
+        let prefix = format!("SELECT * FROM {} WHERE ", relation.table);
+        let predicate = in_list(&lookup_column);
+        let signature = if takes_count {
+            quote! { fn relation_query(count: usize) -> String }
+        } else {
+            quote! { fn relation_query() -> String }
+        };
+
+        // `Option::from` takes both a key and an `Option` of it, so either side of the
+        // relation may be nullable
+        quote! {
+            impl Relation<#target> for #name {
+                type Key = #key_type;
+
+                #signature {
+                    format!("{}{}", #prefix, #predicate)
+                }
+
+                fn relation_key(&self) -> ::core::option::Option<Self::Key> {
+                    ::core::option::Option::<Self::Key>::from(#own_key)
+                }
+
+                fn related_key(related: &#target) -> ::core::option::Option<Self::Key> {
+                    ::core::option::Option::<Self::Key>::from(#related_key)
+                }
+
+                fn attach(&mut self, related: Vec<#target>) {
+                    #attach
+                }
+            }
+        }
+    });
+
+    quote! { #(#impls)* }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(to_snake_case("OrderItem"), "order_item");
+        assert_eq!(to_snake_case("User"), "user");
+    }
+
+    #[test]
+    fn test_pluralize() {
+        assert_eq!(pluralize("order_item"), "order_items");
+        assert_eq!(pluralize("category"), "categories");
+        assert_eq!(pluralize("key"), "keys");
+        assert_eq!(pluralize("address"), "addresses");
+    }
+
+    #[test]
+    fn test_parse_relations_defaults() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[has_many(OrderItem, foreign_key = "order_id")]
+            #[belongs_to(Customer)]
+            struct Order {
+                id: i64,
+                customer_id: i64,
+                order_items: Vec<OrderItem>,
+                customer: Option<Customer>,
+            }
+        };
+
+        let relations = parse_relations(&input);
+        assert_eq!(relations.len(), 2);
+
+        assert_eq!(relations[0].kind, RelationKind::HasMany);
+        assert_eq!(relations[0].table, "order_items");
+        assert_eq!(relations[0].foreign_key, "order_id");
+        assert_eq!(relations[0].references, "id");
+        assert_eq!(relations[0].field, "order_items");
+
+        assert_eq!(relations[1].kind, RelationKind::BelongsTo);
+        assert_eq!(relations[1].table, "customers");
+        assert_eq!(relations[1].foreign_key, "customer_id");
+        assert_eq!(relations[1].field, "customer");
+    }
+
+    #[test]
+    fn test_parse_relations_overrides() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[has_many(OrderItem, table = "line_items", foreign_key = "order_ref", references = "code", field = "items")]
+            struct Order {
+                code: String,
+                items: Vec<OrderItem>,
+            }
+        };
+
+        let relations = parse_relations(&input);
+        assert_eq!(relations[0].table, "line_items");
+        assert_eq!(relations[0].foreign_key, "order_ref");
+        assert_eq!(relations[0].references, "code");
+        assert_eq!(relations[0].field, "items");
+        assert_eq!(relation_field_names(&input), vec!["items".to_string()]);
+    }
+
+    #[test]
+    fn test_nullable_key_type() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[belongs_to(Customer)]
+            struct Order {
+                id: i64,
+                customer_id: Option<i64>,
+                customer: Option<Customer>,
+            }
+        };
+
+        let tokens = generate_relations(&input, |column| quote! { #column }, false).to_string();
+        assert!(tokens.contains("type Key = i64 ;"));
+        assert!(tokens.contains("Option :: < Self :: Key > :: from (self . customer_id . clone ())"));
+    }
+}
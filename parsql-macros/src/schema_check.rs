@@ -0,0 +1,707 @@
+//! Compile-time validation of derive attributes against a schema snapshot.
+//!
+//! Enabled with the `schema-check` feature. The snapshot is a JSON file produced by
+//! `parsql schema snapshot` and read from `parsql-schema.json` in the crate root, or from
+//! the path in `PARSQL_SCHEMA_SNAPSHOT` (relative to the crate root):
+//!
+//! ```json
+//! {
+//!   "dialect": "postgres",
+//!   "tables": {
+//!     "users": {
+//!       "id": { "type": "int8", "nullable": false },
+//!       "email": { "type": "text", "nullable": true }
+//!     }
+//!   }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use regex::Regex;
+use syn::{Attribute, Data, DeriveInput, Fields, GenericArgument, PathArguments, Type};
+
+use crate::relations::relation_field_names;
+use crate::utils::option_inner;
+
+/// The derive being validated; decides which attributes and fields are checked.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum DeriveKind {
+    Queryable,
+    Insertable,
+    Updateable,
+    Deletable,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Dialect {
+    Postgres,
+    Sqlite,
+}
+
+pub(crate) struct Column {
+    pub sql_type: String,
+    pub nullable: bool,
+}
+
+/// Tables and columns known to the database, keyed by lowercase name.
+pub(crate) struct Snapshot {
+    pub dialect: Dialect,
+    pub tables: HashMap<String, HashMap<String, Column>>,
+}
+
+impl Snapshot {
+    /// Parses the JSON produced by `parsql schema snapshot`.
+    pub(crate) fn parse(json: &str) -> Result<Self, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+
+        let dialect = match value.get("dialect").and_then(|d| d.as_str()) {
+            Some("postgres") | Some("postgresql") => Dialect::Postgres,
+            Some("sqlite") => Dialect::Sqlite,
+            Some(other) => return Err(format!("unknown dialect `{}`", other)),
+            None => return Err("missing `dialect`".to_string()),
+        };
+
+        let mut tables = HashMap::new();
+        let table_values = value
+            .get("tables")
+            .and_then(|t| t.as_object())
+            .ok_or("missing `tables` object")?;
+
+        for (table, columns) in table_values {
+            let columns = columns
+                .as_object()
+                .ok_or_else(|| format!("columns of `{}` must be an object", table))?;
+            let mut parsed = HashMap::new();
+            for (column, info) in columns {
+                parsed.insert(
+                    column.to_lowercase(),
+                    Column {
+                        sql_type: info
+                            .get("type")
+                            .and_then(|t| t.as_str())
+                            .unwrap_or("")
+                            .to_lowercase(),
+                        nullable: info
+                            .get("nullable")
+                            .and_then(|n| n.as_bool())
+                            .unwrap_or(true),
+                    },
+                );
+            }
+            tables.insert(table.to_lowercase(), parsed);
+        }
+
+        Ok(Snapshot { dialect, tables })
+    }
+}
+
+fn snapshot_path() -> PathBuf {
+    let root = env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    match env::var("PARSQL_SCHEMA_SNAPSHOT") {
+        Ok(path) => root.join(path),
+        Err(_) => root.join("parsql-schema.json"),
+    }
+}
+
+fn load_snapshot() -> &'static Result<(PathBuf, Snapshot), String> {
+    static SNAPSHOT: OnceLock<Result<(PathBuf, Snapshot), String>> = OnceLock::new();
+    SNAPSHOT.get_or_init(|| {
+        let path = snapshot_path();
+        let json = std::fs::read_to_string(&path).map_err(|e| {
+            format!(
+                "schema check: cannot read snapshot `{}` ({}); run `parsql schema snapshot` to create it",
+                path.display(),
+                e
+            )
+        })?;
+        let snapshot = Snapshot::parse(&json)
+            .map_err(|e| format!("schema check: `{}`: {}", path.display(), e))?;
+        Ok((path, snapshot))
+    })
+}
+
+/// Validates the derive input against the schema snapshot.
+///
+/// Returns the compile errors for every problem found, plus an `include_bytes!` of the
+/// snapshot so the crate is rebuilt when the snapshot changes.
+pub(crate) fn validate(input: &DeriveInput, kind: DeriveKind) -> TokenStream {
+    match load_snapshot() {
+        Ok((path, snapshot)) => {
+            let path = path.to_string_lossy().to_string();
+            let errors = check(input, kind, snapshot)
+                .into_iter()
+                .map(|e| e.to_compile_error());
+            quote! {
+                const _: &[u8] = include_bytes!(#path);
+                #(#errors)*
+            }
+        }
+        Err(message) => syn::Error::new(Span::call_site(), message).to_compile_error(),
+    }
+}
+
+fn string_attr<'a>(input: &'a DeriveInput, name: &str) -> Option<(&'a Attribute, String)> {
+    input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident(name))
+        .and_then(|attr| {
+            attr.parse_args::<syn::LitStr>()
+                .ok()
+                .map(|lit| (attr, lit.value()))
+        })
+}
+
+/// Tables referenced by a query, with their aliases.
+struct Scope<'a> {
+    snapshot: &'a Snapshot,
+    /// alias or table name -> table name
+    names: HashMap<String, String>,
+    /// Tables in the order they appear, the main table first
+    tables: Vec<String>,
+}
+
+impl<'a> Scope<'a> {
+    fn add(&mut self, table: &str, alias: Option<&str>) {
+        let table = table.to_lowercase();
+        self.names.insert(table.clone(), table.clone());
+        if let Some(alias) = alias {
+            self.names.insert(alias.to_lowercase(), table.clone());
+        }
+        self.tables.push(table);
+    }
+
+    /// Finds a column, either qualified (`u.email`) or bare (`email`).
+    fn column(&self, reference: &str) -> Result<Option<&'a Column>, String> {
+        let reference = reference.to_lowercase();
+        if let Some((qualifier, column)) = reference.split_once('.') {
+            let table = self
+                .names
+                .get(qualifier)
+                .ok_or_else(|| format!("unknown table or alias `{}`", qualifier))?;
+            let Some(columns) = self.snapshot.tables.get(table) else {
+                return Ok(None);
+            };
+            if column == "*" {
+                return Ok(None);
+            }
+            return columns
+                .get(column)
+                .map(Some)
+                .ok_or_else(|| format!("column `{}` does not exist in table `{}`", column, table));
+        }
+
+        for table in &self.tables {
+            if let Some(column) = self
+                .snapshot
+                .tables
+                .get(table)
+                .and_then(|columns| columns.get(&reference))
+            {
+                return Ok(Some(column));
+            }
+        }
+        Err(format!(
+            "column `{}` does not exist in {}",
+            reference,
+            self.tables
+                .iter()
+                .map(|t| format!("`{}`", t))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "or", "not", "null", "true", "false", "is", "in", "like", "ilike", "between",
+    "exists", "any", "all", "on", "using", "where", "as",
+];
+
+/// Extracts the column references on the left of comparison operators in a condition.
+fn condition_columns(clause: &str) -> Vec<String> {
+    let literals = Regex::new(r"'(?:[^']|'')*'").unwrap();
+    let clause = literals.replace_all(clause, "''");
+    let re = Regex::new(
+        r"(?i)([A-Za-z_][\w]*(?:\.[A-Za-z_]\w*)?)\s*(?:=|<>|!=|>=|<=|>|<|\bnot\s+like\b|\blike\b|\bilike\b|\bnot\s+in\b|\bin\b|\bis\b|\bbetween\b)",
+    )
+    .unwrap();
+    re.captures_iter(&clause)
+        .map(|cap| cap[1].to_string())
+        .filter(|name| !KEYWORDS.contains(&name.to_lowercase().as_str()))
+        .collect()
+}
+
+/// Splits a select list at top-level commas.
+fn split_select(select: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in select.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        items.push(current.trim().to_string());
+    }
+    items
+}
+
+/// Normalized type families used to compare Rust and SQL types.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Kind {
+    Int(u8),
+    Float(u8),
+    Numeric,
+    Text,
+    Bool,
+    Bytes,
+    Timestamp,
+    TimestampTz,
+    Date,
+    Time,
+    Json,
+    Uuid,
+}
+
+pub(crate) fn sql_kind(sql_type: &str, dialect: Dialect) -> Option<Kind> {
+    let t = sql_type.trim().to_lowercase();
+    let base = t.split('(').next().unwrap_or("").trim();
+    let kind = match base {
+        "int2" | "smallint" | "smallserial" => Kind::Int(2),
+        "int4" | "int" | "integer" | "serial" | "mediumint" => Kind::Int(4),
+        "int8" | "bigint" | "bigserial" => Kind::Int(8),
+        "float4" | "real" => Kind::Float(4),
+        "float8" | "double precision" | "double" | "float" => Kind::Float(8),
+        "numeric" | "decimal" => Kind::Numeric,
+        "text" | "varchar" | "character varying" | "char" | "character" | "bpchar" | "name"
+        | "citext" | "clob" => Kind::Text,
+        "bool" | "boolean" => Kind::Bool,
+        "bytea" | "blob" => Kind::Bytes,
+        "timestamp" | "timestamp without time zone" | "datetime" => Kind::Timestamp,
+        "timestamptz" | "timestamp with time zone" => Kind::TimestampTz,
+        "date" => Kind::Date,
+        "time" | "time without time zone" => Kind::Time,
+        "json" | "jsonb" => Kind::Json,
+        "uuid" => Kind::Uuid,
+        _ if dialect == Dialect::Sqlite => {
+            // SQLite type affinity rules
+            if t.contains("int") {
+                Kind::Int(8)
+            } else if t.contains("char") || t.contains("clob") || t.contains("text") {
+                Kind::Text
+            } else if t.contains("real") || t.contains("floa") || t.contains("doub") {
+                Kind::Float(8)
+            } else if t.contains("blob") || t.is_empty() {
+                return None;
+            } else {
+                Kind::Numeric
+            }
+        }
+        _ => return None,
+    };
+    // SQLite stores every integer as a 64-bit value
+    Some(match (kind, dialect) {
+        (Kind::Int(_), Dialect::Sqlite) => Kind::Int(8),
+        (kind, _) => kind,
+    })
+}
+
+pub(crate) fn rust_kind(ty: &Type) -> Option<Kind> {
+    match ty {
+        Type::Reference(reference) => rust_kind(&reference.elem),
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let kind = match segment.ident.to_string().as_str() {
+                "i8" | "i16" => Kind::Int(2),
+                "i32" => Kind::Int(4),
+                "i64" | "isize" | "u32" => Kind::Int(8),
+                "f32" => Kind::Float(4),
+                "f64" => Kind::Float(8),
+                "Decimal" | "BigDecimal" => Kind::Numeric,
+                "String" | "str" | "Cow" => Kind::Text,
+                "bool" => Kind::Bool,
+                "Vec" => {
+                    let inner = match &segment.arguments {
+                        PathArguments::AngleBracketed(args) => args.args.first(),
+                        _ => None,
+                    };
+                    match inner {
+                        Some(GenericArgument::Type(Type::Path(p))) if p.path.is_ident("u8") => {
+                            Kind::Bytes
+                        }
+                        _ => return None,
+                    }
+                }
+                "NaiveDateTime" | "PrimitiveDateTime" | "SystemTime" => Kind::Timestamp,
+                "DateTime" | "OffsetDateTime" => Kind::TimestampTz,
+                "NaiveDate" | "Date" => Kind::Date,
+                "NaiveTime" | "Time" => Kind::Time,
+                "Value" | "Json" => Kind::Json,
+                "Uuid" => Kind::Uuid,
+                _ => return None,
+            };
+            Some(kind)
+        }
+        Type::Slice(slice) => match &*slice.elem {
+            Type::Path(p) if p.path.is_ident("u8") => Some(Kind::Bytes),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether a Rust value of kind `rust` can be stored in / read from a column of kind `column`.
+pub(crate) fn compatible(column: Kind, rust: Kind, dialect: Dialect) -> bool {
+    if column == rust {
+        return true;
+    }
+    match dialect {
+        // The postgres driver checks types exactly, including integer widths
+        Dialect::Postgres => matches!((column, rust), (Kind::Json, Kind::Text)),
+        Dialect::Sqlite => match column {
+            Kind::Int(_) => matches!(
+                rust,
+                Kind::Int(_) | Kind::Bool | Kind::Timestamp | Kind::TimestampTz
+            ),
+            Kind::Float(_) => matches!(rust, Kind::Float(_)),
+            Kind::Numeric => matches!(rust, Kind::Int(_) | Kind::Float(_) | Kind::Bool),
+            Kind::Bool => matches!(rust, Kind::Int(_)),
+            Kind::Text | Kind::Json => !matches!(rust, Kind::Bytes | Kind::Bool),
+            Kind::Bytes => matches!(rust, Kind::Uuid),
+            Kind::Timestamp | Kind::TimestampTz | Kind::Date | Kind::Time => matches!(
+                rust,
+                Kind::Text
+                    | Kind::Int(_)
+                    | Kind::Timestamp
+                    | Kind::TimestampTz
+                    | Kind::Date
+                    | Kind::Time
+            ),
+            Kind::Uuid => matches!(rust, Kind::Text | Kind::Bytes),
+        },
+    }
+}
+
+/// Runs every check for the derive input and collects the errors.
+pub(crate) fn check(input: &DeriveInput, kind: DeriveKind, snapshot: &Snapshot) -> Vec<syn::Error> {
+    let mut errors = Vec::new();
+
+    let Some((table_attr, table)) = string_attr(input, "table") else {
+        return errors;
+    };
+
+    let mut scope = Scope {
+        snapshot,
+        names: HashMap::new(),
+        tables: Vec::new(),
+    };
+
+    // `users`, `users u` or `users AS u`
+    let table_parts: Vec<&str> = table
+        .split_whitespace()
+        .filter(|part| !part.eq_ignore_ascii_case("as"))
+        .collect();
+    let Some(main_table) = table_parts.first() else {
+        return errors;
+    };
+    if !snapshot.tables.contains_key(&main_table.to_lowercase()) {
+        errors.push(syn::Error::new_spanned(
+            table_attr,
+            format!("table `{}` does not exist in the schema snapshot", main_table),
+        ));
+        return errors;
+    }
+    scope.add(main_table, table_parts.get(1).copied());
+
+    let join_re =
+        Regex::new(r"(?i)\bjoin\s+([A-Za-z_]\w*)(?:\s+(?:as\s+)?([A-Za-z_]\w*))?").unwrap();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("join")) {
+        let Ok(join) = attr.parse_args::<syn::LitStr>() else {
+            continue;
+        };
+        for cap in join_re.captures_iter(&join.value()) {
+            let joined = &cap[1];
+            if !snapshot.tables.contains_key(&joined.to_lowercase()) {
+                errors.push(syn::Error::new_spanned(
+                    attr,
+                    format!("table `{}` does not exist in the schema snapshot", joined),
+                ));
+                continue;
+            }
+            let alias = cap
+                .get(2)
+                .map(|m| m.as_str())
+                .filter(|a| !KEYWORDS.contains(&a.to_lowercase().as_str()));
+            scope.add(joined, alias);
+        }
+    }
+
+    let check_reference = |attr: &Attribute, reference: &str, errors: &mut Vec<syn::Error>| {
+        if let Err(message) = scope.column(reference) {
+            errors.push(syn::Error::new_spanned(attr, message));
+        }
+    };
+
+    for name in ["where_clause", "having"] {
+        if let Some((attr, clause)) = string_attr(input, name) {
+            for column in condition_columns(&clause) {
+                check_reference(attr, &column, &mut errors);
+            }
+        }
+    }
+
+    // Select aliases are valid field names even though they are not columns
+    let mut aliases = Vec::new();
+    if kind == DeriveKind::Queryable {
+        if let Some((attr, select)) = string_attr(input, "select") {
+            let alias_re = Regex::new(r"(?i)^(.*?)\s+as\s+([A-Za-z_]\w*)$").unwrap();
+            let column_re = Regex::new(r"^[A-Za-z_]\w*(?:\.(?:[A-Za-z_]\w*|\*))?$").unwrap();
+            for item in split_select(&select) {
+                let expression = match alias_re.captures(&item) {
+                    Some(cap) => {
+                        aliases.push(cap[2].to_lowercase());
+                        cap[1].trim().to_string()
+                    }
+                    None => item.clone(),
+                };
+                if column_re.is_match(&expression) {
+                    check_reference(attr, &expression, &mut errors);
+                }
+            }
+        }
+    }
+
+    if kind == DeriveKind::Updateable {
+        if let Some((attr, update)) = string_attr(input, "update") {
+            for column in update.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+                check_reference(attr, column, &mut errors);
+            }
+        }
+    }
+
+    if kind == DeriveKind::Insertable {
+        if let Some((attr, returning)) = string_attr(input, "returning") {
+            check_reference(attr, returning.trim(), &mut errors);
+        }
+    }
+
+    // Deletable structs only carry WHERE parameters, which are already checked
+    if kind == DeriveKind::Deletable {
+        return errors;
+    }
+
+    let Data::Struct(data) = &input.data else {
+        return errors;
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return errors;
+    };
+    let relation_fields = relation_field_names(input);
+    let result_type = string_attr(input, "result_type").is_some();
+
+    for field in &fields.named {
+        let ident = field.ident.as_ref().unwrap();
+        let name = ident.to_string();
+        if relation_fields.contains(&name) {
+            continue;
+        }
+
+        let column = match scope.column(&name) {
+            Ok(column) => column,
+            Err(_) if aliases.contains(&name.to_lowercase()) => None,
+            Err(_) => {
+                errors.push(syn::Error::new_spanned(
+                    ident,
+                    format!(
+                        "field `{}` does not match any column of {}",
+                        name,
+                        scope
+                            .tables
+                            .iter()
+                            .map(|t| format!("`{}`", t))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                ));
+                continue;
+            }
+        };
+        let Some(column) = column else { continue };
+
+        let (ty, optional) = match option_inner(&field.ty) {
+            Some(inner) => (inner, true),
+            None => (&field.ty, false),
+        };
+
+        // Rows read into a non-Option field fail at runtime when the column holds NULL
+        if kind == DeriveKind::Queryable && !result_type && column.nullable && !optional {
+            errors.push(syn::Error::new_spanned(
+                &field.ty,
+                format!(
+                    "column `{}` is nullable; use `Option<_>` for field `{}`",
+                    name, name
+                ),
+            ));
+        }
+
+        if let (Some(column_kind), Some(field_kind)) = (
+            sql_kind(&column.sql_type, snapshot.dialect),
+            rust_kind(ty),
+        ) {
+            if !compatible(column_kind, field_kind, snapshot.dialect) {
+                errors.push(syn::Error::new_spanned(
+                    ty,
+                    format!(
+                        "field `{}` has type `{}` which does not match column type `{}`",
+                        name,
+                        quote!(#ty).to_string().replace(' ', ""),
+                        column.sql_type
+                    ),
+                ));
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(dialect: &str) -> Snapshot {
+        Snapshot::parse(&format!(
+            r#"{{
+                "dialect": "{}",
+                "tables": {{
+                    "users": {{
+                        "id": {{ "type": "int8", "nullable": false }},
+                        "name": {{ "type": "text", "nullable": false }},
+                        "email": {{ "type": "varchar", "nullable": true }},
+                        "state": {{ "type": "int2", "nullable": false }}
+                    }},
+                    "posts": {{
+                        "id": {{ "type": "int4", "nullable": false }},
+                        "user_id": {{ "type": "int8", "nullable": false }}
+                    }}
+                }}
+            }}"#,
+            dialect
+        ))
+        .unwrap()
+    }
+
+    fn messages(input: DeriveInput, kind: DeriveKind, dialect: &str) -> Vec<String> {
+        check(&input, kind, &snapshot(dialect))
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_queryable() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[table("users u")]
+            #[join("INNER JOIN posts p ON u.id = p.user_id")]
+            #[select("u.id, u.name, u.email, COUNT(p.id) AS post_count")]
+            #[where_clause("u.id = $ AND u.state >= $")]
+            struct UserPosts {
+                id: i64,
+                name: String,
+                email: Option<String>,
+                post_count: i64,
+                state: i16,
+            }
+        };
+        assert!(messages(input, DeriveKind::Queryable, "postgres").is_empty());
+    }
+
+    #[test]
+    fn test_unknown_table_and_columns() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[table("user")]
+            struct GetUser { id: i64 }
+        };
+        let errors = messages(input, DeriveKind::Queryable, "postgres");
+        assert_eq!(errors, vec!["table `user` does not exist in the schema snapshot"]);
+
+        let input: DeriveInput = syn::parse_quote! {
+            #[table("users")]
+            #[update("name, mail")]
+            #[where_clause("idx = $")]
+            struct UpdateUser { id: i64, name: String, mail: String }
+        };
+        let errors = messages(input, DeriveKind::Updateable, "postgres");
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].contains("column `idx`"));
+        assert!(errors[1].contains("column `mail`"));
+        assert!(errors[2].contains("field `mail`"));
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[table("users")]
+            struct InsertUser { id: i32, name: String, email: String, state: bool }
+        };
+        let errors = messages(input, DeriveKind::Insertable, "postgres");
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("`i32`"));
+        assert!(errors[1].contains("`bool`"));
+
+        // SQLite stores integers and booleans the same way
+        let input: DeriveInput = syn::parse_quote! {
+            #[table("users")]
+            struct InsertUser { id: i32, name: String, email: String, state: bool }
+        };
+        assert!(messages(input, DeriveKind::Insertable, "sqlite").is_empty());
+    }
+
+    #[test]
+    fn test_nullable_column_requires_option() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[table("users")]
+            #[where_clause("id = $")]
+            struct GetUser { id: i64, email: String }
+        };
+        let errors = messages(input, DeriveKind::Queryable, "postgres");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("nullable"));
+    }
+
+    #[test]
+    fn test_sqlite_affinity() {
+        assert_eq!(sql_kind("VARCHAR(255)", Dialect::Sqlite), Some(Kind::Text));
+        assert_eq!(sql_kind("UNSIGNED BIG INT", Dialect::Sqlite), Some(Kind::Int(8)));
+        assert_eq!(sql_kind("INTEGER", Dialect::Sqlite), Some(Kind::Int(8)));
+        assert_eq!(sql_kind("", Dialect::Sqlite), None);
+        assert_eq!(sql_kind("money", Dialect::Postgres), None);
+    }
+
+    #[test]
+    fn test_condition_columns() {
+        assert_eq!(
+            condition_columns("name LIKE $ AND (state IN ($, $) OR email IS NULL) AND note = 'a = b'"),
+            vec!["name", "state", "email", "note"]
+        );
+    }
+}
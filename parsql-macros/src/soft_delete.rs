@@ -0,0 +1,92 @@
+use syn::{parse::ParseStream, DeriveInput, Ident, LitStr, Token};
+
+/// Column used when `#[soft_delete]` is given without options.
+const DEFAULT_COLUMN: &str = "deleted_at";
+
+/// Returns the deletion marker column of `#[soft_delete(column = "...")]`, if present.
+pub(crate) fn soft_delete_column(input: &DeriveInput) -> Option<String> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("soft_delete"))?;
+
+    // A bare `#[soft_delete]` uses the default column
+    if matches!(attr.meta, syn::Meta::Path(_)) {
+        return Some(DEFAULT_COLUMN.to_string());
+    }
+
+    let column = attr
+        .parse_args_with(|input: ParseStream| {
+            let key: Ident = input.parse()?;
+            if key != "column" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "Unknown soft_delete option; expected `column`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            let _ = input.parse::<Token![,]>();
+            Ok(value.value())
+        })
+        .expect("Expected `#[soft_delete(column = \"...\")]`");
+
+    Some(column)
+}
+
+/// Adds `column IS NULL` (or `IS NOT NULL`) to an already numbered WHERE clause.
+pub(crate) fn with_null_check(where_clause: &str, column: &str, negated: bool) -> String {
+    let check = if negated { "IS NOT NULL" } else { "IS NULL" };
+    if where_clause.trim().is_empty() {
+        format!("{} {}", column, check)
+    } else {
+        format!("({}) AND {} {}", where_clause.trim(), column, check)
+    }
+}
+
+/// Qualifies `column` with the table name, sanitized the same way as in the FROM clause.
+pub(crate) fn qualified_column(table: &str, column: &str) -> String {
+    let table = table
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_')
+        .collect::<String>();
+    format!("{}.{}", table, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soft_delete_column() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[soft_delete(column = "removed_at")]
+            struct DeleteUser { id: i64 }
+        };
+        assert_eq!(soft_delete_column(&input).as_deref(), Some("removed_at"));
+
+        let input: DeriveInput = syn::parse_quote! {
+            #[soft_delete]
+            struct DeleteUser { id: i64 }
+        };
+        assert_eq!(soft_delete_column(&input).as_deref(), Some("deleted_at"));
+
+        let input: DeriveInput = syn::parse_quote! { struct DeleteUser { id: i64 } };
+        assert_eq!(soft_delete_column(&input), None);
+    }
+
+    #[test]
+    fn test_with_null_check() {
+        assert_eq!(
+            with_null_check("id = $1 OR email = $2", "deleted_at", false),
+            "(id = $1 OR email = $2) AND deleted_at IS NULL"
+        );
+        assert_eq!(with_null_check("", "deleted_at", true), "deleted_at IS NOT NULL");
+    }
+
+    #[test]
+    fn test_qualified_column() {
+        assert_eq!(qualified_column("users", "deleted_at"), "users.deleted_at");
+        assert_eq!(qualified_column("user_roles", "deleted_at"), "user_roles.deleted_at");
+    }
+}
@@ -2,8 +2,10 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
+use crate::relations::relation_field_names;
+use crate::text_search::parse_search;
 use crate::{
-    extract_fields_from_where_clause, log_message, number_where_clause_params, query_builder,
+    extract_fields_from_where_clause, number_where_clause_params, query_builder,
     SqlParamCounter,
 };
 
@@ -33,12 +35,16 @@ pub(crate) fn derive_sql_params_impl(input: TokenStream) -> TokenStream {
                 .value()
         });
 
+    // Relation fields hold loaded records, not parameters
+    let relation_fields = relation_field_names(&input);
+
     let fields = if let Data::Struct(data) = &input.data {
         if let Fields::Named(fields) = &data.fields {
             fields
                 .named
                 .iter()
                 .map(|f| f.ident.as_ref().unwrap().to_string())
+                .filter(|f| !relation_fields.contains(f))
                 .collect::<Vec<_>>()
         } else {
             panic!("SqlParams can only be derived for structs with named fields");
@@ -50,6 +56,19 @@ pub(crate) fn derive_sql_params_impl(input: TokenStream) -> TokenStream {
     // where_clause ve having_clause'daki parametreleri belirle
     let mut param_fields = Vec::new();
 
+    // The search expression is bound first, see `Queryable`
+    let search_query = parse_search(&input).map(|search| search.query_field().to_string());
+    let fields = match &search_query {
+        Some(query) => {
+            if !fields.contains(query) {
+                panic!("Search query field `{}` does not exist", query);
+            }
+            param_fields.push(query.clone());
+            fields.into_iter().filter(|f| f != query).collect()
+        }
+        None => fields,
+    };
+
     // WHERE cümlesindeki alan adlarını bulma
     if let Some(clause) = &where_clause {
         // Boş where_clause durumunu kontrol et
@@ -0,0 +1,93 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse::ParseStream, DeriveInput, Ident, LitStr, Token};
+
+/// Column used when `#[tenant_scoped]` is given without options.
+const DEFAULT_COLUMN: &str = "tenant_id";
+
+/// Returns the tenant column of `#[tenant_scoped(column = "...")]`, if present.
+pub(crate) fn tenant_column(input: &DeriveInput) -> Option<String> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("tenant_scoped"))?;
+
+    // A bare `#[tenant_scoped]` uses the default column
+    if matches!(attr.meta, syn::Meta::Path(_)) {
+        return Some(DEFAULT_COLUMN.to_string());
+    }
+
+    let column = attr
+        .parse_args_with(|input: ParseStream| {
+            let key: Ident = input.parse()?;
+            if key != "column" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "Unknown tenant_scoped option; expected `column`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            let _ = input.parse::<Token![,]>();
+            Ok(value.value())
+        })
+        .expect("Expected `#[tenant_scoped(column = \"...\")]`");
+
+    Some(column)
+}
+
+/// Adds `column = $n` to an already numbered WHERE clause.
+pub(crate) fn with_tenant_check(where_clause: &str, column: &str, param_number: usize) -> String {
+    let check = format!("{} = ${}", column, param_number);
+    if where_clause.trim().is_empty() {
+        check
+    } else {
+        format!("({}) AND {}", where_clause.trim(), check)
+    }
+}
+
+/// Generates the `TenantScoped` implementation. `param_number` is the 1-based placeholder
+/// of the tenant value in the generated SQL.
+pub(crate) fn tenant_scoped_impl(struct_name: &Ident, column: &str, param_number: usize) -> TokenStream {
+    let param_index = param_number - 1;
+    quote! {
+        impl TenantScoped for #struct_name {
+            fn tenant_column() -> &'static str {
+                #column
+            }
+
+            fn tenant_param_index() -> usize {
+                #param_index
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_column() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[tenant_scoped(column = "org_id")]
+            struct GetUser { id: i64 }
+        };
+        assert_eq!(tenant_column(&input).as_deref(), Some("org_id"));
+
+        let input: DeriveInput = syn::parse_quote! {
+            #[tenant_scoped]
+            struct GetUser { id: i64 }
+        };
+        assert_eq!(tenant_column(&input).as_deref(), Some("tenant_id"));
+    }
+
+    #[test]
+    fn test_with_tenant_check() {
+        assert_eq!(
+            with_tenant_check("id = $1 OR email = $2", "tenant_id", 3),
+            "(id = $1 OR email = $2) AND tenant_id = $3"
+        );
+        assert_eq!(with_tenant_check("", "tenant_id", 1), "tenant_id = $1");
+    }
+}
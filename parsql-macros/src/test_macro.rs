@@ -0,0 +1,169 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::ItemFn;
+
+/// The `parsql` features providing the test transactions.
+#[derive(Clone, Copy)]
+pub(crate) struct Backends {
+    pub postgres: bool,
+    pub tokio_postgres: bool,
+}
+
+impl Backends {
+    /// The features this crate was built with, which `parsql` enables along with its own.
+    pub(crate) fn enabled() -> Self {
+        Backends {
+            postgres: cfg!(feature = "postgres"),
+            tokio_postgres: cfg!(feature = "tokio-postgres"),
+        }
+    }
+}
+
+/// Expands `#[parsql::test]` on a function taking the test transaction.
+///
+/// The function is kept as an inner function of a `#[test]` with the same name. A sync
+/// function gets a `parsql::postgres` transaction, an `async fn` a `parsql::tokio_postgres`
+/// one run on a single-threaded runtime. The transaction is rolled back when it is dropped.
+pub(crate) fn expand_test(
+    args: TokenStream,
+    item: ItemFn,
+    backends: Backends,
+) -> syn::Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(syn::Error::new(
+            args.span(),
+            "#[parsql::test] does not take arguments",
+        ));
+    }
+    if item.sig.inputs.len() != 1 {
+        return Err(syn::Error::new(
+            item.sig.inputs.span(),
+            "#[parsql::test] functions take one argument, the test transaction",
+        ));
+    }
+    if !item.sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            item.sig.generics.span(),
+            "#[parsql::test] functions cannot be generic",
+        ));
+    }
+
+    let is_async = item.sig.asyncness.is_some();
+    let missing = match is_async {
+        true if !backends.tokio_postgres => Some(("an `async fn`", "tokio-postgres")),
+        false if !backends.postgres => Some(("a sync function", "postgres")),
+        _ => None,
+    };
+    if let Some((kind, feature)) = missing {
+        return Err(syn::Error::new(
+            item.sig.span(),
+            format!(
+                "#[parsql::test] on {} needs the `{}` feature of parsql",
+                kind, feature
+            ),
+        ));
+    }
+
+    let name = &item.sig.ident;
+    let output = &item.sig.output;
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let inner = ItemFn {
+        attrs: Vec::new(),
+        vis: syn::Visibility::Inherited,
+        ..item.clone()
+    };
+
+    let body = if is_async {
+        quote! {
+            ::parsql::tokio_postgres::testing::block_on(async {
+                let mut client = ::parsql::tokio_postgres::testing::TestClient::from_env().await;
+                let tx = client.begin().await.unwrap_or_else(|err| {
+                    panic!("Failed to begin the test transaction: {}", err)
+                });
+                #name(&tx).await
+            })
+        }
+    } else {
+        quote! {
+            let mut client = ::parsql::postgres::testing::TestClient::from_env();
+            let mut tx = client.begin().unwrap_or_else(|err| {
+                panic!("Failed to begin the test transaction: {}", err)
+            });
+            #name(&mut tx)
+        }
+    };
+
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #name() #output {
+            #inner
+            #body
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: Backends = Backends {
+        postgres: true,
+        tokio_postgres: true,
+    };
+
+    fn expand_with(item: &str, backends: Backends) -> syn::Result<String> {
+        let item: ItemFn = syn::parse_str(item).unwrap();
+        expand_test(TokenStream::new(), item, backends).map(|tokens| tokens.to_string())
+    }
+
+    fn expand(item: &str) -> syn::Result<String> {
+        expand_with(item, ALL)
+    }
+
+    #[test]
+    fn test_sync_and_async_backends() {
+        let sync =
+            expand("fn inserts(tx: &mut Transaction<'_>) { tx.insert(user).unwrap(); }").unwrap();
+        assert!(sync.contains(":: parsql :: postgres :: testing :: TestClient :: from_env ()"));
+        assert!(sync.contains("inserts (& mut tx)"));
+
+        let asynchronous = expand(
+            "#[should_panic] async fn inserts(tx: &Transaction<'_>) { tx.insert(user).await.unwrap(); }",
+        )
+        .unwrap();
+        assert!(asynchronous.starts_with("# [test] # [should_panic]"));
+        assert!(asynchronous.contains(":: parsql :: tokio_postgres :: testing :: block_on"));
+        assert!(asynchronous.contains("inserts (& tx) . await"));
+    }
+
+    #[test]
+    fn test_requires_transaction_argument() {
+        assert!(expand("fn no_tx() {}").is_err());
+        assert!(expand("fn generic<T>(tx: &mut T) {}").is_err());
+    }
+
+    #[test]
+    fn test_names_the_missing_feature() {
+        let sync = "fn inserts(tx: &mut Transaction<'_>) {}";
+        let asynchronous = "async fn inserts(tx: &Transaction<'_>) {}";
+        let only_sync = Backends {
+            tokio_postgres: false,
+            ..ALL
+        };
+        let only_async = Backends {
+            postgres: false,
+            ..ALL
+        };
+
+        let err = expand_with(asynchronous, only_sync).unwrap_err();
+        assert!(err.to_string().contains("`tokio-postgres` feature"), "{}", err);
+        assert!(expand_with(sync, only_sync).is_ok());
+
+        let err = expand_with(sync, only_async).unwrap_err();
+        assert!(err.to_string().contains("`postgres` feature"), "{}", err);
+        assert!(expand_with(asynchronous, only_async).is_ok());
+    }
+}
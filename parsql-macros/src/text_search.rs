@@ -0,0 +1,258 @@
+use syn::DeriveInput;
+
+use crate::fts::{literal, parse_fts, split_list, FtsDecl, DEFAULT_QUERY_FIELD};
+use crate::utils::parse_options;
+
+/// A parsed `#[text_search(...)]` declaration of a PostgreSQL full-text search.
+pub(crate) struct TextSearchDecl {
+    /// The text search configuration, e.g. `english`
+    pub config: String,
+    /// Columns combined into the document, when no `vector` column is given
+    pub columns: Vec<String>,
+    /// A stored `tsvector` column searched instead of `columns`
+    pub vector: Option<String>,
+    /// The field holding the search string
+    pub query: String,
+    /// The function turning the search string into a `tsquery`
+    pub parser: &'static str,
+    /// Columns returned as `<column>_headline`
+    pub headline: Vec<String>,
+    /// Markers placed around matched terms in headlines
+    pub open: String,
+    pub close: String,
+}
+
+/// Returns the `#[text_search(config = "...", columns = "...")]` declaration, if present.
+pub(crate) fn parse_text_search(input: &DeriveInput) -> Option<TextSearchDecl> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("text_search"))?;
+
+    let options = parse_options(
+        attr,
+        "text_search",
+        &[
+            "config", "columns", "vector", "query", "syntax", "headline", "open", "close",
+        ],
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.value())
+    };
+
+    let columns = option("columns").map_or_else(Vec::new, |value| split_list(&value));
+    let vector = option("vector");
+    if columns.is_empty() == vector.is_none() {
+        panic!("`#[text_search]` requires either `columns = \"...\"` or `vector = \"...\"`");
+    }
+
+    let parser = match option("syntax").as_deref().unwrap_or("websearch") {
+        "websearch" => "websearch_to_tsquery",
+        "plain" => "plainto_tsquery",
+        "phrase" => "phraseto_tsquery",
+        "raw" => "to_tsquery",
+        other => panic!(
+            "Unknown text_search syntax `{}`; expected one of: websearch, plain, phrase, raw",
+            other
+        ),
+    };
+
+    Some(TextSearchDecl {
+        config: option("config").unwrap_or_else(|| "simple".to_string()),
+        columns,
+        vector,
+        query: option("query").unwrap_or_else(|| DEFAULT_QUERY_FIELD.to_string()),
+        parser,
+        headline: option("headline").map_or_else(Vec::new, |value| split_list(&value)),
+        open: option("open").unwrap_or_else(|| "<b>".to_string()),
+        close: option("close").unwrap_or_else(|| "</b>".to_string()),
+    })
+}
+
+/// Qualifies a column with the searched table unless it names a table already.
+fn qualified(table: &str, column: &str) -> String {
+    if column.contains('.') {
+        column.to_string()
+    } else {
+        format!("{}.{}", table, column)
+    }
+}
+
+impl TextSearchDecl {
+    /// The searched document: the stored vector, or the columns joined with spaces.
+    fn document(&self, table: &str) -> String {
+        match &self.vector {
+            Some(vector) => qualified(table, vector),
+            None => format!(
+                "to_tsvector({}, {})",
+                literal(&self.config),
+                self.columns
+                    .iter()
+                    .map(|column| format!("coalesce({}, '')", qualified(table, column)))
+                    .collect::<Vec<_>>()
+                    .join(" || ' ' || ")
+            ),
+        }
+    }
+
+    /// `websearch_to_tsquery('<config>', $n)`
+    fn tsquery(&self, param_number: usize) -> String {
+        format!(
+            "{}({}, ${})",
+            self.parser,
+            literal(&self.config),
+            param_number
+        )
+    }
+
+    /// `<document> @@ <tsquery>`
+    pub fn predicate(&self, table: &str, param_number: usize) -> String {
+        format!("{} @@ {}", self.document(table), self.tsquery(param_number))
+    }
+
+    /// The `rank` and headline columns added to the select list.
+    pub fn output_columns(&self, table: &str, param_number: usize) -> Vec<String> {
+        let tsquery = self.tsquery(param_number);
+        let options = literal(&format!("StartSel={}, StopSel={}", self.open, self.close));
+
+        let mut outputs = vec![format!(
+            "ts_rank_cd({}, {}) AS rank",
+            self.document(table),
+            tsquery
+        )];
+        outputs.extend(self.headline.iter().map(|column| {
+            format!(
+                "ts_headline({}, {}, {}, {}) AS {}_headline",
+                literal(&self.config),
+                qualified(table, column),
+                tsquery,
+                options,
+                column.rsplit('.').next().unwrap()
+            )
+        }));
+        outputs
+    }
+}
+
+/// A full-text search declared on a `Queryable` struct, with `#[fts]` for SQLite or
+/// `#[text_search]` for PostgreSQL.
+pub(crate) enum Search {
+    Fts(FtsDecl),
+    Text(TextSearchDecl),
+}
+
+/// Returns the search declared on the struct, if any.
+pub(crate) fn parse_search(input: &DeriveInput) -> Option<Search> {
+    match (parse_fts(input), parse_text_search(input)) {
+        (Some(_), Some(_)) => panic!("`#[fts]` and `#[text_search]` cannot be combined"),
+        (Some(fts), None) => Some(Search::Fts(fts)),
+        (None, Some(text)) => Some(Search::Text(text)),
+        (None, None) => None,
+    }
+}
+
+impl Search {
+    /// The field bound to the search parameter.
+    pub fn query_field(&self) -> &str {
+        match self {
+            Search::Fts(fts) => &fts.query,
+            Search::Text(text) => &text.query,
+        }
+    }
+
+    /// The join needed before the search can be matched, if any.
+    pub fn join(&self, table: &str) -> Option<String> {
+        match self {
+            Search::Fts(fts) => Some(fts.join(table)),
+            Search::Text(_) => None,
+        }
+    }
+
+    /// The WHERE predicate matching the search parameter `$n`.
+    pub fn predicate(&self, table: &str, param_number: usize) -> String {
+        match self {
+            Search::Fts(fts) => fts.match_clause(param_number),
+            Search::Text(text) => text.predicate(table, param_number),
+        }
+    }
+
+    /// The columns added to the select list.
+    pub fn output_columns(&self, table: &str, param_number: usize) -> Vec<String> {
+        match self {
+            Search::Fts(fts) => fts.output_columns(),
+            Search::Text(text) => text.output_columns(table, param_number),
+        }
+    }
+
+    /// Best matches first: `bm25()` is lower for better matches, `ts_rank_cd()` higher.
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            Search::Fts(_) => "rank",
+            Search::Text(_) => "rank DESC",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_search() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[text_search(config = "english", columns = "title, body", headline = "body")]
+            struct SearchArticles {
+                query: String,
+            }
+        };
+
+        let search = parse_search(&input).unwrap();
+        assert_eq!(search.query_field(), "query");
+        assert_eq!(search.join("articles"), None);
+        assert_eq!(
+            search.predicate("articles", 1),
+            "to_tsvector('english', coalesce(articles.title, '') || ' ' || coalesce(articles.body, '')) \
+             @@ websearch_to_tsquery('english', $1)"
+        );
+        assert_eq!(
+            search.output_columns("articles", 1)[1],
+            "ts_headline('english', articles.body, websearch_to_tsquery('english', $1), \
+             'StartSel=<b>, StopSel=</b>') AS body_headline"
+        );
+        assert_eq!(search.order_by(), "rank DESC");
+    }
+
+    #[test]
+    fn test_parse_text_search_vector_column() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[text_search(vector = "search_vector", syntax = "plain", query = "terms")]
+            struct SearchArticles {
+                terms: String,
+            }
+        };
+
+        let search = parse_search(&input).unwrap();
+        assert_eq!(search.query_field(), "terms");
+        assert_eq!(
+            search.output_columns("articles", 2),
+            vec!["ts_rank_cd(articles.search_vector, plainto_tsquery('simple', $2)) AS rank"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "either `columns")]
+    fn test_parse_text_search_without_document() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[text_search(config = "english")]
+            struct SearchArticles {
+                query: String,
+            }
+        };
+        parse_search(&input);
+    }
+}
@@ -0,0 +1,124 @@
+use syn::{parse::ParseStream, DeriveInput, Ident, LitStr, Token};
+
+/// Columns filled with the database clock, from `#[timestamps(created = "...", updated = "...")]`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Timestamps {
+    /// Set on INSERT
+    pub created: Option<String>,
+    /// Set on INSERT and UPDATE
+    pub updated: Option<String>,
+}
+
+/// Returns the timestamp columns of a model. A bare `#[timestamps]` uses
+/// `created_at` and `updated_at`.
+pub(crate) fn timestamps(input: &DeriveInput) -> Option<Timestamps> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("timestamps"))?;
+
+    if matches!(attr.meta, syn::Meta::Path(_)) {
+        return Some(Timestamps {
+            created: Some("created_at".to_string()),
+            updated: Some("updated_at".to_string()),
+        });
+    }
+
+    let timestamps = attr
+        .parse_args_with(|input: ParseStream| {
+            let mut timestamps = Timestamps {
+                created: None,
+                updated: None,
+            };
+            while !input.is_empty() {
+                let key: Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+                let value: LitStr = input.parse()?;
+                match key.to_string().as_str() {
+                    "created" => timestamps.created = Some(value.value()),
+                    "updated" => timestamps.updated = Some(value.value()),
+                    _ => {
+                        return Err(syn::Error::new(
+                            key.span(),
+                            "Unknown timestamps option; expected `created` or `updated`",
+                        ))
+                    }
+                }
+                if input.parse::<Token![,]>().is_err() {
+                    break;
+                }
+            }
+            Ok(timestamps)
+        })
+        .expect("Expected `#[timestamps(created = \"...\", updated = \"...\")]`");
+
+    Some(timestamps)
+}
+
+/// Returns the optimistic locking column of `#[version_column("...")]`, if present.
+pub(crate) fn version_column(input: &DeriveInput) -> Option<String> {
+    input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("version_column"))
+        .map(|attr| {
+            attr.parse_args::<LitStr>()
+                .expect("Expected a string literal for version_column")
+                .value()
+        })
+}
+
+/// Rejects a version column listed in `#[update("...")]`: the generated SQL increments it,
+/// so setting it as well would assign the column twice.
+pub(crate) fn check_version_not_updated(column: &str, update_columns: &[String]) {
+    if update_columns.iter().any(|updated| updated == column) {
+        panic!(
+            "version_column `{}` is incremented automatically and must not be listed in #[update]",
+            column
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[timestamps(updated = "modified_at")]
+            struct UpdateUser { id: i64 }
+        };
+        assert_eq!(
+            timestamps(&input),
+            Some(Timestamps {
+                created: None,
+                updated: Some("modified_at".to_string()),
+            })
+        );
+
+        let input: DeriveInput = syn::parse_quote! {
+            #[timestamps]
+            struct InsertUser { name: String }
+        };
+        let columns = timestamps(&input).unwrap();
+        assert_eq!(columns.created.as_deref(), Some("created_at"));
+        assert_eq!(columns.updated.as_deref(), Some("updated_at"));
+    }
+
+    #[test]
+    fn test_version_column() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[version_column("lock_version")]
+            struct UpdateUser { id: i64, lock_version: i32 }
+        };
+        assert_eq!(version_column(&input).as_deref(), Some("lock_version"));
+    }
+
+    #[test]
+    #[should_panic(expected = "version_column `lock_version` is incremented automatically")]
+    fn test_version_column_cannot_be_updated() {
+        let columns = vec!["name".to_string(), "lock_version".to_string()];
+        check_version_not_updated("lock_version", &columns);
+    }
+}
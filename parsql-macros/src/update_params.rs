@@ -3,10 +3,11 @@ use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
 use crate::{
-    extract_fields_from_where_clause, log_message, number_where_clause_params, query_builder,
+    extract_fields_from_where_clause, number_where_clause_params, query_builder,
     SqlParamCounter,
 };
-
+use crate::relations::relation_field_names;
+use crate::timestamps::version_column;
 
 pub(crate) fn derive_update_params_impl(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -32,12 +33,16 @@ pub(crate) fn derive_update_params_impl(input: TokenStream) -> TokenStream {
         .expect("Expected a string literal for where_clause")
         .value();
 
+    // Relation fields hold loaded records, not parameters
+    let relation_fields = relation_field_names(&input);
+
     let fields = if let Data::Struct(data) = &input.data {
         if let Fields::Named(fields) = &data.fields {
             fields
                 .named
                 .iter()
                 .map(|f| f.ident.as_ref().unwrap().to_string())
+                .filter(|f| !relation_fields.contains(f))
                 .collect::<Vec<_>>()
         } else {
             panic!("UpdateParams can only be derived for structs with named fields");
@@ -65,13 +70,19 @@ pub(crate) fn derive_update_params_impl(input: TokenStream) -> TokenStream {
         .map(|f| syn::Ident::new(f, struct_name.span()))
         .collect();
 
+    // The expected version follows the WHERE parameters, see `Updateable`
+    let version_field = version_column(&input)
+        .map(|column| syn::Ident::new(&column, struct_name.span()))
+        .into_iter();
+
     let expanded = quote! {
         impl UpdateParams for #struct_name {
             fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
                 let update_values: Vec<&(dyn ToSql + Sync)> = vec![#(&self.#update_field_names as &(dyn ToSql + Sync)),*];
                 let condition_values: Vec<&(dyn ToSql + Sync)> = vec![#(&self.#condition_field_names as &(dyn ToSql + Sync)),*];
+                let version_values: Vec<&(dyn ToSql + Sync)> = vec![#(&self.#version_field as &(dyn ToSql + Sync)),*];
 
-                [update_values, condition_values].concat()
+                [update_values, condition_values, version_values].concat()
             }
         }
     };
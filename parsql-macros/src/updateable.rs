@@ -3,9 +3,12 @@ use quote::quote;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
 use crate::{
-    extract_fields_from_where_clause, log_message, number_where_clause_params, query_builder,
-    SqlParamCounter,
+    extract_fields_from_where_clause, NOW, number_where_clause_params,
+    query_builder, SqlParamCounter,
 };
+use crate::relations::relation_field_names;
+use crate::tenant::{tenant_column, tenant_scoped_impl, with_tenant_check};
+use crate::timestamps::{check_version_not_updated, timestamps, version_column};
 
 /// Implements the Updateable derive macro.
 pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
@@ -37,6 +40,12 @@ pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
         .map(|s| s.trim().to_string())
         .collect();
 
+    // Relation fields hold loaded records, not columns
+    let relation_fields = relation_field_names(&input);
+    if let Some(field) = column_order.iter().find(|col| relation_fields.contains(col)) {
+        panic!("Relation field `{}` is not a column and cannot be updated", field);
+    }
+
     // Get the optional where_clause attribute
     let where_clause = input
         .attrs
@@ -55,6 +64,7 @@ pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
                 .named
                 .iter()
                 .map(|f| f.ident.as_ref().unwrap().to_string())
+                .filter(|f| !relation_fields.contains(f))
                 .collect::<Vec<_>>()
         } else {
             panic!("Updateable can only be derived for structs with named fields");
@@ -81,10 +91,35 @@ pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
 
     // Parametre sayacı update alanlarından sonra devam eder
     // WHERE cümlesindeki parametreler SET parametrelerinden sonraki değerleri alır
-    let adjusted_where_clause = where_clause
+    let mut adjusted_where_clause = where_clause
         .map(|clause| number_where_clause_params(&clause, &mut param_counter))
         .unwrap_or_else(|| "".to_string());
 
+    // The version read with the record is bound after the WHERE parameters
+    let version_column = version_column(&input);
+    if let Some(column) = &version_column {
+        if !fields.contains(column) {
+            panic!("version_column `{}` must be a field of the struct", column);
+        }
+        check_version_not_updated(column, &column_order);
+        let check = format!("{} = ${}", column, param_counter.next());
+        adjusted_where_clause = if adjusted_where_clause.trim().is_empty() {
+            check
+        } else {
+            format!("({}) AND {}", adjusted_where_clause.trim(), check)
+        };
+    }
+
+    // The tenant value is bound last and can never be changed by the update
+    let tenant_scoped = tenant_column(&input).map(|column| {
+        if column_order.contains(&column) {
+            panic!("The tenant column `{}` cannot be updated", column);
+        }
+        let param_number = param_counter.next();
+        adjusted_where_clause = with_tenant_check(&adjusted_where_clause, &column, param_number);
+        tenant_scoped_impl(struct_name, &column, param_number)
+    });
+
     let mut builder = query_builder::SafeQueryBuilder::new();
 
     builder.add_keyword("UPDATE");
@@ -92,7 +127,7 @@ pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
     builder.add_keyword("SET");
 
     // Build SET statements safely
-    let update_statements: Vec<String> = column_order
+    let mut update_statements: Vec<String> = column_order
         .iter()
         .enumerate()
         .map(|(i, col)| {
@@ -104,6 +139,15 @@ pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
         })
         .collect();
 
+    if let Some(updated) = timestamps(&input).and_then(|timestamps| timestamps.updated) {
+        if !column_order.contains(&updated) {
+            update_statements.push(format!("{} = {}", updated, NOW));
+        }
+    }
+    if let Some(column) = &version_column {
+        update_statements.push(format!("{0} = {0} + 1", column));
+    }
+
     builder.add_raw(&update_statements.join(", "));
 
     if !adjusted_where_clause.is_empty() {
@@ -113,9 +157,17 @@ pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
 
     let safe_query = builder.build();
 
-    // Log mesajlarını PARSQL_TRACE kontrolü ile yazdır
-    log_message(&format!("Generated UPDATE SQL: {}", safe_query));
-    log_message(&format!("Total param count: {}", param_counter.count()));
+    // Versioned models can be passed to `update_versioned`, which reports a stale object
+    // when no row matches
+    let versioned = version_column.map(|column| {
+        quote! {
+            impl Versioned for #struct_name {
+                fn version_column() -> &'static str {
+                    #column
+                }
+            }
+        }
+    });
 
     let expanded = quote! {
         impl SqlCommand for #struct_name {
@@ -123,6 +175,17 @@ pub(crate) fn derive_updateable_impl(input: TokenStream) -> TokenStream {
                 #safe_query.to_string()
             }
         }
+
+        #versioned
+
+        #tenant_scoped
+    };
+
+    #[cfg(feature = "schema-check")]
+    let expanded = {
+        let checks =
+            crate::schema_check::validate(&input, crate::schema_check::DeriveKind::Updateable);
+        quote! { #expanded #checks }
     };
 
     TokenStream::from(expanded)
@@ -1,4 +1,7 @@
 use regex::Regex;
+use syn::{
+    parse::ParseStream, Attribute, GenericArgument, Ident, LitStr, PathArguments, Token, Type,
+};
 
 /// Extracts field names from a WHERE clause.
 /// 
@@ -44,6 +47,7 @@ impl SqlParamCounter {
     }
     
     /// Toplam parametre sayısını döndürür (current - 1)
+    #[cfg(test)]
     pub fn count(&self) -> usize {
         self.current - 1
     }
@@ -64,4 +68,71 @@ pub(crate) fn number_where_clause_params(clause: &str, counter: &mut SqlParamCou
             }
         })
         .collect::<String>()
-}
\ No newline at end of file
+}
+
+/// SQL expression for the current time, valid on SQLite and PostgreSQL alike.
+///
+/// Cargo enables the features of this crate for the whole build, so a program using the
+/// SQLite and a PostgreSQL backend together sees all of them: the generated SQL must not
+/// depend on which backend features are on.
+pub(crate) const NOW: &str = "CURRENT_TIMESTAMP";
+
+/// Returns the inner type of `Option<T>`, if the type is an option.
+pub(crate) fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// Parses the `key = "value", ...` options of a `#[name(...)]` attribute, rejecting keys
+/// that are not in `known`.
+pub(crate) fn parse_options(
+    attr: &Attribute,
+    name: &str,
+    known: &[&str],
+) -> syn::Result<Vec<(Ident, LitStr)>> {
+    let options = attr
+        .parse_args_with(|input: ParseStream| {
+            let mut options = Vec::new();
+            while !input.is_empty() {
+                let key: Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+                let value: LitStr = input.parse()?;
+                options.push((key, value));
+                if input.parse::<Token![,]>().is_err() {
+                    break;
+                }
+            }
+            Ok(options)
+        })
+        .map_err(|err| {
+            syn::Error::new(
+                err.span(),
+                format!("Expected `#[{}(key = \"value\", ...)]`", name),
+            )
+        })?;
+
+    for (key, _) in &options {
+        if !known.iter().any(|known| key == known) {
+            return Err(syn::Error::new(
+                key.span(),
+                format!(
+                    "Unknown {} option `{}`; expected one of: {}",
+                    name,
+                    key,
+                    known.join(", ")
+                ),
+            ));
+        }
+    }
+    Ok(options)
+}
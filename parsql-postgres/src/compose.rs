@@ -0,0 +1,109 @@
+//! Query composition.
+//!
+//! A [`Fragment`] is a piece of SQL together with the parameters bound to its placeholders.
+//! Placeholders are kept as references into the parameter list, so fragments can be
+//! combined freely and are renumbered when the final SQL is rendered.
+//!
+//! Any `Queryable` struct can be turned into a fragment with `SqlQuery::fragment`, and
+//! reused as a CTE, a subquery or a member of a `UNION ALL`:
+//!
+//! ```rust
+//! # use parsql_postgres::compose::Fragment;
+//! let state = 1;
+//! let min_score = 10;
+//! let active = Fragment::new("SELECT id, name, score FROM users WHERE state = $1", vec![&state]);
+//! let query = Fragment::new("SELECT id, name FROM active WHERE score > $1", vec![&min_score])
+//!     .with_cte("active", active);
+//!
+//! let (sql, params) = query.build();
+//! assert_eq!(
+//!     sql,
+//!     "WITH active AS (SELECT id, name, score FROM users WHERE state = $1) SELECT id, name FROM active WHERE score > $2"
+//! );
+//! assert_eq!(params.len(), 2);
+//! ```
+
+use crate::expr::Postgres;
+
+/// A SQL fragment with its bound parameters.
+pub type Fragment<'a> = parsql_core::compose::Fragment<'a, Postgres>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_renumbers_placeholders() {
+        let (state, level) = (1, 3);
+        let query = Fragment::new(
+            "SELECT id FROM users WHERE state = $1 OR parent_state = $1",
+            vec![&state],
+        )
+        .push(" AND id IN (")
+        .append(Fragment::new(
+            "SELECT user_id FROM admins WHERE level = $1",
+            vec![&level],
+        ))
+        .push(")");
+
+        let (sql, params) = query.build();
+        assert_eq!(
+            sql,
+            "SELECT id FROM users WHERE state = $1 OR parent_state = $1 AND id IN (SELECT user_id FROM admins WHERE level = $2)"
+        );
+        assert_eq!(format!("{:?}", params), "[1, 3]");
+    }
+
+    #[test]
+    fn test_placeholders_in_string_literals_are_kept() {
+        let id = 1;
+        let (sql, params) =
+            Fragment::new("SELECT '$1' FROM users WHERE id = $1", vec![&id]).build();
+        assert_eq!(sql, "SELECT '$1' FROM users WHERE id = $1");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn test_nested_ctes_are_declared_first() {
+        let (state, min_id, max_id) = (1, 10, 20);
+        let active = Fragment::new("SELECT id FROM users WHERE state = $1", vec![&state]);
+        let recent = Fragment::new("SELECT id FROM active WHERE id > $1", vec![&min_id])
+            .with_cte("active", active);
+        let query = Fragment::new("SELECT id FROM recent WHERE id < $1", vec![&max_id])
+            .with_cte("recent", recent);
+
+        let (sql, params) = query.build();
+        assert_eq!(
+            sql,
+            "WITH active AS (SELECT id FROM users WHERE state = $1), \
+             recent AS (SELECT id FROM active WHERE id > $2) \
+             SELECT id FROM recent WHERE id < $3"
+        );
+        assert_eq!(format!("{:?}", params), "[1, 10, 20]");
+    }
+
+    #[test]
+    fn test_union_all_parenthesizes_members() {
+        let (state, level) = (1, 3);
+        let users = Fragment::new(
+            "SELECT id FROM users WHERE state = $1 ORDER BY id LIMIT 5",
+            vec![&state],
+        );
+        let admins = Fragment::new("SELECT user_id FROM admins WHERE level = $1", vec![&level]);
+
+        let (sql, params) = users.union_all(admins).build();
+        assert_eq!(
+            sql,
+            "(SELECT id FROM users WHERE state = $1 ORDER BY id LIMIT 5) UNION ALL \
+             (SELECT user_id FROM admins WHERE level = $2)"
+        );
+        assert_eq!(format!("{:?}", params), "[1, 3]");
+    }
+
+    #[test]
+    #[should_panic(expected = "Placeholder $2 has no parameter")]
+    fn test_missing_parameter_panics() {
+        let id = 1;
+        Fragment::new("SELECT id FROM users WHERE id = $1 OR id = $2", vec![&id]);
+    }
+}
@@ -1,8 +1,16 @@
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use crate::compose::Fragment;
+use crate::error::{check_version, UpdateError, WhereError};
+use crate::expr::Expr;
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+    Versioned,
+};
 use postgres::{
     types::{FromSql, ToSql},
     Client, Error, Row,
 };
+use std::collections::HashSet;
 
 // CrudOps trait implementasyonu postgres::Client için
 impl CrudOps for Client {
@@ -21,6 +29,14 @@ impl CrudOps for Client {
         delete(self, entity)
     }
 
+    fn restore<T: SoftDelete + SqlParams>(&mut self, entity: T) -> Result<u64, Error> {
+        restore(self, entity)
+    }
+
+    fn force_delete<T: SoftDelete + SqlParams>(&mut self, entity: T) -> Result<u64, Error> {
+        force_delete(self, entity)
+    }
+
     fn fetch<P, R>(&mut self, params: &P) -> Result<R, Error>
     where
         P: SqlQuery<R> + SqlParams,
@@ -44,12 +60,9 @@ impl CrudOps for Client {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let row = self.query_one(&sql, &params)?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+        let row = observation.finish(self.query_one(&sql, &params), |_| 1)?;
         to_model(&row)
     }
 
@@ -60,15 +73,31 @@ impl CrudOps for Client {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let rows = self.query(&sql, &params)?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+        let rows = observation.finish(self.query(&sql, &params), |rows| rows.len() as u64)?;
 
         rows.iter().map(to_model).collect()
     }
+
+    fn preload<R, C>(&mut self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C>,
+        C: FromRow + Clone,
+    {
+        preload(self, records)
+    }
+
+    fn fetch_all_where<R>(&mut self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow,
+    {
+        fetch_all_where(self, expr)
+    }
+
+    fn fetch_all_fragment<R: FromRow>(&mut self, fragment: &Fragment) -> Result<Vec<R>, Error> {
+        fetch_all_fragment(self, fragment)
+    }
 }
 
 /// # insert
@@ -134,12 +163,9 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql<'a> + Send + Sync>(
     entity: T,
 ) -> Result<P, Error> {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = client.query_one(&sql, &params)?;
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+    let row = observation.finish(client.query_one(&sql, &params), |_| 1)?;
     row.try_get::<_, P>(0)
 }
 
@@ -152,7 +178,8 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql<'a> + Send + Sync>(
 /// - `entity`: Data object containing the update information (must implement SqlCommand and UpdateParams traits)
 ///
 /// ## Return Value
-/// - `Result<u64, Error>`: On success, returns the number of updated records; on failure, returns Error
+/// - `Result<u64, Error>`: On success, returns the number of updated records; on failure, returns Error.
+///   Models with `#[version_column("...")]` should use [`update_versioned`] to detect conflicts.
 ///
 /// ## Struct Definition
 /// Structs used with this function should be annotated with the following derive macros:
@@ -178,7 +205,7 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql<'a> + Send + Sync>(
 ///
 /// ## Example Usage
 /// ```rust,no_run
-/// use postgres::{Client, NoTls, Error};
+/// use postgres::{Client, NoTls};
 /// use parsql::postgres::update;
 ///
 /// #[derive(Updateable, UpdateParams)]
@@ -192,7 +219,7 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql<'a> + Send + Sync>(
 ///     pub state: i16,  // This field won't be updated as it's not specified in the update attribute
 /// }
 ///
-/// fn main() -> Result<(), Error> {
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut client = Client::connect(
 ///         "host=localhost user=postgres dbname=test",
 ///         NoTls,
@@ -215,12 +242,60 @@ pub fn update<T: SqlCommand + UpdateParams>(
     entity: T,
 ) -> Result<u64, Error> {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    client.execute(&sql, &params)
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    observation.finish(client.execute(&sql, &params), |rows| *rows)
+}
+
+/// # update_versioned
+///
+/// Updates a record of a model with `#[version_column("...")]` using optimistic locking.
+///
+/// The generated SQL matches the version read with the record and increments it. When no row
+/// matches, the record was changed or deleted since it was read and the update fails with
+/// `UpdateError::StaleObject`.
+///
+/// ## Parameters
+/// - `client`: Database connection client
+/// - `entity`: Data object containing the update information (must implement Versioned and UpdateParams traits)
+///
+/// ## Return Value
+/// - `Result<(), UpdateError>`: On failure, returns `UpdateError::StaleObject` or the database error.
+///
+/// ## Example Usage
+/// ```rust,no_run
+/// use postgres::{Client, NoTls};
+/// use parsql_postgres::traits::*;
+/// use parsql_postgres::*;
+///
+/// #[derive(Updateable, UpdateParams)]
+/// #[table("accounts")]
+/// #[update("balance")]
+/// #[where_clause("id = $")]
+/// #[version_column("version")]
+/// pub struct UpdateAccount {
+///     pub id: i64,
+///     pub balance: i64,
+///     pub version: i64,
+/// }
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut client = Client::connect("host=localhost user=postgres dbname=test", NoTls)?;
+///     let account = UpdateAccount { id: 1, balance: 50, version: 3 };
+///
+///     match update_versioned(&mut client, account) {
+///         Err(err) if err.is_stale() => println!("Reload the account and try again"),
+///         result => result?,
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn update_versioned<T: Versioned + UpdateParams>(
+    client: &mut postgres::Client,
+    entity: T,
+) -> Result<(), UpdateError> {
+    let affected_rows = update(client, entity)?;
+    check_version::<T>(affected_rows > 0)
 }
 
 /// # delete
@@ -282,12 +357,93 @@ pub fn delete<T: SqlCommand + SqlParams>(
     entity: T,
 ) -> Result<u64, Error> {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(client.execute(&sql, &params), |rows| *rows)
+}
 
+/// # restore
+///
+/// Restores soft-deleted records by clearing their deletion marker.
+///
+/// ## Parameters
+/// - `client`: Database connection client
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<u64, Error>`: On success, returns the number of restored records; on failure, returns Error
+///
+/// ## Example Usage
+/// ```rust,no_run
+/// use parsql_postgres::traits::*;
+/// use parsql_postgres::*;
+/// use postgres::NoTls;
+///
+/// #[derive(Deletable, SqlParams)]
+/// #[table("users")]
+/// #[where_clause("id = $")]
+/// #[soft_delete(column = "deleted_at")]
+/// pub struct DeleteUser {
+///     pub id: i32,
+/// }
+///
+/// fn main() -> Result<(), Error> {
+///     let mut client = Client::connect("host=localhost user=postgres dbname=test", NoTls)?;
+///
+///     let count = restore(&mut client, DeleteUser { id: 6 })?;
+///     Ok(())
+/// }
+/// ```
+pub fn restore<T: SoftDelete + SqlParams>(
+    client: &mut postgres::Client,
+    entity: T,
+) -> Result<u64, Error> {
+    let sql = T::restore_query();
     let params = entity.params();
-    client.execute(&sql, &params)
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    observation.finish(client.execute(&sql, &params), |rows| *rows)
+}
+
+/// # force_delete
+///
+/// Permanently deletes records of a table that uses soft delete.
+///
+/// ## Parameters
+/// - `client`: Database connection client
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<u64, Error>`: On success, returns the number of deleted records; on failure, returns Error
+///
+/// ## Example Usage
+/// ```rust,no_run
+/// use parsql_postgres::traits::*;
+/// use parsql_postgres::*;
+/// use postgres::NoTls;
+///
+/// #[derive(Deletable, SqlParams)]
+/// #[table("users")]
+/// #[where_clause("id = $")]
+/// #[soft_delete(column = "deleted_at")]
+/// pub struct DeleteUser {
+///     pub id: i32,
+/// }
+///
+/// fn main() -> Result<(), Error> {
+///     let mut client = Client::connect("host=localhost user=postgres dbname=test", NoTls)?;
+///
+///     let count = force_delete(&mut client, DeleteUser { id: 6 })?;
+///     Ok(())
+/// }
+/// ```
+pub fn force_delete<T: SoftDelete + SqlParams>(
+    client: &mut postgres::Client,
+    entity: T,
+) -> Result<u64, Error> {
+    let sql = T::force_delete_query();
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(client.execute(&sql, &params), |rows| *rows)
 }
 
 /// # fetch
@@ -320,12 +476,9 @@ where
     R: FromRow,
 {
     let sql = P::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let row = client.query_one(&sql, &query_params)?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let row = observation.finish(client.query_one(&sql, &query_params), |_| 1)?;
     R::from_row(&row)
 }
 
@@ -360,12 +513,9 @@ where
     R: FromRow,
 {
     let sql = P::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let rows = client.query(&sql, &query_params)?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let rows = observation.finish(client.query(&sql, &query_params), |rows| rows.len() as u64)?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -375,6 +525,196 @@ where
     Ok(results)
 }
 
+/// # preload
+///
+/// Loads the records related to a list of records with a single `= ANY($1)` query
+/// and attaches them, avoiding one query per record (N+1).
+///
+/// ## Parameters
+/// - `client`: Database connection client
+/// - `records`: Records previously returned by `fetch_all` (must implement Relation<C>)
+///
+/// ## Return Value
+/// - `Result<(), Error>`: On success, the relation field of every record is filled; on failure, returns Error
+///
+/// ## Example Usage
+/// Relations are declared on the model with the `has_many` or `belongs_to` attributes
+/// of the `FromRow` derive macro; a nullable foreign key is an `Option` field:
+///
+/// ```rust
+/// use parsql_postgres::traits::*;
+/// use parsql_postgres::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("orders")]
+/// #[select("id, customer_id")]
+/// #[where_clause("customer_id = $")]
+/// #[result_type("Order")]
+/// pub struct OrdersByCustomer {
+///     pub customer_id: i64,
+/// }
+///
+/// #[derive(FromRow, Debug)]
+/// #[has_many(OrderItem, foreign_key = "order_id", field = "items")]
+/// pub struct Order {
+///     pub id: i64,
+///     pub customer_id: i64,
+///     pub items: Vec<OrderItem>,    // Filled by preload
+/// }
+///
+/// #[derive(FromRow, Clone, Debug)]
+/// pub struct OrderItem {
+///     pub id: i64,
+///     pub order_id: Option<i64>,
+///     pub product: String,
+/// }
+///
+/// fn orders_with_items(client: &mut Client, customer_id: i64) -> Result<Vec<Order>, Error> {
+///     let mut orders: Vec<Order> = fetch_all(client, &OrdersByCustomer { customer_id })?;
+///     preload::<Order, OrderItem>(client, &mut orders)?;
+///     Ok(orders)
+/// }
+/// # fn main() {}
+/// ```
+pub fn preload<R, C>(client: &mut Client, records: &mut [R]) -> Result<(), Error>
+where
+    R: Relation<C>,
+    C: FromRow + Clone,
+{
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    // Each distinct key is sent only once; records without a key have no related rows
+    let mut seen = HashSet::new();
+    let keys: Vec<R::Key> = records
+        .iter()
+        .filter_map(|record| record.relation_key())
+        .filter(|key| seen.insert(key.clone()))
+        .collect();
+
+    let sql = R::relation_query();
+    let observation = Observation::start::<C>(QueryKind::Select, &sql, &[&keys]);
+    let rows = observation.finish(client.query(&sql, &[&keys]), |rows| rows.len() as u64)?;
+
+    let mut related = Vec::with_capacity(rows.len());
+    for row in rows {
+        related.push(C::from_row(&row)?);
+    }
+
+    R::stitch(records, related);
+    Ok(())
+}
+
+/// # fetch_all_where
+///
+/// Retrieves the records of a table that match a typed WHERE expression.
+///
+/// ## Parameters
+/// - `client`: Database connection client
+/// - `expr`: The condition, built from the column constants generated by the `Columns` derive
+///
+/// ## Return Value
+/// - `Result<Vec<R>, WhereError>`: On success, returns the matching records; on failure, returns WhereError
+///
+/// Like the `Queryable` queries of the model, rows marked by `#[soft_delete]` are skipped, and
+/// a `#[tenant_scoped]` model needs the condition to be restricted with
+/// `TenantContext::filter`; otherwise the query is not run and fails with
+/// `WhereError::MissingTenant`.
+///
+/// ## Example Usage
+///
+/// ```rust
+/// use parsql_postgres::traits::*;
+/// use parsql_postgres::*;
+///
+/// #[derive(Columns, FromRow, Debug)]
+/// #[table("users")]
+/// #[soft_delete]
+/// pub struct User {
+///     pub id: i64,
+///     pub email: String,
+///     pub state: i16,
+/// }
+///
+/// fn active_users(client: &mut Client) -> Result<Vec<User>, WhereError> {
+///     let expr = users::state.eq(1).and(users::email.ilike("%@example.com"));
+///     fetch_all_where::<User>(client, expr)
+/// }
+/// # fn main() {}
+/// ```
+pub fn fetch_all_where<R>(client: &mut Client, expr: Expr) -> Result<Vec<R>, WhereError>
+where
+    R: Columns + FromRow,
+{
+    let (sql, params) = expr.select::<R>()?;
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params), |rows| rows.len() as u64)?;
+    Ok(rows.iter().map(R::from_row).collect::<Result<_, _>>()?)
+}
+
+/// # fetch_all_fragment
+///
+/// Retrieves all records returned by a composed query.
+///
+/// ## Parameters
+/// - `client`: Database connection client
+/// - `fragment`: The query, usually built from `Queryable` structs with `SqlQuery::fragment`
+///
+/// ## Return Value
+/// - `Result<Vec<R>, Error>`: On success, returns the records; on failure, returns Error
+///
+/// ## Example Usage
+///
+/// ```rust,no_run
+/// use postgres::{Client, NoTls, Error};
+/// use parsql_postgres::traits::*;
+/// use parsql_postgres::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("users")]
+/// #[select("id, name")]
+/// #[where_clause("state = $")]
+/// #[result_type("UserSummary")]
+/// struct ActiveUsers {
+///     state: i32,
+/// }
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("admins")]
+/// #[select("id, name")]
+/// #[where_clause("level >= $")]
+/// #[result_type("UserSummary")]
+/// struct Admins {
+///     level: i32,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct UserSummary {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// fn main() -> Result<(), Error> {
+///     let mut client = Client::connect("host=localhost user=postgres", NoTls)?;
+///
+///     let active = ActiveUsers { state: 1 };
+///     let admins = Admins { level: 3 };
+///     let query = active.fragment().union_all(admins.fragment());
+///     let users: Vec<UserSummary> = fetch_all_fragment(&mut client, &query)?;
+///     Ok(())
+/// }
+/// ```
+pub fn fetch_all_fragment<R: FromRow>(
+    client: &mut Client,
+    fragment: &Fragment,
+) -> Result<Vec<R>, Error> {
+    let (sql, params) = fragment.build();
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params), |rows| rows.len() as u64)?;
+    rows.iter().map(R::from_row).collect()
+}
+
 /// # get_by_query
 ///
 /// Retrieves multiple records from the database using a custom SQL query.
@@ -417,11 +757,8 @@ pub fn get_by_query<T: FromRow>(
     query: &str,
     params: &[&(dyn ToSql + Sync)],
 ) -> Result<Vec<T>, Error> {
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", query);
-    }
-
-    let rows = client.query(query, params)?;
+    let observation = Observation::start::<T>(QueryKind::Select, query, params);
+    let rows = observation.finish(client.query(query, params), |rows| rows.len() as u64)?;
     rows.iter()
         .map(|row| T::from_row(row))
         .collect::<Result<Vec<_>, _>>()
@@ -516,13 +853,10 @@ where
     F: Fn(&Row) -> Result<T, Error>,
 {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
 
-    match client.query_one(&sql, &params) {
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    match observation.finish(client.query_one(&sql, &params), |_| 1) {
         Ok(_row) => to_model(&_row),
         Err(e) => Err(e),
     }
@@ -619,13 +953,10 @@ where
     F: Fn(&Row) -> Result<T, Error>,
 {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
 
-    let rows = client.query(&sql, &params)?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params), |rows| rows.len() as u64)?;
 
     rows.iter()
         .map(|row| to_model(row))
@@ -0,0 +1,124 @@
+//! Error types returned by update operations and typed WHERE queries.
+
+use postgres::Error;
+use parsql_core::tenant::MissingTenant;
+use std::fmt;
+
+/// Error returned by `update_versioned`.
+///
+/// Besides database errors, versioned updates fail with [`UpdateError::StaleObject`] when no
+/// row matched the expected version, i.e. the record was changed or deleted since it was read.
+/// Plain `update` calls return the database `Error` directly.
+#[derive(Debug)]
+pub enum UpdateError {
+    /// The database returned an error
+    Database(Error),
+    /// The versioned update matched no rows
+    StaleObject {
+        /// Type name of the update model
+        entity: &'static str,
+    },
+}
+
+impl UpdateError {
+    /// Returns `true` if the record was changed concurrently.
+    pub fn is_stale(&self) -> bool {
+        matches!(self, UpdateError::StaleObject { .. })
+    }
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::Database(err) => write!(f, "Database error: {}", err),
+            UpdateError::StaleObject { entity } => write!(
+                f,
+                "Stale object: {} was modified or deleted by another transaction",
+                entity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UpdateError::Database(err) => Some(err),
+            UpdateError::StaleObject { .. } => None,
+        }
+    }
+}
+
+impl From<Error> for UpdateError {
+    fn from(err: Error) -> Self {
+        UpdateError::Database(err)
+    }
+}
+
+/// Error returned by `fetch_all_where`.
+///
+/// Besides database errors, the query fails with [`WhereError::MissingTenant`] when the model
+/// is `#[tenant_scoped]` and the condition was not restricted with `TenantContext::filter`.
+#[derive(Debug)]
+pub enum WhereError {
+    /// The database returned an error
+    Database(Error),
+    /// The model is tenant scoped and the condition has no tenant
+    MissingTenant {
+        /// Type name of the model
+        entity: &'static str,
+    },
+}
+
+impl WhereError {
+    /// Returns `true` if the query was rejected because no tenant was given.
+    pub fn is_missing_tenant(&self) -> bool {
+        matches!(self, WhereError::MissingTenant { .. })
+    }
+}
+
+impl fmt::Display for WhereError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WhereError::Database(err) => write!(f, "Database error: {}", err),
+            WhereError::MissingTenant { entity } => write!(
+                f,
+                "Missing tenant: {} is tenant scoped and the condition was not restricted \
+                 with TenantContext::filter",
+                entity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WhereError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WhereError::Database(err) => Some(err),
+            WhereError::MissingTenant { .. } => None,
+        }
+    }
+}
+
+impl From<Error> for WhereError {
+    fn from(err: Error) -> Self {
+        WhereError::Database(err)
+    }
+}
+
+impl From<MissingTenant> for WhereError {
+    fn from(err: MissingTenant) -> Self {
+        WhereError::MissingTenant { entity: err.entity }
+    }
+}
+
+/// Turns a versioned update that matched no rows into [`UpdateError::StaleObject`].
+pub(crate) fn check_version<T: crate::traits::Versioned>(updated: bool) -> Result<(), UpdateError> {
+    if updated {
+        Ok(())
+    } else {
+        Err(UpdateError::StaleObject {
+            entity: std::any::type_name::<T>(),
+        })
+    }
+}
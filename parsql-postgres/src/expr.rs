@@ -0,0 +1,145 @@
+//! Typed WHERE expressions.
+//!
+//! Column constants are generated by the `Columns` derive macro (`users::email`,
+//! `users::state`, ...). Comparing a column with a value produces an [`Expr`] that renders
+//! to a SQL fragment with numbered `$n` placeholders and keeps the values as bound parameters.
+//!
+//! ```rust
+//! # use parsql_postgres::expr::Column;
+//! # #[allow(non_upper_case_globals)]
+//! # mod users {
+//! #     use parsql_postgres::expr::Column;
+//! #     pub const state: Column<i16> = Column::new("state");
+//! #     pub const email: Column<String> = Column::new("email");
+//! # }
+//! let expr = users::state.eq(1).and(users::email.like("%@example.com"));
+//! let (sql, params) = expr.to_sql();
+//! assert_eq!(sql, "(state = $1 AND email LIKE $2)");
+//! assert_eq!(params.len(), 2);
+//! ```
+
+use postgres::types::ToSql;
+
+use parsql_core::dialect::{Bind, Dialect, ILike};
+
+pub use parsql_core::expr::IntoValue;
+
+/// The PostgreSQL dialect: numbered `$n` placeholders and `ILIKE`.
+pub struct Postgres;
+
+impl Dialect for Postgres {
+    type Param<'a> = dyn ToSql + Sync + 'a;
+    type Value = dyn ToSql + Sync + Send;
+
+    fn placeholder(n: usize) -> String {
+        format!("${}", n)
+    }
+
+    fn param(value: &Self::Value) -> &Self::Param<'_> {
+        value
+    }
+}
+
+impl<V: ToSql + Sync + Send + 'static> Bind<V> for Postgres {
+    fn bind(value: V) -> Box<Self::Value> {
+        Box::new(value)
+    }
+}
+
+impl ILike for Postgres {}
+
+/// A column of a table whose values have the Rust type `T`.
+pub type Column<T> = parsql_core::expr::Column<T, Postgres>;
+
+/// A WHERE condition with its bound parameters.
+pub type Expr = parsql_core::expr::Expr<Postgres>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenant::TenantContext;
+    use crate::traits::Columns;
+
+    const ID: Column<i64> = Column::new("id");
+    const NAME: Column<String> = Column::new("name");
+    const NOTE: Column<Option<String>> = Column::new("note");
+
+    struct User;
+
+    impl Columns for User {
+        fn table() -> &'static str {
+            "users"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["id", "name"]
+        }
+
+        fn soft_delete_column() -> Option<&'static str> {
+            Some("deleted_at")
+        }
+
+        fn tenant_column() -> Option<&'static str> {
+            Some("tenant_id")
+        }
+    }
+
+    #[test]
+    fn test_empty_in_list_matches_nothing() {
+        let expr = ID.in_list(Vec::<i64>::new());
+        let (sql, params) = expr.to_sql();
+        assert_eq!(sql, "1 = 0");
+        assert!(params.is_empty());
+
+        let expr = ID.in_list([1, 2, 3]);
+        let (sql, params) = expr.to_sql();
+        assert_eq!(sql, "id IN ($1, $2, $3)");
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn test_not_and_nested_conditions() {
+        let excluded = !ID.gt(1).or(NAME.eq("ada"));
+        let expr = excluded.and(NOTE.is_null().or(NOTE.eq("x")));
+        let (sql, params) = expr.to_sql();
+        assert_eq!(
+            sql,
+            "(NOT ((id > $1 OR name = $2)) AND (note IS NULL OR note = $3))"
+        );
+        assert_eq!(params.len(), 3);
+
+        let expr = ID.in_list([1, 2]).or(ID.eq(3).and(!NAME.eq("ada")));
+        let (sql, params) = expr.to_sql();
+        assert_eq!(sql, "(id IN ($1, $2) OR (id = $3 AND NOT (name = $4)))");
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn test_select_applies_row_filters() {
+        let tenant = TenantContext::new(7_i64);
+        let expr = tenant.filter::<User>(NAME.eq("ada"));
+        let (sql, params) = expr.select::<User>().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, name FROM users WHERE (name = $1 AND tenant_id = $2) \
+             AND users.deleted_at IS NULL"
+        );
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_select_requires_a_tenant_context() {
+        let err = NAME.eq("ada").select::<User>().err().unwrap();
+        assert_eq!(err.entity, std::any::type_name::<User>());
+
+        // A tenant comparison written by hand does not replace the context
+        let tenant_id: Column<i64> = Column::new("tenant_id");
+        let expr = NAME.eq("ada").and(tenant_id.eq(7));
+        assert!(expr.select::<User>().is_err());
+
+        // A tenant on one side of an OR does not restrict the rows
+        let tenant = TenantContext::new(7_i64);
+        let expr = tenant.filter::<User>(NAME.eq("ada")).or(NAME.eq("bob"));
+        assert!(expr.select::<User>().is_err());
+    }
+}
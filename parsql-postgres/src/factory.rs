@@ -0,0 +1,93 @@
+//! Seed data factories for tests.
+//!
+//! `#[derive(Factory)]` on an `Insertable` struct generates `<Struct>Factory`, which
+//! implements [`Factory`]: every entity it builds gets the next sequence number of the
+//! factory, so defaults such as `name-1`, `name-2` stay unique, and `with_<field>` sets a
+//! field for all of them. [`FactoryExt`] inserts the entities with [`CrudOps::insert`], so
+//! `create` returns the `#[returning]` column, which associates the rows of another factory
+//! with it:
+//!
+//! ```rust,no_run
+//! use parsql_postgres::traits::*;
+//! use parsql_postgres::*;
+//! use postgres::NoTls;
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("users")]
+//! #[returning("id")]
+//! #[factory(name = "UserFactory")]
+//! struct InsertUser {
+//!     name: String,
+//!     #[factory(sequence = "user{n}@example.com")]
+//!     email: String,
+//! }
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("posts")]
+//! #[returning("id")]
+//! #[factory(name = "PostFactory")]
+//! struct InsertPost {
+//!     user_id: i64,
+//!     title: String,
+//! }
+//!
+//! # fn main() -> Result<(), postgres::Error> {
+//! let mut client = Client::connect("host=localhost user=postgres", NoTls)?;
+//! let mut tx = client.transaction()?;
+//! let user_id: i64 = UserFactory::new().with_name("Ada").create(&mut tx)?;
+//! let post_ids: Vec<i64> = PostFactory::new().with_user_id(user_id).create_many(&mut tx, 3)?;
+//! tx.commit()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use postgres::{types::FromSql, Error};
+
+use crate::traits::{CrudOps, SqlCommand, SqlParams};
+
+pub use parsql_core::factory::Factory;
+
+/// Inserts the entities of a [`Factory`]; implemented for every factory of `Insertable`
+/// entities.
+pub trait FactoryExt: Factory {
+    /// Inserts the next entity and returns its `#[returning]` column.
+    fn create<C, P>(&self, conn: &mut C) -> Result<P, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync;
+
+    /// Inserts `count` entities and returns their `#[returning]` columns.
+    ///
+    /// The entities are inserted one statement at a time and are not wrapped in a
+    /// transaction: an error leaves the earlier rows in place, so pass a transaction to
+    /// insert them atomically.
+    fn create_many<C, P>(&self, conn: &mut C, count: usize) -> Result<Vec<P>, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync;
+}
+
+impl<F> FactoryExt for F
+where
+    F: Factory + ?Sized,
+    F::Entity: SqlCommand + SqlParams,
+{
+    fn create<C, P>(&self, conn: &mut C) -> Result<P, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        conn.insert(self.build())
+    }
+
+    fn create_many<C, P>(&self, conn: &mut C, count: usize) -> Result<Vec<P>, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        self.build_many(count)
+            .into_iter()
+            .map(|entity| conn.insert(entity))
+            .collect()
+    }
+}
@@ -161,7 +161,16 @@
 //! }
 //! ```
 
+pub mod compose;
 pub mod crud_ops;
+pub mod error;
+pub mod expr;
+pub mod factory;
+pub mod observer;
+pub mod search;
+pub use parsql_core::stats;
+pub mod tenant;
+pub mod testing;
 pub mod transaction_ops;
 pub mod traits;
 pub mod macros;
@@ -173,9 +182,21 @@ pub use macros::*;
 
 // Re-export crud operations
 pub use crud_ops::{
-    delete, fetch, fetch_all, get_by_query, insert, select, select_all, update,
+    delete, fetch, fetch_all, fetch_all_fragment, fetch_all_where, force_delete, get_by_query,
+    insert, preload, restore, select, select_all, update,
+    update_versioned,
 };
 
+pub use compose::Fragment;
+pub use error::{UpdateError, WhereError};
+pub use factory::{Factory, FactoryExt};
+pub use observer::{set_observer, QueryEvent, QueryObserver};
+
+pub use stats::QueryStats;
+
+pub use tenant::{Scoped, TenantContext};
+pub use expr::{Column, Expr, IntoValue};
+
 // Eski isimlerle fonksiyonları deprecated olarak dışa aktar
 #[allow(deprecated)]
 pub use crud_ops::{get, get_all};
@@ -183,7 +204,8 @@ pub use crud_ops::{get, get_all};
 // Re-export transaction operations in a transactional module
 pub mod transactional {
     pub use crate::transaction_ops::{
-        begin, tx_delete, tx_fetch, tx_fetch_all, tx_insert, tx_select, tx_select_all, tx_update,
+        begin, tx_delete, tx_fetch, tx_fetch_all, tx_fetch_all_fragment, tx_fetch_all_where,
+        tx_force_delete, tx_insert, tx_preload, tx_restore, tx_select, tx_select_all, tx_update,
     };
 
     // Eski isimlerle fonksiyonları deprecated olarak dışa aktar
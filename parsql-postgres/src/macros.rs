@@ -1,6 +1,6 @@
 pub use parsql_macros::{
-    Deletable, FromRowPostgres as FromRow, Insertable, Queryable, SqlParams, UpdateParams,
-    Updateable,
+    query, Columns, Deletable, Factory, FromRowPostgres as FromRow, Insertable, Queryable,
+    SqlParams, UpdateParams, Updateable,
 };
 
 pub use crate::traits::SqlCommand;
@@ -0,0 +1,155 @@
+//! PostgreSQL full-text search.
+//!
+//! A `Queryable` struct with `#[text_search(config = "...", columns = "...")]` matches the
+//! columns against its `query` field with `@@ websearch_to_tsquery(config, $1)`, selects
+//! `ts_rank_cd()` as `rank` and orders by it, best match first; `headline` adds
+//! `<column>_headline` columns built with `ts_headline()`. The search string is always bound
+//! as a parameter, and `websearch_to_tsquery` accepts any user input without syntax errors.
+//!
+//! Computing the document on every query scans the whole table. For large tables, store it
+//! in an indexed `tsvector` column with [`vector_column_statements`] and search it with
+//! `vector = "..."` instead of `columns`.
+//!
+//! ```rust,no_run
+//! use parsql_postgres::search;
+//! use parsql_postgres::traits::*;
+//! use parsql_postgres::*;
+//! use postgres::NoTls;
+//!
+//! #[derive(Queryable, SqlParams)]
+//! #[table("articles")]
+//! #[text_search(config = "english", vector = "search_vector", headline = "body")]
+//! #[result_type("ArticleHit")]
+//! struct SearchArticles {
+//!     query: String,
+//! }
+//!
+//! #[derive(FromRow)]
+//! struct ArticleHit {
+//!     id: i32,
+//!     title: String,
+//!     rank: f32,
+//!     body_headline: String,
+//! }
+//!
+//! # fn main() -> Result<(), postgres::Error> {
+//! let mut client = Client::connect("host=localhost user=postgres", NoTls)?;
+//! search::create_vector_column(
+//!     &mut client,
+//!     "articles",
+//!     "search_vector",
+//!     "english",
+//!     &["title", "body"],
+//! )?;
+//!
+//! let search = SearchArticles { query: "rust -async \"error handling\"".to_string() };
+//! let hits: Vec<ArticleHit> = client.fetch_all(&search)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use postgres::{Client, Error};
+
+pub use parsql_core::search::{
+    drop_vector_column_statements, index_statement, vector_column_statements,
+};
+
+/// Adds the generated `tsvector` column and its index; see [`vector_column_statements`].
+pub fn create_vector_column(
+    client: &mut Client,
+    table: &str,
+    vector_column: &str,
+    config: &str,
+    columns: &[&str],
+) -> Result<(), Error> {
+    for sql in vector_column_statements(table, vector_column, config, columns) {
+        client.execute(sql.as_str(), &[])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Queryable, SqlParams)]
+    #[table("articles")]
+    #[text_search(config = "english", columns = "title, body", headline = "body")]
+    #[where_clause("published = $ AND author_id = $")]
+    #[result_type("ArticleHit")]
+    struct SearchArticles {
+        query: String,
+        published: bool,
+        author_id: i64,
+    }
+
+    #[derive(Debug, FromRow)]
+    struct ArticleHit {
+        id: i32,
+        title: String,
+        rank: f32,
+        body_headline: String,
+    }
+
+    fn search(query: &str) -> SearchArticles {
+        SearchArticles { query: query.into(), published: true, author_id: 7 }
+    }
+
+    #[test]
+    fn test_search_query() {
+        let document = "to_tsvector('english', \
+                        coalesce(articles.title, '') || ' ' || coalesce(articles.body, ''))";
+        let tsquery = "websearch_to_tsquery('english', $1)";
+
+        assert_eq!(
+            SearchArticles::query(),
+            format!(
+                "SELECT articles.*, ts_rank_cd({document}, {tsquery}) AS rank, \
+                 ts_headline('english', articles.body, {tsquery}, \
+                 'StartSel=<b>, StopSel=</b>') AS body_headline \
+                 FROM articles WHERE {document} @@ {tsquery} \
+                 AND (published = $2 AND author_id = $3) ORDER BY rank DESC"
+            )
+        );
+    }
+
+    #[test]
+    fn test_search_string_is_the_first_parameter() {
+        let search = search("rust -async");
+
+        assert_eq!(format!("{:?}", search.params()), r#"["rust -async", true, 7]"#);
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    fn test_search_runs_with_the_where_clause() {
+        let mut client = TestClient::from_env();
+        let mut tx = client.begin().unwrap();
+        tx.batch_execute(
+            "CREATE TEMP TABLE articles (
+                 id int PRIMARY KEY, title text, body text, published bool, author_id bigint
+             );
+             INSERT INTO articles VALUES
+                 (1, 'Error handling in Rust', 'Error handling with Result', true, 7),
+                 (2, 'Async Rust', 'Futures and error handling', true, 7),
+                 (3, 'Error handling drafts', 'Not published yet', false, 7),
+                 (4, 'Rust errors', 'By another author', true, 8);",
+        )
+        .unwrap();
+
+        // The search string is bound as is, so operators and quotes are never SQL
+        let query = search("rust -async \"error handling\"");
+        let hits: Vec<ArticleHit> = tx.fetch_all(&query).unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(hits[0].title, "Error handling in Rust");
+        assert!(hits[0].rank > 0.0);
+        assert!(hits[0].body_headline.contains("<b>Error</b>"), "{:?}", hits[0]);
+
+        let query = search("rust'; DROP TABLE articles; --");
+        let hits: Vec<ArticleHit> = tx.fetch_all(&query).unwrap();
+        assert!(hits.is_empty());
+    }
+}
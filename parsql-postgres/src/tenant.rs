@@ -0,0 +1,211 @@
+//! Multi-tenant row scoping.
+//!
+//! Models marked with `#[tenant_scoped(column = "tenant_id")]` get a `tenant_id = $n` predicate
+//! in their generated SELECT, UPDATE and DELETE statements, and the tenant column in their
+//! INSERT statement. The tenant value itself comes from a [`TenantContext`], which wraps a
+//! model with [`TenantContext::scope`] before it is passed to the CRUD functions:
+//!
+//! ```rust
+//! use parsql_postgres::traits::*;
+//! use parsql_postgres::*;
+//!
+//! #[derive(Queryable, FromRow, SqlParams)]
+//! #[table("projects")]
+//! #[where_clause("name = $")]
+//! #[tenant_scoped]
+//! struct Project {
+//!     id: i64,
+//!     name: String,
+//! }
+//!
+//! let acme = TenantContext::new(1_i64);
+//! let query = acme.scope(Project { id: 0, name: "Website".into() });
+//! assert_eq!(
+//!     <Scoped<Project> as SqlQuery<Project>>::query(),
+//!     "SELECT id, name FROM projects WHERE (name = $1) AND tenant_id = $2"
+//! );
+//! assert_eq!(query.params().len(), 2);
+//! // let projects: Vec<Project> = fetch_all(&mut client, &query)?;
+//! ```
+//!
+//! The tenant placeholder is part of the generated SQL, so running a scoped model without a
+//! context fails with a parameter count error instead of reading other tenants' rows.
+//! `fetch_all_where` on a tenant-scoped model fails with `WhereError::MissingTenant` unless its
+//! condition was restricted with [`TenantContext::filter`].
+
+use postgres::types::ToSql;
+
+use crate::expr::Postgres;
+use crate::traits::{
+    SoftDelete, SoftDeleteQuery, SqlCommand, SqlParams, SqlQuery, TenantScoped, UpdateParams,
+    Versioned,
+};
+
+/// The tenant of the current request.
+///
+/// Cloning is cheap; the tenant value is shared between clones.
+pub type TenantContext = parsql_core::tenant::TenantContext<Postgres>;
+
+/// A tenant-scoped model together with its tenant, created by [`TenantContext::scope`].
+///
+/// Generates the same SQL as the wrapped model and adds the tenant value to its parameters.
+pub type Scoped<T> = parsql_core::tenant::Scoped<T, Postgres>;
+
+impl<T, R> SqlQuery<R> for Scoped<T>
+where
+    T: SqlQuery<R> + TenantScoped,
+{
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: SqlCommand + TenantScoped> SqlCommand for Scoped<T> {
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: Versioned + TenantScoped> Versioned for Scoped<T> {
+    fn version_column() -> &'static str {
+        T::version_column()
+    }
+}
+
+impl<T: SoftDelete + TenantScoped> SoftDelete for Scoped<T> {
+    fn restore_query() -> String {
+        T::restore_query()
+    }
+
+    fn force_delete_query() -> String {
+        T::force_delete_query()
+    }
+}
+
+impl<T: SoftDeleteQuery + TenantScoped> SoftDeleteQuery for Scoped<T> {
+    fn query_with_deleted() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams + TenantScoped> SqlParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+impl<T: UpdateParams + TenantScoped> UpdateParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use postgres::Transaction;
+
+    use crate::expr::Column;
+    use crate::testing::TestClient;
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams)]
+    #[table("projects")]
+    #[returning("id")]
+    #[tenant_scoped]
+    struct InsertProject {
+        name: String,
+    }
+
+    #[derive(Queryable, FromRow, SqlParams, Columns, Debug)]
+    #[table("projects")]
+    #[where_clause("id > $")]
+    #[tenant_scoped]
+    struct Project {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("projects")]
+    #[update("name")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct RenameProject {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("projects")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct DeleteProject {
+        id: i64,
+    }
+
+    fn create_projects(tx: &mut Transaction<'_>) {
+        tx.batch_execute(
+            "CREATE TEMP TABLE projects (id BIGSERIAL PRIMARY KEY, name TEXT, tenant_id BIGINT)",
+        )
+        .unwrap();
+    }
+
+    fn names(projects: Vec<Project>) -> Vec<String> {
+        projects.into_iter().map(|project| project.name).collect()
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    fn test_scoped_writes_and_reads_stay_in_the_tenant() {
+        let mut client = TestClient::from_env();
+        let mut tx = client.begin().unwrap();
+        create_projects(&mut tx);
+        let acme = TenantContext::new(1_i64);
+        let globex = TenantContext::new(2_i64);
+
+        let website: i64 = tx.insert(acme.scope(InsertProject { name: "Website".into() })).unwrap();
+        let _: i64 = tx.insert(globex.scope(InsertProject { name: "Shop".into() })).unwrap();
+
+        let rename = |name: &str| RenameProject { id: website, name: name.into() };
+        assert_eq!(tx.update(globex.scope(rename("Hijacked"))).unwrap(), 0);
+        assert_eq!(tx.update(acme.scope(rename("Homepage"))).unwrap(), 1);
+        assert_eq!(tx.delete(globex.scope(DeleteProject { id: website })).unwrap(), 0);
+
+        let all = Project { id: 0, name: String::new() };
+        let projects: Vec<Project> = tx.fetch_all(&acme.scope(all)).unwrap();
+        assert_eq!(names(projects), vec!["Homepage"]);
+
+        let expr = globex.filter::<Project>(projects::id.gt(0));
+        let projects: Vec<Project> = tx.fetch_all_where(expr).unwrap();
+        assert_eq!(names(projects), vec!["Shop"]);
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    fn test_fetch_all_where_without_a_context_fails() {
+        let mut client = TestClient::from_env();
+        let mut tx = client.begin().unwrap();
+        create_projects(&mut tx);
+
+        let err = tx.fetch_all_where::<Project>(projects::id.gt(0)).unwrap_err();
+        assert!(err.is_missing_tenant());
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    fn test_tenant_comparison_does_not_bypass_the_context() {
+        let mut client = TestClient::from_env();
+        let mut tx = client.begin().unwrap();
+        create_projects(&mut tx);
+        let acme = TenantContext::new(1_i64);
+        let tenant_id = Column::<i64>::new("tenant_id");
+
+        let err = tx.fetch_all_where::<Project>(tenant_id.eq(2)).unwrap_err();
+        assert!(err.is_missing_tenant());
+
+        let expr = acme.filter::<Project>(projects::id.gt(0)).or(tenant_id.eq(2));
+        let err = tx.fetch_all_where::<Project>(expr).unwrap_err();
+        assert!(err.is_missing_tenant());
+    }
+}
@@ -0,0 +1,67 @@
+//! Integration test fixtures that roll back everything a test wrote.
+//!
+//! [`TestClient::begin`] opens a transaction that is rolled back when it is dropped, whether
+//! the test returns, fails or panics, so tests can run in parallel against one database
+//! without cleaning up. The transaction implements [`CrudOps`], and nested
+//! `transaction()` calls inside a test become savepoints, so code under test that commits its
+//! own transactions still leaves nothing behind.
+//!
+//! ```rust,no_run
+//! use parsql_postgres::testing::TestClient;
+//! use parsql_postgres::traits::*;
+//! use parsql_postgres::*;
+//!
+//! #[derive(Insertable, SqlParams)]
+//! #[table("users")]
+//! #[returning("id")]
+//! struct InsertUser {
+//!     name: String,
+//! }
+//!
+//! #[test]
+//! fn inserts_user() {
+//!     let mut client = TestClient::from_env();
+//!     let mut tx = client.begin().unwrap();
+//!     let id: i64 = tx.insert(InsertUser { name: "Ada".into() }).unwrap();
+//!     assert!(id > 0);
+//! }
+//! ```
+//!
+//! `#[parsql::test]` does the same for a test taking `tx: &mut Transaction<'_>`.
+//!
+//! Rows written by one test are invisible to the others until it ends, but unique keys still
+//! conflict: a test inserting a key another running test inserted waits for it to roll back.
+//!
+//! [`CrudOps`]: crate::traits::CrudOps
+
+use postgres::{Client, Error, NoTls, Transaction};
+
+/// A connection for a single test.
+pub struct TestClient {
+    client: Client,
+}
+
+impl TestClient {
+    /// Connects to the database at `url`, without TLS.
+    pub fn connect(url: &str) -> Result<Self, Error> {
+        Ok(TestClient {
+            client: Client::connect(url, NoTls)?,
+        })
+    }
+
+    /// Connects to the database in the `DATABASE_URL` environment variable.
+    ///
+    /// # Panics
+    /// Panics if `DATABASE_URL` is not set or the connection fails, failing the test.
+    pub fn from_env() -> Self {
+        let url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set to run database tests");
+        Self::connect(&url)
+            .unwrap_or_else(|err| panic!("Failed to connect to DATABASE_URL: {}", err))
+    }
+
+    /// Opens the test transaction, rolled back when it is dropped.
+    pub fn begin(&mut self) -> Result<Transaction<'_>, Error> {
+        self.client.transaction()
+    }
+}
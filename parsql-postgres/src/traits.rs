@@ -3,12 +3,32 @@ use postgres::{
     types::{FromSql, ToSql},
     Error, Row,
 };
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::compose::Fragment;
+use crate::error::{check_version, UpdateError, WhereError};
+use crate::expr::Expr;
+
+pub use parsql_core::traits::{Columns, TenantScoped};
 
 /// SQL sorguları oluşturmak için trait (SELECT işlemleri için).
 /// Bu trait, `Queryable` derive makrosu tarafından uygulanır.
 pub trait SqlQuery<R> {
     /// SQL sorgu string'ini döndürür.
     fn query() -> String;
+
+    /// Sorguyu ve parametrelerini birleştirilebilir bir parça olarak döndürür;
+    /// CTE, alt sorgu veya `UNION ALL` üyesi olarak kullanılabilir.
+    ///
+    /// SQL her çağrıda yer tutucular için yeniden taranır; parçayı döngü içinde değil,
+    /// bir kez oluşturun.
+    fn fragment(&self) -> Fragment<'_>
+    where
+        Self: SqlParams + Sized,
+    {
+        Fragment::new(<Self as SqlQuery<R>>::query(), self.params())
+    }
 }
 
 /// SQL komutları oluşturmak için trait (INSERT/UPDATE/DELETE işlemleri için).
@@ -32,6 +52,51 @@ pub trait UpdateParams {
     fn params(&self) -> Vec<&(dyn ToSql + Sync)>;
 }
 
+/// İyimser kilitleme kullanan güncelleme modelleri için trait.
+/// Bu trait, `#[version_column("...")]` kullanıldığında `Updateable` derive makrosu tarafından
+/// uygulanır; `update_versioned` yalnızca bu modelleri kabul eder.
+pub trait Versioned: SqlCommand {
+    /// Her güncellemede artırılan kolonu döndürür.
+    fn version_column() -> &'static str;
+}
+
+/// Soft-delete uygulanan tabloların komutları için trait.
+/// Bu trait, `#[soft_delete]` kullanıldığında `Deletable` derive makrosu tarafından uygulanır.
+pub trait SoftDelete {
+    /// Silinme işaretini temizleyen SQL komutunu döndürür.
+    fn restore_query() -> String;
+
+    /// Kaydı kalıcı olarak silen SQL komutunu döndürür.
+    fn force_delete_query() -> String;
+}
+
+/// Soft-delete ile silinmiş kayıtları atlayan sorgular için trait.
+/// Bu trait, `#[soft_delete]` kullanıldığında `Queryable` derive makrosu tarafından uygulanır.
+pub trait SoftDeleteQuery {
+    /// Soft-delete filtresi olmadan SQL sorgusunu döndürür.
+    fn query_with_deleted() -> String;
+}
+
+/// Soft-delete uygulanan bir sorguyu silinmiş kayıtlarla birlikte çalıştırır,
+/// örn. `fetch_all(&mut client, &WithDeleted(query))`.
+#[derive(Debug, Clone)]
+pub struct WithDeleted<T>(pub T);
+
+impl<T, R> SqlQuery<R> for WithDeleted<T>
+where
+    T: SqlQuery<R> + SoftDeleteQuery,
+{
+    fn query() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams> SqlParams for WithDeleted<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0.params()
+    }
+}
+
 /// Veritabanı satırlarını Rust struct'larına dönüştürmek için trait.
 /// Bu trait, `FromRow` derive makrosu tarafından uygulanır.
 pub trait FromRow {
@@ -47,6 +112,54 @@ pub trait FromRow {
         Self: Sized;
 }
 
+/// Bir modele ait ilişkili kayıtları toplu olarak yüklemek için trait.
+/// Bu trait, `FromRow` derive makrosu tarafından modeldeki her
+/// `#[has_many(...)]` ve `#[belongs_to(...)]` tanımı için uygulanır.
+pub trait Relation<C> {
+    /// Modeli ilişkili kayıtlarına bağlayan değerin tipi.
+    type Key: ToSql + Sync + Eq + Hash + Clone;
+
+    /// İlişkili kayıtları `= ANY($1)` ile tek sorguda yükleyen SQL'i döndürür.
+    fn relation_query() -> String;
+
+    /// Bu kaydın ilişkili kayıtlarını aramak için kullanılan anahtarı döndürür;
+    /// `None` (`NULL` yabancı anahtar) hiçbir kayıtla eşleşmez.
+    fn relation_key(&self) -> Option<Self::Key>;
+
+    /// İlişkili bir kaydı sahibiyle eşleştirmek için kullanılan anahtarı döndürür;
+    /// `None` hiçbir sahiple eşleşmez.
+    fn related_key(related: &C) -> Option<Self::Key>;
+
+    /// Yüklenen ilişkili kayıtları bu kayda yerleştirir.
+    fn attach(&mut self, related: Vec<C>);
+
+    /// Yüklenen ilişkili kayıtları ait oldukları kayıtlara dağıtır.
+    ///
+    /// # Argümanlar
+    /// * `records` - İlişkinin yüklendiği kayıtlar
+    /// * `related` - İlişki sorgusunun döndürdüğü tüm kayıtlar
+    fn stitch(records: &mut [Self], related: Vec<C>)
+    where
+        Self: Sized,
+        C: Clone,
+    {
+        let mut grouped: HashMap<Self::Key, Vec<C>> = HashMap::new();
+        for item in related {
+            if let Some(key) = Self::related_key(&item) {
+                grouped.entry(key).or_default().push(item);
+            }
+        }
+
+        for record in records {
+            let related = record
+                .relation_key()
+                .and_then(|key| grouped.get(&key).cloned())
+                .unwrap_or_default();
+            record.attach(related);
+        }
+    }
+}
+
 /// CrudOps trait defines the CRUD (Create, Read, Update, Delete) operations
 /// that can be performed on a PostgreSQL database.
 ///
@@ -123,6 +236,19 @@ pub trait CrudOps {
     /// * `Result<u64, Error>` - On success, returns the number of updated records; on failure, returns Error
     fn update<T: SqlCommand + UpdateParams>(&mut self, entity: T) -> Result<u64, Error>;
 
+    /// Updates a record of a model with `#[version_column("...")]`.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing the update information (must implement Versioned and UpdateParams traits)
+    ///
+    /// # Returns
+    /// * `Result<(), UpdateError>` - On failure, returns `UpdateError::StaleObject` when no row matched
+    ///   the expected version, or the database error
+    fn update_versioned<T: Versioned + UpdateParams>(&mut self, entity: T) -> Result<(), UpdateError> {
+        let affected_rows = self.update(entity)?;
+        check_version::<T>(affected_rows > 0)
+    }
+
     /// Deletes records from the PostgreSQL database.
     ///
     /// # Arguments
@@ -132,6 +258,24 @@ pub trait CrudOps {
     /// * `Result<u64, Error>` - On success, returns the number of deleted records; on failure, returns Error
     fn delete<T: SqlCommand + SqlParams>(&mut self, entity: T) -> Result<u64, Error>;
 
+    /// Restores soft-deleted records by clearing their deletion marker.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Returns
+    /// * `Result<u64, Error>` - On success, returns the number of restored records; on failure, returns Error
+    fn restore<T: SoftDelete + SqlParams>(&mut self, entity: T) -> Result<u64, Error>;
+
+    /// Permanently deletes records of a soft-deleted table.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Returns
+    /// * `Result<u64, Error>` - On success, returns the number of deleted records; on failure, returns Error
+    fn force_delete<T: SoftDelete + SqlParams>(&mut self, entity: T) -> Result<u64, Error>;
+
     /// Retrieves a single record from the PostgreSQL database.
     ///
     /// # Arguments
@@ -181,4 +325,36 @@ pub trait CrudOps {
     where
         T: SqlQuery<T> + SqlParams,
         F: FnMut(&Row) -> Result<R, Error>;
+
+    /// Loads the records related to the given records with a single query and attaches them.
+    ///
+    /// # Arguments
+    /// * `records` - Records previously returned by `fetch_all` (must implement Relation<C>)
+    ///
+    /// # Returns
+    /// * `Result<(), Error>` - On success, the relation field of every record is filled; on failure, returns Error
+    fn preload<R, C>(&mut self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C>,
+        C: FromRow + Clone;
+
+    /// Retrieves the records of `R`'s table that match a typed WHERE expression.
+    ///
+    /// # Arguments
+    /// * `expr` - The condition, built from the column constants generated by `Columns`
+    ///
+    /// # Returns
+    /// * `Result<Vec<R>, WhereError>` - A vector of matching records or an error
+    fn fetch_all_where<R>(&mut self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow;
+
+    /// Retrieves all records returned by a composed query.
+    ///
+    /// # Arguments
+    /// * `fragment` - The query, built with `with_cte`, `union_all` or `in_subquery`
+    ///
+    /// # Returns
+    /// * `Result<Vec<R>, Error>` - On success, returns a vector of records; on failure, returns Error
+    fn fetch_all_fragment<R: FromRow>(&mut self, fragment: &Fragment) -> Result<Vec<R>, Error>;
 }
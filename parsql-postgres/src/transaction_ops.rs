@@ -1,5 +1,12 @@
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use crate::compose::Fragment;
+use crate::error::WhereError;
+use crate::expr::Expr;
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+};
 use postgres::{types::FromSql, Error, Row, Transaction};
+use std::collections::HashSet;
 
 /// CrudOps trait implementasyonu Transaction<'_> için.
 /// Bu sayede transaction içinde tüm CRUD işlemleri extension metotları olarak kullanılabilir.
@@ -19,6 +26,14 @@ impl<'a> CrudOps for Transaction<'a> {
         tx_delete(self, entity)
     }
 
+    fn restore<T: SoftDelete + SqlParams>(&mut self, entity: T) -> Result<u64, Error> {
+        tx_restore(self, entity)
+    }
+
+    fn force_delete<T: SoftDelete + SqlParams>(&mut self, entity: T) -> Result<u64, Error> {
+        tx_force_delete(self, entity)
+    }
+
     fn fetch<P, R>(&mut self, params: &P) -> Result<R, Error>
     where
         P: SqlQuery<R> + SqlParams,
@@ -42,12 +57,9 @@ impl<'a> CrudOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let row = self.query_one(&sql, &params)?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+        let row = observation.finish(self.query_one(&sql, &params), |_| 1)?;
         to_model(&row)
     }
 
@@ -58,15 +70,31 @@ impl<'a> CrudOps for Transaction<'a> {
     {
         let sql = T::query();
 
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
-        let rows = self.query(&sql, &params)?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+        let rows = observation.finish(self.query(&sql, &params), |rows| rows.len() as u64)?;
 
         rows.iter().map(to_model).collect()
     }
+
+    fn preload<R, C>(&mut self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C>,
+        C: FromRow + Clone,
+    {
+        tx_preload(self, records)
+    }
+
+    fn fetch_all_where<R>(&mut self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow,
+    {
+        tx_fetch_all_where(self, expr)
+    }
+
+    fn fetch_all_fragment<R: FromRow>(&mut self, fragment: &Fragment) -> Result<Vec<R>, Error> {
+        tx_fetch_all_fragment(self, fragment)
+    }
 }
 
 /// # begin
@@ -157,12 +185,9 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = tx.query_one(&sql, &params)?;
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+    let row = observation.finish(tx.query_one(&sql, &params), |_| 1)?;
     row.try_get::<_, P>(0)
 }
 
@@ -175,11 +200,11 @@ where
 /// - `entity`: Güncellenecek veri nesnesi (SqlQuery ve UpdateParams trait'lerini implement etmeli)
 ///
 /// ## Dönüş Değeri
-/// - `Result<(Transaction<'_>, u64), Error>`: Başarılı olursa, transaction ve etkilenen kayıt sayısını döner; hata durumunda Error döner
+/// - `Result<u64, Error>`: Başarılı olursa etkilenen kayıt sayısını döner; hata durumunda Error döner.
 ///
 /// ## Örnek Kullanım
 /// ```rust,no_run
-/// use postgres::{Client, NoTls, Error};
+/// use postgres::{Client, NoTls};
 /// use parsql::postgres::transactional::{begin, tx_update};
 ///
 /// #[derive(Updateable, UpdateParams)]
@@ -192,7 +217,7 @@ where
 ///     pub email: String,
 /// }
 ///
-/// fn main() -> Result<(), Error> {
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let mut client = Client::connect(
 ///         "host=localhost user=postgres dbname=test",
 ///         NoTls,
@@ -221,12 +246,9 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    tx.execute(&sql, &params)
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    observation.finish(tx.execute(&sql, &params), |rows| *rows)
 }
 
 /// # tx_delete
@@ -277,12 +299,51 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(tx.execute(&sql, &params), |rows| *rows)
+}
+
+/// # tx_restore
+///
+/// Transaction içinde soft-delete ile silinmiş kayıtları geri getirir.
+///
+/// ## Parametreler
+/// - `tx`: Transaction nesnesi
+/// - `entity`: Silme koşullarını içeren veri nesnesi (SoftDelete ve SqlParams trait'lerini uygulamalıdır)
+///
+/// ## Dönüş Değeri
+/// - `Result<u64, Error>`: Başarılı olursa, geri getirilen kayıt sayısını döner; hata durumunda Error döner
+pub fn tx_restore<'a, T>(tx: &mut Transaction<'a>, entity: T) -> Result<u64, Error>
+where
+    T: SoftDelete + SqlParams,
+{
+    let sql = T::restore_query();
+
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    observation.finish(tx.execute(&sql, &params), |rows| *rows)
+}
+
+/// # tx_force_delete
+///
+/// Transaction içinde soft-delete uygulanan bir tablodan kayıtları kalıcı olarak siler.
+///
+/// ## Parametreler
+/// - `tx`: Transaction nesnesi
+/// - `entity`: Silme koşullarını içeren veri nesnesi (SoftDelete ve SqlParams trait'lerini uygulamalıdır)
+///
+/// ## Dönüş Değeri
+/// - `Result<u64, Error>`: Başarılı olursa, silinen kayıt sayısını döner; hata durumunda Error döner
+pub fn tx_force_delete<'a, T>(tx: &mut Transaction<'a>, entity: T) -> Result<u64, Error>
+where
+    T: SoftDelete + SqlParams,
+{
+    let sql = T::force_delete_query();
 
     let params = entity.params();
-    tx.execute(&sql, &params)
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(tx.execute(&sql, &params), |rows| *rows)
 }
 
 /// # tx_fetch
@@ -340,12 +401,9 @@ where
 {
     let sql = P::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let row = tx.query_one(&sql, &query_params)?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let row = observation.finish(tx.query_one(&sql, &query_params), |_| 1)?;
     R::from_row(&row)
 }
 
@@ -406,12 +464,9 @@ where
 {
     let sql = P::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let rows = tx.query(&sql, &query_params)?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let rows = observation.finish(tx.query(&sql, &query_params), |rows| rows.len() as u64)?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -439,12 +494,9 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = tx.query_one(&sql, &params)?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let row = observation.finish(tx.query_one(&sql, &params), |_| 1)?;
     to_model(&row)
 }
 
@@ -470,12 +522,9 @@ where
 {
     let sql = T::query();
 
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let rows = tx.query(&sql, &params)?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(tx.query(&sql, &params), |rows| rows.len() as u64)?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -485,6 +534,90 @@ where
     Ok(results)
 }
 
+/// # tx_preload
+///
+/// Transaction içinde kayıtlara ait ilişkili kayıtları tek sorguda yükler ve yerleştirir.
+///
+/// ## Parametreler
+/// - `tx`: Transaction nesnesi
+/// - `records`: `fetch_all` ile alınmış kayıtlar (Relation<C> trait'ini implement etmeli)
+///
+/// ## Dönüş Değeri
+/// - `Result<(), Error>`: Başarılı olursa, her kaydın ilişki alanı doldurulur; hata durumunda Error döner
+pub fn tx_preload<'a, R, C>(tx: &mut Transaction<'a>, records: &mut [R]) -> Result<(), Error>
+where
+    R: Relation<C>,
+    C: FromRow + Clone,
+{
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let mut seen = HashSet::new();
+    let keys: Vec<R::Key> = records
+        .iter()
+        .filter_map(|record| record.relation_key())
+        .filter(|key| seen.insert(key.clone()))
+        .collect();
+
+    let sql = R::relation_query();
+
+    let observation = Observation::start::<C>(QueryKind::Select, &sql, &[&keys]);
+    let rows = observation.finish(tx.query(&sql, &[&keys]), |rows| rows.len() as u64)?;
+
+    let mut related = Vec::with_capacity(rows.len());
+    for row in rows {
+        related.push(C::from_row(&row)?);
+    }
+
+    R::stitch(records, related);
+    Ok(())
+}
+
+/// # tx_fetch_all_where
+///
+/// Transaction içinde tipli bir WHERE ifadesine uyan kayıtları getirir.
+///
+/// ## Parametreler
+/// - `tx`: Transaction nesnesi
+/// - `expr`: `Columns` derive makrosunun ürettiği sütun sabitleriyle kurulan koşul
+///
+/// ## Dönüş Değeri
+/// - `Result<Vec<R>, WhereError>`: Başarılı olursa, eşleşen kayıtları döner; hata durumunda WhereError döner
+///
+/// `#[tenant_scoped]` bir modelin koşulu `TenantContext::filter` ile kısıtlanmamışsa sorgu
+/// çalıştırılmaz ve `WhereError::MissingTenant` döner.
+pub fn tx_fetch_all_where<'a, R>(tx: &mut Transaction<'a>, expr: Expr) -> Result<Vec<R>, WhereError>
+where
+    R: Columns + FromRow,
+{
+    let (sql, params) = expr.select::<R>()?;
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(tx.query(&sql, &params), |rows| rows.len() as u64)?;
+    Ok(rows.iter().map(R::from_row).collect::<Result<_, _>>()?)
+}
+
+/// # tx_fetch_all_fragment
+///
+/// Transaction içinde birleştirilmiş bir sorgunun döndürdüğü tüm kayıtları getirir.
+///
+/// ## Parametreler
+/// - `tx`: Transaction nesnesi
+/// - `fragment`: `with_cte`, `union_all` veya `in_subquery` ile kurulan sorgu
+///
+/// ## Dönüş Değeri
+/// - `Result<Vec<R>, Error>`: Başarılı olursa, kayıtları döner; hata durumunda Error döner
+pub fn tx_fetch_all_fragment<'a, R: FromRow>(
+    tx: &mut Transaction<'a>,
+    fragment: &Fragment,
+) -> Result<Vec<R>, Error> {
+    let (sql, params) = fragment.build();
+
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(tx.query(&sql, &params), |rows| rows.len() as u64)?;
+    rows.iter().map(R::from_row).collect()
+}
+
 // Geriye dönük uyumluluk için eski tx_get fonksiyonunu koruyalım
 #[deprecated(
     since = "0.2.0",
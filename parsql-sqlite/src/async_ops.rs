@@ -0,0 +1,509 @@
+//! Async CRUD operations, available with the `async` feature.
+//!
+//! rusqlite blocks the calling thread, which stalls every other task of an async runtime.
+//! [`AsyncConnection`] moves a connection to a dedicated thread and sends it the operations
+//! through a channel; [`AsyncCrudOps`] offers the methods of `parsql-tokio-postgres`'s
+//! `CrudOps` on top of it, so code can switch between the two backends without changing its
+//! structure.
+//!
+//! The operations of one connection run one after another. An operation whose future is
+//! dropped still runs to completion on the connection thread.
+//!
+//! ```rust
+//! use parsql_sqlite::async_ops::{AsyncConnection, AsyncCrudOps};
+//! use parsql_sqlite::traits::*;
+//! use parsql_sqlite::*;
+//!
+//! #[derive(Insertable, SqlParams)]
+//! #[table("users")]
+//! struct InsertUser {
+//!     name: String,
+//! }
+//!
+//! #[derive(Queryable, FromRow, SqlParams)]
+//! #[table("users")]
+//! #[where_clause("id = $")]
+//! struct User {
+//!     id: i64,
+//!     name: String,
+//! }
+//!
+//! # fn main() -> Result<(), rusqlite::Error> {
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let conn = AsyncConnection::open_in_memory()?;
+//! conn.call(|conn| conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)", []))
+//!     .await?;
+//!
+//! let id: i64 = conn.insert(InsertUser { name: "Ada".into() }).await?;
+//! let user: User = conn.fetch(User { id, name: String::new() }).await?;
+//! assert_eq!(user.name, "Ada");
+//!
+//! // Transactions run as one call on the connection thread
+//! conn.call(|conn| {
+//!     let tx = conn.transaction()?;
+//!     tx.execute("UPDATE users SET name = 'Grace'", [])?;
+//!     tx.commit()
+//! })
+//! .await?;
+//! # Ok(())
+//! # })
+//! # }
+//! ```
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+use rusqlite::types::{FromSql, ToSqlOutput, Value};
+use rusqlite::{Connection, Error, Row, ToSql};
+use tokio::sync::oneshot;
+
+use crate::compose::Fragment;
+use crate::crud_ops;
+use crate::error::{check_version, UpdateError, WhereError};
+use crate::expr::Expr;
+use crate::traits::{
+    Columns, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+    Versioned,
+};
+
+type Job = Box<dyn FnOnce(&mut Connection) + Send>;
+
+/// A connection running on its own thread.
+///
+/// Clones share the connection; its thread stops when the last clone is dropped.
+#[derive(Clone)]
+pub struct AsyncConnection {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl AsyncConnection {
+    /// Moves `conn` to a new thread.
+    ///
+    /// # Panics
+    /// Panics if the thread cannot be spawned, like `std::thread::spawn`.
+    pub fn new(conn: Connection) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("parsql-sqlite".into())
+            .spawn(move || {
+                let mut conn = conn;
+                for job in queue {
+                    job(&mut conn);
+                }
+            })
+            .expect("failed to spawn the connection thread");
+        AsyncConnection { jobs }
+    }
+
+    /// Opens the database file on a new thread.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::new(Connection::open(path)?))
+    }
+
+    /// Opens an in-memory database on a new thread.
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Ok(Self::new(Connection::open_in_memory()?))
+    }
+
+    /// Runs `f` on the connection thread and returns its result.
+    ///
+    /// Use it for transactions and anything else not covered by [`AsyncCrudOps`]. A panic in
+    /// `f` is resumed in the caller; the connection stays usable.
+    pub async fn call<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Connection) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel::<Result<T, Box<dyn Any + Send>>>();
+        let job: Job = Box::new(move |conn| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(conn)));
+            // The caller may have stopped waiting
+            let _ = sender.send(result);
+        });
+        self.jobs
+            .send(job)
+            .expect("the connection thread runs while a handle exists");
+        match receiver
+            .await
+            .expect("the connection thread answers every job")
+        {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+/// Async CRUD operations with the method names of `parsql-tokio-postgres`'s `CrudOps`.
+///
+/// Arguments are moved to the connection thread, so they must be `Send + 'static`.
+#[async_trait::async_trait]
+pub trait AsyncCrudOps {
+    /// Inserts a new record, returning the value of its `RETURNING` column.
+    async fn insert<T, P>(&self, entity: T) -> Result<P, Error>
+    where
+        T: SqlCommand + SqlParams + Send + 'static,
+        P: FromSql + Send + Sync + 'static;
+
+    /// Updates records, returning the number of updated records.
+    async fn update<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SqlCommand + UpdateParams + Send + 'static;
+
+    /// Updates a record of a model with `#[version_column("...")]`, failing with
+    /// `UpdateError::StaleObject` when no row matched the expected version.
+    async fn update_versioned<T>(&self, entity: T) -> Result<(), UpdateError>
+    where
+        T: Versioned + UpdateParams + Send + 'static,
+    {
+        let affected_rows = self.update(entity).await?;
+        check_version::<T>(affected_rows > 0)
+    }
+
+    /// Deletes records, returning the number of deleted records.
+    async fn delete<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SqlCommand + SqlParams + Send + 'static;
+
+    /// Restores soft-deleted records, returning the number of restored records.
+    async fn restore<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SoftDelete + SqlParams + Send + 'static;
+
+    /// Permanently deletes records of a soft-deleted table.
+    async fn force_delete<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SoftDelete + SqlParams + Send + 'static;
+
+    /// Retrieves a single record.
+    async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Send + 'static,
+        R: FromRow + Send + 'static;
+
+    /// Retrieves multiple records.
+    async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Send + 'static,
+        R: FromRow + Send + 'static;
+
+    /// Retrieves a single record with a custom conversion function.
+    async fn select<T, F, R>(&self, entity: T, to_model: F) -> Result<R, Error>
+    where
+        T: SqlQuery<T> + SqlParams + Send + 'static,
+        F: Fn(&Row) -> Result<R, Error> + Send + 'static,
+        R: Send + 'static;
+
+    /// Retrieves multiple records with a custom conversion function.
+    async fn select_all<T, F, R>(&self, entity: T, to_model: F) -> Result<Vec<R>, Error>
+    where
+        T: SqlQuery<T> + SqlParams + Send + 'static,
+        F: Fn(&Row) -> Result<R, Error> + Send + 'static,
+        R: Send + 'static;
+
+    /// Loads the records related to the given records with a single query and attaches them.
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C> + Send,
+        R::Key: Send + 'static,
+        C: FromRow + Clone + Send + 'static;
+
+    /// Retrieves the records of `R`'s table that match a typed WHERE expression.
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow + Send + 'static;
+
+    /// Retrieves all records returned by a composed query.
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, Error>
+    where
+        R: FromRow + Send + 'static;
+}
+
+#[async_trait::async_trait]
+impl AsyncCrudOps for AsyncConnection {
+    async fn insert<T, P>(&self, entity: T) -> Result<P, Error>
+    where
+        T: SqlCommand + SqlParams + Send + 'static,
+        P: FromSql + Send + Sync + 'static,
+    {
+        self.call(move |conn| crud_ops::insert(conn, entity)).await
+    }
+
+    async fn update<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SqlCommand + UpdateParams + Send + 'static,
+    {
+        self.call(move |conn| crud_ops::update(conn, entity)).await
+    }
+
+    async fn delete<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SqlCommand + SqlParams + Send + 'static,
+    {
+        self.call(move |conn| crud_ops::delete(conn, entity)).await
+    }
+
+    async fn restore<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SoftDelete + SqlParams + Send + 'static,
+    {
+        self.call(move |conn| crud_ops::restore(conn, entity)).await
+    }
+
+    async fn force_delete<T>(&self, entity: T) -> Result<usize, Error>
+    where
+        T: SoftDelete + SqlParams + Send + 'static,
+    {
+        self.call(move |conn| crud_ops::force_delete(conn, entity))
+            .await
+    }
+
+    async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Send + 'static,
+        R: FromRow + Send + 'static,
+    {
+        self.call(move |conn| crud_ops::fetch(conn, &params)).await
+    }
+
+    async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Send + 'static,
+        R: FromRow + Send + 'static,
+    {
+        self.call(move |conn| crud_ops::fetch_all(conn, &params))
+            .await
+    }
+
+    async fn select<T, F, R>(&self, entity: T, to_model: F) -> Result<R, Error>
+    where
+        T: SqlQuery<T> + SqlParams + Send + 'static,
+        F: Fn(&Row) -> Result<R, Error> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.call(move |conn| crud_ops::select(conn, &entity, to_model))
+            .await
+    }
+
+    async fn select_all<T, F, R>(&self, entity: T, to_model: F) -> Result<Vec<R>, Error>
+    where
+        T: SqlQuery<T> + SqlParams + Send + 'static,
+        F: Fn(&Row) -> Result<R, Error> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.call(move |conn| crud_ops::select_all(conn, &entity, to_model))
+            .await
+    }
+
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C> + Send,
+        R::Key: Send + 'static,
+        C: FromRow + Clone + Send + 'static,
+    {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        // The records stay here; only their keys travel to the connection thread
+        let keys = crud_ops::relation_keys(records);
+        let related = self
+            .call(move |conn| crud_ops::load_related::<R, C>(conn, &keys))
+            .await?;
+        R::stitch(records, related);
+        Ok(())
+    }
+
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow + Send + 'static,
+    {
+        self.call(move |conn| crud_ops::fetch_all_where(conn, expr)).await
+    }
+
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, Error>
+    where
+        R: FromRow + Send + 'static,
+    {
+        let (sql, values) = {
+            let (sql, params) = fragment.build();
+            (sql, owned_values(&params)?)
+        };
+        self.call(move |conn| crud_ops::fetch_all_fragment(conn, &owned_fragment(&sql, &values)))
+            .await
+    }
+}
+
+/// Copies borrowed parameters, which cannot be sent to the connection thread.
+fn owned_values(params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Value>, Error> {
+    params
+        .iter()
+        .map(|param| match param.to_sql()? {
+            ToSqlOutput::Borrowed(value) => Ok(value.into()),
+            ToSqlOutput::Owned(value) => Ok(value),
+            _ => Err(Error::ToSqlConversionFailure(
+                "unsupported parameter type for an async query".into(),
+            )),
+        })
+        .collect()
+}
+
+/// Rebuilds a fragment over the copied parameters on the connection thread.
+fn owned_fragment<'a>(sql: &str, values: &'a [Value]) -> Fragment<'a> {
+    Fragment::new(
+        sql,
+        values
+            .iter()
+            .map(|value| value as &(dyn ToSql + Sync))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams)]
+    #[table("users")]
+    struct InsertUser {
+        name: String,
+    }
+
+    #[derive(Queryable, FromRow, SqlParams, Columns, Debug)]
+    #[table("users")]
+    #[where_clause("id = $")]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("users")]
+    #[update("name")]
+    #[where_clause("id = $")]
+    struct RenameUser {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("users")]
+    #[where_clause("id = $")]
+    struct DeleteUser {
+        id: i64,
+    }
+
+    async fn connection() -> AsyncConnection {
+        let conn = AsyncConnection::open_in_memory().unwrap();
+        conn.call(|conn| {
+            conn.execute_batch("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+        })
+        .await
+        .unwrap();
+        conn
+    }
+
+    async fn insert_user(conn: &AsyncConnection, name: &str) -> i64 {
+        conn.insert(InsertUser { name: name.into() }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_crud_round_trip() {
+        let conn = connection().await;
+        let id = insert_user(&conn, "Ada").await;
+
+        let renamed = conn
+            .update(RenameUser {
+                id,
+                name: "Grace".into(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(renamed, 1);
+
+        let user: User = conn
+            .fetch(User {
+                id,
+                name: String::new(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(user.name, "Grace");
+
+        assert_eq!(conn.delete(DeleteUser { id }).await.unwrap(), 1);
+        let missing: Result<User, Error> = conn
+            .fetch(User {
+                id,
+                name: String::new(),
+            })
+            .await;
+        assert!(matches!(missing, Err(Error::QueryReturnedNoRows)));
+    }
+
+    #[tokio::test]
+    async fn test_panic_is_resumed_and_connection_stays_usable() {
+        let conn = connection().await;
+        let caller = conn.clone();
+        let result =
+            tokio::spawn(async move { caller.call(|_| -> () { panic!("job failed") }).await })
+                .await;
+        assert!(result.unwrap_err().is_panic());
+
+        let id = insert_user(&conn, "Ada").await;
+        assert_eq!(id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_call_still_runs() {
+        let conn = connection().await;
+        let slow = conn.call(|conn| {
+            std::thread::sleep(Duration::from_millis(50));
+            conn.execute("INSERT INTO users (name) VALUES ('Ada')", [])
+        });
+        // Polling once queues the job, then the future is dropped
+        assert!(tokio::time::timeout(Duration::ZERO, slow).await.is_err());
+
+        let count: i64 = conn
+            .call(|conn| conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0)))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_clones_share_the_connection() {
+        let conn = connection().await;
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let conn = conn.clone();
+                tokio::spawn(async move { insert_user(&conn, &format!("user-{i}")).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let users: Vec<User> = conn.fetch_all_where(users::id.gt(0)).await.unwrap();
+        assert_eq!(users.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_borrowed_params_are_copied_to_the_connection_thread() {
+        let conn = connection().await;
+        insert_user(&conn, "Ada").await;
+        insert_user(&conn, "Grace").await;
+
+        let users: Vec<User> = conn.fetch_all_where(users::name.eq("Grace")).await.unwrap();
+        assert_eq!(users.iter().map(|user| user.id).collect::<Vec<_>>(), vec![2]);
+
+        let name = String::from("Ada");
+        let fragment = Fragment::new("SELECT id, name FROM users WHERE name = ?", vec![&name]);
+        let users: Vec<User> = conn.fetch_all_fragment(&fragment).await.unwrap();
+        assert_eq!(users.iter().map(|user| user.id).collect::<Vec<_>>(), vec![1]);
+    }
+}
@@ -0,0 +1,229 @@
+//! Hot backups of a running SQLite database.
+//!
+//! [`backup_to`] copies the database page by page with SQLite's online backup API, pausing
+//! between steps so the application keeps reading and writing while it runs. A write made
+//! through another connection restarts the copy, so it always ends consistent.
+//! [`vacuum_into`] writes a compacted snapshot in a single statement instead, and
+//! [`restore_from`] replaces the contents of a database with a backup file.
+//!
+//! With a [`SqlitePool`], back up through `pool.reader()` and restore through
+//! `&mut pool.writer()`.
+//!
+//! ```rust,no_run
+//! use parsql_sqlite::backup::{self, BackupOptions};
+//! use parsql_sqlite::Connection;
+//!
+//! # fn main() -> Result<(), rusqlite::Error> {
+//! let conn = Connection::open("app.db")?;
+//!
+//! backup::backup_to(&conn, "app-backup.db", &BackupOptions::new(), |progress| {
+//!     let done = progress.pagecount - progress.remaining;
+//!     println!("{}/{} pages", done, progress.pagecount);
+//! })?;
+//!
+//! backup::vacuum_into(&conn, "app-snapshot.db")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SqlitePool`]: crate::pool::SqlitePool
+
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, Error, OpenFlags};
+
+pub use rusqlite::backup::Progress;
+
+/// How a backup or restore steps through the database.
+#[derive(Debug, Clone)]
+pub struct BackupOptions {
+    pages_per_step: i32,
+    pause: Duration,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        BackupOptions {
+            pages_per_step: 100,
+            pause: Duration::from_millis(10),
+        }
+    }
+}
+
+impl BackupOptions {
+    /// 100 pages per step with a 10ms pause between steps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pages copied per step; `-1` copies the whole database in one step.
+    pub fn pages_per_step(mut self, pages: i32) -> Self {
+        self.pages_per_step = pages;
+        self
+    }
+
+    /// Time given to other connections between steps, and before retrying a step that found
+    /// the database locked.
+    pub fn pause(mut self, pause: Duration) -> Self {
+        self.pause = pause;
+        self
+    }
+}
+
+/// Copies the database of `conn` to the file at `path`, replacing its contents.
+///
+/// `progress` is called after every step.
+pub fn backup_to<P, F>(
+    conn: &Connection,
+    path: P,
+    options: &BackupOptions,
+    progress: F,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    F: FnMut(Progress),
+{
+    let mut destination = Connection::open(path)?;
+    let backup = Backup::new(conn, &mut destination)?;
+    run(&backup, options, progress)
+}
+
+/// Replaces the database of `conn` with the backup file at `path`.
+///
+/// `progress` is called after every step.
+pub fn restore_from<P, F>(
+    conn: &mut Connection,
+    path: P,
+    options: &BackupOptions,
+    progress: F,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    F: FnMut(Progress),
+{
+    let source = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    let backup = Backup::new(&source, conn)?;
+    run(&backup, options, progress)
+}
+
+/// Writes a vacuumed copy of the database of `conn` to a new file at `path`.
+///
+/// The copy is smaller than a page-by-page backup but is made in one statement, which holds a
+/// read transaction for its whole duration. Fails if a non-empty file exists at `path`.
+pub fn vacuum_into<P: AsRef<Path>>(conn: &Connection, path: P) -> Result<(), Error> {
+    let path = path.as_ref().to_string_lossy().into_owned();
+    conn.execute("VACUUM INTO ?1", [path])?;
+    Ok(())
+}
+
+fn run<F>(backup: &Backup<'_, '_>, options: &BackupOptions, mut progress: F) -> Result<(), Error>
+where
+    F: FnMut(Progress),
+{
+    loop {
+        let step = backup.step(options.pages_per_step)?;
+        progress(backup.progress());
+        match step {
+            StepResult::Done => return Ok(()),
+            // More pages, or a writer holds the lock: give the other connections a turn
+            _ => thread::sleep(options.pause),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A database file that is removed with its journal files when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let file = format!("parsql-backup-{}-{}.db", name, nanoid::nanoid!());
+            TempFile(std::env::temp_dir().join(file))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            for suffix in ["", "-journal", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", self.0.display(), suffix));
+            }
+        }
+    }
+
+    fn options() -> BackupOptions {
+        BackupOptions::new().pages_per_step(4).pause(Duration::ZERO)
+    }
+
+    fn source(file: &TempFile) -> Connection {
+        let conn = Connection::open(&file.0).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, body BLOB);
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100)
+             INSERT INTO notes SELECT i, randomblob(1000) FROM n;",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_backup_and_restore_round_trip() {
+        let (database, copy) = (TempFile::new("source"), TempFile::new("copy"));
+        let mut conn = source(&database);
+
+        let mut steps = Vec::new();
+        backup_to(&conn, &copy.0, &options(), |progress| steps.push(progress)).unwrap();
+        assert!(steps.len() > 1, "copied in {} steps", steps.len());
+        assert_eq!(steps.last().map(|progress| progress.remaining), Some(0));
+        assert_eq!(count(&Connection::open(&copy.0).unwrap()), 100);
+
+        conn.execute_batch("DELETE FROM notes WHERE id > 10; CREATE TABLE extra (id INTEGER);")
+            .unwrap();
+        restore_from(&mut conn, &copy.0, &options(), |_| {}).unwrap();
+
+        assert_eq!(count(&conn), 100);
+        let extra: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = 'extra'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(extra, 0);
+    }
+
+    #[test]
+    fn test_restore_from_a_missing_file_fails() {
+        let (database, missing) = (TempFile::new("source"), TempFile::new("missing"));
+        let mut conn = source(&database);
+
+        assert!(restore_from(&mut conn, &missing.0, &options(), |_| {}).is_err());
+        assert_eq!(count(&conn), 100);
+    }
+
+    #[test]
+    fn test_vacuum_into_keeps_an_existing_target() {
+        let (database, snapshot) = (TempFile::new("source"), TempFile::new("snapshot"));
+        let conn = source(&database);
+
+        vacuum_into(&conn, &snapshot.0).unwrap();
+        assert_eq!(count(&Connection::open(&snapshot.0).unwrap()), 100);
+
+        conn.execute("DELETE FROM notes", []).unwrap();
+        assert!(vacuum_into(&conn, &snapshot.0).is_err());
+        assert_eq!(count(&Connection::open(&snapshot.0).unwrap()), 100);
+    }
+}
@@ -0,0 +1,133 @@
+//! Query composition.
+//!
+//! A [`Fragment`] is a piece of SQL together with the parameters bound to its placeholders.
+//! Placeholders are kept as references into the parameter list, so fragments can be
+//! combined freely and are renumbered when the final SQL is rendered.
+//!
+//! Any `Queryable` struct can be turned into a fragment with `SqlQuery::fragment`, and
+//! reused as a CTE, a subquery or a member of a `UNION ALL`:
+//!
+//! ```rust
+//! # use parsql_sqlite::compose::Fragment;
+//! let state = 1;
+//! let min_score = 10;
+//! let active = Fragment::new("SELECT id, name, score FROM users WHERE state = $1", vec![&state]);
+//! let query = Fragment::new("SELECT id, name FROM active WHERE score > $1", vec![&min_score])
+//!     .with_cte("active", active);
+//!
+//! let (sql, params) = query.build();
+//! assert_eq!(
+//!     sql,
+//!     "WITH active AS (SELECT id, name, score FROM users WHERE state = ?1) SELECT id, name FROM active WHERE score > ?2"
+//! );
+//! assert_eq!(params.len(), 2);
+//! ```
+
+use crate::expr::Sqlite;
+
+/// A SQL fragment with its bound parameters.
+pub type Fragment<'a> = parsql_core::compose::Fragment<'a, Sqlite>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::types::{ToSqlOutput, Value, ValueRef};
+    use rusqlite::{params_from_iter, Connection, ToSql};
+
+    fn values(params: &[&(dyn ToSql + Sync)]) -> Vec<i64> {
+        params
+            .iter()
+            .map(|param| match param.to_sql().unwrap() {
+                ToSqlOutput::Owned(Value::Integer(value))
+                | ToSqlOutput::Borrowed(ValueRef::Integer(value)) => value,
+                other => panic!("unexpected parameter {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_question_mark_placeholders_are_renumbered() {
+        let (state, min_id, max_id, level) = (1, 10, 20, 3);
+        let users = Fragment::new(
+            "SELECT id FROM users WHERE state = ? AND id > ?2 AND id < ?",
+            vec![&state, &min_id, &max_id],
+        );
+        let query = Fragment::new(
+            "SELECT id FROM admins WHERE level = $1 AND id IN (",
+            vec![&level],
+        )
+        .append(users)
+        .push(")");
+
+        let (sql, params) = query.build();
+        assert_eq!(
+            sql,
+            "SELECT id FROM admins WHERE level = ?1 AND id IN \
+             (SELECT id FROM users WHERE state = ?2 AND id > ?3 AND id < ?4)"
+        );
+        assert_eq!(values(&params), vec![3, 1, 10, 20]);
+    }
+
+    #[test]
+    fn test_placeholders_in_string_literals_are_kept() {
+        let id = 1;
+        let (sql, params) = Fragment::new("SELECT '?' FROM users WHERE id = ?", vec![&id]).build();
+        assert_eq!(sql, "SELECT '?' FROM users WHERE id = ?1");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn test_nested_ctes_are_declared_first() {
+        let (state, min_id, max_id) = (1, 10, 20);
+        let active = Fragment::new("SELECT id FROM users WHERE state = ?", vec![&state]);
+        let recent = Fragment::new("SELECT id FROM active WHERE id > ?", vec![&min_id])
+            .with_cte("active", active);
+        let query = Fragment::new("SELECT id FROM recent WHERE id < ?", vec![&max_id])
+            .with_cte("recent", recent);
+
+        let (sql, params) = query.build();
+        assert_eq!(
+            sql,
+            "WITH active AS (SELECT id FROM users WHERE state = ?1), \
+             recent AS (SELECT id FROM active WHERE id > ?2) \
+             SELECT id FROM recent WHERE id < ?3"
+        );
+        assert_eq!(values(&params), vec![1, 10, 20]);
+    }
+
+    #[test]
+    fn test_union_all_keeps_member_clauses_local() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, state INTEGER);
+             INSERT INTO users VALUES (1, 1), (2, 1), (3, 2);",
+        )
+        .unwrap();
+
+        let (active, inactive) = (1, 2);
+        let query = Fragment::new(
+            "SELECT id FROM users WHERE state = ? ORDER BY id DESC LIMIT 1",
+            vec![&active],
+        )
+        .union_all(Fragment::new(
+            "SELECT id FROM users WHERE state = ?",
+            vec![&inactive],
+        ));
+
+        let (sql, params) = query.build();
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let ids: Vec<i64> = stmt
+            .query_map(params_from_iter(params), |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(ids, vec![2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Placeholder ?2 has no parameter")]
+    fn test_missing_parameter_panics() {
+        let id = 1;
+        Fragment::new("SELECT id FROM users WHERE id = ? OR id = ?", vec![&id]);
+    }
+}
@@ -0,0 +1,318 @@
+//! Opening connections with user-defined functions and collations.
+//!
+//! SQLite functions and collations are registered per connection, so a query that calls
+//! `slugify(...)` or sorts with `COLLATE turkish_ci` fails on every connection that was opened
+//! without them. [`ConnectionBuilder`] collects the registrations once and applies them to
+//! every connection it opens, including the connections of a [`SqlitePool`] created with
+//! [`PoolOptions::connection`], so the same `where_clause` and `order_by` strings work
+//! everywhere.
+//!
+//! ```rust
+//! use parsql_sqlite::connection::{ConnectionBuilder, FunctionFlags};
+//! use parsql_sqlite::traits::*;
+//! use parsql_sqlite::*;
+//!
+//! #[derive(Queryable, FromRow, SqlParams)]
+//! #[table("cities")]
+//! #[where_clause("slugify(name) = $")]
+//! #[order_by("name COLLATE turkish_ci")]
+//! struct CityBySlug {
+//!     name: String,
+//! }
+//!
+//! fn turkish_lowercase(s: &str) -> String {
+//!     s.chars()
+//!         .map(|c| match c {
+//!             'I' => 'ı',
+//!             'İ' => 'i',
+//!             c => c.to_lowercase().next().unwrap_or(c),
+//!         })
+//!         .collect()
+//! }
+//!
+//! # fn main() -> Result<(), rusqlite::Error> {
+//! let builder = ConnectionBuilder::new()
+//!     .collation("turkish_ci", |a, b| turkish_lowercase(a).cmp(&turkish_lowercase(b)))
+//!     .scalar(
+//!         "slugify",
+//!         1,
+//!         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+//!         |ctx| {
+//!             let text: String = ctx.get(0)?;
+//!             Ok(turkish_lowercase(&text).replace(' ', "-"))
+//!         },
+//!     );
+//!
+//! let conn = builder.open_in_memory()?;
+//! conn.execute_batch("CREATE TABLE cities (name TEXT); INSERT INTO cities VALUES ('İzmir');")?;
+//! let city: CityBySlug = conn.fetch(&CityBySlug { name: "izmir".into() })?;
+//! assert_eq!(city.name, "İzmir");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SqlitePool`]: crate::pool::SqlitePool
+//! [`PoolOptions::connection`]: crate::pool::PoolOptions::connection
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use rusqlite::functions::SqlFnOutput;
+use rusqlite::{Connection, Error};
+
+pub use rusqlite::functions::{Aggregate, Context, FunctionFlags};
+
+type Registration = Arc<dyn Fn(&Connection) -> Result<(), Error> + Send + Sync>;
+
+/// Opens connections and registers functions and collations on each of them.
+///
+/// Registrations are applied in the order they were added.
+#[derive(Clone, Default)]
+pub struct ConnectionBuilder {
+    registrations: Vec<Registration>,
+}
+
+impl fmt::Debug for ConnectionBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionBuilder")
+            .field("registrations", &self.registrations.len())
+            .finish()
+    }
+}
+
+impl ConnectionBuilder {
+    /// Creates a builder without registrations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a scalar function taking `n_arg` arguments, or any number for `-1`.
+    ///
+    /// Pass `SQLITE_DETERMINISTIC` in `flags` for functions whose result only depends on their
+    /// arguments, so SQLite can use them in indexes and factor them out of loops.
+    pub fn scalar<F, T>(self, name: &str, n_arg: i32, flags: FunctionFlags, function: F) -> Self
+    where
+        F: Fn(&Context<'_>) -> Result<T, Error> + Send + Sync + 'static,
+        T: SqlFnOutput,
+    {
+        let name = name.to_string();
+        let function = Arc::new(function);
+        self.register(move |conn| {
+            let function = Arc::clone(&function);
+            conn.create_scalar_function(name.as_str(), n_arg, flags, move |ctx| function(ctx))
+        })
+    }
+
+    /// Registers an aggregate function; every connection gets its own clone of `aggregate`.
+    pub fn aggregate<A, D, T>(
+        self,
+        name: &str,
+        n_arg: i32,
+        flags: FunctionFlags,
+        aggregate: D,
+    ) -> Self
+    where
+        A: std::panic::RefUnwindSafe + std::panic::UnwindSafe,
+        D: Aggregate<A, T> + Clone + Send + Sync + 'static,
+        T: SqlFnOutput,
+    {
+        let name = name.to_string();
+        self.register(move |conn| {
+            conn.create_aggregate_function(name.as_str(), n_arg, flags, aggregate.clone())
+        })
+    }
+
+    /// Registers a collation, used with `COLLATE name` in queries and column definitions.
+    pub fn collation<C>(self, name: &str, compare: C) -> Self
+    where
+        C: Fn(&str, &str) -> Ordering + Send + Sync + 'static,
+    {
+        let name = name.to_string();
+        let compare = Arc::new(compare);
+        self.register(move |conn| {
+            let compare = Arc::clone(&compare);
+            conn.create_collation(name.as_str(), move |a, b| compare(a, b))
+        })
+    }
+
+    /// Runs `setup` on every opened connection, e.g. to set pragmas or attach databases.
+    pub fn on_open<F>(self, setup: F) -> Self
+    where
+        F: Fn(&Connection) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.register(setup)
+    }
+
+    /// Opens the database file, creating it if needed.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<Connection, Error> {
+        let conn = Connection::open(path)?;
+        self.apply(&conn)?;
+        Ok(conn)
+    }
+
+    /// Opens an in-memory database.
+    pub fn open_in_memory(&self) -> Result<Connection, Error> {
+        let conn = Connection::open_in_memory()?;
+        self.apply(&conn)?;
+        Ok(conn)
+    }
+
+    /// Applies the registrations to a connection opened elsewhere.
+    pub fn apply(&self, conn: &Connection) -> Result<(), Error> {
+        self.registrations
+            .iter()
+            .try_for_each(|registration| registration(conn))
+    }
+
+    fn register<F>(mut self, registration: F) -> Self
+    where
+        F: Fn(&Connection) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.registrations.push(Arc::new(registration));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::pool::{PoolOptions, SqlitePool};
+
+    /// Concatenates its text arguments with `|`.
+    #[derive(Clone)]
+    struct Join;
+
+    impl Aggregate<Vec<String>, String> for Join {
+        fn init(&self, _: &mut Context<'_>) -> Result<Vec<String>, Error> {
+            Ok(Vec::new())
+        }
+
+        fn step(&self, ctx: &mut Context<'_>, parts: &mut Vec<String>) -> Result<(), Error> {
+            parts.push(ctx.get(0)?);
+            Ok(())
+        }
+
+        fn finalize(
+            &self,
+            _: &mut Context<'_>,
+            parts: Option<Vec<String>>,
+        ) -> Result<String, Error> {
+            Ok(parts.unwrap_or_default().join("|"))
+        }
+    }
+
+    fn builder() -> ConnectionBuilder {
+        ConnectionBuilder::new()
+            .scalar(
+                "double",
+                1,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                |ctx| Ok(ctx.get::<i64>(0)? * 2),
+            )
+            .aggregate("joined", 1, FunctionFlags::SQLITE_UTF8, Join)
+            .collation("by_length", |a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    }
+
+    fn names(conn: &Connection, sql: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(sql).unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_functions_and_collations_are_registered() {
+        let conn = builder().open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE words (n INTEGER, word TEXT);
+             CREATE INDEX words_double ON words (double(n));
+             INSERT INTO words VALUES (1, 'ccc'), (2, 'a'), (3, 'bb');",
+        )
+        .unwrap();
+
+        let doubled: i64 = conn
+            .query_row("SELECT n FROM words WHERE double(n) = 4", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(doubled, 2);
+
+        let joined: String = conn
+            .query_row(
+                "SELECT joined(word) FROM (SELECT word FROM words ORDER BY n)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(joined, "ccc|a|bb");
+
+        let sorted = names(&conn, "SELECT word FROM words ORDER BY word COLLATE by_length");
+        assert_eq!(sorted, vec!["a", "bb", "ccc"]);
+    }
+
+    #[test]
+    fn test_unregistered_connections_reject_the_queries() {
+        let conn = ConnectionBuilder::new().open_in_memory().unwrap();
+        assert!(conn.prepare("SELECT double(1)").is_err());
+        assert!(conn.prepare("SELECT 'a' < 'b' COLLATE by_length").is_err());
+
+        // Registrations can be applied to a connection opened elsewhere
+        builder().apply(&conn).unwrap();
+        let doubled: i64 = conn.query_row("SELECT double(21)", [], |row| row.get(0)).unwrap();
+        assert_eq!(doubled, 42);
+    }
+
+    #[test]
+    fn test_setup_runs_in_order_and_stops_at_the_first_error() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let record = |step: &'static str| {
+            let calls = Arc::clone(&calls);
+            move |_: &Connection| {
+                calls.lock().unwrap().push(step);
+                Ok(())
+            }
+        };
+
+        let builder = ConnectionBuilder::new()
+            .on_open(record("first"))
+            .on_open(|conn| conn.execute_batch("PRAGMA no_such_pragma = (;"))
+            .on_open(record("last"));
+        assert!(builder.open_in_memory().is_err());
+        assert_eq!(*calls.lock().unwrap(), vec!["first"]);
+    }
+
+    #[test]
+    fn test_pool_connections_get_the_registrations() {
+        let path = std::env::temp_dir().join(format!("parsql-connection-{}.db", nanoid::nanoid!()));
+        let opened = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&opened);
+        let builder = builder().on_open(move |_| {
+            *counter.lock().unwrap() += 1;
+            Ok(())
+        });
+
+        let options = PoolOptions::new().readers(2).connection(builder);
+        let pool = SqlitePool::open_with(&path, options).unwrap();
+        // The writer and both readers
+        assert_eq!(*opened.lock().unwrap(), 3);
+
+        let doubled: i64 = pool
+            .writer()
+            .query_row("SELECT double(2)", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(doubled, 4);
+        for _ in 0..2 {
+            let sql = "SELECT 'bb' UNION SELECT 'a' ORDER BY 1 COLLATE by_length";
+            let sorted = names(&pool.reader(), sql);
+            assert_eq!(sorted, vec!["a", "bb"]);
+        }
+
+        drop(pool);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
@@ -1,6 +1,14 @@
 use rusqlite::{types::FromSql, Error, Row, ToSql};
+use std::collections::HashSet;
 
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use crate::compose::Fragment;
+use crate::error::{check_version, UpdateError, WhereError};
+use crate::expr::Expr;
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+    Versioned,
+};
 
 // CrudOps trait implementasyonu rusqlite::Connection için
 impl CrudOps for rusqlite::Connection {
@@ -19,6 +27,14 @@ impl CrudOps for rusqlite::Connection {
         delete(self, entity)
     }
 
+    fn restore<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error> {
+        restore(self, entity)
+    }
+
+    fn force_delete<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error> {
+        force_delete(self, entity)
+    }
+
     fn fetch<P, R>(&self, params: &P) -> Result<R, Error>
     where
         P: SqlQuery<R> + SqlParams,
@@ -40,13 +56,11 @@ impl CrudOps for rusqlite::Connection {
         F: Fn(&Row) -> Result<R, Error>,
     {
         let sql = T::query();
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-SQLITE] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
         let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
-        self.query_row(&sql, param_refs.as_slice(), to_model)
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &param_refs);
+        let result = self.query_row(&sql, param_refs.as_slice(), to_model);
+        observation.finish(result, |_| 1)
     }
 
     fn select_all<T: SqlQuery<T> + SqlParams, F, R>(
@@ -58,21 +72,34 @@ impl CrudOps for rusqlite::Connection {
         F: Fn(&Row) -> Result<R, Error>,
     {
         let sql = T::query();
-        if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-            println!("[PARSQL-SQLITE] Execute SQL: {}", sql);
-        }
-
         let params = entity.params();
         let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
-        let mut stmt = self.prepare(&sql)?;
-        let rows = stmt.query_map(param_refs.as_slice(), to_model)?;
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &param_refs);
+        let result = (|| {
+            let mut stmt = self.prepare(&sql)?;
+            let rows = stmt.query_map(param_refs.as_slice(), to_model)?;
+            rows.collect::<Result<Vec<R>, Error>>()
+        })();
+        observation.finish(result, Vec::len)
+    }
+
+    fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C>,
+        C: FromRow + Clone,
+    {
+        preload(self, records)
+    }
 
-        let mut results = Vec::new();
-        for row in rows {
-            results.push(row?);
-        }
+    fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow,
+    {
+        fetch_all_where(self, expr)
+    }
 
-        Ok(results)
+    fn fetch_all_fragment<R: FromRow>(&self, fragment: &Fragment) -> Result<Vec<R>, Error> {
+        fetch_all_fragment(self, fragment)
     }
 }
 
@@ -145,15 +172,12 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql + Send + Sync>(
     entity: T,
 ) -> Result<P, rusqlite::Error> {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-SQLITE] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
     let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &param_refs);
 
     // Check if the SQL contains RETURNING clause
-    if sql.to_uppercase().contains("RETURNING") {
+    let result = if sql.to_uppercase().contains("RETURNING") {
         // Use query_row for RETURNING statements
         conn.query_row(&sql, param_refs.as_slice(), |row| {
             P::column_result(row.get_ref(0)?).map_err(|e| {
@@ -166,18 +190,19 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql + Send + Sync>(
         })
     } else {
         // Use execute for regular INSERT statements
-        conn.execute(&sql, param_refs.as_slice())?;
-
-        // Get the last inserted ID and use FromSql to convert it
-        let last_id = conn.last_insert_rowid();
-        P::column_result(rusqlite::types::ValueRef::Integer(last_id)).map_err(|e| {
-            rusqlite::Error::FromSqlConversionFailure(
-                0,
-                rusqlite::types::Type::Integer,
-                Box::new(e),
-            )
+        conn.execute(&sql, param_refs.as_slice()).and_then(|_| {
+            // Get the last inserted ID and use FromSql to convert it
+            let last_id = conn.last_insert_rowid();
+            P::column_result(rusqlite::types::ValueRef::Integer(last_id)).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Integer,
+                    Box::new(e),
+                )
+            })
         })
-    }
+    };
+    observation.finish(result, |_| 1)
 }
 
 /// # update
@@ -189,7 +214,8 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql + Send + Sync>(
 /// - `entity`: The entity to update (must implement SqlCommand and UpdateParams traits)
 ///
 /// ## Return Value
-/// - `Result<usize, Error>`: On success, returns the number of rows affected; on failure, returns Error
+/// - `Result<usize, Error>`: On success, returns the number of rows affected; on failure, returns Error.
+///   Models with `#[version_column("...")]` should use [`update_versioned`] to detect conflicts.
 ///
 /// ## Struct Definition
 /// Structs used with this function should be annotated with the following derive macros:
@@ -211,11 +237,11 @@ pub fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql + Send + Sync>(
 /// ## Example Usage
 ///
 /// ```rust,no_run
-/// use rusqlite::{Connection, Result};
+/// use rusqlite::Connection;
 /// use parsql_macros::{Updateable, UpdateParams};
 /// use parsql_sqlite::update;
 ///
-/// fn main() -> Result<()> {
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     // Create database connection
 ///     let conn = Connection::open("test.db")?;
 ///     conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT, state INTEGER)", [])?;
@@ -252,14 +278,63 @@ pub fn update<T: SqlCommand + UpdateParams>(
     entity: T,
 ) -> Result<usize, Error> {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-SQLITE] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
     let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
-    let affected_rows = conn.execute(&sql, param_refs.as_slice())?;
-    Ok(affected_rows)
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &param_refs);
+    let result = conn.execute(&sql, param_refs.as_slice());
+    observation.finish(result, |rows| *rows)
+}
+
+/// # update_versioned
+///
+/// Updates a record of a model with `#[version_column("...")]` using optimistic locking.
+///
+/// The generated SQL matches the version read with the record and increments it. When no row
+/// matches, the record was changed or deleted since it was read and the update fails with
+/// `UpdateError::StaleObject`.
+///
+/// ## Parameters
+/// - `conn`: SQLite database connection
+/// - `entity`: The entity to update (must implement Versioned and UpdateParams traits)
+///
+/// ## Return Value
+/// - `Result<(), UpdateError>`: On failure, returns `UpdateError::StaleObject` or the database error.
+///
+/// ## Example Usage
+///
+/// ```rust,no_run
+/// use rusqlite::Connection;
+/// use parsql_sqlite::traits::*;
+/// use parsql_sqlite::*;
+///
+/// #[derive(Updateable, UpdateParams)]
+/// #[table("accounts")]
+/// #[update("balance")]
+/// #[where_clause("id = ?")]
+/// #[version_column("version")]
+/// pub struct UpdateAccount {
+///     pub id: i64,
+///     pub balance: i64,
+///     pub version: i64,
+/// }
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let conn = Connection::open("test.db")?;
+///     let account = UpdateAccount { id: 1, balance: 50, version: 3 };
+///
+///     match update_versioned(&conn, account) {
+///         Err(err) if err.is_stale() => println!("Reload the account and try again"),
+///         result => result?,
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn update_versioned<T: Versioned + UpdateParams>(
+    conn: &rusqlite::Connection,
+    entity: T,
+) -> Result<(), UpdateError> {
+    let affected_rows = update(conn, entity)?;
+    check_version::<T>(affected_rows > 0)
 }
 
 /// # delete
@@ -325,14 +400,114 @@ pub fn delete<T: SqlCommand + SqlParams>(
     entity: T,
 ) -> Result<usize, Error> {
     let sql = T::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-SQLITE] Execute SQL: {}", sql);
-    }
+    let params = entity.params();
+    let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &param_refs);
+    let result = conn.execute(&sql, param_refs.as_slice());
+    observation.finish(result, |rows| *rows)
+}
+
+/// # restore
+///
+/// Restores soft-deleted records by clearing their deletion marker.
+///
+/// ## Parameters
+/// - `conn`: SQLite database connection
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<usize, Error>`: On success, returns the number of restored records; on failure, returns Error
+///
+/// ## Example Usage
+/// ```rust
+/// use parsql_sqlite::traits::*;
+/// use parsql_sqlite::*;
+///
+/// #[derive(Deletable, SqlParams)]
+/// #[table("users")]
+/// #[where_clause("id = $")]
+/// #[soft_delete(column = "deleted_at")]
+/// pub struct DeleteUser {
+///     pub id: i64,
+/// }
+///
+/// fn main() -> Result<(), Error> {
+///     let conn = Connection::open_in_memory()?;
+///     conn.execute_batch(
+///         "CREATE TABLE users (id INTEGER PRIMARY KEY, deleted_at TEXT);
+///          INSERT INTO users VALUES (1, '2024-01-01');",
+///     )?;
+///
+///     let count = restore(&conn, DeleteUser { id: 1 })?;
+///     assert_eq!(count, 1);
+///
+///     let deleted_at: Option<String> =
+///         conn.query_row("SELECT deleted_at FROM users WHERE id = 1", [], |row| row.get(0))?;
+///     assert_eq!(deleted_at, None);
+///     Ok(())
+/// }
+/// ```
+pub fn restore<T: SoftDelete + SqlParams>(
+    conn: &rusqlite::Connection,
+    entity: T,
+) -> Result<usize, Error> {
+    let sql = T::restore_query();
+    let params = entity.params();
+    let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &param_refs);
+    let result = conn.execute(&sql, param_refs.as_slice());
+    observation.finish(result, |rows| *rows)
+}
 
+/// # force_delete
+///
+/// Permanently deletes records of a table that uses soft delete.
+///
+/// ## Parameters
+/// - `conn`: SQLite database connection
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<usize, Error>`: On success, returns the number of deleted records; on failure, returns Error
+///
+/// ## Example Usage
+/// ```rust
+/// use parsql_sqlite::traits::*;
+/// use parsql_sqlite::*;
+///
+/// #[derive(Deletable, SqlParams)]
+/// #[table("users")]
+/// #[where_clause("id = $")]
+/// #[soft_delete(column = "deleted_at")]
+/// pub struct DeleteUser {
+///     pub id: i64,
+/// }
+///
+/// fn main() -> Result<(), Error> {
+///     let conn = Connection::open_in_memory()?;
+///     conn.execute_batch(
+///         "CREATE TABLE users (id INTEGER PRIMARY KEY, deleted_at TEXT);
+///          INSERT INTO users VALUES (1, '2024-01-01');",
+///     )?;
+///
+///     let count = force_delete(&conn, DeleteUser { id: 1 })?;
+///     assert_eq!(count, 1);
+///
+///     let remaining: i64 = conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+///     assert_eq!(remaining, 0);
+///     Ok(())
+/// }
+/// ```
+pub fn force_delete<T: SoftDelete + SqlParams>(
+    conn: &rusqlite::Connection,
+    entity: T,
+) -> Result<usize, Error> {
+    let sql = T::force_delete_query();
     let params = entity.params();
     let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
-    let affected_rows = conn.execute(&sql, param_refs.as_slice())?;
-    Ok(affected_rows)
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &param_refs);
+    let result = conn.execute(&sql, param_refs.as_slice());
+    observation.finish(result, |rows| *rows)
 }
 
 /// # fetch
@@ -402,13 +577,11 @@ where
     R: FromRow,
 {
     let sql = P::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-SQLITE] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
     let param_refs: Vec<&dyn ToSql> = query_params.iter().map(|p| *p as &dyn ToSql).collect();
-    conn.query_row(&sql, param_refs.as_slice(), |row| R::from_row(row))
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &param_refs);
+    let result = conn.query_row(&sql, param_refs.as_slice(), |row| R::from_row(row));
+    observation.finish(result, |_| 1)
 }
 
 /// # fetch_all
@@ -467,21 +640,15 @@ where
     R: FromRow,
 {
     let sql = P::query();
-    if std::env::var("PARSQL_TRACE").unwrap_or_default() == "1" {
-        println!("[PARSQL-SQLITE] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
     let param_refs: Vec<&dyn ToSql> = query_params.iter().map(|p| *p as &dyn ToSql).collect();
-    let mut stmt = conn.prepare(&sql)?;
-    let rows = stmt.query_map(param_refs.as_slice(), |row| R::from_row(row))?;
-
-    let mut results = Vec::new();
-    for row in rows {
-        results.push(row?);
-    }
-
-    Ok(results)
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &param_refs);
+    let result = (|| {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(param_refs.as_slice(), |row| R::from_row(row))?;
+        rows.collect::<Result<Vec<R>, Error>>()
+    })();
+    observation.finish(result, Vec::len)
 }
 
 /// # get
@@ -682,3 +849,575 @@ where
 {
     conn.select_all(entity, to_model)
 }
+
+/// # preload
+///
+/// Loads the records related to a list of records with one `IN (...)` query per 999 keys
+/// and attaches them, avoiding one query per record (N+1).
+///
+/// ## Parameters
+/// - `conn`: SQLite database connection
+/// - `records`: Records previously returned by `fetch_all` (must implement Relation<C>)
+///
+/// ## Return Value
+/// - `Result<(), Error>`: On success, the relation field of every record is filled; on failure, returns Error
+///
+/// ## Example Usage
+/// Relations are declared on the model with the `has_many` or `belongs_to` attributes
+/// of the `FromRow` derive macro; a nullable foreign key is an `Option` field:
+///
+/// ```rust
+/// use parsql_sqlite::traits::*;
+/// use parsql_sqlite::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("orders")]
+/// #[select("id, customer_id")]
+/// #[where_clause("customer_id = ?")]
+/// #[result_type("Order")]
+/// pub struct OrdersByCustomer {
+///     pub customer_id: i64,
+/// }
+///
+/// #[derive(FromRow, Debug)]
+/// #[has_many(OrderItem, foreign_key = "order_id", field = "items")]
+/// pub struct Order {
+///     pub id: i64,
+///     pub customer_id: i64,
+///     pub items: Vec<OrderItem>,    // Filled by preload
+/// }
+///
+/// #[derive(FromRow, Clone, Debug)]
+/// pub struct OrderItem {
+///     pub id: i64,
+///     pub order_id: Option<i64>,
+///     pub product: String,
+/// }
+///
+/// fn main() -> Result<(), rusqlite::Error> {
+///     let conn = Connection::open_in_memory()?;
+///     conn.execute_batch(
+///         "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER);
+///          CREATE TABLE order_items (id INTEGER PRIMARY KEY, order_id INTEGER, product TEXT);
+///          INSERT INTO orders VALUES (1, 1), (2, 1);
+///          INSERT INTO order_items VALUES (1, 1, 'pen'), (2, 1, 'ink'), (3, NULL, 'gift');",
+///     )?;
+///
+///     let mut orders: Vec<Order> = fetch_all(&conn, &OrdersByCustomer { customer_id: 1 })?;
+///     preload::<Order, OrderItem>(&conn, &mut orders)?;
+///
+///     assert_eq!(orders[0].items.len(), 2);
+///     assert!(orders[1].items.is_empty());
+///     Ok(())
+/// }
+/// ```
+pub fn preload<R, C>(conn: &rusqlite::Connection, records: &mut [R]) -> Result<(), Error>
+where
+    R: Relation<C>,
+    C: FromRow + Clone,
+{
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let keys = relation_keys(records);
+    let related = load_related::<R, C>(conn, &keys)?;
+
+    R::stitch(records, related);
+    Ok(())
+}
+
+/// Returns the distinct relation keys of `records`; each key is bound only once and
+/// records without a key are skipped.
+pub(crate) fn relation_keys<R, C>(records: &[R]) -> Vec<R::Key>
+where
+    R: Relation<C>,
+{
+    let mut seen = HashSet::new();
+    records
+        .iter()
+        .filter_map(|record| record.relation_key())
+        .filter(|key| seen.insert(key.clone()))
+        .collect()
+}
+
+/// The most keys bound by one relation query; SQLite builds before 3.32 allow at most 999
+/// variables per statement.
+const MAX_RELATION_KEYS: usize = 999;
+
+/// Runs the relation query of `R` for `keys`, in batches of at most [`MAX_RELATION_KEYS`].
+pub(crate) fn load_related<R, C>(
+    conn: &rusqlite::Connection,
+    keys: &[R::Key],
+) -> Result<Vec<C>, Error>
+where
+    R: Relation<C>,
+    C: FromRow,
+{
+    let mut related = Vec::new();
+    for batch in keys.chunks(MAX_RELATION_KEYS) {
+        let sql = R::relation_query(batch.len());
+        let param_refs: Vec<&dyn ToSql> = batch.iter().map(|k| k as &dyn ToSql).collect();
+        let observation = Observation::start::<C>(QueryKind::Select, &sql, &param_refs);
+        let result = (|| {
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(param_refs.as_slice(), |row| C::from_row(row))?;
+            rows.collect::<Result<Vec<C>, Error>>()
+        })();
+        related.extend(observation.finish(result, Vec::len)?);
+    }
+    Ok(related)
+}
+
+/// # fetch_all_where
+///
+/// Retrieves the records of a table that match a typed WHERE expression.
+///
+/// ## Parameters
+/// - `conn`: SQLite database connection
+/// - `expr`: The condition, built from the column constants generated by the `Columns` derive
+///
+/// ## Return Value
+/// - `Result<Vec<R>, WhereError>`: On success, returns the matching records; on failure, returns WhereError
+///
+/// Like the `Queryable` queries of the model, rows marked by `#[soft_delete]` are skipped, and
+/// a `#[tenant_scoped]` model needs the condition to be restricted with
+/// `TenantContext::filter`; otherwise the query is not run and fails with
+/// `WhereError::MissingTenant`.
+///
+/// ## Example Usage
+///
+/// ```rust
+/// use parsql_sqlite::traits::*;
+/// use parsql_sqlite::*;
+///
+/// #[derive(Columns, FromRow, Debug)]
+/// #[table("users")]
+/// #[soft_delete]
+/// pub struct User {
+///     pub id: i64,
+///     pub email: String,
+///     pub state: i16,
+/// }
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let conn = Connection::open_in_memory()?;
+///     conn.execute_batch(
+///         "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT, state INTEGER, deleted_at TEXT);
+///          INSERT INTO users VALUES (1, 'ada@example.com', 1, NULL), (2, 'bob@example.com', 1, '2024-01-01');",
+///     )?;
+///
+///     let expr = users::state.eq(1).and(users::email.like("%@example.com"));
+///     let users: Vec<User> = fetch_all_where::<User>(&conn, expr)?;
+///     assert_eq!(users.len(), 1);
+///     Ok(())
+/// }
+/// ```
+pub fn fetch_all_where<R>(conn: &rusqlite::Connection, expr: Expr) -> Result<Vec<R>, WhereError>
+where
+    R: Columns + FromRow,
+{
+    let (sql, params) = expr.select::<R>()?;
+    let params: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let result = (|| {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params.as_slice(), |row| R::from_row(row))?;
+        rows.collect::<Result<Vec<R>, Error>>()
+    })();
+    Ok(observation.finish(result, Vec::len)?)
+}
+
+/// # fetch_all_fragment
+///
+/// Retrieves all records returned by a composed query.
+///
+/// ## Parameters
+/// - `conn`: SQLite database connection
+/// - `fragment`: The query, usually built from `Queryable` structs with `SqlQuery::fragment`
+///
+/// ## Return Value
+/// - `Result<Vec<R>, Error>`: On success, returns the records; on failure, returns Error
+///
+/// ## Example Usage
+///
+/// ```rust
+/// use parsql_sqlite::traits::*;
+/// use parsql_sqlite::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("users")]
+/// #[select("id, name")]
+/// #[where_clause("state = $")]
+/// #[result_type("UserSummary")]
+/// struct ActiveUsers {
+///     state: i32,
+/// }
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("admins")]
+/// #[select("id, name")]
+/// #[where_clause("level >= $")]
+/// #[result_type("UserSummary")]
+/// struct Admins {
+///     level: i32,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct UserSummary {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// fn main() -> Result<(), Error> {
+///     let conn = Connection::open_in_memory()?;
+///     conn.execute_batch(
+///         "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, state INTEGER);
+///          CREATE TABLE admins (id INTEGER PRIMARY KEY, name TEXT, level INTEGER);
+///          INSERT INTO users VALUES (1, 'Ada', 1), (2, 'Alan', 0);
+///          INSERT INTO admins VALUES (10, 'Grace', 3);",
+///     )?;
+///
+///     let active = ActiveUsers { state: 1 };
+///     let admins = Admins { level: 3 };
+///     let query = active.fragment().union_all(admins.fragment());
+///     let users: Vec<UserSummary> = fetch_all_fragment(&conn, &query)?;
+///     assert_eq!(users.iter().map(|user| user.id).collect::<Vec<_>>(), vec![1, 10]);
+///     Ok(())
+/// }
+/// ```
+pub fn fetch_all_fragment<R: FromRow>(
+    conn: &rusqlite::Connection,
+    fragment: &Fragment,
+) -> Result<Vec<R>, Error> {
+    let (sql, params) = fragment.build();
+    let params: Vec<&dyn ToSql> = params.iter().map(|p| *p as &dyn ToSql).collect();
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let result = (|| {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params.as_slice(), |row| R::from_row(row))?;
+        rows.collect::<Result<Vec<R>, Error>>()
+    })();
+    observation.finish(result, Vec::len)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams, FromRow, Clone, Debug)]
+    #[table("orders")]
+    #[has_many(OrderItem, foreign_key = "order_id", field = "items")]
+    struct Order {
+        id: i64,
+        items: Vec<OrderItem>,
+    }
+
+    #[derive(Queryable, SqlParams)]
+    #[table("orders")]
+    #[select("id")]
+    #[where_clause("id > ?")]
+    #[result_type("Order")]
+    struct OrdersAfter {
+        id: i64,
+    }
+
+    #[derive(FromRow, Clone, Debug)]
+    #[belongs_to(Order)]
+    struct OrderItem {
+        id: i64,
+        order_id: Option<i64>,
+        order: Option<Order>,
+    }
+
+    #[derive(Queryable, SqlParams)]
+    #[table("order_items")]
+    #[select("id, order_id")]
+    #[where_clause("id > ?")]
+    #[result_type("OrderItem")]
+    struct ItemsAfter {
+        id: i64,
+    }
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY);
+             CREATE TABLE order_items (id INTEGER PRIMARY KEY, order_id INTEGER);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_insert_skips_relation_fields() {
+        let conn = connection();
+        let id: i64 = insert(
+            &conn,
+            Order {
+                id: 7,
+                items: Vec::new(),
+            },
+        )
+        .unwrap();
+        assert_eq!(id, 7);
+    }
+
+    #[test]
+    fn test_preload_batches_keys_over_the_variable_limit() {
+        let conn = connection();
+        let count = super::MAX_RELATION_KEYS * 2 + 1;
+        conn.execute_batch(&format!(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {count})
+             INSERT INTO orders SELECT i FROM n;
+             INSERT INTO order_items (order_id) SELECT id FROM orders;
+             INSERT INTO order_items (order_id) VALUES ({count});"
+        ))
+        .unwrap();
+
+        let mut orders: Vec<Order> = fetch_all(&conn, &OrdersAfter { id: 0 }).unwrap();
+        assert_eq!(orders.len(), count);
+        preload::<Order, OrderItem>(&conn, &mut orders).unwrap();
+
+        assert!(orders[..count - 1].iter().all(|order| order.items.len() == 1));
+        assert_eq!(orders[count - 1].items.len(), 2);
+    }
+
+    #[test]
+    fn test_preload_optional_foreign_key() {
+        let conn = connection();
+        conn.execute_batch(
+            "INSERT INTO orders VALUES (1);
+             INSERT INTO order_items VALUES (1, 1), (2, NULL);",
+        )
+        .unwrap();
+
+        let mut items: Vec<OrderItem> = fetch_all(&conn, &ItemsAfter { id: 0 }).unwrap();
+        preload::<OrderItem, Order>(&conn, &mut items).unwrap();
+
+        assert_eq!(items[0].order.as_ref().map(|order| order.id), Some(1));
+        assert_eq!(items[1].id, 2);
+        assert!(items[1].order.is_none());
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("accounts")]
+    #[update("balance")]
+    #[where_clause("id = $")]
+    struct SetBalance {
+        id: i64,
+        balance: i64,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("accounts")]
+    #[update("balance")]
+    #[where_clause("id = $")]
+    #[version_column("version")]
+    struct UpdateAccount {
+        id: i64,
+        balance: i64,
+        version: i64,
+    }
+
+    fn accounts() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE accounts (id INTEGER PRIMARY KEY, balance INTEGER, version INTEGER);
+             INSERT INTO accounts VALUES (1, 100, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_plain_update_returns_database_error() {
+        let conn = accounts();
+        let missing = update(&conn, SetBalance { id: 2, balance: 0 });
+        assert_eq!(missing.unwrap(), 0);
+
+        conn.execute_batch("DROP TABLE accounts").unwrap();
+        let err: Error = update(&conn, SetBalance { id: 1, balance: 0 }).unwrap_err();
+        assert!(err.to_string().contains("no such table"));
+    }
+
+    #[test]
+    fn test_versioned_update_reports_stale_object() {
+        let conn = accounts();
+        let account = || UpdateAccount {
+            id: 1,
+            balance: 50,
+            version: 1,
+        };
+
+        // Plain updates do not check the version
+        assert_eq!(update(&conn, account()).unwrap(), 1);
+        assert_eq!(update(&conn, account()).unwrap(), 0);
+
+        let account = |version| UpdateAccount {
+            id: 1,
+            balance: 50,
+            version,
+        };
+        update_versioned(&conn, account(2)).unwrap();
+        assert!(update_versioned(&conn, account(2)).unwrap_err().is_stale());
+        assert!(conn.update_versioned(account(3)).is_ok());
+    }
+
+    #[derive(Insertable, SqlParams)]
+    #[table("posts")]
+    #[timestamps]
+    struct InsertPost {
+        title: String,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("posts")]
+    #[update("title")]
+    #[where_clause("id = $")]
+    #[timestamps(updated = "updated_at")]
+    struct RenamePost {
+        id: i64,
+        title: String,
+    }
+
+    #[test]
+    fn test_timestamps_use_the_database_clock() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE posts (
+                id INTEGER PRIMARY KEY, title TEXT, created_at TEXT, updated_at TEXT
+             );",
+        )
+        .unwrap();
+        let timestamps = |conn: &Connection| -> (Option<String>, Option<String>) {
+            conn.query_row("SELECT created_at, updated_at FROM posts", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap()
+        };
+
+        insert::<_, i64>(&conn, InsertPost { title: "draft".into() }).unwrap();
+        let (created_at, updated_at) = timestamps(&conn);
+        assert!(created_at.is_some() && updated_at.is_some());
+
+        conn.execute_batch("UPDATE posts SET updated_at = NULL").unwrap();
+        let post = RenamePost {
+            id: 1,
+            title: "final".into(),
+        };
+        assert_eq!(update(&conn, post).unwrap(), 1);
+        assert_eq!(timestamps(&conn).0, created_at);
+        assert!(timestamps(&conn).1.is_some());
+    }
+
+    #[derive(Queryable, SqlParams)]
+    #[table("notes")]
+    #[select("notes.id")]
+    #[join("INNER JOIN authors ON authors.id = notes.author_id")]
+    #[where_clause("notes.id > ?")]
+    #[result_type("Note")]
+    #[soft_delete]
+    struct NotesWithAuthors {
+        id: i64,
+    }
+
+    #[test]
+    fn test_soft_delete_check_is_qualified_in_joins() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, deleted_at TEXT);
+             CREATE TABLE notes (id INTEGER PRIMARY KEY, author_id INTEGER, deleted_at TEXT);
+             INSERT INTO authors VALUES (1, '2024-01-01');
+             INSERT INTO notes VALUES (1, 1, NULL), (2, 1, '2024-01-01');",
+        )
+        .unwrap();
+
+        let notes: Vec<Note> = fetch_all(&conn, &NotesWithAuthors { id: 0 }).unwrap();
+        assert_eq!(notes.iter().map(|note| note.id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("notes")]
+    #[where_clause("id = $")]
+    #[soft_delete]
+    struct DeleteNote {
+        id: i64,
+    }
+
+    #[derive(Queryable, SqlParams)]
+    #[table("notes")]
+    #[select("id")]
+    #[where_clause("id > ?")]
+    #[result_type("Note")]
+    #[soft_delete]
+    struct NotesAfter {
+        id: i64,
+    }
+
+    #[test]
+    fn test_soft_delete_and_restore() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, deleted_at TEXT);
+             INSERT INTO notes VALUES (1, NULL), (2, NULL);",
+        )
+        .unwrap();
+        let visible = |conn: &Connection| -> Vec<i64> {
+            let notes: Vec<Note> = fetch_all(conn, &NotesAfter { id: 0 }).unwrap();
+            notes.iter().map(|note| note.id).collect()
+        };
+
+        assert_eq!(delete(&conn, DeleteNote { id: 1 }).unwrap(), 1);
+        let deleted_at: Option<String> = conn
+            .query_row("SELECT deleted_at FROM notes WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(deleted_at.is_some());
+        assert_eq!(visible(&conn), vec![2]);
+        // Deleting again does not move the timestamp
+        assert_eq!(delete(&conn, DeleteNote { id: 1 }).unwrap(), 0);
+
+        assert_eq!(restore(&conn, DeleteNote { id: 1 }).unwrap(), 1);
+        assert_eq!(visible(&conn), vec![1, 2]);
+        assert_eq!(force_delete(&conn, DeleteNote { id: 1 }).unwrap(), 1);
+        assert_eq!(visible(&conn), vec![2]);
+    }
+
+    #[derive(Columns, FromRow, Debug)]
+    #[table("notes")]
+    #[soft_delete]
+    #[tenant_scoped]
+    struct Note {
+        id: i64,
+    }
+
+    fn notes() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, tenant_id INTEGER, deleted_at TEXT);
+             INSERT INTO notes VALUES (1, 1, NULL), (2, 1, '2024-01-01'), (3, 2, NULL);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_fetch_all_where_applies_row_filters() {
+        let conn = notes();
+        let tenant = TenantContext::new(1i64);
+
+        let expr = tenant.filter::<Note>(notes::id.gt(0));
+        let notes: Vec<Note> = fetch_all_where(&conn, expr).unwrap();
+        assert_eq!(notes.iter().map(|note| note.id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_fetch_all_where_requires_tenant() {
+        let conn = notes();
+        let err = fetch_all_where::<Note>(&conn, notes::id.gt(0)).unwrap_err();
+        assert!(err.is_missing_tenant());
+    }
+}
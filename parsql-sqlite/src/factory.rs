@@ -0,0 +1,189 @@
+//! Seed data factories for tests.
+//!
+//! `#[derive(Factory)]` on an `Insertable` struct generates `<Struct>Factory`, which
+//! implements [`Factory`]: every entity it builds gets the next sequence number of the
+//! factory, so defaults such as `name-1`, `name-2` stay unique, and `with_<field>` sets a
+//! field for all of them. [`FactoryExt`] inserts the entities with [`CrudOps::insert`], so
+//! `create` returns the inserted row id (or the `#[returning]` column), which associates
+//! the rows of another factory with it:
+//!
+//! ```rust
+//! use parsql_sqlite::traits::*;
+//! use parsql_sqlite::*;
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("users")]
+//! #[factory(name = "UserFactory")]
+//! struct InsertUser {
+//!     name: String,
+//!     #[factory(sequence = "user{n}@example.com")]
+//!     email: String,
+//! }
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("posts")]
+//! #[factory(name = "PostFactory")]
+//! struct InsertPost {
+//!     user_id: i64,
+//!     title: String,
+//! }
+//!
+//! # fn main() -> Result<(), rusqlite::Error> {
+//! let conn = Connection::open_in_memory()?;
+//! conn.execute_batch(
+//!     "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT UNIQUE);
+//!      CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users, title TEXT);",
+//! )?;
+//!
+//! let user_id: i64 = UserFactory::new().with_name("Ada").create(&conn)?;
+//! let post_ids: Vec<i64> = PostFactory::new().with_user_id(user_id).create_many(&conn, 3)?;
+//! assert_eq!(post_ids.len(), 3);
+//! # Ok(())
+//! # }
+//! ```
+
+use rusqlite::{types::FromSql, Error};
+
+use crate::traits::{CrudOps, SqlCommand, SqlParams};
+
+pub use parsql_core::factory::Factory;
+
+/// Inserts the entities of a [`Factory`]; implemented for every factory of `Insertable`
+/// entities.
+pub trait FactoryExt: Factory {
+    /// Inserts the next entity and returns its row id or `#[returning]` column.
+    fn create<C, P>(&self, conn: &C) -> Result<P, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: FromSql + Send + Sync;
+
+    /// Inserts `count` entities and returns their row ids or `#[returning]` columns.
+    ///
+    /// The entities are inserted one statement at a time and are not wrapped in a
+    /// transaction: an error leaves the earlier rows in place, so pass a transaction to
+    /// insert them atomically (and to commit them once).
+    fn create_many<C, P>(&self, conn: &C, count: usize) -> Result<Vec<P>, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: FromSql + Send + Sync;
+}
+
+impl<F> FactoryExt for F
+where
+    F: Factory + ?Sized,
+    F::Entity: SqlCommand + SqlParams,
+{
+    fn create<C, P>(&self, conn: &C) -> Result<P, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: FromSql + Send + Sync,
+    {
+        conn.insert(self.build())
+    }
+
+    fn create_many<C, P>(&self, conn: &C, count: usize) -> Result<Vec<P>, Error>
+    where
+        C: CrudOps + ?Sized,
+        P: FromSql + Send + Sync,
+    {
+        self.build_many(count)
+            .into_iter()
+            .map(|entity| conn.insert(entity))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::thread;
+
+    use rusqlite::Connection;
+
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams, Factory)]
+    #[table("users")]
+    #[factory(name = "UserFactory")]
+    struct InsertUser {
+        name: String,
+        #[factory(sequence = "user{n}@example.com")]
+        email: String,
+        rank: i32,
+        nickname: Option<String>,
+    }
+
+    #[derive(Insertable, SqlParams, Factory)]
+    #[table("teams")]
+    struct InsertTeam {
+        name: String,
+    }
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT UNIQUE,
+                                 rank INTEGER, nickname TEXT);
+             CREATE TABLE teams (id INTEGER PRIMARY KEY, name TEXT UNIQUE);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_defaults_follow_the_sequence_number() {
+        let user = UserFactory::new().build_with(7);
+        assert_eq!(user.name, "name-7");
+        assert_eq!(user.email, "user7@example.com");
+        assert_eq!(user.rank, 7);
+        assert_eq!(user.nickname, None);
+
+        let user = UserFactory::new()
+            .with_name("Ada")
+            .with_nickname(Some("ada".to_string()))
+            .build_with(8);
+        assert_eq!(user.name, "Ada");
+        assert_eq!(user.email, "user8@example.com");
+        assert_eq!(user.nickname.as_deref(), Some("ada"));
+    }
+
+    #[test]
+    fn test_sequence_is_unique_across_threads() {
+        let builders: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    (0..100)
+                        .map(|_| UserFactory::new().build().email)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut emails = HashSet::new();
+        for builder in builders {
+            for email in builder.join().unwrap() {
+                assert!(emails.insert(email.clone()), "{} was built twice", email);
+            }
+        }
+        assert_eq!(emails.len(), 800);
+    }
+
+    #[test]
+    fn test_factories_fill_unique_columns() {
+        let conn = connection();
+        let admins = UserFactory::new().with_rank(1);
+
+        let ids: Vec<i64> = admins.create_many(&conn, 5).unwrap();
+        let more: Vec<i64> = UserFactory::new().create_many(&conn, 5).unwrap();
+        let team: i64 = InsertTeamFactory::new().create(&conn).unwrap();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        assert_eq!(more, vec![6, 7, 8, 9, 10]);
+        assert_eq!(team, 1);
+
+        let ranked: i64 = conn
+            .query_row("SELECT COUNT(*) FROM users WHERE rank = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(ranked, 5);
+    }
+}
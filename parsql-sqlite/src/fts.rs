@@ -0,0 +1,276 @@
+//! SQLite FTS5 full-text search.
+//!
+//! A `Queryable` struct with `#[fts(table = "...", columns = "...")]` searches an FTS5 table
+//! and joins the matching rows back to its content table. The generated query binds the
+//! struct's `query` field to `MATCH`, selects `bm25()` as `rank` and orders by it, best match
+//! first; `snippet` and `highlight` add `<column>_snippet` and `<column>_highlight` columns.
+//!
+//! FTS5 gives quotes, `*`, `-`, `:` and words such as `OR` and `NEAR` a meaning, so raw user
+//! input makes a search fail or match something else. [`Match`] turns user input into a
+//! match expression where every word is searched literally.
+//!
+//! The FTS table and the triggers keeping it in sync with the content table are created by
+//! [`create_table`], or by running [`create_statements`] from a migration.
+//!
+//! ```rust
+//! use parsql_sqlite::fts::{self, Match};
+//! use parsql_sqlite::traits::*;
+//! use parsql_sqlite::*;
+//!
+//! #[derive(Queryable, SqlParams)]
+//! #[table("products")]
+//! #[fts(table = "products_fts", columns = "title, body", snippet = "body", highlight = "title")]
+//! #[result_type("ProductHit")]
+//! struct SearchProducts {
+//!     query: Match,
+//! }
+//!
+//! #[derive(FromRow)]
+//! struct ProductHit {
+//!     id: i64,
+//!     rank: f64,
+//!     title_highlight: String,
+//!     body_snippet: String,
+//! }
+//!
+//! # fn main() -> Result<(), rusqlite::Error> {
+//! let conn = Connection::open_in_memory()?;
+//! conn.execute("CREATE TABLE products (id INTEGER PRIMARY KEY, title TEXT, body TEXT)", [])?;
+//! fts::create_table(&conn, "products", "products_fts", &["title", "body"])?;
+//! conn.execute(
+//!     "INSERT INTO products (title, body) VALUES ('Rust book', 'Learn \"Rust\" the easy way')",
+//!     [],
+//! )?;
+//!
+//! // The quote in the input is searched for, not parsed
+//! let search = SearchProducts { query: Match::new("rust \"easy") };
+//! let hits: Vec<ProductHit> = conn.fetch_all(&search)?;
+//! assert_eq!(hits[0].title_highlight, "<b>Rust</b> book");
+//! # Ok(())
+//! # }
+//! ```
+
+use rusqlite::types::{ToSqlOutput, ValueRef};
+use rusqlite::{Connection, Error, ToSql};
+
+/// An FTS5 match expression, bound to the `MATCH` placeholder of a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match(String);
+
+impl Match {
+    /// Matches rows containing every word of `text`, each searched literally.
+    ///
+    /// Input without words matches no rows.
+    pub fn new(text: &str) -> Self {
+        Match(escape(text))
+    }
+
+    /// Like [`Match::new`], but the last word also matches as a prefix, for search-as-you-type.
+    pub fn prefix(text: &str) -> Self {
+        let mut expression = escape(text);
+        if expression != "\"\"" {
+            expression.push('*');
+        }
+        Match(expression)
+    }
+
+    /// Uses `expression` as it is, with the full FTS5 query syntax.
+    ///
+    /// Never pass user input here; use [`Match::new`] instead.
+    pub fn raw(expression: impl Into<String>) -> Self {
+        Match(expression.into())
+    }
+
+    /// Returns the match expression.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ToSql for Match {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>, Error> {
+        Ok(ToSqlOutput::Borrowed(ValueRef::Text(self.0.as_bytes())))
+    }
+}
+
+/// Turns `text` into a match expression of quoted words, so that FTS5 operators and quotes
+/// in it are searched for instead of being interpreted.
+pub fn escape(text: &str) -> String {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    if words.is_empty() {
+        // An empty phrase, which matches nothing
+        return "\"\"".to_string();
+    }
+    words.join(" ")
+}
+
+/// Returns the statements creating an external-content FTS5 table over `columns` of
+/// `content_table`, the triggers keeping it in sync and the initial indexing of existing rows.
+///
+/// The statements are run one by one, e.g. in the `up` of a migration.
+pub fn create_statements(content_table: &str, fts_table: &str, columns: &[&str]) -> Vec<String> {
+    let list = columns.join(", ");
+    let new_values = prefixed("new", columns);
+    let old_values = prefixed("old", columns);
+
+    vec![
+        format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS {fts} USING fts5({list}, content='{content}', content_rowid='rowid')",
+            fts = fts_table,
+            list = list,
+            content = content_table
+        ),
+        format!(
+            "CREATE TRIGGER IF NOT EXISTS {fts}_ai AFTER INSERT ON {content} BEGIN \
+             INSERT INTO {fts} (rowid, {list}) VALUES (new.rowid, {new}); END",
+            fts = fts_table,
+            content = content_table,
+            list = list,
+            new = new_values
+        ),
+        format!(
+            "CREATE TRIGGER IF NOT EXISTS {fts}_ad AFTER DELETE ON {content} BEGIN \
+             INSERT INTO {fts} ({fts}, rowid, {list}) VALUES ('delete', old.rowid, {old}); END",
+            fts = fts_table,
+            content = content_table,
+            list = list,
+            old = old_values
+        ),
+        format!(
+            "CREATE TRIGGER IF NOT EXISTS {fts}_au AFTER UPDATE ON {content} BEGIN \
+             INSERT INTO {fts} ({fts}, rowid, {list}) VALUES ('delete', old.rowid, {old}); \
+             INSERT INTO {fts} (rowid, {list}) VALUES (new.rowid, {new}); END",
+            fts = fts_table,
+            content = content_table,
+            list = list,
+            old = old_values,
+            new = new_values
+        ),
+        format!("INSERT INTO {fts} ({fts}) VALUES ('rebuild')", fts = fts_table),
+    ]
+}
+
+/// Returns the statements removing what [`create_statements`] created, e.g. for the `down`
+/// of a migration.
+pub fn drop_statements(fts_table: &str) -> Vec<String> {
+    vec![
+        format!("DROP TRIGGER IF EXISTS {}_ai", fts_table),
+        format!("DROP TRIGGER IF EXISTS {}_ad", fts_table),
+        format!("DROP TRIGGER IF EXISTS {}_au", fts_table),
+        format!("DROP TABLE IF EXISTS {}", fts_table),
+    ]
+}
+
+/// Creates the FTS5 table of `content_table` and its sync triggers, and indexes the existing
+/// rows; see [`create_statements`].
+pub fn create_table(
+    conn: &Connection,
+    content_table: &str,
+    fts_table: &str,
+    columns: &[&str],
+) -> Result<(), Error> {
+    for sql in create_statements(content_table, fts_table, columns) {
+        conn.execute(&sql, [])?;
+    }
+    Ok(())
+}
+
+fn prefixed(row: &str, columns: &[&str]) -> String {
+    columns
+        .iter()
+        .map(|column| format!("{}.{}", row, column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &[&str] = &[
+        "cats dogs",
+        "cats or dogs",
+        "say \"hi\" near the river",
+        "category",
+    ];
+
+    fn notes() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE VIRTUAL TABLE notes USING fts5(body)", []).unwrap();
+        for body in NOTES {
+            conn.execute("INSERT INTO notes (body) VALUES (?1)", [body]).unwrap();
+        }
+        conn
+    }
+
+    /// Returns the matching notes, in insertion order.
+    fn search(conn: &Connection, expression: &Match) -> Result<Vec<String>, Error> {
+        let mut statement =
+            conn.prepare("SELECT body FROM notes WHERE notes MATCH ?1 ORDER BY rowid")?;
+        let rows = statement.query_map([expression], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    #[test]
+    fn test_escape_quotes_every_word() {
+        assert_eq!(escape("cats  dogs"), r#""cats" "dogs""#);
+        assert_eq!(escape(r#"say "hi""#), r#""say" """hi""""#);
+        assert_eq!(
+            escape("a OR b NEAR(c) d* -e title:f"),
+            r#""a" "OR" "b" "NEAR(c)" "d*" "-e" "title:f""#
+        );
+        assert_eq!(escape(" \t\n"), r#""""#);
+    }
+
+    #[test]
+    fn test_prefix_applies_to_the_last_word() {
+        assert_eq!(Match::prefix("cats do").as_str(), r#""cats" "do"*"#);
+        assert_eq!(Match::prefix("").as_str(), r#""""#);
+    }
+
+    #[test]
+    fn test_operators_are_searched_literally() {
+        let conn = notes();
+        let both = vec!["cats dogs", "cats or dogs"];
+        assert_eq!(search(&conn, &Match::raw("cats OR dogs")).unwrap(), both);
+        assert!(search(&conn, &Match::raw("-dogs")).is_err());
+
+        let cases: &[(&str, &[&str])] = &[
+            ("cats OR dogs", &["cats or dogs"]),
+            ("cats NOT dogs", &[]),
+            ("cat*", &[]),
+            ("NEAR(cats dogs)", &[]),
+            ("body:cats", &[]),
+            // "-" is a separator inside a quoted word, so this searches "dogs"
+            ("-dogs", &["cats dogs", "cats or dogs"]),
+        ];
+        for (input, expected) in cases {
+            let hits = search(&conn, &Match::new(input)).unwrap();
+            assert_eq!(hits, *expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_quotes_are_searched_literally() {
+        let conn = notes();
+
+        assert!(search(&conn, &Match::raw(r#"say "hi"#)).is_err());
+        let hits = search(&conn, &Match::new(r#"say "hi"#)).unwrap();
+        assert_eq!(hits, [r#"say "hi" near the river"#]);
+        let hits = search(&conn, &Match::new(r#""near" river""#)).unwrap();
+        assert_eq!(hits, [r#"say "hi" near the river"#]);
+    }
+
+    #[test]
+    fn test_prefix_and_empty_input() {
+        let conn = notes();
+
+        let hits = search(&conn, &Match::prefix("cat")).unwrap();
+        assert_eq!(hits, ["cats dogs", "cats or dogs", "category"]);
+        assert_eq!(search(&conn, &Match::new("cat")).unwrap(), Vec::<String>::new());
+        assert_eq!(search(&conn, &Match::new("  ")).unwrap(), Vec::<String>::new());
+    }
+}
@@ -108,7 +108,7 @@
 //! You can perform database operations within a transaction to ensure atomicity:
 //! 
 //! ```rust,no_run
-//! use rusqlite::{Connection, Result};
+//! use rusqlite::Connection;
 //! use parsql::sqlite::transactional;
 //! use parsql::macros::{Insertable, SqlParams, Updateable, UpdateParams};
 //! 
@@ -128,7 +128,7 @@
 //!     email: String,
 //! }
 //! 
-//! fn main() -> Result<()> {
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     let conn = Connection::open("test.db")?;
 //!     
 //!     // Begin a transaction
@@ -172,7 +172,20 @@
 //! parsql-macros = "0.3.7"
 //! ```
 
+#[cfg(feature = "async")]
+pub mod async_ops;
+pub mod backup;
+pub mod compose;
+pub mod connection;
 pub mod crud_ops;
+pub mod error;
+pub mod expr;
+pub mod factory;
+pub mod fts;
+pub mod observer;
+pub mod pool;
+pub use parsql_core::stats;
+pub mod tenant;
 pub mod transactional_ops;
 pub mod traits;
 pub mod macros;
@@ -189,10 +202,37 @@ pub use crud_ops::{
     select, 
     select_all, 
     update, 
+    update_versioned,
     delete, 
+    restore,
+    force_delete,
     fetch, 
     fetch_all,
+    preload,
+    fetch_all_where,
+    fetch_all_fragment,
 };
 
+pub use compose::Fragment;
+
+pub use connection::ConnectionBuilder;
+
+pub use error::{UpdateError, WhereError};
+
+pub use factory::{Factory, FactoryExt};
+
+pub use observer::{set_observer, QueryEvent, QueryObserver};
+
+pub use pool::SqlitePool;
+
+#[cfg(feature = "async")]
+pub use async_ops::{AsyncConnection, AsyncCrudOps};
+
+pub use stats::QueryStats;
+
+pub use tenant::{Scoped, TenantContext};
+
+pub use expr::{Column, Expr, IntoValue};
+
 // Re-export transaction operations
 pub use transactional_ops as transactional;
@@ -1,5 +1,6 @@
 pub use parsql_macros::{
-    Deletable, FromRowSqlite as FromRow, Insertable, Queryable, SqlParams, UpdateParams, Updateable,
+    query, Columns, Deletable, Factory, FromRowSqlite as FromRow, Insertable, Queryable, SqlParams,
+    UpdateParams, Updateable,
 };
 
 pub use crate::traits::SqlCommand;
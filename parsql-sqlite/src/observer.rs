@@ -0,0 +1,109 @@
+//! Query instrumentation.
+//!
+//! Every CRUD function reports the statement it executed to the registered
+//! [`QueryObserver`]: the SQL, the number of parameters, optionally their values, the
+//! duration, the number of rows and the error, if any.
+//!
+//! ```rust
+//! use parsql_sqlite::observer::{set_observer, ParamCapture, QueryEvent, QueryObserver};
+//!
+//! struct SlowQueries;
+//!
+//! impl QueryObserver for SlowQueries {
+//!     fn on_query(&self, event: &QueryEvent<'_>) {
+//!         if event.duration.as_millis() > 100 {
+//!             eprintln!("slow {}: {} {:?}", event.kind.as_str(), event.sql, event.params);
+//!         }
+//!     }
+//!
+//!     fn param_capture(&self) -> ParamCapture {
+//!         ParamCapture::Redacted
+//!     }
+//! }
+//!
+//! set_observer(SlowQueries);
+//! ```
+//!
+//! Register [`PrintObserver`] to print every statement to stdout. With the `tracing`
+//! feature, [`TracingObserver`] emits a span per statement.
+//!
+//! These types live in [`parsql_core::observer`]; the registered observer receives the
+//! statements of every parsql backend, with [`QueryEvent::system`] set to `"sqlite"`.
+
+use rusqlite::types::{ToSqlOutput, Value};
+use rusqlite::ToSql;
+use std::error::Error as StdError;
+
+pub use parsql_core::observer::{
+    clear_observer, set_observer, ParamCapture, PrintObserver, QueryEvent, QueryKind,
+    QueryObserver,
+};
+#[cfg(feature = "tracing")]
+pub use parsql_core::observer::TracingObserver;
+
+/// A statement being executed; reports it to the observer when finished.
+pub(crate) struct Observation<'a>(parsql_core::observer::Observation<'a>);
+
+impl<'a> Observation<'a> {
+    /// Starts observing a statement generated for `E`.
+    pub(crate) fn start<E: ?Sized>(kind: QueryKind, sql: &'a str, params: &[&dyn ToSql]) -> Self {
+        Observation(parsql_core::observer::Observation::start(
+            "sqlite",
+            kind,
+            std::any::type_name::<E>(),
+            sql,
+            params,
+            |param, redact| format_param(*param, redact),
+        ))
+    }
+
+    /// Reports the result of the statement and passes it through. `rows` returns the number
+    /// of affected or returned rows of a successful result.
+    pub(crate) fn finish<T, E>(
+        self,
+        result: Result<T, E>,
+        rows: impl FnOnce(&T) -> usize,
+    ) -> Result<T, E>
+    where
+        E: StdError + 'static,
+    {
+        self.0.finish(result, |value| rows(value) as u64)
+    }
+}
+
+fn format_param(param: &dyn ToSql, redact: bool) -> String {
+    let value = match param.to_sql() {
+        Ok(ToSqlOutput::Borrowed(value)) => Value::from(value),
+        Ok(ToSqlOutput::Owned(value)) => value,
+        _ => return "<unknown>".to_string(),
+    };
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::Real(value) => value.to_string(),
+        _ if redact => "<redacted>".to_string(),
+        Value::Text(value) => format!("{:?}", value),
+        Value::Blob(value) => format!("<{} bytes>", value.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted_params_keep_only_numbers_and_null() {
+        assert_eq!(format_param(&42, true), "42");
+        assert_eq!(format_param(&1.5, true), "1.5");
+        assert_eq!(format_param(&true, true), "1");
+        assert_eq!(format_param(&Option::<i64>::None, true), "NULL");
+        assert_eq!(format_param(&"secret", true), "<redacted>");
+        assert_eq!(format_param(&vec![1u8, 2], true), "<redacted>");
+    }
+
+    #[test]
+    fn test_all_params_are_formatted() {
+        assert_eq!(format_param(&"secret", false), "\"secret\"");
+        assert_eq!(format_param(&vec![1u8, 2], false), "<2 bytes>");
+    }
+}
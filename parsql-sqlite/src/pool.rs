@@ -0,0 +1,546 @@
+//! A connection pool for sharing one SQLite file across threads.
+//!
+//! SQLite allows one writer at a time. Connections that write concurrently wait on each
+//! other's locks and fail with `SQLITE_BUSY` once their busy timeout runs out. [`SqlitePool`]
+//! avoids this by opening the database in WAL mode, where readers and the writer do not block
+//! each other, and by sending every write through a single writer connection. Callers wait
+//! for the writer in arrival order.
+//!
+//! The pool implements [`CrudOps`]: `fetch`, `fetch_all`, `select`, `select_all` and the other
+//! reads go to the reader connections, `insert`, `update`, `delete`, `restore` and
+//! `force_delete` go to the writer. Transactions are started on [`SqlitePool::writer`].
+//!
+//! ```rust
+//! use parsql_sqlite::pool::{PoolOptions, SqlitePool, Synchronous};
+//! use parsql_sqlite::traits::*;
+//! use parsql_sqlite::*;
+//! use std::sync::Arc;
+//! use std::thread;
+//!
+//! #[derive(Insertable, SqlParams)]
+//! #[table("visits")]
+//! struct InsertVisit {
+//!     page: String,
+//! }
+//!
+//! #[derive(Queryable, FromRow, SqlParams)]
+//! #[table("visits")]
+//! #[where_clause("page = $")]
+//! struct Visit {
+//!     id: i64,
+//!     page: String,
+//! }
+//!
+//! # fn main() -> Result<(), rusqlite::Error> {
+//! # let path = std::env::temp_dir().join(format!("parsql-pool-doc-{}.db", std::process::id()));
+//! let options = PoolOptions::new().readers(4).synchronous(Synchronous::Normal);
+//! let pool = Arc::new(SqlitePool::open_with(&path, options)?);
+//! pool.writer()
+//!     .execute("CREATE TABLE visits (id INTEGER PRIMARY KEY, page TEXT)", [])?;
+//!
+//! let workers: Vec<_> = (0..4)
+//!     .map(|_| {
+//!         let pool = Arc::clone(&pool);
+//!         thread::spawn(move || pool.insert::<_, i64>(InsertVisit { page: "/".into() }))
+//!     })
+//!     .collect();
+//! for worker in workers {
+//!     worker.join().unwrap()?;
+//! }
+//!
+//! let visits: Vec<Visit> = pool.fetch_all(&Visit { id: 0, page: "/".into() })?;
+//! assert_eq!(visits.len(), 4);
+//!
+//! let mut writer = pool.writer();
+//! let tx = writer.transaction()?;
+//! tx.execute("DELETE FROM visits", [])?;
+//! tx.commit()?;
+//! # drop(writer);
+//! # drop(pool);
+//! # for suffix in ["", "-wal", "-shm"] {
+//! #     let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+//! # }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, TryLockError};
+use std::time::Duration;
+
+use rusqlite::types::FromSql;
+use rusqlite::{Connection, Error, Row};
+
+use crate::compose::Fragment;
+use crate::connection::ConnectionBuilder;
+use crate::error::WhereError;
+use crate::expr::Expr;
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+};
+
+/// The `synchronous` setting, i.e. how often SQLite waits for writes to reach the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Synchronous {
+    /// Never waits; a power loss can corrupt the database.
+    Off,
+    /// Waits at checkpoints; in WAL mode a power loss can only lose the latest commits.
+    #[default]
+    Normal,
+    /// Waits at every commit.
+    Full,
+    /// Like `Full`, and also syncs the directory after deleting journal files.
+    Extra,
+}
+
+impl Synchronous {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Synchronous::Off => "OFF",
+            Synchronous::Normal => "NORMAL",
+            Synchronous::Full => "FULL",
+            Synchronous::Extra => "EXTRA",
+        }
+    }
+}
+
+/// Settings of a [`SqlitePool`].
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    readers: usize,
+    busy_timeout: Duration,
+    synchronous: Synchronous,
+    connection: ConnectionBuilder,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoolOptions {
+    /// Creates the default settings: 4 readers, a busy timeout of 5 seconds and
+    /// `synchronous = NORMAL`.
+    pub fn new() -> Self {
+        PoolOptions {
+            readers: 4,
+            busy_timeout: Duration::from_secs(5),
+            synchronous: Synchronous::Normal,
+            connection: ConnectionBuilder::new(),
+        }
+    }
+
+    /// Sets the number of reader connections; at least one is opened.
+    pub fn readers(mut self, readers: usize) -> Self {
+        self.readers = readers;
+        self
+    }
+
+    /// Sets how long a connection waits for a lock held by another process.
+    pub fn busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+
+    /// Sets the `synchronous` pragma of the connections.
+    pub fn synchronous(mut self, synchronous: Synchronous) -> Self {
+        self.synchronous = synchronous;
+        self
+    }
+
+    /// Opens the reader and writer connections with `connection`, which registers its
+    /// functions and collations on each of them.
+    pub fn connection(mut self, connection: ConnectionBuilder) -> Self {
+        self.connection = connection;
+        self
+    }
+}
+
+/// Reader connections and a single writer connection to one SQLite file.
+pub struct SqlitePool {
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
+    writer: Mutex<Connection>,
+    queue: WriteQueue,
+}
+
+impl SqlitePool {
+    /// Opens the database file with the default [`PoolOptions`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::open_with(path, PoolOptions::new())
+    }
+
+    /// Opens the database file, creating it if needed.
+    ///
+    /// In-memory databases are not supported, since every connection would open its own;
+    /// `:memory:`, an empty path and `mode=memory` URIs fail with [`Error::InvalidPath`].
+    pub fn open_with<P: AsRef<Path>>(path: P, options: PoolOptions) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if is_in_memory(path) {
+            return Err(Error::InvalidPath(path.to_path_buf()));
+        }
+
+        // The writer switches the file to WAL mode before the readers open it
+        let writer = options.connection.open(path)?;
+        writer.busy_timeout(options.busy_timeout)?;
+        writer.pragma_update(None, "journal_mode", "WAL")?;
+        writer.pragma_update(None, "synchronous", options.synchronous.as_str())?;
+
+        let readers = (0..options.readers.max(1))
+            .map(|_| {
+                let reader = options.connection.open(path)?;
+                reader.busy_timeout(options.busy_timeout)?;
+                reader.pragma_update(None, "synchronous", options.synchronous.as_str())?;
+                reader.pragma_update(None, "query_only", true)?;
+                Ok(Mutex::new(reader))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(SqlitePool {
+            readers,
+            next_reader: AtomicUsize::new(0),
+            writer: Mutex::new(writer),
+            queue: WriteQueue::new(),
+        })
+    }
+
+    /// Returns a reader connection, preferring an idle one.
+    ///
+    /// Reader connections are `query_only`: statements that write fail.
+    pub fn reader(&self) -> MutexGuard<'_, Connection> {
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        let count = self.readers.len();
+        for offset in 0..count {
+            match self.readers[(start + offset) % count].try_lock() {
+                Ok(reader) => return reader,
+                Err(TryLockError::Poisoned(poisoned)) => return poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => {}
+            }
+        }
+        // Every reader is busy; wait for the next one in turn
+        lock(&self.readers[start % count])
+    }
+
+    /// Returns the writer connection once the earlier callers are done with it.
+    ///
+    /// Use it for transactions and for writes that are not covered by [`CrudOps`].
+    pub fn writer(&self) -> Writer<'_> {
+        let turn = self.queue.wait();
+        Writer {
+            connection: lock(&self.writer),
+            _turn: turn,
+        }
+    }
+}
+
+/// The writer connection of a [`SqlitePool`]; the next caller gets it when this is dropped.
+pub struct Writer<'a> {
+    connection: MutexGuard<'a, Connection>,
+    _turn: Turn<'a>,
+}
+
+impl Deref for Writer<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl DerefMut for Writer<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        &mut self.connection
+    }
+}
+
+/// Hands out the writer in arrival order.
+struct WriteQueue {
+    next_ticket: AtomicU64,
+    serving: Mutex<u64>,
+    turn: Condvar,
+}
+
+impl WriteQueue {
+    fn new() -> Self {
+        WriteQueue {
+            next_ticket: AtomicU64::new(0),
+            serving: Mutex::new(0),
+            turn: Condvar::new(),
+        }
+    }
+
+    fn wait(&self) -> Turn<'_> {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        let mut serving = lock(&self.serving);
+        while *serving != ticket {
+            serving = self
+                .turn
+                .wait(serving)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        Turn { queue: self }
+    }
+}
+
+/// Passes the writer to the next ticket when dropped, also when the holder panics.
+struct Turn<'a> {
+    queue: &'a WriteQueue,
+}
+
+impl Drop for Turn<'_> {
+    fn drop(&mut self) {
+        *lock(&self.queue.serving) += 1;
+        self.queue.turn.notify_all();
+    }
+}
+
+fn is_in_memory(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.is_empty()
+        || path == ":memory:"
+        || (path.starts_with("file:")
+            && (path.starts_with("file::memory:") || path.contains("mode=memory")))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while holding a connection leaves it usable
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl CrudOps for SqlitePool {
+    fn insert<T: SqlCommand + SqlParams, P: for<'a> FromSql + Send + Sync>(
+        &self,
+        entity: T,
+    ) -> Result<P, Error> {
+        self.writer().insert(entity)
+    }
+
+    fn update<T: SqlCommand + UpdateParams>(&self, entity: T) -> Result<usize, Error> {
+        self.writer().update(entity)
+    }
+
+    fn delete<T: SqlCommand + SqlParams>(&self, entity: T) -> Result<usize, Error> {
+        self.writer().delete(entity)
+    }
+
+    fn restore<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error> {
+        self.writer().restore(entity)
+    }
+
+    fn force_delete<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error> {
+        self.writer().force_delete(entity)
+    }
+
+    fn fetch<P, R>(&self, params: &P) -> Result<R, Error>
+    where
+        P: SqlQuery<R> + SqlParams,
+        R: FromRow,
+    {
+        self.reader().fetch(params)
+    }
+
+    fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, Error>
+    where
+        P: SqlQuery<R> + SqlParams,
+        R: FromRow,
+    {
+        self.reader().fetch_all(params)
+    }
+
+    fn select<T: SqlQuery<T> + SqlParams, F, R>(&self, entity: &T, to_model: F) -> Result<R, Error>
+    where
+        F: Fn(&Row) -> Result<R, Error>,
+    {
+        self.reader().select(entity, to_model)
+    }
+
+    fn select_all<T: SqlQuery<T> + SqlParams, F, R>(
+        &self,
+        entity: &T,
+        to_model: F,
+    ) -> Result<Vec<R>, Error>
+    where
+        F: Fn(&Row) -> Result<R, Error>,
+    {
+        self.reader().select_all(entity, to_model)
+    }
+
+    fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C>,
+        C: FromRow + Clone,
+    {
+        self.reader().preload(records)
+    }
+
+    fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow,
+    {
+        self.reader().fetch_all_where(expr)
+    }
+
+    fn fetch_all_fragment<R: FromRow>(&self, fragment: &Fragment) -> Result<Vec<R>, Error> {
+        self.reader().fetch_all_fragment(fragment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams)]
+    #[table("events")]
+    struct InsertEvent {
+        worker: i64,
+    }
+
+    /// A database file that is removed with its WAL files when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new() -> Self {
+            TempFile(std::env::temp_dir().join(format!("parsql-pool-{}.db", nanoid::nanoid!())))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", self.0.display(), suffix));
+            }
+        }
+    }
+
+    fn pool(file: &TempFile, readers: usize) -> Arc<SqlitePool> {
+        let pool = SqlitePool::open_with(&file.0, PoolOptions::new().readers(readers)).unwrap();
+        pool.writer()
+            .execute("CREATE TABLE events (id INTEGER PRIMARY KEY, worker INTEGER)", [])
+            .unwrap();
+        Arc::new(pool)
+    }
+
+    fn count(pool: &SqlitePool) -> i64 {
+        pool.reader()
+            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_in_memory_databases_are_rejected() {
+        for path in [":memory:", "", "file::memory:?cache=shared", "file:db?mode=memory"] {
+            assert!(
+                matches!(SqlitePool::open(path), Err(Error::InvalidPath(_))),
+                "{path:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn test_writer_is_handed_out_in_arrival_order() {
+        let file = TempFile::new();
+        let pool = pool(&file, 1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = pool.writer();
+        let arrived = pool.queue.next_ticket.load(Ordering::Relaxed);
+        let waiters: Vec<_> = (0..5)
+            .map(|i| {
+                let (waiting, order) = (Arc::clone(&pool), Arc::clone(&order));
+                let waiter = thread::spawn(move || {
+                    let _writer = waiting.writer();
+                    order.lock().unwrap().push(i);
+                });
+                // Let this thread take its ticket before the next one arrives
+                while pool.queue.next_ticket.load(Ordering::Relaxed) <= arrived + i {
+                    thread::yield_now();
+                }
+                waiter
+            })
+            .collect();
+        drop(first);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_readers_reject_writes() {
+        let file = TempFile::new();
+        let pool = pool(&file, 2);
+
+        let result = pool.reader().execute("INSERT INTO events (worker) VALUES (1)", []);
+        assert!(matches!(
+            result,
+            Err(Error::SqliteFailure(ref error, _)) if error.code == rusqlite::ErrorCode::ReadOnly
+        ));
+        assert_eq!(count(&pool), 0);
+    }
+
+    #[test]
+    fn test_connections_survive_a_panicking_holder() {
+        let file = TempFile::new();
+        let pool = pool(&file, 1);
+
+        let holder = Arc::clone(&pool);
+        let result = thread::spawn(move || {
+            let _reader = holder.reader();
+            let _writer = holder.writer();
+            panic!("holder failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(pool.readers[0].is_poisoned());
+        assert!(pool.writer.is_poisoned());
+
+        let id: i64 = pool.insert(InsertEvent { worker: 1 }).unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(count(&pool), 1);
+    }
+
+    #[test]
+    fn test_concurrent_inserts_are_all_written() {
+        let file = TempFile::new();
+        let pool = pool(&file, 4);
+
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let pool = Arc::clone(&pool);
+                thread::spawn(move || {
+                    for _ in 0..25 {
+                        pool.insert::<_, i64>(InsertEvent { worker }).unwrap();
+                        pool.reader()
+                            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get::<_, i64>(0))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(count(&pool), 200);
+        let complete_workers: i64 = pool
+            .reader()
+            .query_row(
+                "SELECT COUNT(*) FROM (SELECT worker FROM events GROUP BY worker HAVING COUNT(*) = 25)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(complete_workers, 8);
+    }
+}
@@ -0,0 +1,236 @@
+//! Multi-tenant row scoping.
+//!
+//! Models marked with `#[tenant_scoped(column = "tenant_id")]` get a `tenant_id = ?` predicate
+//! in their generated SELECT, UPDATE and DELETE statements, and the tenant column in their
+//! INSERT statement. The tenant value itself comes from a [`TenantContext`], which wraps a
+//! model with [`TenantContext::scope`] before it is passed to the CRUD functions:
+//!
+//! ```rust
+//! use parsql_sqlite::traits::*;
+//! use parsql_sqlite::*;
+//!
+//! #[derive(Insertable, SqlParams)]
+//! #[table("projects")]
+//! #[tenant_scoped]
+//! struct InsertProject {
+//!     name: String,
+//! }
+//!
+//! #[derive(Queryable, FromRow, SqlParams)]
+//! #[table("projects")]
+//! #[where_clause("name = $")]
+//! #[tenant_scoped]
+//! struct Project {
+//!     id: i64,
+//!     name: String,
+//! }
+//!
+//! # fn main() -> Result<(), rusqlite::Error> {
+//! let conn = rusqlite::Connection::open_in_memory()?;
+//! conn.execute("CREATE TABLE projects (id INTEGER PRIMARY KEY, name TEXT, tenant_id INTEGER)", [])?;
+//!
+//! let acme = TenantContext::new(1_i64);
+//! let _: i64 = insert(&conn, acme.scope(InsertProject { name: "Website".into() }))?;
+//!
+//! let query = || Project { id: 0, name: "Website".into() };
+//! let projects: Vec<Project> = fetch_all(&conn, &acme.scope(query()))?;
+//! assert_eq!(projects.len(), 1);
+//!
+//! let other = TenantContext::new(2_i64);
+//! let projects: Vec<Project> = fetch_all(&conn, &other.scope(query()))?;
+//! assert!(projects.is_empty());
+//!
+//! // Without a context the tenant placeholder stays unbound and the query fails
+//! assert!(fetch_all::<Project, Project>(&conn, &query()).is_err());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `fetch_all_where` on a tenant-scoped model fails with `WhereError::MissingTenant` unless its
+//! condition was restricted with [`TenantContext::filter`].
+
+use rusqlite::ToSql;
+
+use crate::expr::Sqlite;
+use crate::traits::{
+    SoftDelete, SoftDeleteQuery, SqlCommand, SqlParams, SqlQuery, TenantScoped, UpdateParams,
+    Versioned,
+};
+
+/// The tenant of the current request.
+///
+/// Cloning is cheap; the tenant value is shared between clones.
+pub type TenantContext = parsql_core::tenant::TenantContext<Sqlite>;
+
+/// A tenant-scoped model together with its tenant, created by [`TenantContext::scope`].
+///
+/// Generates the same SQL as the wrapped model and adds the tenant value to its parameters.
+pub type Scoped<T> = parsql_core::tenant::Scoped<T, Sqlite>;
+
+impl<T, R> SqlQuery<R> for Scoped<T>
+where
+    T: SqlQuery<R> + TenantScoped,
+{
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: SqlCommand + TenantScoped> SqlCommand for Scoped<T> {
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: Versioned + TenantScoped> Versioned for Scoped<T> {
+    fn version_column() -> &'static str {
+        T::version_column()
+    }
+}
+
+impl<T: SoftDelete + TenantScoped> SoftDelete for Scoped<T> {
+    fn restore_query() -> String {
+        T::restore_query()
+    }
+
+    fn force_delete_query() -> String {
+        T::force_delete_query()
+    }
+}
+
+impl<T: SoftDeleteQuery + TenantScoped> SoftDeleteQuery for Scoped<T> {
+    fn query_with_deleted() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams + TenantScoped> SqlParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+impl<T: UpdateParams + TenantScoped> UpdateParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::expr::Column;
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams)]
+    #[table("projects")]
+    #[tenant_scoped]
+    struct InsertProject {
+        name: String,
+    }
+
+    #[derive(Queryable, FromRow, SqlParams, Columns, Debug)]
+    #[table("projects")]
+    #[where_clause("id > $")]
+    #[tenant_scoped]
+    struct Project {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("projects")]
+    #[update("name")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct RenameProject {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("projects")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct DeleteProject {
+        id: i64,
+    }
+
+    fn projects() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE projects (id INTEGER PRIMARY KEY, name TEXT, tenant_id INTEGER)",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn names(projects: Vec<Project>) -> Vec<String> {
+        projects.into_iter().map(|project| project.name).collect()
+    }
+
+    #[test]
+    fn test_scoped_writes_and_reads_stay_in_the_tenant() {
+        let conn = projects();
+        let acme = TenantContext::new(1_i64);
+        let globex = TenantContext::new(2_i64);
+
+        let add = |tenant: &TenantContext, name: &str| -> i64 {
+            insert(&conn, tenant.scope(InsertProject { name: name.into() })).unwrap()
+        };
+        let website = add(&acme, "Website");
+        add(&globex, "Shop");
+
+        let rename = |name: &str| RenameProject {
+            id: website,
+            name: name.into(),
+        };
+        assert_eq!(update(&conn, globex.scope(rename("Hijacked"))).unwrap(), 0);
+        assert_eq!(update(&conn, acme.scope(rename("Homepage"))).unwrap(), 1);
+        let remove = |tenant: &TenantContext| {
+            delete(&conn, tenant.scope(DeleteProject { id: website }))
+        };
+        assert_eq!(remove(&globex).unwrap(), 0);
+
+        let all = Project { id: 0, name: String::new() };
+        let acme_projects: Vec<Project> = fetch_all(&conn, &acme.scope(all)).unwrap();
+        assert_eq!(names(acme_projects), vec!["Homepage"]);
+
+        let expr = globex.filter::<Project>(projects::id.gt(0));
+        let globex_projects: Vec<Project> = fetch_all_where(&conn, expr).unwrap();
+        assert_eq!(names(globex_projects), vec!["Shop"]);
+
+        assert_eq!(remove(&acme).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_fetch_all_where_without_a_context_fails() {
+        let conn = projects();
+        let err = fetch_all_where::<Project>(&conn, projects::id.gt(0)).unwrap_err();
+        assert!(err.is_missing_tenant());
+    }
+
+    #[test]
+    fn test_tenant_comparison_does_not_bypass_the_context() {
+        let conn = projects();
+        conn.execute_batch("INSERT INTO projects VALUES (1, 'Website', 1), (2, 'Shop', 2)")
+            .unwrap();
+        let acme = TenantContext::new(1_i64);
+        let tenant_id = Column::<i64>::new("tenant_id");
+
+        let err = fetch_all_where::<Project>(&conn, tenant_id.eq(2)).unwrap_err();
+        assert!(err.is_missing_tenant());
+
+        let expr = acme.filter::<Project>(projects::id.gt(0)).or(tenant_id.eq(2));
+        let err = fetch_all_where::<Project>(&conn, expr).unwrap_err();
+        assert!(err.is_missing_tenant());
+
+        // Extra conditions on the tenant column only narrow the scoped query
+        let expr = acme.filter::<Project>(tenant_id.eq(2));
+        let projects: Vec<Project> = fetch_all_where(&conn, expr).unwrap();
+        assert!(projects.is_empty());
+    }
+}
@@ -2,12 +2,32 @@ use rusqlite::{
     types::{FromSql, ToSql},
     Error, Row,
 };
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::compose::Fragment;
+use crate::error::{check_version, UpdateError, WhereError};
+use crate::expr::Expr;
+
+pub use parsql_core::traits::{Columns, TenantScoped};
 
 /// Trait for generating SQL queries (for SELECT operations).
 /// This trait is implemented by the derive macro `Queryable`.
 pub trait SqlQuery<R> {
     /// Returns the SQL query string.
     fn query() -> String;
+
+    /// Returns the query and its parameters as a composable fragment,
+    /// to be used as a CTE, a subquery or a member of a `UNION ALL`.
+    ///
+    /// The SQL is scanned for placeholders on every call, so build the fragment once
+    /// instead of inside a loop.
+    fn fragment(&self) -> Fragment<'_>
+    where
+        Self: SqlParams + Sized,
+    {
+        Fragment::new(<Self as SqlQuery<R>>::query(), self.params())
+    }
 }
 
 /// Trait for generating SQL commands (for INSERT/UPDATE/DELETE operations).
@@ -31,6 +51,51 @@ pub trait UpdateParams {
     fn params(&self) -> Vec<&(dyn ToSql + Sync)>;
 }
 
+/// Trait for update models with optimistic locking.
+/// This trait is implemented by the derive macro `Updateable` when `#[version_column("...")]`
+/// is present; only such models can be passed to `update_versioned`.
+pub trait Versioned: SqlCommand {
+    /// Returns the column incremented by every update.
+    fn version_column() -> &'static str;
+}
+
+/// Trait for commands of soft-deleted tables.
+/// This trait is implemented by the derive macro `Deletable` when `#[soft_delete]` is present.
+pub trait SoftDelete {
+    /// Returns the SQL command that clears the deletion marker.
+    fn restore_query() -> String;
+
+    /// Returns the SQL command that removes the record permanently.
+    fn force_delete_query() -> String;
+}
+
+/// Trait for queries that skip soft-deleted records.
+/// This trait is implemented by the derive macro `Queryable` when `#[soft_delete]` is present.
+pub trait SoftDeleteQuery {
+    /// Returns the SQL query without the soft-delete filter.
+    fn query_with_deleted() -> String;
+}
+
+/// Runs a soft-delete aware query including the soft-deleted records,
+/// e.g. `fetch_all(&conn, &WithDeleted(query))`.
+#[derive(Debug, Clone)]
+pub struct WithDeleted<T>(pub T);
+
+impl<T, R> SqlQuery<R> for WithDeleted<T>
+where
+    T: SqlQuery<R> + SoftDeleteQuery,
+{
+    fn query() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams> SqlParams for WithDeleted<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0.params()
+    }
+}
+
 /// Trait for converting database rows to Rust structs.
 /// This trait is implemented by the derive macro `FromRow`.
 pub trait FromRow {
@@ -46,6 +111,54 @@ pub trait FromRow {
         Self: Sized;
 }
 
+/// Trait for batch loading records related to a model.
+/// This trait is implemented by the derive macro `FromRow` for every
+/// `#[has_many(...)]` and `#[belongs_to(...)]` declaration on the model.
+pub trait Relation<C> {
+    /// Type of the value that links the model to its related records.
+    type Key: ToSql + Eq + Hash + Clone;
+
+    /// Returns the SQL query that loads the related records for `count` keys.
+    fn relation_query(count: usize) -> String;
+
+    /// Returns the key of this record used to look up its related records;
+    /// `None` (a `NULL` foreign key) has no related records.
+    fn relation_key(&self) -> Option<Self::Key>;
+
+    /// Returns the key of a related record used to match it with its owner;
+    /// `None` matches no owner.
+    fn related_key(related: &C) -> Option<Self::Key>;
+
+    /// Stores the loaded related records on this record.
+    fn attach(&mut self, related: Vec<C>);
+
+    /// Distributes the loaded related records over the records they belong to.
+    ///
+    /// # Arguments
+    /// * `records` - The records the relation was loaded for
+    /// * `related` - All related records returned by the relation query
+    fn stitch(records: &mut [Self], related: Vec<C>)
+    where
+        Self: Sized,
+        C: Clone,
+    {
+        let mut grouped: HashMap<Self::Key, Vec<C>> = HashMap::new();
+        for item in related {
+            if let Some(key) = Self::related_key(&item) {
+                grouped.entry(key).or_default().push(item);
+            }
+        }
+
+        for record in records {
+            let related = record
+                .relation_key()
+                .and_then(|key| grouped.get(&key).cloned())
+                .unwrap_or_default();
+            record.attach(related);
+        }
+    }
+}
+
 /// CrudOps trait defines the CRUD (Create, Read, Update, Delete) operations
 /// that can be performed on a SQLite database.
 ///
@@ -119,6 +232,19 @@ pub trait CrudOps {
     /// * `Result<usize, Error>` - On success, returns the number of updated records; on failure, returns Error
     fn update<T: SqlCommand + UpdateParams>(&self, entity: T) -> Result<usize, Error>;
 
+    /// Updates a record of a model with `#[version_column("...")]`.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing the update information (must implement Versioned and UpdateParams traits)
+    ///
+    /// # Returns
+    /// * `Result<(), UpdateError>` - On failure, returns `UpdateError::StaleObject` when no row matched
+    ///   the expected version, or the database error
+    fn update_versioned<T: Versioned + UpdateParams>(&self, entity: T) -> Result<(), UpdateError> {
+        let affected_rows = self.update(entity)?;
+        check_version::<T>(affected_rows > 0)
+    }
+
     /// Deletes records from the SQLite database.
     ///
     /// # Arguments
@@ -128,6 +254,24 @@ pub trait CrudOps {
     /// * `Result<usize, Error>` - On success, returns the number of deleted records; on failure, returns Error
     fn delete<T: SqlCommand + SqlParams>(&self, entity: T) -> Result<usize, Error>;
 
+    /// Restores soft-deleted records by clearing their deletion marker.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Returns
+    /// * `Result<usize, Error>` - On success, returns the number of restored records; on failure, returns Error
+    fn restore<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error>;
+
+    /// Permanently deletes records of a soft-deleted table.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Returns
+    /// * `Result<usize, Error>` - On success, returns the number of deleted records; on failure, returns Error
+    fn force_delete<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error>;
+
     /// Retrieves a single record from the SQLite database.
     ///
     /// # Arguments
@@ -215,4 +359,36 @@ pub trait CrudOps {
     ) -> Result<Vec<R>, Error>
     where
         F: Fn(&Row) -> Result<R, Error>;
+
+    /// Loads the records related to the given records with a single query and attaches them.
+    ///
+    /// # Arguments
+    /// * `records` - Records previously returned by `fetch_all` (must implement Relation<C>)
+    ///
+    /// # Returns
+    /// * `Result<(), Error>` - On success, the relation field of every record is filled; on failure, returns Error
+    fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C>,
+        C: FromRow + Clone;
+
+    /// Retrieves the records of `R`'s table that match a typed WHERE expression.
+    ///
+    /// # Arguments
+    /// * `expr` - The condition, built from the column constants generated by `Columns`
+    ///
+    /// # Returns
+    /// * `Result<Vec<R>, WhereError>` - A vector of matching records or an error
+    fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow;
+
+    /// Retrieves all records returned by a composed query.
+    ///
+    /// # Arguments
+    /// * `fragment` - The query, built with `with_cte`, `union_all` or `in_subquery`
+    ///
+    /// # Returns
+    /// * `Result<Vec<R>, Error>` - A vector of records or an error
+    fn fetch_all_fragment<R: FromRow>(&self, fragment: &Fragment) -> Result<Vec<R>, Error>;
 }
@@ -2,8 +2,14 @@
 //!
 //! This module provides functions for performing CRUD operations within a transaction.
 
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
-use rusqlite::{types::FromSql, Connection, Error, Row, ToSql, Transaction};
+use crate::compose::Fragment;
+use crate::error::WhereError;
+use crate::expr::Expr;
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+};
+use rusqlite::{types::FromSql, Connection, Error, ToSql, Transaction};
 
 /// CrudOps trait implementasyonu Transaction<'_> için.
 impl<'a> CrudOps for Transaction<'a> {
@@ -52,13 +58,17 @@ impl<'a> CrudOps for Transaction<'a> {
         let sql = T::query();
         let params_vec = entity.params();
         let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-        let mut stmt = self.prepare(&sql)?;
-        let mut rows = stmt.query(param_refs.as_slice())?;
-        if let Some(row) = rows.next()? {
-            row.get(0)
-        } else {
-            Err(Error::QueryReturnedNoRows)
-        }
+        let observation = Observation::start::<T>(QueryKind::Insert, &sql, &param_refs);
+        let result = (|| {
+            let mut stmt = self.prepare(&sql)?;
+            let mut rows = stmt.query(param_refs.as_slice())?;
+            if let Some(row) = rows.next()? {
+                row.get(0)
+            } else {
+                Err(Error::QueryReturnedNoRows)
+            }
+        })();
+        observation.finish(result, |_| 1)
     }
 
     /// Updates a record in the database and returns the number of rows affected.
@@ -72,7 +82,7 @@ impl<'a> CrudOps for Transaction<'a> {
     ///
     /// # Example
     /// ```rust,no_run
-    /// use rusqlite::{Connection, Result};
+    /// use rusqlite::Connection;
     /// use parsql::sqlite::CrudOps;
     /// use parsql::sqlite::transactional;
     /// use parsql::macros::{Updateable, UpdateParams};
@@ -87,7 +97,7 @@ impl<'a> CrudOps for Transaction<'a> {
     ///     email: String,
     /// }
     ///
-    /// fn main() -> Result<()> {
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let conn = Connection::open("test.db")?;
     ///     let tx = transactional::begin(&conn)?;
     ///     
@@ -107,7 +117,9 @@ impl<'a> CrudOps for Transaction<'a> {
         let sql = T::query();
         let params_vec = entity.params();
         let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-        self.execute(&sql, param_refs.as_slice())
+        let observation = Observation::start::<T>(QueryKind::Update, &sql, &param_refs);
+        let result = self.execute(&sql, param_refs.as_slice());
+        observation.finish(result, |rows| *rows)
     }
 
     /// Deletes a record from the database and returns the number of rows affected.
@@ -149,7 +161,33 @@ impl<'a> CrudOps for Transaction<'a> {
         let sql = T::query();
         let params_vec = entity.params();
         let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-        self.execute(&sql, param_refs.as_slice())
+        let observation = Observation::start::<T>(QueryKind::Delete, &sql, &param_refs);
+        let result = self.execute(&sql, param_refs.as_slice());
+        observation.finish(result, |rows| *rows)
+    }
+
+    /// Restores soft-deleted records within the transaction.
+    /// This function is an extension to the Transaction struct and is available when the CrudOps trait is in scope.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Returns
+    /// * `Result<usize, Error>` - Number of affected rows or an error
+    fn restore<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error> {
+        crate::crud_ops::restore(self, entity)
+    }
+
+    /// Permanently deletes records of a soft-deleted table within the transaction.
+    /// This function is an extension to the Transaction struct and is available when the CrudOps trait is in scope.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Returns
+    /// * `Result<usize, Error>` - Number of affected rows or an error
+    fn force_delete<T: SoftDelete + SqlParams>(&self, entity: T) -> Result<usize, Error> {
+        crate::crud_ops::force_delete(self, entity)
     }
 
     /// Retrieves a single record from the database and converts it to a struct.
@@ -202,14 +240,17 @@ impl<'a> CrudOps for Transaction<'a> {
         let sql = P::query();
         let params_vec = params.params();
         let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-        let mut stmt = self.prepare(&sql)?;
-        let mut rows = stmt.query(param_refs.as_slice())?;
-        if let Some(row) = rows.next()? {
-            let result = R::from_row(row)?;
-            Ok(result)
-        } else {
-            Err(Error::QueryReturnedNoRows)
-        }
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &param_refs);
+        let result = (|| {
+            let mut stmt = self.prepare(&sql)?;
+            let mut rows = stmt.query(param_refs.as_slice())?;
+            if let Some(row) = rows.next()? {
+                R::from_row(row)
+            } else {
+                Err(Error::QueryReturnedNoRows)
+            }
+        })();
+        observation.finish(result, |_| 1)
     }
 
     /// Retrieves multiple records from the database and converts them to a vector of structs.
@@ -262,13 +303,13 @@ impl<'a> CrudOps for Transaction<'a> {
         let sql = P::query();
         let params_vec = params.params();
         let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-        let mut stmt = self.prepare(&sql)?;
-        let rows = stmt.query_map(param_refs.as_slice(), |row| R::from_row(row))?;
-        let mut results = Vec::new();
-        for row_result in rows {
-            results.push(row_result?);
-        }
-        Ok(results)
+        let observation = Observation::start::<P>(QueryKind::Select, &sql, &param_refs);
+        let result = (|| {
+            let mut stmt = self.prepare(&sql)?;
+            let rows = stmt.query_map(param_refs.as_slice(), |row| R::from_row(row))?;
+            rows.collect::<Result<Vec<R>, Error>>()
+        })();
+        observation.finish(result, Vec::len)
     }
 
     /// Executes a custom SELECT query and transforms the result using a provided function.
@@ -317,8 +358,9 @@ impl<'a> CrudOps for Transaction<'a> {
         let sql = T::query();
         let params_vec = entity.params();
         let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-        let mut stmt = self.prepare(&sql)?;
-        stmt.query_row(param_refs.as_slice(), to_model)
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &param_refs);
+        let result = self.query_row(&sql, param_refs.as_slice(), to_model);
+        observation.finish(result, |_| 1)
     }
 
     /// Executes a custom SELECT query and transforms all results using a provided function.
@@ -373,13 +415,56 @@ impl<'a> CrudOps for Transaction<'a> {
         let sql = T::query();
         let params_vec = entity.params();
         let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-        let mut stmt = self.prepare(&sql)?;
-        let rows = stmt.query_map(param_refs.as_slice(), to_model)?;
-        let mut results = Vec::new();
-        for row in rows {
-            results.push(row?);
-        }
-        Ok(results)
+        let observation = Observation::start::<T>(QueryKind::Select, &sql, &param_refs);
+        let result = (|| {
+            let mut stmt = self.prepare(&sql)?;
+            let rows = stmt.query_map(param_refs.as_slice(), to_model)?;
+            rows.collect::<Result<Vec<R>, Error>>()
+        })();
+        observation.finish(result, Vec::len)
+    }
+
+    /// Loads the records related to the given records within the transaction.
+    /// This function is an extension to the Transaction struct and is available when the CrudOps trait is in scope.
+    ///
+    /// # Arguments
+    /// * `records` - Records that implement Relation<C> for the related type
+    ///
+    /// # Returns
+    /// * `Result<(), Error>` - Success or an error
+    fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C>,
+        C: FromRow + Clone,
+    {
+        crate::crud_ops::preload(self, records)
+    }
+
+    /// Retrieves the records matching a typed WHERE expression within the transaction.
+    /// This function is an extension to the Transaction struct and is available when the CrudOps trait is in scope.
+    ///
+    /// # Arguments
+    /// * `expr` - The condition, built from the column constants generated by `Columns`
+    ///
+    /// # Returns
+    /// * `Result<Vec<R>, WhereError>` - A vector of matching records or an error
+    fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow,
+    {
+        crate::crud_ops::fetch_all_where(self, expr)
+    }
+
+    /// Retrieves all records returned by a composed query within the transaction.
+    /// This function is an extension to the Transaction struct and is available when the CrudOps trait is in scope.
+    ///
+    /// # Arguments
+    /// * `fragment` - The query, built with `with_cte`, `union_all` or `in_subquery`
+    ///
+    /// # Returns
+    /// * `Result<Vec<R>, Error>` - A vector of records or an error
+    fn fetch_all_fragment<R: FromRow>(&self, fragment: &Fragment) -> Result<Vec<R>, Error> {
+        crate::crud_ops::fetch_all_fragment(self, fragment)
     }
 }
 
@@ -463,7 +548,7 @@ pub fn tx_insert<'a, T: SqlCommand + SqlParams, P: FromSql + Send + Sync>(
 ///
 /// # Example
 /// ```rust,no_run
-/// use rusqlite::{Connection, Result};
+/// use rusqlite::Connection;
 /// use parsql::sqlite::transactional;
 /// use parsql::macros::{Updateable, UpdateParams};
 ///
@@ -477,7 +562,7 @@ pub fn tx_insert<'a, T: SqlCommand + SqlParams, P: FromSql + Send + Sync>(
 ///     email: String,
 /// }
 ///
-/// fn main() -> Result<()> {
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let conn = Connection::open("test.db")?;
 ///     let tx = transactional::begin(&conn)?;
 ///     
@@ -590,17 +675,7 @@ where
     P: SqlQuery<R> + SqlParams,
     R: FromRow,
 {
-    let sql = P::query();
-    let params_vec = params.params();
-    let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-    let mut stmt = tx.prepare(&sql)?;
-    let mut rows = stmt.query(param_refs.as_slice())?;
-    if let Some(row) = rows.next()? {
-        let result = R::from_row(row)?;
-        Ok(result)
-    } else {
-        Err(Error::QueryReturnedNoRows)
-    }
+    tx.fetch(params)
 }
 
 /// Fetches multiple records from the database within a transaction.
@@ -652,16 +727,7 @@ where
     P: SqlQuery<R> + SqlParams,
     R: FromRow,
 {
-    let sql = P::query();
-    let params_vec = params.params();
-    let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-    let mut stmt = tx.prepare(&sql)?;
-    let rows = stmt.query_map(param_refs.as_slice(), |row| R::from_row(row))?;
-    let mut results = Vec::new();
-    for row_result in rows {
-        results.push(row_result?);
-    }
-    Ok(results)
+    tx.fetch_all(params)
 }
 
 /// Gets a single record from the database within a transaction.
@@ -759,11 +825,7 @@ pub fn tx_select<'a, T: SqlQuery<T> + SqlParams, F, R>(
 where
     F: Fn(&rusqlite::Row) -> Result<R, Error>,
 {
-    let sql = T::query();
-    let params_vec = entity.params();
-    let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-    let mut stmt = tx.prepare(&sql)?;
-    stmt.query_row(param_refs.as_slice(), to_model)
+    tx.select(entity, to_model)
 }
 
 /// Execute a custom SELECT query within a transaction and transform all results.
@@ -778,14 +840,5 @@ pub fn tx_select_all<'a, T: SqlQuery<T> + SqlParams, F, R>(
 where
     F: Fn(&rusqlite::Row) -> Result<R, Error>,
 {
-    let sql = T::query();
-    let params_vec = entity.params();
-    let param_refs: Vec<&dyn ToSql> = params_vec.iter().map(|p| *p as &dyn ToSql).collect();
-    let mut stmt = tx.prepare(&sql)?;
-    let rows = stmt.query_map(param_refs.as_slice(), to_model)?;
-    let mut results = Vec::new();
-    for row in rows {
-        results.push(row?);
-    }
-    Ok(results)
+    tx.select_all(entity, to_model)
 }
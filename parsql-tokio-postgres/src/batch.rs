@@ -0,0 +1,552 @@
+//! Pipelined batches.
+//!
+//! Awaiting the CRUD functions one after another costs a round trip per statement.
+//! A [`Batch`] collects `insert`, `update`, `delete`, `fetch` and `fetch_all` calls and sends
+//! them together on one connection: tokio-postgres pipelines statements that are polled
+//! concurrently, so the batch waits for the slowest statement instead of the sum of all.
+//!
+//! Every call returns a typed [`Handle`] to its result in [`BatchResults`]. The statements
+//! are sent in the order they were added. [`Batch::run`] fails with the error of the first
+//! failing statement; without a transaction the statements before it stay applied.
+//!
+//! ```rust,no_run
+//! # use tokio_postgres::NoTls;
+//! use parsql_tokio_postgres::batch::batch;
+//! # use parsql_tokio_postgres::traits::{FromRow, SqlCommand, SqlParams, SqlQuery};
+//! # use parsql_tokio_postgres::*;
+//! #
+//! # #[derive(Insertable, SqlParams)]
+//! # #[table("events")]
+//! # #[returning("id")]
+//! # struct InsertEvent { name: String }
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams)]
+//! # #[table("users")]
+//! # #[where_clause("id = $")]
+//! # struct User { id: i64, name: String }
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams)]
+//! # #[table("orders")]
+//! # #[where_clause("user_id = $")]
+//! # struct Order { id: i64, user_id: i64 }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! # let (mut client, connection) = tokio_postgres::connect("", NoTls).await?;
+//! # tokio::spawn(async move { connection.await; });
+//! let mut page = batch();
+//! let user = page.fetch::<_, User>(User { id: 7, name: String::new() });
+//! let orders = page.fetch_all::<_, Order>(Order { id: 0, user_id: 7 });
+//! let event = page.insert::<_, i64>(InsertEvent { name: "profile_viewed".into() });
+//!
+//! // One round trip for the three statements
+//! let mut results = page.run(&client).await?;
+//! let user: User = results.take(user);
+//! let orders: Vec<Order> = results.take(orders);
+//! let event_id: i64 = results.take(event);
+//!
+//! // All or nothing
+//! let mut events = batch();
+//! events.insert::<_, i64>(InsertEvent { name: "a".into() });
+//! events.insert::<_, i64>(InsertEvent { name: "b".into() });
+//! events.run_in_transaction(&mut client).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::any::Any;
+use std::marker::PhantomData;
+
+use futures_util::future::join_all;
+use postgres::types::FromSql;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, Error, GenericClient, Row};
+
+use crate::error::{check_version, UpdateError};
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams, Versioned};
+
+/// Creates an empty batch.
+pub fn batch() -> Batch {
+    Batch::new()
+}
+
+/// Statements sent together on one connection, see the [module documentation](self).
+#[derive(Default)]
+pub struct Batch {
+    statements: Vec<Statement>,
+}
+
+/// The position and type of a statement's result in [`BatchResults`].
+pub struct Handle<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+/// The results of a batch, taken with the handles returned while building it.
+pub struct BatchResults {
+    values: Vec<Option<Box<dyn Any + Send>>>,
+}
+
+impl BatchResults {
+    /// Takes the result of a statement.
+    ///
+    /// # Panics
+    /// Panics if the handle belongs to another batch or its result was already taken.
+    pub fn take<T: 'static>(&mut self, handle: Handle<T>) -> T {
+        self.values
+            .get_mut(handle.index)
+            .and_then(Option::take)
+            .and_then(|value| value.downcast::<T>().ok())
+            .map(|value| *value)
+            .expect("the handle belongs to this batch and its result is taken once")
+    }
+}
+
+enum Mode {
+    One,
+    All,
+    Execute,
+}
+
+enum Output {
+    Row(Row),
+    Rows(Vec<Row>),
+    Affected(u64),
+}
+
+type Decode = Box<dyn FnOnce(Output) -> Result<Box<dyn Any + Send>, UpdateError> + Send + Sync>;
+
+trait Values: Send + Sync {
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)>;
+}
+
+struct Params<T>(T);
+
+impl<T: SqlParams + Send + Sync> Values for Params<T> {
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0.params()
+    }
+}
+
+struct Updates<T>(T);
+
+impl<T: UpdateParams + Send + Sync> Values for Updates<T> {
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0.params()
+    }
+}
+
+struct Statement {
+    kind: QueryKind,
+    entity: &'static str,
+    sql: String,
+    mode: Mode,
+    params: Box<dyn Values>,
+    decode: Decode,
+}
+
+impl Batch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of statements.
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    /// Returns `true` if no statement was added.
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// Adds an insert, returning the first column of the inserted row.
+    pub fn insert<T, P>(&mut self, entity: T) -> Handle<P>
+    where
+        T: SqlCommand + SqlParams + Send + Sync + 'static,
+        P: for<'a> FromSql<'a> + Send + 'static,
+    {
+        self.push::<T, P>(
+            QueryKind::Insert,
+            T::query(),
+            Mode::One,
+            Box::new(Params(entity)),
+            |output| Ok(row(output).try_get::<_, P>(0)?),
+        )
+    }
+
+    /// Adds an update, returning whether a row was updated.
+    pub fn update<T>(&mut self, entity: T) -> Handle<bool>
+    where
+        T: SqlCommand + UpdateParams + Send + Sync + 'static,
+    {
+        self.push::<T, bool>(
+            QueryKind::Update,
+            T::query(),
+            Mode::Execute,
+            Box::new(Updates(entity)),
+            |output| Ok(affected(output) > 0),
+        )
+    }
+
+    /// Adds an update of a model with `#[version_column("...")]`, which fails the batch with
+    /// `UpdateError::StaleObject` when no row matched the expected version.
+    pub fn update_versioned<T>(&mut self, entity: T) -> Handle<()>
+    where
+        T: Versioned + UpdateParams + Send + Sync + 'static,
+    {
+        self.push::<T, ()>(
+            QueryKind::Update,
+            T::query(),
+            Mode::Execute,
+            Box::new(Updates(entity)),
+            |output| check_version::<T>(affected(output) > 0),
+        )
+    }
+
+    /// Adds a delete, returning the number of deleted rows.
+    pub fn delete<T>(&mut self, entity: T) -> Handle<u64>
+    where
+        T: SqlCommand + SqlParams + Send + Sync + 'static,
+    {
+        self.push::<T, u64>(
+            QueryKind::Delete,
+            T::query(),
+            Mode::Execute,
+            Box::new(Params(entity)),
+            |output| Ok(affected(output)),
+        )
+    }
+
+    /// Adds a query returning exactly one row.
+    pub fn fetch<P, R>(&mut self, params: P) -> Handle<R>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
+        R: FromRow + Send + 'static,
+    {
+        self.push::<P, R>(
+            QueryKind::Select,
+            P::query(),
+            Mode::One,
+            Box::new(Params(params)),
+            |output| Ok(R::from_row(&row(output))?),
+        )
+    }
+
+    /// Adds a query returning all matching rows.
+    pub fn fetch_all<P, R>(&mut self, params: P) -> Handle<Vec<R>>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
+        R: FromRow + Send + 'static,
+    {
+        self.push::<P, Vec<R>>(
+            QueryKind::Select,
+            P::query(),
+            Mode::All,
+            Box::new(Params(params)),
+            |output| {
+                Ok(rows(output)
+                    .iter()
+                    .map(R::from_row)
+                    .collect::<Result<Vec<R>, Error>>()?)
+            },
+        )
+    }
+
+    /// Sends the statements pipelined on `client`, which may be a `Client` or a `Transaction`.
+    pub async fn run<C: GenericClient + Sync>(
+        self,
+        client: &C,
+    ) -> Result<BatchResults, UpdateError> {
+        let outputs = join_all(
+            self.statements
+                .iter()
+                .map(|statement| execute(client, statement)),
+        )
+        .await;
+
+        let mut values = Vec::with_capacity(outputs.len());
+        for (statement, output) in self.statements.into_iter().zip(outputs) {
+            values.push(Some((statement.decode)(output?)?));
+        }
+        Ok(BatchResults { values })
+    }
+
+    /// Sends the statements pipelined in a transaction, which is committed when all of them
+    /// succeed and rolled back otherwise.
+    pub async fn run_in_transaction(
+        self,
+        client: &mut Client,
+    ) -> Result<BatchResults, UpdateError> {
+        let transaction = client.transaction().await?;
+        // Dropping the transaction rolls it back
+        let results = self.run(&transaction).await?;
+        transaction.commit().await?;
+        Ok(results)
+    }
+
+    fn push<E: ?Sized, T: Send + 'static>(
+        &mut self,
+        kind: QueryKind,
+        sql: String,
+        mode: Mode,
+        params: Box<dyn Values>,
+        decode: impl FnOnce(Output) -> Result<T, UpdateError> + Send + Sync + 'static,
+    ) -> Handle<T> {
+        self.statements.push(Statement {
+            kind,
+            entity: std::any::type_name::<E>(),
+            sql,
+            mode,
+            params,
+            decode: Box::new(move |output| Ok(Box::new(decode(output)?) as Box<dyn Any + Send>)),
+        });
+        Handle {
+            index: self.statements.len() - 1,
+            marker: PhantomData,
+        }
+    }
+}
+
+async fn execute<C: GenericClient + Sync>(
+    client: &C,
+    statement: &Statement,
+) -> Result<Output, Error> {
+    let sql = statement.sql.as_str();
+    let params = statement.params.values();
+    let observation = Observation::start_named(statement.kind, statement.entity, sql, &params);
+    match statement.mode {
+        Mode::One => observation
+            .finish(client.query_one(sql, &params).await, |_| 1)
+            .map(Output::Row),
+        Mode::All => observation
+            .finish(client.query(sql, &params).await, |rows| rows.len() as u64)
+            .map(Output::Rows),
+        Mode::Execute => observation
+            .finish(client.execute(sql, &params).await, |rows| *rows)
+            .map(Output::Affected),
+    }
+}
+
+fn row(output: Output) -> Row {
+    match output {
+        Output::Row(row) => row,
+        _ => unreachable!("single-row statements return a row"),
+    }
+}
+
+fn rows(output: Output) -> Vec<Row> {
+    match output {
+        Output::Rows(rows) => rows,
+        _ => unreachable!("multi-row statements return rows"),
+    }
+}
+
+fn affected(output: Output) -> u64 {
+    match output {
+        Output::Affected(rows) => rows,
+        _ => unreachable!("commands return the number of affected rows"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use crate::traits::*;
+    use crate::*;
+    use tokio_postgres::error::SqlState;
+    use tokio_postgres::NoTls;
+
+    #[derive(Insertable, SqlParams)]
+    #[table("batch_items")]
+    #[returning("id")]
+    struct InsertItem {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Queryable, FromRow, SqlParams)]
+    #[table("batch_items")]
+    #[where_clause("id = $")]
+    struct Item {
+        id: i64,
+        name: String,
+        version: i64,
+    }
+
+    #[derive(Queryable, FromRow, SqlParams)]
+    #[table("batch_items")]
+    #[where_clause("id >= $")]
+    struct ItemsFrom {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("batch_items")]
+    #[where_clause("id = $")]
+    struct DeleteItem {
+        id: i64,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("batch_items")]
+    #[update("name")]
+    #[where_clause("id = $")]
+    #[version_column("version")]
+    struct RenameItem {
+        id: i64,
+        name: String,
+        version: i64,
+    }
+
+    const CREATE: &str = "CREATE TEMP TABLE batch_items (
+        id bigint PRIMARY KEY, name text NOT NULL, version bigint NOT NULL DEFAULT 1
+    )";
+
+    fn item(id: i64) -> Item {
+        Item { id, name: String::new(), version: 0 }
+    }
+
+    fn insert(id: i64, name: &str) -> InsertItem {
+        InsertItem { id, name: name.into() }
+    }
+
+    fn rename(name: &str, version: i64) -> RenameItem {
+        RenameItem { id: 1, name: name.into(), version }
+    }
+
+    async fn count(client: &impl GenericClient) -> i64 {
+        client
+            .query_one("SELECT count(*) FROM batch_items", &[])
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    #[test]
+    fn test_handles_index_the_statements() {
+        let mut page = batch();
+        assert!(page.is_empty());
+        let first = page.delete(DeleteItem { id: 1 });
+        let second = page.fetch::<_, Item>(item(1));
+
+        assert_eq!((first.index, second.index), (0, 1));
+        assert_eq!(page.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "its result is taken once")]
+    fn test_result_is_taken_once() {
+        let handle = Handle::<u64> { index: 0, marker: PhantomData };
+        let mut results = BatchResults { values: vec![Some(Box::new(3_u64))] };
+
+        assert_eq!(results.take(handle), 3);
+        results.take(handle);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_handles_map_to_their_results() {
+        let mut client = TestClient::from_env().await;
+        let tx = client.begin().await.unwrap();
+        tx.batch_execute(CREATE).await.unwrap();
+
+        let mut page = batch();
+        let ada = page.insert::<_, i64>(insert(1, "Ada"));
+        let alan = page.insert::<_, i64>(insert(2, "Alan"));
+        let fetched = page.fetch::<_, Item>(item(2));
+        let all = page.fetch_all::<_, ItemsFrom>(ItemsFrom { id: 1, name: String::new() });
+        let deleted = page.delete(DeleteItem { id: 1 });
+        let mut results = page.run(&tx).await.unwrap();
+
+        // Taken in any order, each handle returns its own statement's result
+        assert_eq!(results.take(deleted), 1);
+        assert_eq!(results.take(fetched).name, "Alan");
+        assert_eq!(results.take(alan), 2);
+        assert_eq!(results.take(ada), 1);
+        let mut names: Vec<_> = results.take(all).into_iter().map(|item| item.name).collect();
+        names.sort();
+        assert_eq!(names, ["Ada", "Alan"]);
+        assert_eq!(count(&tx).await, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_run_fails_with_the_first_error() {
+        // Outside a transaction, so that a failing statement leaves the others applied
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let (client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+        client.batch_execute(CREATE).await.unwrap();
+
+        let mut page = batch();
+        page.insert::<_, i64>(insert(1, "Ada"));
+        page.insert::<_, i64>(insert(1, "Ada again"));
+        page.fetch::<_, Item>(item(42));
+        page.insert::<_, i64>(insert(2, "Alan"));
+        let err = page.run(&client).await.err().expect("the batch fails");
+
+        let UpdateError::Database(err) = err else {
+            panic!("expected a database error, got {:?}", err);
+        };
+        assert_eq!(err.code(), Some(&SqlState::UNIQUE_VIOLATION));
+        assert_eq!(count(&client).await, 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_run_in_transaction_rolls_back_on_error() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let (mut client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+        client.batch_execute(CREATE).await.unwrap();
+
+        let mut page = batch();
+        page.insert::<_, i64>(insert(1, "Ada"));
+        page.insert::<_, i64>(insert(1, "Ada again"));
+        assert!(page.run_in_transaction(&mut client).await.is_err());
+        assert_eq!(count(&client).await, 0);
+
+        let mut page = batch();
+        page.insert::<_, i64>(insert(1, "Ada"));
+        page.run_in_transaction(&mut client).await.unwrap();
+        assert_eq!(count(&client).await, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_update_versioned_reports_a_stale_object() {
+        let mut client = TestClient::from_env().await;
+        let tx = client.begin().await.unwrap();
+        tx.batch_execute(CREATE).await.unwrap();
+        tx.batch_execute("INSERT INTO batch_items (id, name) VALUES (1, 'Ada')")
+            .await
+            .unwrap();
+
+        let mut page = batch();
+        let renamed = page.update_versioned(rename("Ada L.", 1));
+        let mut results = page.run(&tx).await.unwrap();
+        results.take(renamed);
+
+        // The version is now 2, so an update based on version 1 is stale
+        let mut page = batch();
+        page.update_versioned(rename("Ada B.", 1));
+        let err = page.run(&tx).await.err().expect("the update is stale");
+        assert!(err.is_stale(), "{:?}", err);
+
+        let row = tx.query_one("SELECT name, version FROM batch_items", &[]).await.unwrap();
+        assert_eq!(row.get::<_, String>(0), "Ada L.");
+        assert_eq!(row.get::<_, i64>(1), 2);
+    }
+}
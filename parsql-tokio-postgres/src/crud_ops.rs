@@ -1,6 +1,13 @@
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use crate::compose::Fragment;
+use crate::error::{check_version, UpdateError, WhereError};
+use crate::expr::Expr;
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+    Versioned,
+};
 use postgres::types::{FromSql, ToSql};
-use std::sync::OnceLock;
+use std::collections::HashSet;
 use tokio_postgres::{Client, Error, Row, Transaction};
 
 #[async_trait::async_trait]
@@ -26,6 +33,20 @@ impl CrudOps for Client {
         delete(self, entity).await
     }
 
+    async fn restore<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        restore(self, entity).await
+    }
+
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        force_delete(self, entity).await
+    }
+
     async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
@@ -59,6 +80,28 @@ impl CrudOps for Client {
     {
         select_all(self, entity, to_model).await
     }
+
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C> + Send + Sync + 'static,
+        C: FromRow + Clone + Send + Sync + 'static,
+    {
+        preload(self, records).await
+    }
+
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow + Send + Sync + 'static,
+    {
+        fetch_all_where(self, expr).await
+    }
+
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, Error>
+    where
+        R: FromRow + Send + Sync + 'static,
+    {
+        fetch_all_fragment(self, fragment).await
+    }
 }
 
 /// # insert
@@ -80,16 +123,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = client.query_one(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+    let row = observation.finish(client.query_one(&sql, &params).await, |_| 1)?;
     row.try_get::<_, P>(0)
 }
 
@@ -102,26 +138,73 @@ where
 /// - `entity`: Data object containing the update information (must implement SqlQuery and UpdateParams traits)
 ///
 /// ## Return Value
-/// - `Result<bool, Error>`: On success, returns true; on failure, returns Error
+/// - `Result<bool, Error>`: On success, returns true if a record was updated; on failure, returns Error.
+///   Models with `#[version_column("...")]` should use [`update_versioned`] to detect conflicts.
 pub async fn update<T>(client: &Client, entity: T) -> Result<bool, Error>
 where
     T: SqlCommand + UpdateParams + Send + Sync + 'static,
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = client.execute(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    let result = observation.finish(client.execute(&sql, &params).await, |rows| *rows)?;
     Ok(result > 0)
 }
 
+/// # update_versioned
+///
+/// Updates a record of a model with `#[version_column("...")]` using optimistic locking.
+///
+/// The generated SQL matches the version read with the record and increments it. When no row
+/// matches, the record was changed or deleted since it was read and the update fails with
+/// `UpdateError::StaleObject`.
+///
+/// ## Parameters
+/// - `client`: Database connection client
+/// - `entity`: Data object containing the update information (must implement Versioned and UpdateParams traits)
+///
+/// ## Return Value
+/// - `Result<(), UpdateError>`: On failure, returns `UpdateError::StaleObject` or the database error.
+///
+/// ## Example Usage
+/// ```rust,no_run
+/// use tokio_postgres::{NoTls, Client};
+/// use parsql_tokio_postgres::traits::*;
+/// use parsql_tokio_postgres::*;
+///
+/// #[derive(Updateable, UpdateParams)]
+/// #[table("accounts")]
+/// #[update("balance")]
+/// #[where_clause("id = $")]
+/// #[version_column("version")]
+/// pub struct UpdateAccount {
+///     pub id: i64,
+///     pub balance: i64,
+///     pub version: i64,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let (client, connection) = tokio_postgres::connect("host=localhost user=postgres", NoTls).await?;
+///     tokio::spawn(async move { connection.await });
+///
+///     let account = UpdateAccount { id: 1, balance: 50, version: 3 };
+///     match update_versioned(&client, account).await {
+///         Err(err) if err.is_stale() => println!("Reload the account and try again"),
+///         result => result?,
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn update_versioned<T>(client: &Client, entity: T) -> Result<(), UpdateError>
+where
+    T: Versioned + UpdateParams + Send + Sync + 'static,
+{
+    let updated = update(client, entity).await?;
+    check_version::<T>(updated)
+}
+
 /// # delete
 ///
 /// Deletes a record from the database.
@@ -138,16 +221,51 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(client.execute(&sql, &params).await, |rows| *rows)
+}
 
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
+/// # restore
+///
+/// Restores soft-deleted records by clearing their deletion marker.
+///
+/// ## Parameters
+/// - `client`: Database connection object
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<u64, Error>`: On success, returns the number of restored records; on failure, returns Error
+pub async fn restore<T>(client: &Client, entity: T) -> Result<u64, Error>
+where
+    T: SoftDelete + SqlParams + Send + Sync + 'static,
+{
+    let sql = T::restore_query();
+
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    observation.finish(client.execute(&sql, &params).await, |rows| *rows)
+}
+
+/// # force_delete
+///
+/// Permanently deletes records of a table that uses soft delete.
+///
+/// ## Parameters
+/// - `client`: Database connection object
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<u64, Error>`: On success, returns the number of deleted records; on failure, returns Error
+pub async fn force_delete<T>(client: &Client, entity: T) -> Result<u64, Error>
+where
+    T: SoftDelete + SqlParams + Send + Sync + 'static,
+{
+    let sql = T::force_delete_query();
 
     let params = entity.params();
-    client.execute(&sql, &params).await
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(client.execute(&sql, &params).await, |rows| *rows)
 }
 
 /// # fetch
@@ -167,16 +285,9 @@ where
 {
     let sql = P::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let row = client.query_one(&sql, &query_params).await?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let row = observation.finish(client.query_one(&sql, &query_params).await, |_| 1)?;
     R::from_row(&row)
 }
 
@@ -197,16 +308,12 @@ where
 {
     let sql = P::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let rows = client.query(&sql, &query_params).await?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let rows = observation.finish(
+        client.query(&sql, &query_params).await,
+        |rows| rows.len() as u64,
+    )?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -236,16 +343,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = client.query_one(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let row = observation.finish(client.query_one(&sql, &params).await, |_| 1)?;
     to_model(&row)
 }
 
@@ -269,16 +369,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let rows = client.query(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params).await, |rows| rows.len() as u64)?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -335,3 +428,185 @@ where
 {
     fetch_all(client, params).await
 }
+
+/// Loads the records related to a list of records with a single `= ANY($1)` query
+/// and attaches them, avoiding one query per record (N+1).
+///
+/// # Arguments
+/// * `client` - Database client
+/// * `records` - Records previously returned by `fetch_all` (must implement Relation<C>)
+///
+/// # Return Value
+/// * `Result<(), Error>` - On success, the relation field of every record is filled; on failure, returns Error
+///
+/// # Example
+/// Relations are declared on the model with the `has_many` or `belongs_to` attributes
+/// of the `FromRow` derive macro; a nullable foreign key is an `Option` field:
+///
+/// ```rust
+/// use parsql_tokio_postgres::traits::*;
+/// use parsql_tokio_postgres::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("orders")]
+/// #[select("id, customer_id")]
+/// #[where_clause("customer_id = $")]
+/// #[result_type("Order")]
+/// pub struct OrdersByCustomer {
+///     pub customer_id: i64,
+/// }
+///
+/// #[derive(FromRow, Debug)]
+/// #[has_many(OrderItem, foreign_key = "order_id", field = "items")]
+/// pub struct Order {
+///     pub id: i64,
+///     pub customer_id: i64,
+///     pub items: Vec<OrderItem>,    // Filled by preload
+/// }
+///
+/// #[derive(FromRow, Clone, Debug)]
+/// pub struct OrderItem {
+///     pub id: i64,
+///     pub order_id: Option<i64>,
+///     pub product: String,
+/// }
+///
+/// async fn orders_with_items(client: &Client, customer_id: i64) -> Result<Vec<Order>, Error> {
+///     let mut orders: Vec<Order> = fetch_all(client, OrdersByCustomer { customer_id }).await?;
+///     preload::<Order, OrderItem>(client, &mut orders).await?;
+///     Ok(orders)
+/// }
+/// # fn main() {}
+/// ```
+pub async fn preload<R, C>(client: &Client, records: &mut [R]) -> Result<(), Error>
+where
+    R: Relation<C> + Send + Sync + 'static,
+    C: FromRow + Clone + Send + Sync + 'static,
+{
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    // Each distinct key is sent only once; records without a key have no related rows
+    let mut seen = HashSet::new();
+    let keys: Vec<R::Key> = records
+        .iter()
+        .filter_map(|record| record.relation_key())
+        .filter(|key| seen.insert(key.clone()))
+        .collect();
+
+    let sql = R::relation_query();
+
+    let observation = Observation::start::<C>(QueryKind::Select, &sql, &[&keys]);
+    let rows = observation.finish(client.query(&sql, &[&keys]).await, |rows| rows.len() as u64)?;
+
+    let mut related = Vec::with_capacity(rows.len());
+    for row in rows {
+        related.push(C::from_row(&row)?);
+    }
+
+    R::stitch(records, related);
+    Ok(())
+}
+
+/// # fetch_all_where
+///
+/// Retrieves the records of a table that match a typed WHERE expression.
+///
+/// ## Parameters
+/// - `client`: Database connection object
+/// - `expr`: The condition, built from the column constants generated by the `Columns` derive
+///
+/// ## Return Value
+/// - `Result<Vec<R>, WhereError>`: On success, returns the matching records; on failure, returns WhereError
+///
+/// Like the `Queryable` queries of the model, rows marked by `#[soft_delete]` are skipped, and
+/// a `#[tenant_scoped]` model needs the condition to be restricted with
+/// `TenantContext::filter`; otherwise the query is not run and fails with
+/// `WhereError::MissingTenant`.
+///
+/// ## Example Usage
+///
+/// ```rust
+/// use parsql_tokio_postgres::traits::*;
+/// use parsql_tokio_postgres::*;
+///
+/// #[derive(Columns, FromRow, Debug)]
+/// #[table("users")]
+/// #[soft_delete]
+/// pub struct User {
+///     pub id: i64,
+///     pub email: String,
+///     pub state: i16,
+/// }
+///
+/// async fn active_users(client: &Client) -> Result<Vec<User>, WhereError> {
+///     let expr = users::state.eq(1).and(users::email.ilike("%@example.com"));
+///     fetch_all_where::<User>(client, expr).await
+/// }
+/// # fn main() {}
+/// ```
+pub async fn fetch_all_where<R>(client: &Client, expr: Expr) -> Result<Vec<R>, WhereError>
+where
+    R: Columns + FromRow + Send + Sync + 'static,
+{
+    let (sql, params) = expr.select::<R>()?;
+
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params).await, |rows| rows.len() as u64)?;
+    Ok(rows.iter().map(R::from_row).collect::<Result<_, _>>()?)
+}
+
+/// # fetch_all_fragment
+///
+/// Retrieves all records returned by a composed query.
+///
+/// ## Parameters
+/// - `client`: Database connection object
+/// - `fragment`: The query, usually built from `Queryable` structs with `SqlQuery::fragment`
+///
+/// ## Return Value
+/// - `Result<Vec<R>, Error>`: On success, returns the records; on failure, returns Error
+///
+/// ## Example Usage
+/// ```rust,no_run
+/// # use tokio_postgres::NoTls;
+/// use parsql_tokio_postgres::compose::Fragment;
+/// use parsql_tokio_postgres::traits::*;
+/// use parsql_tokio_postgres::*;
+///
+/// #[derive(Queryable, SqlParams)]
+/// #[table("orders")]
+/// #[select("customer_id")]
+/// #[where_clause("total >= $")]
+/// #[result_type("Customer")]
+/// struct RecentOrders {
+///     min_total: i64,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct Customer {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (client, connection) = tokio_postgres::connect("", NoTls).await?;
+/// # tokio::spawn(async move { connection.await; });
+/// let recent = RecentOrders { min_total: 100 };
+/// let query = Fragment::raw("SELECT id, name FROM customers")
+///     .in_subquery("id", recent.fragment());
+/// let customers: Vec<Customer> = fetch_all_fragment(&client, &query).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn fetch_all_fragment<R>(client: &Client, fragment: &Fragment<'_>) -> Result<Vec<R>, Error>
+where
+    R: FromRow + Send + Sync + 'static,
+{
+    let (sql, params) = fragment.build();
+
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(client.query(&sql, &params).await, |rows| rows.len() as u64)?;
+    rows.iter().map(R::from_row).collect()
+}
@@ -0,0 +1,177 @@
+//! Error types returned by update operations, typed WHERE queries and timed queries.
+
+use tokio_postgres::Error;
+use parsql_core::tenant::MissingTenant;
+use std::fmt;
+use std::time::Duration;
+
+/// Error returned by `update_versioned`.
+///
+/// Besides database errors, versioned updates fail with [`UpdateError::StaleObject`] when no
+/// row matched the expected version, i.e. the record was changed or deleted since it was read.
+/// Plain `update` calls return the database `Error` directly.
+#[derive(Debug)]
+pub enum UpdateError {
+    /// The database returned an error
+    Database(Error),
+    /// The versioned update matched no rows
+    StaleObject {
+        /// Type name of the update model
+        entity: &'static str,
+    },
+}
+
+impl UpdateError {
+    /// Returns `true` if the record was changed concurrently.
+    pub fn is_stale(&self) -> bool {
+        matches!(self, UpdateError::StaleObject { .. })
+    }
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::Database(err) => write!(f, "Database error: {}", err),
+            UpdateError::StaleObject { entity } => write!(
+                f,
+                "Stale object: {} was modified or deleted by another transaction",
+                entity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UpdateError::Database(err) => Some(err),
+            UpdateError::StaleObject { .. } => None,
+        }
+    }
+}
+
+impl From<Error> for UpdateError {
+    fn from(err: Error) -> Self {
+        UpdateError::Database(err)
+    }
+}
+
+/// Error returned by `fetch_all_where`.
+///
+/// Besides database errors, the query fails with [`WhereError::MissingTenant`] when the model
+/// is `#[tenant_scoped]` and the condition was not restricted with `TenantContext::filter`.
+#[derive(Debug)]
+pub enum WhereError {
+    /// The database returned an error
+    Database(Error),
+    /// The model is tenant scoped and the condition has no tenant
+    MissingTenant {
+        /// Type name of the model
+        entity: &'static str,
+    },
+}
+
+impl WhereError {
+    /// Returns `true` if the query was rejected because no tenant was given.
+    pub fn is_missing_tenant(&self) -> bool {
+        matches!(self, WhereError::MissingTenant { .. })
+    }
+}
+
+impl fmt::Display for WhereError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WhereError::Database(err) => write!(f, "Database error: {}", err),
+            WhereError::MissingTenant { entity } => write!(
+                f,
+                "Missing tenant: {} is tenant scoped and the condition was not restricted \
+                 with TenantContext::filter",
+                entity
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WhereError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WhereError::Database(err) => Some(err),
+            WhereError::MissingTenant { .. } => None,
+        }
+    }
+}
+
+impl From<Error> for WhereError {
+    fn from(err: Error) -> Self {
+        WhereError::Database(err)
+    }
+}
+
+impl From<MissingTenant> for WhereError {
+    fn from(err: MissingTenant) -> Self {
+        WhereError::MissingTenant { entity: err.entity }
+    }
+}
+
+/// Error returned by queries run with a timeout, see [`crate::timeout`].
+#[derive(Debug)]
+pub enum QueryError<E = Error> {
+    /// The query did not finish in time and was cancelled
+    Timeout(Duration),
+    /// The query failed
+    Query(E),
+}
+
+impl<E> QueryError<E> {
+    /// Returns `true` if the query was cancelled because it did not finish in time.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, QueryError::Timeout(_))
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for QueryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Timeout(after) => write!(f, "Query timed out after {:?}", after),
+            QueryError::Query(err) => write!(f, "Query failed: {}", err),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for QueryError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QueryError::Timeout(_) => None,
+            QueryError::Query(err) => Some(err),
+        }
+    }
+}
+
+impl From<Error> for QueryError<Error> {
+    fn from(err: Error) -> Self {
+        QueryError::Query(err)
+    }
+}
+
+impl From<WhereError> for QueryError<WhereError> {
+    fn from(err: WhereError) -> Self {
+        QueryError::Query(err)
+    }
+}
+
+impl From<UpdateError> for QueryError<UpdateError> {
+    fn from(err: UpdateError) -> Self {
+        QueryError::Query(err)
+    }
+}
+
+/// Turns a versioned update that matched no rows into [`UpdateError::StaleObject`].
+pub(crate) fn check_version<T: crate::traits::Versioned>(updated: bool) -> Result<(), UpdateError> {
+    if updated {
+        Ok(())
+    } else {
+        Err(UpdateError::StaleObject {
+            entity: std::any::type_name::<T>(),
+        })
+    }
+}
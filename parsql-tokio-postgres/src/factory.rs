@@ -0,0 +1,144 @@
+//! Seed data factories for tests.
+//!
+//! `#[derive(Factory)]` on an `Insertable` struct generates `<Struct>Factory`, which
+//! implements [`Factory`]: every entity it builds gets the next sequence number of the
+//! factory, so defaults such as `name-1`, `name-2` stay unique, and `with_<field>` sets a
+//! field for all of them. [`FactoryExt`] inserts the entities with [`CrudOps::insert`], so
+//! `create` returns the `#[returning]` column, which associates the rows of another factory
+//! with it:
+//!
+//! ```rust,no_run
+//! use parsql_tokio_postgres::traits::*;
+//! use parsql_tokio_postgres::*;
+//! use tokio_postgres::NoTls;
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("users")]
+//! #[returning("id")]
+//! #[factory(name = "UserFactory")]
+//! struct InsertUser {
+//!     name: String,
+//!     #[factory(sequence = "user{n}@example.com")]
+//!     email: String,
+//! }
+//!
+//! #[derive(Insertable, SqlParams, Factory)]
+//! #[table("posts")]
+//! #[returning("id")]
+//! #[factory(name = "PostFactory")]
+//! struct InsertPost {
+//!     user_id: i64,
+//!     title: String,
+//! }
+//!
+//! # async fn run() -> Result<(), tokio_postgres::Error> {
+//! let (client, connection) = tokio_postgres::connect("host=localhost user=postgres", NoTls).await?;
+//! tokio::spawn(connection);
+//! let user_id: i64 = UserFactory::new().with_name("Ada").create(&client).await?;
+//! let post_ids: Vec<i64> = PostFactory::new()
+//!     .with_user_id(user_id)
+//!     .create_many(&client, 3)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use futures_util::future::join_all;
+use tokio_postgres::{types::FromSql, Error};
+
+use crate::traits::{CrudOps, SqlCommand, SqlParams};
+
+pub use parsql_core::factory::Factory;
+
+/// Inserts the entities of a [`Factory`]; implemented for every factory of `Insertable`
+/// entities.
+#[async_trait::async_trait]
+pub trait FactoryExt: Factory + Sync {
+    /// Inserts the next entity and returns its `#[returning]` column.
+    async fn create<C, P>(&self, conn: &C) -> Result<P, Error>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync;
+
+    /// Inserts `count` entities and returns their `#[returning]` columns, in order.
+    ///
+    /// The inserts are sent together and pipelined on the connection, so they take one round
+    /// trip. They are not wrapped in a transaction: the first error is returned but the other
+    /// rows stay in place, so pass a transaction to insert them atomically.
+    async fn create_many<C, P>(&self, conn: &C, count: usize) -> Result<Vec<P>, Error>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync;
+}
+
+#[async_trait::async_trait]
+impl<F> FactoryExt for F
+where
+    F: Factory + Sync + ?Sized,
+    F::Entity: SqlCommand + SqlParams + Send + Sync + 'static,
+{
+    async fn create<C, P>(&self, conn: &C) -> Result<P, Error>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        conn.insert(self.build()).await
+    }
+
+    async fn create_many<C, P>(&self, conn: &C, count: usize) -> Result<Vec<P>, Error>
+    where
+        C: CrudOps + Sync + ?Sized,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        let inserts = self
+            .build_many(count)
+            .into_iter()
+            .map(|entity| conn.insert(entity));
+        join_all(inserts).await.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TestClient;
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams, Factory)]
+    #[table("factory_users")]
+    #[returning("email")]
+    #[factory(name = "UserFactory")]
+    struct InsertUser {
+        #[factory(sequence = "user{n}@example.com")]
+        email: String,
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_create_many_returns_the_rows_in_order() {
+        let mut client = TestClient::from_env().await;
+        let tx = client.begin().await.unwrap();
+        tx.batch_execute("CREATE TEMP TABLE factory_users (email text PRIMARY KEY)")
+            .await
+            .unwrap();
+
+        let emails: Vec<String> = UserFactory::new().create_many(&tx, 20).await.unwrap();
+        let first: u64 = emails[0]
+            .trim_start_matches("user")
+            .trim_end_matches("@example.com")
+            .parse()
+            .unwrap();
+        let expected: Vec<_> = (first..first + 20)
+            .map(|n| format!("user{}@example.com", n))
+            .collect();
+        assert_eq!(emails, expected);
+
+        // A duplicate email fails the whole call
+        let err = UserFactory::new()
+            .with_email(emails[3].clone())
+            .create_many::<_, String>(&tx, 2)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Some(&tokio_postgres::error::SqlState::UNIQUE_VIOLATION));
+    }
+}
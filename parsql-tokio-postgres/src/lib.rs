@@ -101,7 +101,21 @@
 //! }
 //! ```
 
+pub mod batch;
+pub mod compose;
 pub mod crud_ops;
+pub mod error;
+pub mod expr;
+pub mod factory;
+pub mod mock;
+pub mod observer;
+pub use parsql_core::stats;
+pub mod retry;
+pub mod rls;
+pub mod search;
+pub mod tenant;
+pub mod testing;
+pub mod timeout;
 pub mod traits;
 pub mod macros;
 
@@ -161,13 +175,31 @@ pub use macros::*;
 pub use crate::crud_ops::{
     insert,
     update,
+    update_versioned,
     delete,
+    restore,
+    force_delete,
     fetch,
     fetch_all,
     select,
-    select_all
+    select_all,
+    preload,
+    fetch_all_where,
+    fetch_all_fragment
 };
 
+pub use batch::Batch;
+pub use compose::Fragment;
+pub use error::{QueryError, UpdateError, WhereError};
+pub use factory::{Factory, FactoryExt};
+pub use observer::{set_observer, QueryEvent, QueryObserver};
+
+pub use stats::QueryStats;
+pub use retry::{Reconnecting, RetryPolicy};
+
+pub use tenant::{Scoped, TenantContext};
+pub use expr::{Column, Expr, IntoValue};
+
 // Geriye dönük uyumluluk için eski fonksiyonları deprecated olarak dışa aktaralım
 #[allow(deprecated)]
 pub use crate::crud_ops::{
@@ -0,0 +1,699 @@
+//! A mock executor for unit tests of code written against [`CrudOps`].
+//!
+//! [`MockExecutor`] implements [`CrudOps`] without a database. Every call is recorded with
+//! the SQL the real executor would send and its parameters formatted with `{:?}`, and is
+//! answered by the first matching expectation registered with
+//! [`MockExecutor::expect_query`]. A call that no expectation matches panics with its SQL,
+//! so unexpected queries fail the test.
+//!
+//! Expectations are keyed by the query or command type: the `params` of `fetch`,
+//! `fetch_all`, `select` and `select_all`, the `entity` of `insert`, `update`, `delete`,
+//! `restore` and `force_delete`, and the row type `R` of `fetch_all_where` and
+//! `fetch_all_fragment` and `C` of `preload`, which have no query type.
+//!
+//! ```rust
+//! use parsql_tokio_postgres::mock::MockExecutor;
+//! use parsql_tokio_postgres::traits::*;
+//! use parsql_tokio_postgres::*;
+//!
+//! #[derive(Queryable, SqlParams)]
+//! #[table("users")]
+//! #[where_clause("id = $")]
+//! #[result_type("User")]
+//! struct GetUser {
+//!     id: i64,
+//! }
+//!
+//! #[derive(Clone, Debug, PartialEq, FromRow)]
+//! struct User {
+//!     id: i64,
+//!     name: String,
+//! }
+//!
+//! // The service under test only knows `CrudOps`
+//! async fn user_name(db: &impl CrudOps, id: i64) -> Result<String, Error> {
+//!     let user: User = db.fetch(GetUser { id }).await?;
+//!     Ok(user.name)
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let db = MockExecutor::new();
+//! db.expect_query::<GetUser>()
+//!     .with_params(["7"])
+//!     .returning(User { id: 7, name: "Ada".into() });
+//!
+//! assert_eq!(user_name(&db, 7).await.unwrap(), "Ada");
+//!
+//! let calls = db.calls_of::<GetUser>();
+//! assert_eq!(calls[0].method, "fetch");
+//! assert!(calls[0].sql.contains("WHERE id = $1"));
+//! db.verify();
+//! # }
+//! ```
+//!
+//! [`CrudOps`]: crate::traits::CrudOps
+
+use std::any::{type_name, Any};
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+
+use bytes::BytesMut;
+use postgres::types::{FromSql, IsNull, ToSql, Type};
+use tokio_postgres::{Error, Row};
+
+use crate::compose::Fragment;
+use crate::error::WhereError;
+use crate::expr::Expr;
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+};
+
+/// A call recorded by a [`MockExecutor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    /// The `CrudOps` method, e.g. `"fetch"`
+    pub method: &'static str,
+    /// Type name of the query, command or row type the call is keyed by
+    pub type_name: &'static str,
+    /// The SQL the real executor would send
+    pub sql: String,
+    /// The parameters, formatted with `{:?}`
+    pub params: Vec<String>,
+}
+
+/// The answer of an expectation.
+enum Reply {
+    /// Clones a value of the type the method returns
+    Value {
+        make: Box<dyn Fn() -> Box<dyn Any + Send> + Send + Sync>,
+        type_name: &'static str,
+    },
+    /// A value decoded into the type `insert` returns
+    Id(Box<dyn ToSql + Send + Sync>),
+    /// An error, returned once
+    Error(Option<Error>),
+}
+
+struct Expectation {
+    type_name: &'static str,
+    params: Option<Vec<String>>,
+    times: Option<usize>,
+    used: usize,
+    reply: Option<Reply>,
+}
+
+impl Expectation {
+    fn matches(&self, call: &MockCall) -> bool {
+        // An error is returned once, whatever `times` says
+        let exhausted = matches!(self.reply, Some(Reply::Error(None)))
+            || self.times.is_some_and(|times| self.used >= times);
+        self.type_name == call.type_name
+            && !exhausted
+            && self
+                .params
+                .as_ref()
+                .is_none_or(|params| *params == call.params)
+    }
+
+    fn describe(&self) -> String {
+        match &self.params {
+            Some(params) => format!("{} with params {:?}", self.type_name, params),
+            None => self.type_name.to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    calls: Vec<MockCall>,
+    expectations: Vec<Expectation>,
+}
+
+/// A [`CrudOps`] implementation that records calls and answers them with scripted values.
+///
+/// [`CrudOps`]: crate::traits::CrudOps
+#[derive(Default)]
+pub struct MockExecutor {
+    state: Mutex<State>,
+}
+
+impl MockExecutor {
+    /// Creates an executor without expectations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an expectation for calls keyed by `T` and returns it to script its reply.
+    ///
+    /// Expectations are tried in the order they were registered.
+    pub fn expect_query<T: ?Sized>(&self) -> MockExpectation<'_> {
+        let mut state = self.state();
+        state.expectations.push(Expectation {
+            type_name: type_name::<T>(),
+            params: None,
+            times: None,
+            used: 0,
+            reply: None,
+        });
+        MockExpectation {
+            mock: self,
+            index: state.expectations.len() - 1,
+        }
+    }
+
+    /// Returns all recorded calls, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state().calls.clone()
+    }
+
+    /// Returns the recorded calls keyed by `T`, in order.
+    pub fn calls_of<T: ?Sized>(&self) -> Vec<MockCall> {
+        let name = type_name::<T>();
+        self.state()
+            .calls
+            .iter()
+            .filter(|call| call.type_name == name)
+            .cloned()
+            .collect()
+    }
+
+    /// Panics if an expectation was never used, or used fewer times than set with
+    /// [`MockExpectation::times`].
+    pub fn verify(&self) {
+        let state = self.state();
+        let unmet: Vec<String> = state
+            .expectations
+            .iter()
+            .filter(|expectation| expectation.used < expectation.times.unwrap_or(1))
+            .map(|expectation| {
+                format!(
+                    "{} (called {} of {} times)",
+                    expectation.describe(),
+                    expectation.used,
+                    expectation.times.unwrap_or(1)
+                )
+            })
+            .collect();
+        if !unmet.is_empty() {
+            panic!(
+                "MockExecutor: unmet expectations:\n  {}",
+                unmet.join("\n  ")
+            );
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panicking test poisons the lock; the state is still consistent
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records a call and returns the reply of the first matching expectation.
+    fn call<T: ?Sized>(
+        &self,
+        method: &'static str,
+        sql: String,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Answer {
+        let call = MockCall {
+            method,
+            type_name: type_name::<T>(),
+            sql,
+            params: params.iter().map(|param| format!("{:?}", param)).collect(),
+        };
+
+        let mut state = self.state();
+        state.calls.push(call.clone());
+        let Some(expectation) = state
+            .expectations
+            .iter_mut()
+            .find(|expectation| expectation.matches(&call))
+        else {
+            panic!(
+                "MockExecutor: unexpected {} of {}\n  sql: {}\n  params: {:?}",
+                call.method, call.type_name, call.sql, call.params
+            );
+        };
+        expectation.used += 1;
+
+        match expectation.reply.as_mut() {
+            Some(Reply::Value { make, type_name }) => Answer::Value(make(), type_name),
+            Some(Reply::Id(value)) => Answer::Id(encode(value.as_ref(), &call)),
+            Some(Reply::Error(error)) => {
+                Answer::Error(error.take().expect("error is returned once"))
+            }
+            None => panic!(
+                "MockExecutor: the expectation for {} has no reply; call `returning`",
+                call.type_name
+            ),
+        }
+    }
+}
+
+/// Scripts the reply of an expectation; see [`MockExecutor::expect_query`].
+pub struct MockExpectation<'m> {
+    mock: &'m MockExecutor,
+    index: usize,
+}
+
+impl MockExpectation<'_> {
+    /// Only matches calls whose parameters, formatted with `{:?}`, equal `params`.
+    pub fn with_params<I, S>(self, params: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let params = params.into_iter().map(Into::into).collect();
+        self.update(|expectation| expectation.params = Some(params));
+        self
+    }
+
+    /// Answers exactly `times` calls; [`MockExecutor::verify`] checks all of them were made.
+    pub fn times(self, times: usize) -> Self {
+        self.update(|expectation| expectation.times = Some(times));
+        self
+    }
+
+    /// Answers with a clone of `value`, which must be the type the method returns: `R` for
+    /// `fetch` and `select`, `Vec<R>` for `fetch_all`, `select_all`, `fetch_all_where` and
+    /// `fetch_all_fragment`, `Vec<C>` for `preload`, `bool` for `update` and `u64` for
+    /// `delete`, `restore` and `force_delete`.
+    pub fn returning<V>(self, value: V) -> Self
+    where
+        V: Clone + Send + Sync + 'static,
+    {
+        self.update(|expectation| {
+            expectation.reply = Some(Reply::Value {
+                make: Box::new(move || Box::new(value.clone())),
+                type_name: type_name::<V>(),
+            })
+        });
+        self
+    }
+
+    /// Answers `insert` with `id`, converted to the type the caller asks for as if it was
+    /// returned by the database.
+    pub fn returning_id<V>(self, id: V) -> Self
+    where
+        V: ToSql + Send + Sync + 'static,
+    {
+        self.update(|expectation| expectation.reply = Some(Reply::Id(Box::new(id))));
+        self
+    }
+
+    /// Answers one call with `error`.
+    pub fn returning_error(self, error: Error) -> Self {
+        self.update(|expectation| expectation.reply = Some(Reply::Error(Some(error))));
+        self
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Expectation)) {
+        f(&mut self.mock.state().expectations[self.index]);
+    }
+}
+
+/// The reply to a single call.
+enum Answer {
+    Value(Box<dyn Any + Send>, &'static str),
+    Id(Vec<Encoded>),
+    Error(Error),
+}
+
+impl Answer {
+    fn into_result<T: 'static>(self, method: &str) -> Result<T, Error> {
+        match self {
+            Answer::Value(value, type_name) => Ok(*value.downcast::<T>().unwrap_or_else(|_| {
+                panic!(
+                    "MockExecutor: {} returns {}, but the expectation returns {}",
+                    method,
+                    std::any::type_name::<T>(),
+                    type_name
+                )
+            })),
+            Answer::Id(_) => panic!("MockExecutor: `returning_id` only answers `insert`"),
+            Answer::Error(error) => Err(error),
+        }
+    }
+}
+
+/// A scripted id written as one SQL type; `None` is `NULL`.
+type Encoded = (Type, Option<BytesMut>);
+
+/// Types an `insert` id is encoded as, in order of preference.
+const ID_TYPES: [Type; 8] = [
+    Type::INT8,
+    Type::INT4,
+    Type::INT2,
+    Type::UUID,
+    Type::TEXT,
+    Type::VARCHAR,
+    Type::NUMERIC,
+    Type::BOOL,
+];
+
+/// Encodes a scripted id as every type the value can be written as.
+///
+/// An integer is encoded as every integer type it fits in, so `returning_id(42)` answers an
+/// insert returning `i64` although the literal is an `i32`.
+fn encode(value: &(dyn ToSql + Send + Sync), call: &MockCall) -> Vec<Encoded> {
+    let mut encoded: Vec<Encoded> = Vec::new();
+    for ty in ID_TYPES {
+        let mut buf = BytesMut::new();
+        match value.to_sql_checked(&ty, &mut buf) {
+            Ok(IsNull::No) => encoded.push((ty, Some(buf))),
+            Ok(IsNull::Yes) => encoded.push((ty, None)),
+            Err(_) => {}
+        }
+    }
+
+    let integer = encoded
+        .iter()
+        .find_map(|(ty, buf)| match (ty, buf.as_deref()) {
+            (&Type::INT8, Some(raw)) => i64::from_sql(ty, raw).ok(),
+            (&Type::INT4, Some(raw)) => i32::from_sql(ty, raw).ok().map(i64::from),
+            (&Type::INT2, Some(raw)) => i16::from_sql(ty, raw).ok().map(i64::from),
+            _ => None,
+        });
+    if let Some(integer) = integer {
+        let int4 = i32::try_from(integer).ok();
+        let int2 = i16::try_from(integer).ok();
+        let widths: [(Type, Option<&(dyn ToSql + Sync)>); 3] = [
+            (Type::INT8, Some(&integer)),
+            (Type::INT4, int4.as_ref().map(|value| value as _)),
+            (Type::INT2, int2.as_ref().map(|value| value as _)),
+        ];
+        encoded = widths
+            .into_iter()
+            .filter_map(|(ty, value)| {
+                let mut buf = BytesMut::new();
+                value?.to_sql_checked(&ty, &mut buf).ok()?;
+                Some((ty, Some(buf)))
+            })
+            .collect();
+    }
+
+    if encoded.is_empty() {
+        panic!(
+            "MockExecutor: the id {:?} scripted for {} is not an integer, uuid, text, numeric or bool",
+            value, call.type_name
+        );
+    }
+    encoded
+}
+
+/// Decodes a scripted id as the type `insert` returns.
+fn decode<P: for<'a> FromSql<'a>>(encoded: Vec<Encoded>) -> P {
+    encoded
+        .iter()
+        .filter(|(ty, _)| P::accepts(ty))
+        .find_map(|(ty, buf)| match buf {
+            Some(buf) => P::from_sql(ty, buf).ok(),
+            None => P::from_sql_null(ty).ok(),
+        })
+        .unwrap_or_else(|| {
+            let types: Vec<String> = encoded.iter().map(|(ty, _)| ty.to_string()).collect();
+            panic!(
+                "MockExecutor: the scripted id ({}) cannot be read as {}",
+                types.join(", "),
+                type_name::<P>()
+            )
+        })
+}
+
+#[async_trait::async_trait]
+impl CrudOps for MockExecutor {
+    async fn insert<T, P: for<'a> FromSql<'a> + Send + Sync>(&self, entity: T) -> Result<P, Error>
+    where
+        T: SqlCommand + SqlParams + Send + Sync + 'static,
+    {
+        match self.call::<T>("insert", T::query(), &entity.params()) {
+            Answer::Id(id) => Ok(decode(id)),
+            Answer::Error(error) => Err(error),
+            Answer::Value(_, _) => panic!("MockExecutor: answer `insert` with `returning_id`"),
+        }
+    }
+
+    async fn update<T>(&self, entity: T) -> Result<bool, Error>
+    where
+        T: SqlCommand + UpdateParams + Send + Sync + 'static,
+    {
+        self.call::<T>("update", T::query(), &entity.params())
+            .into_result("update")
+    }
+
+    async fn delete<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SqlCommand + SqlParams + Send + Sync + 'static,
+    {
+        self.call::<T>("delete", T::query(), &entity.params())
+            .into_result("delete")
+    }
+
+    async fn restore<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        self.call::<T>("restore", T::restore_query(), &entity.params())
+            .into_result("restore")
+    }
+
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        self.call::<T>("force_delete", T::force_delete_query(), &entity.params())
+            .into_result("force_delete")
+    }
+
+    async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
+        R: FromRow + Send + Sync + 'static,
+    {
+        self.call::<P>("fetch", P::query(), &params.params())
+            .into_result("fetch")
+    }
+
+    async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
+        R: FromRow + Send + Sync + 'static,
+    {
+        self.call::<P>("fetch_all", P::query(), &params.params())
+            .into_result("fetch_all")
+    }
+
+    async fn select<T, F, R>(&self, entity: T, _to_model: F) -> Result<R, Error>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync + 'static,
+        F: Fn(&Row) -> Result<R, Error> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        self.call::<T>("select", T::query(), &entity.params())
+            .into_result("select")
+    }
+
+    async fn select_all<T, F, R>(&self, entity: T, _to_model: F) -> Result<Vec<R>, Error>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync + 'static,
+        F: Fn(&Row) -> R + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        self.call::<T>("select_all", T::query(), &entity.params())
+            .into_result("select_all")
+    }
+
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C> + Send + Sync + 'static,
+        C: FromRow + Clone + Send + Sync + 'static,
+    {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        // Each distinct key is sent only once, like the real `preload`
+        let mut seen = HashSet::new();
+        let keys: Vec<R::Key> = records
+            .iter()
+            .filter_map(|record| record.relation_key())
+            .filter(|key| seen.insert(key.clone()))
+            .collect();
+        let related: Vec<C> = self
+            .call::<C>(
+                "preload",
+                R::relation_query(),
+                &[&keys as &(dyn ToSql + Sync)],
+            )
+            .into_result("preload")?;
+        R::stitch(records, related);
+        Ok(())
+    }
+
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow + Send + Sync + 'static,
+    {
+        let (sql, params) = expr.select::<R>()?;
+        Ok(self
+            .call::<R>("fetch_all_where", sql, &params)
+            .into_result("fetch_all_where")?)
+    }
+
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, Error>
+    where
+        R: FromRow + Send + Sync + 'static,
+    {
+        let (sql, params) = fragment.build();
+        self.call::<R>("fetch_all_fragment", sql, &params)
+            .into_result("fetch_all_fragment")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Versioned;
+    use crate::*;
+
+    #[derive(Queryable, SqlParams)]
+    #[table("users")]
+    #[where_clause("id = $")]
+    #[result_type("User")]
+    struct GetUser {
+        id: i64,
+    }
+
+    #[derive(Insertable, SqlParams)]
+    #[table("users")]
+    #[returning("id")]
+    struct InsertUser {
+        name: String,
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("users")]
+    #[where_clause("id = $")]
+    struct DeleteUser {
+        id: i64,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("users")]
+    #[update("name")]
+    #[where_clause("id = $")]
+    #[version_column("version")]
+    struct RenameUser {
+        id: i64,
+        name: String,
+        version: i32,
+    }
+
+    #[derive(Clone, Debug, PartialEq, FromRow)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    fn user(id: i64, name: &str) -> User {
+        User {
+            id,
+            name: name.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expectations_match_by_type_and_params() {
+        let db = MockExecutor::new();
+        db.expect_query::<GetUser>()
+            .with_params(["2"])
+            .returning(user(2, "Grace"));
+        db.expect_query::<GetUser>().returning(user(0, "anyone"));
+        db.expect_query::<DeleteUser>().returning(1u64);
+
+        let grace: User = db.fetch(GetUser { id: 2 }).await.unwrap();
+        let other: User = db.fetch(GetUser { id: 3 }).await.unwrap();
+        assert_eq!(grace, user(2, "Grace"));
+        assert_eq!(other, user(0, "anyone"));
+        assert_eq!(db.delete(DeleteUser { id: 2 }).await.unwrap(), 1);
+
+        let calls = db.calls_of::<GetUser>();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1].params, vec!["3"]);
+        assert_eq!(db.calls().len(), 3);
+        db.verify();
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_expectations_fall_through() {
+        let db = MockExecutor::new();
+        db.expect_query::<GetUser>().times(2).returning(user(1, "Ada"));
+        db.expect_query::<GetUser>().returning(user(1, "Ada Lovelace"));
+
+        let mut names = Vec::new();
+        for _ in 0..3 {
+            let found: User = db.fetch(GetUser { id: 1 }).await.unwrap();
+            names.push(found.name);
+        }
+        assert_eq!(names, vec!["Ada", "Ada", "Ada Lovelace"]);
+        db.verify();
+    }
+
+    #[tokio::test]
+    async fn test_insert_id_is_read_as_the_requested_type() {
+        let db = MockExecutor::new();
+        db.expect_query::<InsertUser>().times(2).returning_id(42);
+
+        let wide: i64 = db.insert(InsertUser { name: "Ada".into() }).await.unwrap();
+        let narrow: i32 = db.insert(InsertUser { name: "Ada".into() }).await.unwrap();
+        assert_eq!((wide, narrow), (42, 42));
+        assert!(db.calls()[0].sql.contains("RETURNING id"));
+    }
+
+    #[tokio::test]
+    async fn test_versioned_update_reports_stale_object() {
+        let db = MockExecutor::new();
+        db.expect_query::<RenameUser>().times(1).returning(true);
+        db.expect_query::<RenameUser>().returning(false);
+        let rename = || RenameUser {
+            id: 1,
+            name: "Ada".into(),
+            version: 1,
+        };
+
+        db.update_versioned(rename()).await.unwrap();
+        assert!(db.update_versioned(rename()).await.unwrap_err().is_stale());
+        db.verify();
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "GetUser (called 1 of 2 times)")]
+    async fn test_verify_reports_missing_calls() {
+        let db = MockExecutor::new();
+        db.expect_query::<GetUser>().times(2).returning(user(1, "Ada"));
+
+        let _: User = db.fetch(GetUser { id: 1 }).await.unwrap();
+        db.verify();
+    }
+
+    #[test]
+    #[should_panic(expected = "unmet expectations")]
+    fn test_verify_reports_unused_expectations() {
+        let db = MockExecutor::new();
+        db.expect_query::<DeleteUser>().returning(1u64);
+        db.verify();
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "unexpected fetch of")]
+    async fn test_unexpected_call_panics() {
+        let db = MockExecutor::new();
+        db.expect_query::<GetUser>()
+            .with_params(["1"])
+            .returning(user(1, "Ada"));
+
+        let _: Result<User, Error> = db.fetch(GetUser { id: 2 }).await;
+    }
+}
@@ -0,0 +1,274 @@
+//! Retrying reads on transient connection errors.
+//!
+//! During a failover every in-flight query fails with a closed connection or an
+//! `admin_shutdown` error, and new connections are refused for a moment. A `Client` is a
+//! single connection and cannot recover by itself, so [`Reconnecting`] keeps a client
+//! created by a connect factory and replaces it when its connection is closed.
+//! [`is_retryable`] decides which errors are retried and [`RetryPolicy`] how long to wait,
+//! with exponential backoff, jitter and a cap. Only idempotent calls are retried:
+//! [`Reconnecting::fetch`], [`Reconnecting::fetch_all`], and any call the caller marks as
+//! safe to repeat with [`Reconnecting::run`] or [`run`].
+//!
+//! ```rust,no_run
+//! # use tokio_postgres::NoTls;
+//! use parsql_tokio_postgres::retry::{Reconnecting, RetryPolicy};
+//! use parsql_tokio_postgres::traits::CrudOps;
+//! # use parsql_tokio_postgres::traits::{
+//! #     FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+//! # };
+//! # use parsql_tokio_postgres::*;
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams, Clone)]
+//! # #[table("users")]
+//! # #[where_clause("id = $")]
+//! # struct User { id: i64, name: String }
+//! #
+//! # #[derive(Updateable, UpdateParams)]
+//! # #[table("users")]
+//! # #[update("name")]
+//! # #[where_clause("id = $")]
+//! # struct RenameUser { id: i64, name: String }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Reconnecting::new(
+//!     || async {
+//!         let (client, connection) = tokio_postgres::connect("host=db user=app", NoTls).await?;
+//!         tokio::spawn(connection);
+//!         Ok(client)
+//!     },
+//!     RetryPolicy::new().max_retries(5),
+//! )
+//! .await?;
+//!
+//! // Retried on connection errors, on a new connection if needed
+//! let user: User = db.fetch(&User { id: 1, name: String::new() }).await?;
+//!
+//! // Not retried: the update may have been applied before the connection dropped
+//! db.client().await?.update(RenameUser { id: 1, name: "Ada".into() }).await?;
+//!
+//! // Retried, because the caller knows that repeating it is harmless
+//! db.run(|client| {
+//!     Box::pin(client.update(RenameUser { id: 1, name: "Ada".into() }))
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::error::Error as StdError;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use parsql_core::retry::{find_source, is_retryable_state};
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, Error};
+
+use crate::traits::{CrudOps, FromRow, SqlParams, SqlQuery};
+
+pub use parsql_core::retry::RetryPolicy;
+
+/// Returns whether an error is a transient connection error worth retrying.
+pub fn is_retryable(error: &Error) -> bool {
+    if error.is_closed() {
+        return true;
+    }
+    if let Some(code) = error.code() {
+        return is_retryable_state(code.code());
+    }
+    // Connection refused or reset while (re)connecting
+    error
+        .source()
+        .is_some_and(|source| source.is::<std::io::Error>())
+}
+
+/// Runs `op`, running it again with `policy` while it fails with a retryable error.
+///
+/// `op` must be safe to repeat: a write may have been applied before its connection
+/// was lost.
+pub async fn run<T, E, F, Fut>(policy: &RetryPolicy, op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: StdError + 'static,
+{
+    policy
+        .run(op, |err| is_retryable_chain(err), tokio::time::sleep)
+        .await
+}
+
+/// Finds the database error in the source chain of `error`, e.g. inside an `UpdateError`.
+fn is_retryable_chain(error: &(dyn StdError + 'static)) -> bool {
+    find_source::<Error>(error).is_some_and(is_retryable)
+}
+
+/// A client that reconnects when its connection is closed and retries idempotent calls.
+pub struct Reconnecting<F> {
+    connect: F,
+    policy: RetryPolicy,
+    client: Mutex<Arc<Client>>,
+}
+
+impl<F, Fut> Reconnecting<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Client, Error>> + Send,
+{
+    /// Connects with `connect`, which is called again whenever the connection is closed.
+    ///
+    /// The factory must spawn the connection task, as with `tokio_postgres::connect`.
+    pub async fn new(connect: F, policy: RetryPolicy) -> Result<Self, Error> {
+        let client = run(&policy, &connect).await?;
+        Ok(Reconnecting {
+            connect,
+            policy,
+            client: Mutex::new(Arc::new(client)),
+        })
+    }
+
+    /// Returns the policy.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Returns the current client, reconnecting first if its connection is closed.
+    ///
+    /// Calls made directly on the client are not retried.
+    pub async fn client(&self) -> Result<Arc<Client>, Error> {
+        // Concurrent callers wait for a single reconnect
+        let mut client = self.client.lock().await;
+        if client.is_closed() {
+            *client = Arc::new((self.connect)().await?);
+        }
+        Ok(Arc::clone(&client))
+    }
+
+    /// Runs `op` on the current client, running it again on a retryable error.
+    ///
+    /// `op` must be safe to repeat: a write may have been applied before its connection
+    /// was lost.
+    pub async fn run<T, E, Op>(&self, op: Op) -> Result<T, E>
+    where
+        Op: for<'c> Fn(&'c Client) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>,
+        E: StdError + From<Error> + 'static,
+    {
+        run(&self.policy, || async {
+            let client = self.client().await?;
+            op(&client).await
+        })
+        .await
+    }
+
+    /// Retrieves a single record, retrying on connection errors.
+    pub async fn fetch<P, R>(&self, params: &P) -> Result<R, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Clone + Send + Sync + 'static,
+        R: FromRow + Send + Sync + 'static,
+    {
+        self.run(|client| client.fetch(params.clone())).await
+    }
+
+    /// Retrieves multiple records, retrying on connection errors.
+    pub async fn fetch_all<P, R>(&self, params: &P) -> Result<Vec<R>, Error>
+    where
+        P: SqlQuery<R> + SqlParams + Clone + Send + Sync + 'static,
+        R: FromRow + Send + Sync + 'static,
+    {
+        self.run(|client| client.fetch_all(params.clone())).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use tokio_postgres::error::SqlState;
+    use tokio_postgres::NoTls;
+
+    async fn refused() -> Result<Client, Error> {
+        let (client, connection) =
+            tokio_postgres::connect("host=127.0.0.1 port=1 user=postgres", NoTls).await?;
+        tokio::spawn(connection);
+        Ok(client)
+    }
+
+    #[test]
+    fn test_retryable_states_match_sqlstates() {
+        let retryable = [
+            SqlState::ADMIN_SHUTDOWN,
+            SqlState::CRASH_SHUTDOWN,
+            SqlState::CANNOT_CONNECT_NOW,
+            SqlState::CONNECTION_EXCEPTION,
+            SqlState::CONNECTION_DOES_NOT_EXIST,
+            SqlState::CONNECTION_FAILURE,
+            SqlState::SQLCLIENT_UNABLE_TO_ESTABLISH_SQLCONNECTION,
+            SqlState::SQLSERVER_REJECTED_ESTABLISHMENT_OF_SQLCONNECTION,
+        ];
+        for state in retryable {
+            assert!(is_retryable_state(state.code()), "{:?}", state);
+        }
+        for state in [SqlState::UNIQUE_VIOLATION, SqlState::QUERY_CANCELED] {
+            assert!(!is_retryable_state(state.code()), "{:?}", state);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refused_connection_is_retryable() {
+        let err = refused().await.unwrap_err();
+
+        assert!(is_retryable(&err));
+    }
+
+    #[tokio::test]
+    async fn test_reconnecting_stops_after_max_retries() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new().max_retries(2).base_delay(Duration::ZERO);
+
+        let result = Reconnecting::new(
+            || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                refused()
+            },
+            policy,
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_constraint_violation_is_not_retryable() {
+        let mut client = TestClient::from_env().await;
+        let tx = client.begin().await.unwrap();
+        tx.batch_execute("CREATE TEMP TABLE retry_keys (id int PRIMARY KEY)")
+            .await
+            .unwrap();
+
+        let insert = "INSERT INTO retry_keys VALUES (1)";
+        tx.execute(insert, &[]).await.unwrap();
+        let err = tx.execute(insert, &[]).await.unwrap_err();
+
+        assert_eq!(err.code(), Some(&SqlState::UNIQUE_VIOLATION));
+        assert!(!is_retryable(&err));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_terminated_backend_is_retryable() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let (client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+
+        // Fails with admin_shutdown, or with a closed connection if that arrives first
+        let err = client
+            .batch_execute("SELECT pg_terminate_backend(pg_backend_pid())")
+            .await
+            .unwrap_err();
+
+        assert!(is_retryable(&err), "{:?}", err);
+    }
+}
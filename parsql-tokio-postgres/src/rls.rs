@@ -0,0 +1,144 @@
+//! Session settings for PostgreSQL row-level security.
+//!
+//! RLS policies usually read the current user from a session variable
+//! (`current_setting('app.user_id')`) or rely on the current role. [`SessionSettings`]
+//! collects these values and [`begin_with_session`] applies them at the start of a
+//! transaction with `SET LOCAL ROLE` and `set_config(name, value, true)`.
+//!
+//! Both are transaction-local: PostgreSQL resets them on commit or rollback, including the
+//! rollback sent when a `Transaction` is dropped, so nothing leaks into later use of the
+//! connection.
+//!
+//! ```rust,no_run
+//! # use tokio_postgres::NoTls;
+//! use parsql_tokio_postgres::rls::{with_session, SessionSettings};
+//! use parsql_tokio_postgres::traits::CrudOps;
+//! # use parsql_tokio_postgres::traits::{FromRow, SqlParams, SqlQuery};
+//! # use parsql_tokio_postgres::*;
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams)]
+//! # #[table("documents")]
+//! # #[where_clause("id > $")]
+//! # struct Document { id: i64, title: String }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! # let (mut client, connection) = tokio_postgres::connect("", NoTls).await?;
+//! # tokio::spawn(async move { connection.await; });
+//! let settings = SessionSettings::new()
+//!     .role("app_user")
+//!     .set("app.user_id", 42);
+//!
+//! let documents: Vec<Document> = with_session(&mut client, &settings, |tx| {
+//!     Box::pin(async move {
+//!         let documents = tx.fetch_all(Document { id: 0, title: String::new() }).await?;
+//!         Ok::<_, tokio_postgres::Error>(documents)
+//!     })
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, Error, Transaction};
+
+pub use parsql_core::rls::SessionSettings;
+
+/// Applies the session settings to an open transaction.
+pub async fn apply(transaction: &Transaction<'_>, settings: &SessionSettings) -> Result<(), Error> {
+    for (sql, params) in settings.statements() {
+        let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|param| param as _).collect();
+        transaction.execute(&sql, &params).await?;
+    }
+    Ok(())
+}
+
+/// Begins a transaction and applies the session settings to it.
+///
+/// # Return Value
+/// * `Result<Transaction<'_>, Error>` - On success, returns the transaction; on failure, returns Error
+pub async fn begin_with_session<'a>(
+    client: &'a mut Client,
+    settings: &SessionSettings,
+) -> Result<Transaction<'a>, Error> {
+    let transaction = client.transaction().await?;
+
+    apply(&transaction, settings).await?;
+    Ok(transaction)
+}
+
+/// Runs `f` in a transaction with the session settings applied.
+///
+/// The transaction is committed when `f` succeeds and rolled back otherwise.
+///
+/// # Return Value
+/// * `Result<R, E>` - The value returned by `f`, or the first error
+pub async fn with_session<F, R, E>(
+    client: &mut Client,
+    settings: &SessionSettings,
+    f: F,
+) -> Result<R, E>
+where
+    F: for<'t> FnOnce(
+        &'t Transaction<'t>,
+    ) -> Pin<Box<dyn Future<Output = Result<R, E>> + Send + 't>>,
+    E: From<Error>,
+{
+    let transaction = begin_with_session(client, settings).await?;
+    match f(&transaction).await {
+        Ok(value) => {
+            transaction.commit().await?;
+            Ok(value)
+        }
+        Err(err) => {
+            // The error of `f` is kept; a failed rollback means the connection is closed
+            let _ = transaction.rollback().await;
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+
+    async fn setting(transaction: &Transaction<'_>, sql: &str) -> String {
+        transaction.query_one(sql, &[]).await.unwrap().get(0)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_apply_sets_role_and_variables() {
+        let mut client = TestClient::from_env().await;
+        let user = {
+            let tx = client.begin().await.unwrap();
+            setting(&tx, "SELECT current_user::text").await
+        };
+        let tx = client.begin().await.unwrap();
+
+        let settings = SessionSettings::new()
+            .role(user.clone())
+            .set("app.user_id", 42)
+            .set("app.note", "it's; --");
+        apply(&tx, &settings).await.unwrap();
+        assert_eq!(setting(&tx, "SELECT current_setting('app.user_id')").await, "42");
+        assert_eq!(setting(&tx, "SELECT current_setting('app.note')").await, "it's; --");
+        assert_eq!(setting(&tx, "SELECT current_user::text").await, user);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_role_name_is_not_sql() {
+        let mut client = TestClient::from_env().await;
+        let tx = client.begin().await.unwrap();
+
+        // The whole string is one identifier, so the role does not exist
+        let settings = SessionSettings::new().role("x\"; SELECT 1; --");
+        let err = apply(&tx, &settings).await.unwrap_err();
+        let message = err.as_db_error().map(|err| err.message()).unwrap_or_default();
+        assert!(message.contains("does not exist"), "{}", message);
+    }
+}
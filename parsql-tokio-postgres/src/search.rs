@@ -0,0 +1,108 @@
+//! PostgreSQL full-text search.
+//!
+//! A `Queryable` struct with `#[text_search(config = "...", columns = "...")]` matches the
+//! columns against its `query` field with `@@ websearch_to_tsquery(config, $1)`, selects
+//! `ts_rank_cd()` as `rank` and orders by it, best match first; `headline` adds
+//! `<column>_headline` columns built with `ts_headline()`. The search string is always bound
+//! as a parameter, and `websearch_to_tsquery` accepts any user input without syntax errors.
+//!
+//! Computing the document on every query scans the whole table. For large tables, store it
+//! in an indexed `tsvector` column with [`vector_column_statements`] and search it with
+//! `vector = "..."` instead of `columns`.
+//!
+//! ```rust,no_run
+//! use parsql_tokio_postgres::search;
+//! use parsql_tokio_postgres::traits::*;
+//! use parsql_tokio_postgres::*;
+//! use tokio_postgres::NoTls;
+//!
+//! #[derive(Queryable, SqlParams)]
+//! #[table("articles")]
+//! #[text_search(config = "english", vector = "search_vector", headline = "body")]
+//! #[result_type("ArticleHit")]
+//! struct SearchArticles {
+//!     query: String,
+//! }
+//!
+//! #[derive(FromRow)]
+//! struct ArticleHit {
+//!     id: i32,
+//!     title: String,
+//!     rank: f32,
+//!     body_headline: String,
+//! }
+//!
+//! # async fn run() -> Result<(), tokio_postgres::Error> {
+//! let (client, connection) = tokio_postgres::connect("host=localhost user=postgres", NoTls).await?;
+//! tokio::spawn(connection);
+//! search::create_vector_column(
+//!     &client,
+//!     "articles",
+//!     "search_vector",
+//!     "english",
+//!     &["title", "body"],
+//! )
+//! .await?;
+//!
+//! let search = SearchArticles { query: "rust -async \"error handling\"".to_string() };
+//! let hits: Vec<ArticleHit> = client.fetch_all(search).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use tokio_postgres::{Client, Error};
+
+pub use parsql_core::search::{
+    drop_vector_column_statements, index_statement, vector_column_statements,
+};
+
+/// Adds the generated `tsvector` column and its index; see [`vector_column_statements`].
+pub async fn create_vector_column(
+    client: &Client,
+    table: &str,
+    vector_column: &str,
+    config: &str,
+    columns: &[&str],
+) -> Result<(), Error> {
+    for sql in vector_column_statements(table, vector_column, config, columns) {
+        client.execute(sql.as_str(), &[]).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Queryable, FromRow, SqlParams)]
+    #[table("articles")]
+    #[text_search(config = "english", vector = "search_vector", syntax = "plain")]
+    #[where_clause("author_id = $ OR editor_id = $")]
+    struct SearchArticles {
+        query: String,
+        author_id: i64,
+        editor_id: i64,
+    }
+
+    #[test]
+    fn test_vector_search_query() {
+        let tsquery = "plainto_tsquery('english', $1)";
+
+        assert_eq!(
+            SearchArticles::query(),
+            format!(
+                "SELECT articles.*, ts_rank_cd(articles.search_vector, {tsquery}) AS rank \
+                 FROM articles WHERE articles.search_vector @@ {tsquery} \
+                 AND (author_id = $2 OR editor_id = $3) ORDER BY rank DESC"
+            )
+        );
+    }
+
+    #[test]
+    fn test_search_string_is_the_first_parameter() {
+        let search = SearchArticles { query: "rust".into(), author_id: 7, editor_id: 8 };
+
+        assert_eq!(format!("{:?}", search.params()), r#"["rust", 7, 8]"#);
+    }
+}
@@ -0,0 +1,213 @@
+//! Multi-tenant row scoping.
+//!
+//! Models marked with `#[tenant_scoped(column = "tenant_id")]` get a `tenant_id = $n` predicate
+//! in their generated SELECT, UPDATE and DELETE statements, and the tenant column in their
+//! INSERT statement. The tenant value itself comes from a [`TenantContext`], which wraps a
+//! model with [`TenantContext::scope`] before it is passed to the CRUD functions:
+//!
+//! ```rust
+//! use parsql_tokio_postgres::traits::*;
+//! use parsql_tokio_postgres::*;
+//!
+//! #[derive(Queryable, FromRow, SqlParams)]
+//! #[table("projects")]
+//! #[where_clause("name = $")]
+//! #[tenant_scoped]
+//! struct Project {
+//!     id: i64,
+//!     name: String,
+//! }
+//!
+//! let acme = TenantContext::new(1_i64);
+//! let query = acme.scope(Project { id: 0, name: "Website".into() });
+//! assert_eq!(
+//!     <Scoped<Project> as SqlQuery<Project>>::query(),
+//!     "SELECT id, name FROM projects WHERE (name = $1) AND tenant_id = $2"
+//! );
+//! assert_eq!(query.params().len(), 2);
+//! // let projects: Vec<Project> = fetch_all(&client, query).await?;
+//! ```
+//!
+//! The tenant placeholder is part of the generated SQL, so running a scoped model without a
+//! context fails with a parameter count error instead of reading other tenants' rows.
+//! `fetch_all_where` on a tenant-scoped model fails with `WhereError::MissingTenant` unless its
+//! condition was restricted with [`TenantContext::filter`].
+
+use tokio_postgres::types::ToSql;
+
+use crate::expr::Postgres;
+use crate::traits::{
+    SoftDelete, SoftDeleteQuery, SqlCommand, SqlParams, SqlQuery, TenantScoped, UpdateParams,
+    Versioned,
+};
+
+/// The tenant of the current request.
+///
+/// Cloning is cheap; the tenant value is shared between clones.
+pub type TenantContext = parsql_core::tenant::TenantContext<Postgres>;
+
+/// A tenant-scoped model together with its tenant, created by [`TenantContext::scope`].
+///
+/// Generates the same SQL as the wrapped model and adds the tenant value to its parameters.
+pub type Scoped<T> = parsql_core::tenant::Scoped<T, Postgres>;
+
+impl<T, R> SqlQuery<R> for Scoped<T>
+where
+    T: SqlQuery<R> + TenantScoped,
+{
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: SqlCommand + TenantScoped> SqlCommand for Scoped<T> {
+    fn query() -> String {
+        T::query()
+    }
+}
+
+impl<T: Versioned + TenantScoped> Versioned for Scoped<T> {
+    fn version_column() -> &'static str {
+        T::version_column()
+    }
+}
+
+impl<T: SoftDelete + TenantScoped> SoftDelete for Scoped<T> {
+    fn restore_query() -> String {
+        T::restore_query()
+    }
+
+    fn force_delete_query() -> String {
+        T::force_delete_query()
+    }
+}
+
+impl<T: SoftDeleteQuery + TenantScoped> SoftDeleteQuery for Scoped<T> {
+    fn query_with_deleted() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams + TenantScoped> SqlParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+impl<T: UpdateParams + TenantScoped> UpdateParams for Scoped<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.with_tenant(self.entity().params())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Column;
+    use crate::mock::MockExecutor;
+    use crate::traits::*;
+    use crate::*;
+
+    #[derive(Insertable, SqlParams)]
+    #[table("projects")]
+    #[returning("id")]
+    #[tenant_scoped]
+    struct InsertProject {
+        name: String,
+    }
+
+    #[derive(Queryable, FromRow, SqlParams, Columns, Clone, Debug, PartialEq)]
+    #[table("projects")]
+    #[where_clause("id > $")]
+    #[tenant_scoped]
+    struct Project {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Updateable, UpdateParams)]
+    #[table("projects")]
+    #[update("name")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct RenameProject {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Deletable, SqlParams)]
+    #[table("projects")]
+    #[where_clause("id = $")]
+    #[tenant_scoped]
+    struct DeleteProject {
+        id: i64,
+    }
+
+    fn website() -> Project {
+        Project { id: 1, name: "Website".into() }
+    }
+
+    #[tokio::test]
+    async fn test_scoped_writes_and_reads_bind_the_tenant() {
+        let db = MockExecutor::new();
+        db.expect_query::<Scoped<InsertProject>>()
+            .with_params(["\"Website\"", "7"])
+            .returning_id(1);
+        db.expect_query::<Scoped<RenameProject>>()
+            .with_params(["\"Homepage\"", "1", "7"])
+            .returning(true);
+        db.expect_query::<Scoped<DeleteProject>>()
+            .with_params(["1", "7"])
+            .returning(1u64);
+        db.expect_query::<Scoped<Project>>()
+            .with_params(["0", "7"])
+            .returning(vec![website()]);
+        db.expect_query::<Project>()
+            .with_params(["0", "7"])
+            .returning(vec![website()]);
+
+        let acme = TenantContext::new(7_i64);
+        let id: i64 = db
+            .insert(acme.scope(InsertProject { name: "Website".into() }))
+            .await
+            .unwrap();
+        let rename = RenameProject { id, name: "Homepage".into() };
+        assert!(db.update(acme.scope(rename)).await.unwrap());
+        assert_eq!(db.delete(acme.scope(DeleteProject { id })).await.unwrap(), 1);
+
+        let all = acme.scope(Project { id: 0, name: String::new() });
+        let projects: Vec<Project> = db.fetch_all(all).await.unwrap();
+        assert_eq!(projects, vec![website()]);
+
+        let expr = acme.filter::<Project>(projects::id.gt(0));
+        let projects: Vec<Project> = db.fetch_all_where(expr).await.unwrap();
+        assert_eq!(projects, vec![website()]);
+        assert!(db.calls_of::<Project>()[0].sql.contains("WHERE (id > $1 AND tenant_id = $2)"));
+        db.verify();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_where_without_a_context_fails() {
+        let db = MockExecutor::new();
+        let err = db
+            .fetch_all_where::<Project>(projects::id.gt(0))
+            .await
+            .unwrap_err();
+        assert!(err.is_missing_tenant());
+        assert!(db.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_comparison_does_not_bypass_the_context() {
+        let db = MockExecutor::new();
+        let acme = TenantContext::new(7_i64);
+        let tenant_id = Column::<i64>::new("tenant_id");
+
+        let err = db.fetch_all_where::<Project>(tenant_id.eq(8)).await.unwrap_err();
+        assert!(err.is_missing_tenant());
+
+        let expr = acme.filter::<Project>(projects::id.gt(0)).or(tenant_id.eq(8));
+        let err = db.fetch_all_where::<Project>(expr).await.unwrap_err();
+        assert!(err.is_missing_tenant());
+        assert!(db.calls().is_empty());
+    }
+}
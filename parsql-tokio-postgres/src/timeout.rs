@@ -0,0 +1,428 @@
+//! Statement timeouts and server-side cancellation.
+//!
+//! The server keeps running a query when its client stops waiting for it. [`with_timeout`]
+//! limits a single call and [`Timed`] applies the same limit to every call made through a
+//! client. Both send a cancel request through the client's `CancelToken` when the limit is
+//! reached and return [`QueryError::Timeout`] once the request has been delivered, so that
+//! it cannot stop the next statement sent on the connection. The cancel request is sent
+//! without TLS.
+//!
+//! The cancel request is also sent when the future of a call is dropped before the query
+//! finishes, e.g. by a `select!` or an aborted task. A destructor cannot wait for it, so
+//! [`Timed`] waits before its next call instead. After dropping a `with_timeout` future,
+//! a cancel request may still be on its way and can stop the next statement of the client;
+//! use [`Timed`] for clients whose calls may be dropped.
+//!
+//! Calls that run concurrently on one client share its connection and are cancelled
+//! together.
+//!
+//! ```rust,no_run
+//! # use tokio_postgres::NoTls;
+//! use parsql_tokio_postgres::error::QueryError;
+//! use parsql_tokio_postgres::timeout::{with_timeout, Timed};
+//! use parsql_tokio_postgres::traits::CrudOps;
+//! # use parsql_tokio_postgres::traits::{FromRow, SqlParams, SqlQuery};
+//! # use parsql_tokio_postgres::*;
+//! # use std::time::Duration;
+//! #
+//! # #[derive(Queryable, FromRow, SqlParams)]
+//! # #[table("orders")]
+//! # #[where_clause("total > $")]
+//! # struct Order { id: i64, total: i64 }
+//! #
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! # let (client, connection) = tokio_postgres::connect("", NoTls).await?;
+//! # tokio::spawn(async move { connection.await; });
+//! let query = Order { id: 0, total: 1000 };
+//! let limit = Duration::from_secs(2);
+//!
+//! match with_timeout(&client, limit, client.fetch_all::<_, Order>(query)).await {
+//!     Ok(orders) => println!("{} orders", orders.len()),
+//!     Err(QueryError::Timeout(after)) => eprintln!("cancelled after {:?}", after),
+//!     Err(QueryError::Query(err)) => return Err(err.into()),
+//! }
+//!
+//! // Every call
+//! let db = Timed::new(client, Duration::from_secs(5));
+//! let orders: Vec<Order> = db.fetch_all(Order { id: 0, total: 1000 }).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use postgres::types::FromSql;
+use tokio::task::JoinHandle;
+use tokio_postgres::{CancelToken, Client, Error, NoTls, Row};
+
+use crate::compose::Fragment;
+use crate::crud_ops;
+use crate::error::{QueryError, UpdateError, WhereError};
+use crate::expr::Expr;
+use crate::traits::{
+    Columns, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+    Versioned,
+};
+
+/// Runs `query` on `client`, cancelling it when it does not finish within `limit`.
+pub async fn with_timeout<T, E, F>(
+    client: &Client,
+    limit: Duration,
+    query: F,
+) -> Result<T, QueryError<E>>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let guard = CancelOnDrop {
+        token: Some(client.cancel_token()),
+        pending: None,
+    };
+    limit_query(guard, limit, query).await
+}
+
+async fn limit_query<T, E, F>(
+    mut guard: CancelOnDrop<'_>,
+    limit: Duration,
+    query: F,
+) -> Result<T, QueryError<E>>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    match tokio::time::timeout(limit, query).await {
+        Ok(result) => {
+            guard.token = None;
+            result.map_err(QueryError::Query)
+        }
+        Err(_) => {
+            // Deliver the cancel request before the client can be used again; the guard
+            // still sends one if this future is dropped meanwhile
+            if let Some(token) = guard.token.clone() {
+                let _ = token.cancel_query(NoTls).await;
+            }
+            guard.token = None;
+            Err(QueryError::Timeout(limit))
+        }
+    }
+}
+
+/// Sends a cancel request when dropped, unless its token was taken.
+struct CancelOnDrop<'a> {
+    token: Option<CancelToken>,
+    /// Where [`Timed`] keeps the request until its next call
+    pending: Option<&'a Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl Drop for CancelOnDrop<'_> {
+    fn drop(&mut self) {
+        let Some(token) = self.token.take() else {
+            return;
+        };
+        // Without a runtime the query is not cancelled; the client discards its result
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let request = runtime.spawn(async move {
+            let _ = token.cancel_query(NoTls).await;
+        });
+        if let Some(pending) = self.pending {
+            lock(pending).push(request);
+        }
+    }
+}
+
+fn lock(pending: &Mutex<Vec<JoinHandle<()>>>) -> MutexGuard<'_, Vec<JoinHandle<()>>> {
+    // The requests stay usable after a panic in another thread
+    pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Wraps a `Client` and limits the duration of every call.
+///
+/// Provides the CRUD methods of the client, returning [`QueryError`]. A call whose future
+/// is dropped is cancelled on the server, and the next call waits until the cancel
+/// request has been delivered.
+pub struct Timed {
+    client: Client,
+    limit: Duration,
+    pending: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Timed {
+    /// Wraps `client` with the given limit.
+    pub fn new(client: Client, limit: Duration) -> Self {
+        Timed {
+            client,
+            limit,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the client, whose calls are not limited, once the cancel requests of
+    /// dropped calls have been delivered.
+    pub async fn client(&self) -> &Client {
+        self.settle().await;
+        &self.client
+    }
+
+    /// Returns the limit.
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    /// Waits for the cancel requests of dropped calls.
+    async fn settle(&self) {
+        let pending = std::mem::take(&mut *lock(&self.pending));
+        for request in pending {
+            let _ = request.await;
+        }
+    }
+
+    async fn call<T, E, F>(&self, query: F) -> Result<T, QueryError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        self.settle().await;
+        let guard = CancelOnDrop {
+            token: Some(self.client.cancel_token()),
+            pending: Some(&self.pending),
+        };
+        limit_query(guard, self.limit, query).await
+    }
+
+    /// Inserts a new record.
+    pub async fn insert<T, P>(&self, entity: T) -> Result<P, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync + 'static,
+        P: for<'a> FromSql<'a> + Send + Sync,
+    {
+        self.call(crud_ops::insert(&self.client, entity)).await
+    }
+
+    /// Updates an existing record.
+    pub async fn update<T>(&self, entity: T) -> Result<bool, QueryError>
+    where
+        T: SqlCommand + UpdateParams + Send + Sync + 'static,
+    {
+        self.call(crud_ops::update(&self.client, entity)).await
+    }
+
+    /// Updates a record of a model with `#[version_column("...")]`, failing with
+    /// `UpdateError::StaleObject` when no row matched the expected version.
+    pub async fn update_versioned<T>(&self, entity: T) -> Result<(), QueryError<UpdateError>>
+    where
+        T: Versioned + UpdateParams + Send + Sync + 'static,
+    {
+        self.call(crud_ops::update_versioned(&self.client, entity)).await
+    }
+
+    /// Deletes a record.
+    pub async fn delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SqlCommand + SqlParams + Send + Sync + 'static,
+    {
+        self.call(crud_ops::delete(&self.client, entity)).await
+    }
+
+    /// Restores soft-deleted records.
+    pub async fn restore<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        self.call(crud_ops::restore(&self.client, entity)).await
+    }
+
+    /// Removes soft-deleted records permanently.
+    pub async fn force_delete<T>(&self, entity: T) -> Result<u64, QueryError>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        self.call(crud_ops::force_delete(&self.client, entity)).await
+    }
+
+    /// Retrieves a single record.
+    pub async fn fetch<P, R>(&self, params: P) -> Result<R, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
+        R: FromRow + Send + Sync + 'static,
+    {
+        self.call(crud_ops::fetch(&self.client, params)).await
+    }
+
+    /// Retrieves multiple records.
+    pub async fn fetch_all<P, R>(&self, params: P) -> Result<Vec<R>, QueryError>
+    where
+        P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
+        R: FromRow + Send + Sync + 'static,
+    {
+        self.call(crud_ops::fetch_all(&self.client, params)).await
+    }
+
+    /// Retrieves a single record with a custom conversion function.
+    pub async fn select<T, F, R>(&self, entity: T, to_model: F) -> Result<R, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync + 'static,
+        F: Fn(&Row) -> Result<R, Error> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        self.call(crud_ops::select(&self.client, entity, to_model)).await
+    }
+
+    /// Retrieves multiple records with a custom conversion function.
+    pub async fn select_all<T, F, R>(&self, entity: T, to_model: F) -> Result<Vec<R>, QueryError>
+    where
+        T: SqlQuery<T> + SqlParams + Send + Sync + 'static,
+        F: Fn(&Row) -> R + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        self.call(crud_ops::select_all(&self.client, entity, to_model)).await
+    }
+
+    /// Loads the related records of `records`.
+    pub async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), QueryError>
+    where
+        R: Relation<C> + Send + Sync + 'static,
+        C: FromRow + Clone + Send + Sync + 'static,
+    {
+        self.call(crud_ops::preload(&self.client, records)).await
+    }
+
+    /// Retrieves the records matching a typed WHERE expression.
+    pub async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, QueryError<WhereError>>
+    where
+        R: Columns + FromRow + Send + Sync + 'static,
+    {
+        self.call(crud_ops::fetch_all_where(&self.client, expr)).await
+    }
+
+    /// Retrieves the records returned by a composed query.
+    pub async fn fetch_all_fragment<R>(
+        &self,
+        fragment: &Fragment<'_>,
+    ) -> Result<Vec<R>, QueryError>
+    where
+        R: FromRow + Send + Sync + 'static,
+    {
+        self.call(crud_ops::fetch_all_fragment(&self.client, fragment)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct One;
+
+    impl FromRow for One {
+        fn from_row(_row: &Row) -> Result<Self, Error> {
+            Ok(One)
+        }
+    }
+
+    async fn connect() -> Client {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let (client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+        client
+    }
+
+    /// Waits until no session runs `sql`, returning how many still do after a second.
+    async fn running(client: &Client, sql: &str) -> i64 {
+        let mut count = 1;
+        for _ in 0..50 {
+            count = client
+                .query_one(
+                    "SELECT count(*) FROM pg_stat_activity WHERE query = $1 AND state = 'active'",
+                    &[&sql],
+                )
+                .await
+                .unwrap()
+                .get(0);
+            if count == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        count
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_with_timeout_cancels_before_returning() {
+        let client = connect().await;
+        let sql = "SELECT pg_sleep(30), 'with_timeout'";
+        let limit = Duration::from_millis(100);
+
+        let result = with_timeout(&client, limit, client.batch_execute(sql)).await;
+        assert!(matches!(result, Err(QueryError::Timeout(after)) if after == limit));
+
+        // The cancel request was delivered, so it cannot stop the next statement
+        for _ in 0..20 {
+            client.batch_execute("SELECT 1").await.unwrap();
+        }
+        assert_eq!(running(&client, sql).await, 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_with_timeout_passes_results_through() {
+        let client = connect().await;
+        let limit = Duration::from_secs(5);
+
+        let row = with_timeout(&client, limit, client.query_one("SELECT 7", &[])).await;
+        assert_eq!(row.unwrap().get::<_, i32>(0), 7);
+
+        let result = with_timeout(&client, limit, client.batch_execute("SELEC 1")).await;
+        assert!(matches!(result, Err(QueryError::Query(_))));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_timed_limits_every_call() {
+        let db = Timed::new(connect().await, Duration::from_millis(100));
+        let sql = "SELECT pg_sleep(30), 'timed'";
+
+        let result = db.fetch_all_fragment::<One>(&Fragment::raw(sql)).await;
+        assert!(matches!(result, Err(QueryError::Timeout(_))));
+
+        let ones = db.fetch_all_fragment::<One>(&Fragment::raw("SELECT 1")).await;
+        assert_eq!(ones.unwrap().len(), 1);
+        assert_eq!(running(db.client().await, sql).await, 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_timed_waits_for_the_cancel_of_a_dropped_call() {
+        let db = Timed::new(connect().await, Duration::from_secs(60));
+        let sql = "SELECT pg_sleep(30), 'dropped'";
+
+        let fragment = Fragment::raw(sql);
+        let dropped = db.fetch_all_fragment::<One>(&fragment);
+        assert!(tokio::time::timeout(Duration::from_millis(100), dropped)
+            .await
+            .is_err());
+
+        // The next calls run after the cancel request and are not stopped by it
+        for _ in 0..20 {
+            let ones = db.fetch_all_fragment::<One>(&Fragment::raw("SELECT 1")).await;
+            assert_eq!(ones.unwrap().len(), 1);
+        }
+        assert_eq!(running(db.client().await, sql).await, 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server in DATABASE_URL"]
+    async fn test_timed_calls_can_be_spawned() {
+        let db = std::sync::Arc::new(Timed::new(connect().await, Duration::from_secs(5)));
+
+        let task = tokio::spawn({
+            let db = db.clone();
+            async move {
+                db.fetch_all_fragment::<One>(&Fragment::raw("SELECT 1"))
+                    .await
+                    .map(|ones| ones.len())
+            }
+        });
+        assert_eq!(task.await.unwrap().unwrap(), 1);
+    }
+}
@@ -2,12 +2,32 @@ use postgres::{
     types::{FromSql, ToSql},
     Error, Row,
 };
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::compose::Fragment;
+use crate::error::{check_version, UpdateError, WhereError};
+use crate::expr::Expr;
+
+pub use parsql_core::traits::{Columns, TenantScoped};
 
 /// Trait for generating SQL queries (for SELECT operations).
 /// This trait is implemented by the derive macro `Queryable`.
 pub trait SqlQuery<R> {
     /// Returns the SQL query string.
     fn query() -> String;
+
+    /// Returns the query and its parameters as a composable fragment,
+    /// to be used as a CTE, a subquery or a member of a `UNION ALL`.
+    ///
+    /// The SQL is scanned for placeholders on every call, so build the fragment once
+    /// instead of inside a loop.
+    fn fragment(&self) -> Fragment<'_>
+    where
+        Self: SqlParams + Sized,
+    {
+        Fragment::new(<Self as SqlQuery<R>>::query(), self.params())
+    }
 }
 
 /// Trait for generating SQL commands (for INSERT/UPDATE/DELETE operations).
@@ -31,6 +51,51 @@ pub trait UpdateParams {
     fn params(&self) -> Vec<&(dyn ToSql + Sync)>;
 }
 
+/// Trait for update models with optimistic locking.
+/// This trait is implemented by the derive macro `Updateable` when `#[version_column("...")]`
+/// is present; only such models can be passed to `update_versioned`.
+pub trait Versioned: SqlCommand {
+    /// Returns the column incremented by every update.
+    fn version_column() -> &'static str;
+}
+
+/// Trait for commands of soft-deleted tables.
+/// This trait is implemented by the derive macro `Deletable` when `#[soft_delete]` is present.
+pub trait SoftDelete {
+    /// Returns the SQL command that clears the deletion marker.
+    fn restore_query() -> String;
+
+    /// Returns the SQL command that removes the record permanently.
+    fn force_delete_query() -> String;
+}
+
+/// Trait for queries that skip soft-deleted records.
+/// This trait is implemented by the derive macro `Queryable` when `#[soft_delete]` is present.
+pub trait SoftDeleteQuery {
+    /// Returns the SQL query without the soft-delete filter.
+    fn query_with_deleted() -> String;
+}
+
+/// Runs a soft-delete aware query including the soft-deleted records,
+/// e.g. `fetch_all(&client, WithDeleted(query))`.
+#[derive(Debug, Clone)]
+pub struct WithDeleted<T>(pub T);
+
+impl<T, R> SqlQuery<R> for WithDeleted<T>
+where
+    T: SqlQuery<R> + SoftDeleteQuery,
+{
+    fn query() -> String {
+        T::query_with_deleted()
+    }
+}
+
+impl<T: SqlParams> SqlParams for WithDeleted<T> {
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0.params()
+    }
+}
+
 /// Trait for converting database rows to Rust structs.
 /// This trait is implemented by the derive macro `FromRow`.
 pub trait FromRow {
@@ -46,6 +111,54 @@ pub trait FromRow {
         Self: Sized;
 }
 
+/// Trait for batch loading records related to a model.
+/// This trait is implemented by the derive macro `FromRow` for every
+/// `#[has_many(...)]` and `#[belongs_to(...)]` declaration on the model.
+pub trait Relation<C> {
+    /// Type of the value that links the model to its related records.
+    type Key: ToSql + Send + Sync + Eq + Hash + Clone;
+
+    /// Returns the SQL query that loads the related records with `= ANY($1)`.
+    fn relation_query() -> String;
+
+    /// Returns the key of this record used to look up its related records;
+    /// `None` (a `NULL` foreign key) has no related records.
+    fn relation_key(&self) -> Option<Self::Key>;
+
+    /// Returns the key of a related record used to match it with its owner;
+    /// `None` matches no owner.
+    fn related_key(related: &C) -> Option<Self::Key>;
+
+    /// Stores the loaded related records on this record.
+    fn attach(&mut self, related: Vec<C>);
+
+    /// Distributes the loaded related records over the records they belong to.
+    ///
+    /// # Arguments
+    /// * `records` - The records the relation was loaded for
+    /// * `related` - All related records returned by the relation query
+    fn stitch(records: &mut [Self], related: Vec<C>)
+    where
+        Self: Sized,
+        C: Clone,
+    {
+        let mut grouped: HashMap<Self::Key, Vec<C>> = HashMap::new();
+        for item in related {
+            if let Some(key) = Self::related_key(&item) {
+                grouped.entry(key).or_default().push(item);
+            }
+        }
+
+        for record in records {
+            let related = record
+                .relation_key()
+                .and_then(|key| grouped.get(&key).cloned())
+                .unwrap_or_default();
+            record.attach(related);
+        }
+    }
+}
+
 /// A trait for extending PostgreSQL client with CRUD operations.
 ///
 /// This trait provides extension methods for tokio_postgres::Client to perform
@@ -130,6 +243,22 @@ pub trait CrudOps {
     where
         T: SqlCommand + UpdateParams + Send + Sync + 'static;
 
+    /// Updates a record of a model with `#[version_column("...")]`.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing the update information (must implement Versioned and UpdateParams traits)
+    ///
+    /// # Return Value
+    /// * `Result<(), UpdateError>` - On failure, returns `UpdateError::StaleObject` when no row matched
+    ///   the expected version, or the database error
+    async fn update_versioned<T>(&self, entity: T) -> Result<(), UpdateError>
+    where
+        T: Versioned + UpdateParams + Send + Sync + 'static,
+    {
+        let updated = self.update(entity).await?;
+        check_version::<T>(updated)
+    }
+
     /// Deletes a record from the database.
     ///
     /// # Arguments
@@ -164,6 +293,72 @@ pub trait CrudOps {
     where
         T: SqlCommand + SqlParams + Send + Sync + 'static;
 
+    /// Restores soft-deleted records by clearing their deletion marker.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Return Value
+    /// * `Result<u64, Error>` - On success, returns the number of restored records; on failure, returns Error
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use tokio_postgres::NoTls;
+    /// use parsql_tokio_postgres::traits::*;
+    /// use parsql_tokio_postgres::*;
+    ///
+    /// #[derive(Deletable, SqlParams)]
+    /// #[table("users")]
+    /// #[where_clause("id = $")]
+    /// #[soft_delete(column = "deleted_at")]
+    /// struct DeleteUser {
+    ///     id: i64,
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let (client, connection) = tokio_postgres::connect("", NoTls).await?;
+    /// # tokio::spawn(async move { connection.await; });
+    /// let count = client.restore(DeleteUser { id: 1 }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn restore<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static;
+
+    /// Permanently deletes records of a table that uses soft delete.
+    ///
+    /// # Arguments
+    /// * `entity` - Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+    ///
+    /// # Return Value
+    /// * `Result<u64, Error>` - On success, returns the number of deleted records; on failure, returns Error
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use tokio_postgres::NoTls;
+    /// use parsql_tokio_postgres::traits::*;
+    /// use parsql_tokio_postgres::*;
+    ///
+    /// #[derive(Deletable, SqlParams)]
+    /// #[table("users")]
+    /// #[where_clause("id = $")]
+    /// #[soft_delete(column = "deleted_at")]
+    /// struct DeleteUser {
+    ///     id: i64,
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let (client, connection) = tokio_postgres::connect("", NoTls).await?;
+    /// # tokio::spawn(async move { connection.await; });
+    /// let count = client.force_delete(DeleteUser { id: 1 }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static;
+
     /// Retrieves a single record from the database and converts it to a struct.
     ///
     /// # Arguments
@@ -352,6 +547,118 @@ pub trait CrudOps {
         F: Fn(&Row) -> R + Send + Sync + 'static,
         R: Send + 'static;
 
+    /// Loads the records related to the given records with a single query and attaches them.
+    ///
+    /// # Arguments
+    /// * `records` - Records previously returned by `fetch_all` (must implement Relation<C>)
+    ///
+    /// # Return Value
+    /// * `Result<(), Error>` - On success, the relation field of every record is filled; on failure, returns Error
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use parsql_tokio_postgres::traits::*;
+    /// # use parsql_tokio_postgres::*;
+    /// # #[derive(Queryable, SqlParams)]
+    /// # #[table("orders")]
+    /// # #[select("id")]
+    /// # #[where_clause("customer_id = $")]
+    /// # #[result_type("Order")]
+    /// # struct OrdersByCustomer { customer_id: i64 }
+    /// # #[derive(FromRow)]
+    /// # #[has_many(OrderItem, foreign_key = "order_id", field = "items")]
+    /// # struct Order { id: i64, items: Vec<OrderItem> }
+    /// # #[derive(FromRow, Clone)]
+    /// # struct OrderItem { order_id: i64 }
+    /// # async fn example(client: &Client) -> Result<(), Error> {
+    /// let mut orders: Vec<Order> = client.fetch_all(OrdersByCustomer { customer_id: 1 }).await?;
+    /// client.preload::<Order, OrderItem>(&mut orders).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C> + Send + Sync + 'static,
+        C: FromRow + Clone + Send + Sync + 'static;
+
+    /// Retrieves the records of `R`'s table that match a typed WHERE expression.
+    ///
+    /// # Arguments
+    /// * `expr` - The condition, built from the column constants generated by `Columns`
+    ///
+    /// # Return Value
+    /// * `Result<Vec<R>, WhereError>` - A vector of matching records or an error
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use parsql_tokio_postgres::traits::*;
+    /// # use parsql_tokio_postgres::*;
+    /// # #[derive(Columns, FromRow)]
+    /// # #[table("users")]
+    /// # struct User { id: i64, email: String, state: i16 }
+    /// # async fn example(client: &Client) -> Result<(), WhereError> {
+    /// let expr = users::state.eq(1).and(users::email.ilike("%@example.com"));
+    /// let users: Vec<User> = client.fetch_all_where::<User>(expr).await?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow + Send + Sync + 'static;
+
+    /// Retrieves all records returned by a composed query.
+    ///
+    /// # Arguments
+    /// * `fragment` - The query, built with `with_cte`, `union_all` or `in_subquery`
+    ///
+    /// # Return Value
+    /// * `Result<Vec<R>, Error>` - A vector of records or an error
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use tokio_postgres::NoTls;
+    /// use parsql_tokio_postgres::traits::*;
+    /// use parsql_tokio_postgres::*;
+    ///
+    /// #[derive(Queryable, SqlParams)]
+    /// #[table("users")]
+    /// #[select("id, name")]
+    /// #[where_clause("state = $")]
+    /// #[result_type("UserSummary")]
+    /// struct ActiveUsers {
+    ///     state: i32,
+    /// }
+    ///
+    /// #[derive(Queryable, SqlParams)]
+    /// #[table("admins")]
+    /// #[select("id, name")]
+    /// #[where_clause("level >= $")]
+    /// #[result_type("UserSummary")]
+    /// struct Admins {
+    ///     level: i32,
+    /// }
+    ///
+    /// #[derive(FromRow)]
+    /// struct UserSummary {
+    ///     id: i64,
+    ///     name: String,
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let (client, connection) = tokio_postgres::connect("", NoTls).await?;
+    /// # tokio::spawn(async move { connection.await; });
+    /// let active = ActiveUsers { state: 1 };
+    /// let admins = Admins { level: 3 };
+    /// let query = active.fragment().union_all(admins.fragment());
+    /// let users: Vec<UserSummary> = client.fetch_all_fragment(&query).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, Error>
+    where
+        R: FromRow + Send + Sync + 'static;
+
     #[deprecated(
         since = "0.2.0",
         note = "Renamed to `fetch`. Please use `fetch` function instead."
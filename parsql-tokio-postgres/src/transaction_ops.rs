@@ -1,6 +1,12 @@
-use crate::traits::{CrudOps, FromRow, SqlCommand, SqlParams, SqlQuery, UpdateParams};
+use crate::compose::Fragment;
+use crate::error::WhereError;
+use crate::expr::Expr;
+use crate::observer::{Observation, QueryKind};
+use crate::traits::{
+    Columns, CrudOps, FromRow, Relation, SoftDelete, SqlCommand, SqlParams, SqlQuery, UpdateParams,
+};
 use postgres::types::FromSql;
-use std::sync::OnceLock;
+use std::collections::HashSet;
 use tokio_postgres::{Client, Error, Row, Transaction};
 
 /// Creates and begins a new transaction.
@@ -79,16 +85,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = transaction.execute(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+    let result = observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)?;
     Ok((transaction, result))
 }
 
@@ -137,21 +136,15 @@ pub async fn tx_update<T>(
     entity: T,
 ) -> Result<(Transaction<'_>, bool), Error>
 where
-    T: SqlQuery<T> + UpdateParams + Send + Sync + 'static,
+    T: SqlCommand + UpdateParams + Send + Sync + 'static,
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = transaction.execute(&sql, &params).await?;
-    Ok((transaction, result > 0))
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    let result = observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)?;
+    let updated = result > 0;
+    Ok((transaction, updated))
 }
 
 /// Deletes a record within a transaction.
@@ -196,16 +189,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = transaction.execute(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    let result = observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)?;
     Ok((transaction, result))
 }
 
@@ -257,16 +243,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let row = transaction.query_one(&sql, &query_params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &query_params);
+    let row = observation.finish(transaction.query_one(&sql, &query_params).await, |_| 1)?;
     let result = T::from_row(&row)?;
     Ok((transaction, result))
 }
@@ -321,16 +300,12 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES-TX] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let rows = transaction.query(&sql, &query_params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &query_params);
+    let rows = observation.finish(
+        transaction.query(&sql, &query_params).await,
+        |rows| rows.len() as u64,
+    )?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -451,6 +426,20 @@ impl<'a> CrudOps for Transaction<'a> {
         delete(self, entity).await
     }
 
+    async fn restore<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        restore(self, entity).await
+    }
+
+    async fn force_delete<T>(&self, entity: T) -> Result<u64, Error>
+    where
+        T: SoftDelete + SqlParams + Send + Sync + 'static,
+    {
+        force_delete(self, entity).await
+    }
+
     async fn fetch<P, R>(&self, params: P) -> Result<R, Error>
     where
         P: SqlQuery<R> + SqlParams + Send + Sync + 'static,
@@ -484,6 +473,28 @@ impl<'a> CrudOps for Transaction<'a> {
     {
         select_all(self, entity, to_model).await
     }
+
+    async fn preload<R, C>(&self, records: &mut [R]) -> Result<(), Error>
+    where
+        R: Relation<C> + Send + Sync + 'static,
+        C: FromRow + Clone + Send + Sync + 'static,
+    {
+        preload(self, records).await
+    }
+
+    async fn fetch_all_where<R>(&self, expr: Expr) -> Result<Vec<R>, WhereError>
+    where
+        R: Columns + FromRow + Send + Sync + 'static,
+    {
+        fetch_all_where(self, expr).await
+    }
+
+    async fn fetch_all_fragment<R>(&self, fragment: &Fragment<'_>) -> Result<Vec<R>, Error>
+    where
+        R: FromRow + Send + Sync + 'static,
+    {
+        fetch_all_fragment(self, fragment).await
+    }
 }
 
 /// # insert
@@ -505,16 +516,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = transaction.query_one(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Insert, &sql, &params);
+    let row = observation.finish(transaction.query_one(&sql, &params).await, |_| 1)?;
     row.try_get::<_, P>(0)
 }
 
@@ -534,16 +538,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let result = transaction.execute(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    let result = observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)?;
     Ok(result > 0)
 }
 
@@ -563,16 +560,51 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)
+}
 
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
+/// # restore
+///
+/// Restores soft-deleted records by clearing their deletion marker within a transaction.
+///
+/// ## Parameters
+/// - `transaction`: Transaction object
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<u64, Error>`: On success, returns the number of restored records; on failure, returns Error
+pub async fn restore<T>(transaction: &Transaction<'_>, entity: T) -> Result<u64, Error>
+where
+    T: SoftDelete + SqlParams + Send + Sync + 'static,
+{
+    let sql = T::restore_query();
+
+    let params = entity.params();
+    let observation = Observation::start::<T>(QueryKind::Update, &sql, &params);
+    observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)
+}
+
+/// # force_delete
+///
+/// Permanently deletes records of a table that uses soft delete within a transaction.
+///
+/// ## Parameters
+/// - `transaction`: Transaction object
+/// - `entity`: Data object containing delete conditions (must implement SoftDelete and SqlParams traits)
+///
+/// ## Return Value
+/// - `Result<u64, Error>`: On success, returns the number of deleted records; on failure, returns Error
+pub async fn force_delete<T>(transaction: &Transaction<'_>, entity: T) -> Result<u64, Error>
+where
+    T: SoftDelete + SqlParams + Send + Sync + 'static,
+{
+    let sql = T::force_delete_query();
 
     let params = entity.params();
-    transaction.execute(&sql, &params).await
+    let observation = Observation::start::<T>(QueryKind::Delete, &sql, &params);
+    observation.finish(transaction.execute(&sql, &params).await, |rows| *rows)
 }
 
 /// # fetch
@@ -592,16 +624,9 @@ where
 {
     let sql = P::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let row = transaction.query_one(&sql, &query_params).await?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let row = observation.finish(transaction.query_one(&sql, &query_params).await, |_| 1)?;
     R::from_row(&row)
 }
 
@@ -622,16 +647,12 @@ where
 {
     let sql = P::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let query_params = params.params();
-    let rows = transaction.query(&sql, &query_params).await?;
+    let observation = Observation::start::<P>(QueryKind::Select, &sql, &query_params);
+    let rows = observation.finish(
+        transaction.query(&sql, &query_params).await,
+        |rows| rows.len() as u64,
+    )?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -664,16 +685,9 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let row = transaction.query_one(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let row = observation.finish(transaction.query_one(&sql, &params).await, |_| 1)?;
     to_model(&row)
 }
 
@@ -700,16 +714,12 @@ where
 {
     let sql = T::query();
 
-    static TRACE_ENABLED: OnceLock<bool> = OnceLock::new();
-    let is_trace_enabled =
-        *TRACE_ENABLED.get_or_init(|| std::env::var("PARSQL_TRACE").unwrap_or_default() == "1");
-
-    if is_trace_enabled {
-        println!("[PARSQL-TOKIO-POSTGRES] Execute SQL: {}", sql);
-    }
-
     let params = entity.params();
-    let rows = transaction.query(&sql, &params).await?;
+    let observation = Observation::start::<T>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(
+        transaction.query(&sql, &params).await,
+        |rows| rows.len() as u64,
+    )?;
 
     let mut results = Vec::with_capacity(rows.len());
     for row in rows {
@@ -718,3 +728,96 @@ where
 
     Ok(results)
 }
+
+/// Loads the records related to a list of records with a single `= ANY($1)` query
+/// and attaches them, avoiding one query per record (N+1).
+///
+/// # Arguments
+/// * `transaction` - An active transaction
+/// * `records` - Records previously returned by `fetch_all` (must implement Relation<C>)
+///
+/// # Return Value
+/// * `Result<(), Error>` - On success, the relation field of every record is filled; on failure, returns Error
+pub async fn preload<R, C>(transaction: &Transaction<'_>, records: &mut [R]) -> Result<(), Error>
+where
+    R: Relation<C> + Send + Sync + 'static,
+    C: FromRow + Clone + Send + Sync + 'static,
+{
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    // Each distinct key is sent only once; records without a key have no related rows
+    let mut seen = HashSet::new();
+    let keys: Vec<R::Key> = records
+        .iter()
+        .filter_map(|record| record.relation_key())
+        .filter(|key| seen.insert(key.clone()))
+        .collect();
+
+    let sql = R::relation_query();
+
+    let observation = Observation::start::<C>(QueryKind::Select, &sql, &[&keys]);
+    let rows = observation.finish(
+        transaction.query(&sql, &[&keys]).await,
+        |rows| rows.len() as u64,
+    )?;
+
+    let mut related = Vec::with_capacity(rows.len());
+    for row in rows {
+        related.push(C::from_row(&row)?);
+    }
+
+    R::stitch(records, related);
+    Ok(())
+}
+
+/// Retrieves the records matching a typed WHERE expression within a transaction.
+///
+/// # Arguments
+/// * `transaction` - An active transaction
+/// * `expr` - The condition, built from the column constants generated by `Columns`
+///
+/// # Return Value
+/// * `Result<Vec<R>, WhereError>` - On success, returns the matching records; on failure, returns WhereError
+pub async fn fetch_all_where<R>(
+    transaction: &Transaction<'_>,
+    expr: Expr,
+) -> Result<Vec<R>, WhereError>
+where
+    R: Columns + FromRow + Send + Sync + 'static,
+{
+    let (sql, params) = expr.select::<R>()?;
+
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(
+        transaction.query(&sql, &params).await,
+        |rows| rows.len() as u64,
+    )?;
+    Ok(rows.iter().map(R::from_row).collect::<Result<_, _>>()?)
+}
+
+/// Retrieves all records returned by a composed query within a transaction.
+///
+/// # Arguments
+/// * `transaction` - An active transaction
+/// * `fragment` - The query, built with `with_cte`, `union_all` or `in_subquery`
+///
+/// # Return Value
+/// * `Result<Vec<R>, Error>` - On success, returns the records; on failure, returns Error
+pub async fn fetch_all_fragment<R>(
+    transaction: &Transaction<'_>,
+    fragment: &Fragment<'_>,
+) -> Result<Vec<R>, Error>
+where
+    R: FromRow + Send + Sync + 'static,
+{
+    let (sql, params) = fragment.build();
+
+    let observation = Observation::start::<R>(QueryKind::Select, &sql, &params);
+    let rows = observation.finish(
+        transaction.query(&sql, &params).await,
+        |rows| rows.len() as u64,
+    )?;
+    rows.iter().map(R::from_row).collect()
+}
@@ -10,5 +10,16 @@ pub use parsql_tokio_postgres as tokio_postgres;
 #[cfg(feature = "deadpool-postgres")]
 pub use parsql_deadpool_postgres as deadpool_postgres;
 
+// Raw SQL queries with captured parameters: `parsql::query!("... {id} ...", R)`
+pub use parsql_macros::query;
+
+// Postgres integration tests rolled back after each test: `#[parsql::test]`
+#[cfg(any(feature = "postgres", feature = "tokio-postgres"))]
+pub use parsql_macros::test;
+
+// Test fixtures for integration tests
+#[cfg(feature = "testing")]
+pub mod testing;
+
 // Prelude module for convenient imports
 pub mod prelude;
@@ -20,16 +20,16 @@
 // This ensures that when multiple features are enabled, we use the most advanced one
 
 #[cfg(feature = "deadpool-postgres")]
-pub use parsql_deadpool_postgres::macros::{FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
+pub use parsql_deadpool_postgres::macros::{query, Columns, FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
 
 #[cfg(all(feature = "tokio-postgres", not(feature = "deadpool-postgres")))]
-pub use parsql_tokio_postgres::macros::{FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
+pub use parsql_tokio_postgres::macros::{query, Columns, FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
 
 #[cfg(all(feature = "postgres", not(feature = "tokio-postgres"), not(feature = "deadpool-postgres")))]
-pub use parsql_postgres::macros::{FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
+pub use parsql_postgres::macros::{query, Columns, FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
 
 #[cfg(all(feature = "sqlite", not(feature = "postgres"), not(feature = "tokio-postgres"), not(feature = "deadpool-postgres")))]
-pub use parsql_sqlite::macros::{FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
+pub use parsql_sqlite::macros::{query, Columns, FromRow, Queryable, SqlParams, Insertable, Updateable, Deletable, UpdateParams};
 
 // Re-export traits with both names - original for macros, and with Trait suffix for clarity
 #[cfg(feature = "deadpool-postgres")]
@@ -39,7 +39,14 @@ pub use parsql_deadpool_postgres::traits::{
     SqlParams, SqlParams as SqlParamsTrait, 
     SqlQuery, 
     SqlCommand, 
-    UpdateParams, UpdateParams as UpdateParamsTrait
+    UpdateParams, UpdateParams as UpdateParamsTrait,
+    Versioned,
+    Relation,
+    Columns,
+    SoftDelete,
+    SoftDeleteQuery,
+    WithDeleted,
+    TenantScoped
 };
 
 #[cfg(all(feature = "tokio-postgres", not(feature = "deadpool-postgres")))]
@@ -49,7 +56,14 @@ pub use parsql_tokio_postgres::traits::{
     SqlParams, SqlParams as SqlParamsTrait, 
     SqlQuery, 
     SqlCommand, 
-    UpdateParams, UpdateParams as UpdateParamsTrait
+    UpdateParams, UpdateParams as UpdateParamsTrait,
+    Versioned,
+    Relation,
+    Columns,
+    SoftDelete,
+    SoftDeleteQuery,
+    WithDeleted,
+    TenantScoped
 };
 
 #[cfg(all(feature = "postgres", not(feature = "tokio-postgres"), not(feature = "deadpool-postgres")))]
@@ -59,7 +73,14 @@ pub use parsql_postgres::traits::{
     SqlParams, SqlParams as SqlParamsTrait, 
     SqlQuery, 
     SqlCommand, 
-    UpdateParams, UpdateParams as UpdateParamsTrait
+    UpdateParams, UpdateParams as UpdateParamsTrait,
+    Versioned,
+    Relation,
+    Columns,
+    SoftDelete,
+    SoftDeleteQuery,
+    WithDeleted,
+    TenantScoped
 };
 
 #[cfg(all(feature = "sqlite", not(feature = "postgres"), not(feature = "tokio-postgres"), not(feature = "deadpool-postgres")))]
@@ -69,21 +90,28 @@ pub use parsql_sqlite::traits::{
     SqlParams, SqlParams as SqlParamsTrait, 
     SqlQuery, 
     SqlCommand, 
-    UpdateParams, UpdateParams as UpdateParamsTrait
+    UpdateParams, UpdateParams as UpdateParamsTrait,
+    Versioned,
+    Relation,
+    Columns,
+    SoftDelete,
+    SoftDeleteQuery,
+    WithDeleted,
+    TenantScoped
 };
 
 // Re-export CRUD functions
 #[cfg(feature = "deadpool-postgres")]
-pub use parsql_deadpool_postgres::{insert, update, delete, fetch, fetch_all};
+pub use parsql_deadpool_postgres::{insert, update, update_versioned, delete, restore, force_delete, fetch, fetch_all, preload, fetch_all_where, fetch_all_fragment, Column, Expr, Fragment, Factory, FactoryExt, UpdateError, Scoped, TenantContext, set_observer, QueryEvent, QueryObserver, QueryStats};
 
 #[cfg(all(feature = "tokio-postgres", not(feature = "deadpool-postgres")))]
-pub use parsql_tokio_postgres::{insert, update, delete, fetch, fetch_all};
+pub use parsql_tokio_postgres::{insert, update, update_versioned, delete, restore, force_delete, fetch, fetch_all, preload, fetch_all_where, fetch_all_fragment, Column, Expr, Fragment, Factory, FactoryExt, UpdateError, Scoped, TenantContext, set_observer, QueryEvent, QueryObserver, QueryStats};
 
 #[cfg(all(feature = "postgres", not(feature = "tokio-postgres"), not(feature = "deadpool-postgres")))]
-pub use parsql_postgres::{insert, update, delete, fetch, fetch_all};
+pub use parsql_postgres::{insert, update, update_versioned, delete, restore, force_delete, fetch, fetch_all, preload, fetch_all_where, fetch_all_fragment, Column, Expr, Fragment, Factory, FactoryExt, UpdateError, Scoped, TenantContext, set_observer, QueryEvent, QueryObserver, QueryStats};
 
 #[cfg(all(feature = "sqlite", not(feature = "postgres"), not(feature = "tokio-postgres"), not(feature = "deadpool-postgres")))]
-pub use parsql_sqlite::{insert, update, delete, fetch, fetch_all};
+pub use parsql_sqlite::{insert, update, update_versioned, delete, restore, force_delete, fetch, fetch_all, preload, fetch_all_where, fetch_all_fragment, Column, Expr, Fragment, Factory, FactoryExt, UpdateError, Scoped, TenantContext, set_observer, QueryEvent, QueryObserver, QueryStats};
 
 // Re-export database types - always include all enabled ones with different names
 #[cfg(feature = "sqlite")]
@@ -0,0 +1,138 @@
+//! Test fixtures for integration tests (`testing` feature).
+//!
+//! [`TestDatabase`] gives every test its own SQLite database, in memory or in a temporary
+//! file, with migrations applied and seed data loaded:
+//!
+//! ```rust
+//! # fn main() -> Result<(), parsql::testing::FixtureError> {
+//! use parsql::testing::TestDatabase;
+//!
+//! let db = TestDatabase::builder()
+//!     .sql("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+//!     .seed_json(r#"{ "users": [{ "id": 1, "name": "Ada" }, { "id": 2, "name": "Alan" }] }"#)
+//!     .open()?;
+//!
+//! let count: i64 = db.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+//! assert_eq!(count, 2);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+mod sqlite;
+
+pub use sqlite::{TestDatabase, TestDatabaseBuilder};
+
+/// Error returned while setting up a test fixture.
+#[derive(Debug)]
+pub enum FixtureError {
+    /// The database returned an error
+    Sqlite(rusqlite::Error),
+    /// A migration failed
+    Migration(parsql_migrations::MigrationError),
+    /// A migration or fixture file could not be read
+    Io {
+        /// The file being read
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// A JSON fixture is not valid JSON
+    Json(serde_json::Error),
+    /// A JSON fixture does not have the `{ "table": [{ "column": value }] }` shape
+    InvalidFixture(String),
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixtureError::Sqlite(err) => write!(f, "Database error: {}", err),
+            FixtureError::Migration(err) => write!(f, "Migration error: {}", err),
+            FixtureError::Io { path, source } => {
+                write!(f, "Failed to read {}: {}", path.display(), source)
+            }
+            FixtureError::Json(err) => write!(f, "Invalid JSON fixture: {}", err),
+            FixtureError::InvalidFixture(message) => write!(f, "Invalid fixture: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for FixtureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FixtureError::Sqlite(err) => Some(err),
+            FixtureError::Migration(err) => Some(err),
+            FixtureError::Io { source, .. } => Some(source),
+            FixtureError::Json(err) => Some(err),
+            FixtureError::InvalidFixture(_) => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for FixtureError {
+    fn from(err: rusqlite::Error) -> Self {
+        FixtureError::Sqlite(err)
+    }
+}
+
+impl From<parsql_migrations::MigrationError> for FixtureError {
+    fn from(err: parsql_migrations::MigrationError) -> Self {
+        FixtureError::Migration(err)
+    }
+}
+
+impl From<serde_json::Error> for FixtureError {
+    fn from(err: serde_json::Error) -> Self {
+        FixtureError::Json(err)
+    }
+}
+
+/// Reads a migration or fixture file.
+pub(crate) fn read_file(path: &std::path::Path) -> Result<String, FixtureError> {
+    std::fs::read_to_string(path).map_err(|source| FixtureError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Returns the `*.up.sql` files of a migrations directory, ordered by the version before the
+/// first `_` of their name.
+pub(crate) fn migration_files(directory: &std::path::Path) -> Result<Vec<PathBuf>, FixtureError> {
+    let entries = std::fs::read_dir(directory).map_err(|source| FixtureError::Io {
+        path: directory.to_path_buf(),
+        source,
+    })?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|source| FixtureError::Io {
+                path: directory.to_path_buf(),
+                source,
+            })?
+            .path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if let Some(base) = name.strip_suffix(".up.sql") {
+            let version = base
+                .split('_')
+                .next()
+                .and_then(|version| version.parse::<i64>().ok())
+                .ok_or_else(|| {
+                    FixtureError::InvalidFixture(format!(
+                        "migration {} does not start with a version number",
+                        name
+                    ))
+                })?;
+            files.push((version, path));
+        }
+    }
+
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
@@ -0,0 +1,361 @@
+//! The SQLite test database fixture.
+
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, process};
+
+use parsql_migrations::sqlite_simple::SqliteMigrationConnection;
+use parsql_migrations::MigrationRunner;
+use parsql_sqlite::ConnectionBuilder;
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use serde_json::Value as Json;
+
+use super::{migration_files, read_file, FixtureError};
+
+/// Distinguishes the temporary files of databases opened by the same process.
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// A setup step, run in the order it was added.
+enum Step {
+    Runner(Box<MigrationRunner>),
+    Directory(PathBuf),
+    Sql(String),
+    SqlFile(PathBuf),
+    Json(String),
+    JsonFile(PathBuf),
+}
+
+/// Builds a [`TestDatabase`]; see [`TestDatabase::builder`].
+#[derive(Default)]
+pub struct TestDatabaseBuilder {
+    temp_file: bool,
+    connection: ConnectionBuilder,
+    steps: Vec<Step>,
+}
+
+impl TestDatabaseBuilder {
+    /// Stores the database in a temporary file, removed when the [`TestDatabase`] is dropped,
+    /// instead of in memory. Needed to open more connections, e.g. a `SqlitePool`, on it.
+    pub fn temp_file(mut self) -> Self {
+        self.temp_file = true;
+        self
+    }
+
+    /// Opens the database with the functions and collations of `connection`.
+    pub fn connection(mut self, connection: ConnectionBuilder) -> Self {
+        self.connection = connection;
+        self
+    }
+
+    /// Runs the migrations of `runner`, recording them in its migrations table.
+    pub fn migrations(mut self, runner: MigrationRunner) -> Self {
+        self.steps.push(Step::Runner(Box::new(runner)));
+        self
+    }
+
+    /// Runs the `*.up.sql` files of a migrations directory in version order.
+    pub fn migrations_dir<P: AsRef<Path>>(mut self, directory: P) -> Self {
+        self.steps
+            .push(Step::Directory(directory.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Runs a batch of SQL statements.
+    pub fn sql(mut self, sql: &str) -> Self {
+        self.steps.push(Step::Sql(sql.to_string()));
+        self
+    }
+
+    /// Runs the SQL statements of a fixture file.
+    pub fn seed_sql_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.steps.push(Step::SqlFile(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Inserts the rows of a JSON fixture of the form
+    /// `{ "table": [{ "column": value, ... }, ...], ... }`.
+    ///
+    /// All rows are inserted in one transaction with foreign key checks deferred to its end,
+    /// so tables can be listed in any order. Arrays and objects are stored as JSON text.
+    pub fn seed_json(mut self, json: &str) -> Self {
+        self.steps.push(Step::Json(json.to_string()));
+        self
+    }
+
+    /// Inserts the rows of a JSON fixture file; see [`TestDatabaseBuilder::seed_json`].
+    pub fn seed_json_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.steps.push(Step::JsonFile(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Opens the database and runs the setup steps.
+    pub fn open(self) -> Result<TestDatabase, FixtureError> {
+        let path = self.temp_file.then(temp_path);
+        let conn = match &path {
+            Some(path) => self.connection.open(path)?,
+            None => self.connection.open_in_memory()?,
+        };
+        // A failing step drops the database, which removes its temporary file
+        let mut db = TestDatabase {
+            conn: Some(conn),
+            path,
+        };
+
+        for step in self.steps {
+            match step {
+                Step::Runner(mut runner) => {
+                    let mut migrations = SqliteMigrationConnection::new(&mut db);
+                    runner.run(&mut migrations)?;
+                }
+                Step::Directory(directory) => {
+                    for file in migration_files(&directory)? {
+                        db.execute_batch(&read_file(&file)?)?;
+                    }
+                }
+                Step::Sql(sql) => db.execute_batch(&sql)?,
+                Step::SqlFile(path) => db.execute_batch(&read_file(&path)?)?,
+                Step::Json(json) => seed_json(&mut db, &json)?,
+                Step::JsonFile(path) => seed_json(&mut db, &read_file(&path)?)?,
+            }
+        }
+
+        Ok(db)
+    }
+}
+
+/// A SQLite database owned by a single test.
+///
+/// Dereferences to its [`Connection`], so it is passed directly to the parsql functions and
+/// `CrudOps` methods. A temporary file is removed when it is dropped.
+pub struct TestDatabase {
+    conn: Option<Connection>,
+    path: Option<PathBuf>,
+}
+
+impl TestDatabase {
+    /// Returns a builder for a database set up by migrations and fixtures.
+    pub fn builder() -> TestDatabaseBuilder {
+        TestDatabaseBuilder::default()
+    }
+
+    /// Opens an empty in-memory database.
+    pub fn in_memory() -> Result<Self, FixtureError> {
+        Self::builder().open()
+    }
+
+    /// The temporary file of a database opened with [`TestDatabaseBuilder::temp_file`].
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+impl Deref for TestDatabase {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection is open until drop")
+    }
+}
+
+impl DerefMut for TestDatabase {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection is open until drop")
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        // The connection is closed first, so SQLite does not recreate the WAL files
+        drop(self.conn.take());
+        if let Some(path) = &self.path {
+            let path = path.to_string_lossy();
+            for suffix in ["", "-wal", "-shm", "-journal"] {
+                let _ = fs::remove_file(format!("{}{}", path, suffix));
+            }
+        }
+    }
+}
+
+fn temp_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.subsec_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir().join(format!(
+        "parsql-test-{}-{}-{}.db",
+        process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed),
+        nanos
+    ))
+}
+
+fn seed_json(conn: &mut Connection, json: &str) -> Result<(), FixtureError> {
+    let fixture: Json = serde_json::from_str(json)?;
+    let tables = fixture
+        .as_object()
+        .ok_or_else(|| FixtureError::InvalidFixture("expected an object of tables".to_string()))?;
+
+    let tx = conn.transaction()?;
+    tx.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+    for (table, rows) in tables {
+        let rows = rows.as_array().ok_or_else(|| {
+            FixtureError::InvalidFixture(format!("rows of {} must be an array", table))
+        })?;
+        for row in rows {
+            let row = row.as_object().ok_or_else(|| {
+                FixtureError::InvalidFixture(format!("rows of {} must be objects", table))
+            })?;
+            let columns: Vec<String> = row.keys().map(|column| quote(column)).collect();
+            let placeholders: Vec<String> = (1..=row.len()).map(|n| format!("?{}", n)).collect();
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                quote(table),
+                columns.join(", "),
+                placeholders.join(", ")
+            );
+            let values: Vec<Value> = row.values().map(to_value).collect();
+            tx.execute(&sql, rusqlite::params_from_iter(values))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn to_value(value: &Json) -> Value {
+    match value {
+        Json::Null => Value::Null,
+        Json::Bool(value) => Value::Integer(i64::from(*value)),
+        Json::Number(number) => match number.as_i64() {
+            Some(integer) => Value::Integer(integer),
+            None => Value::Real(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(text) => Value::Text(text.clone()),
+        Json::Array(_) | Json::Object(_) => Value::Text(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    const SCHEMA: &str = "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)";
+
+    fn count(db: &TestDatabase, table: &str) -> i64 {
+        db.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_in_memory_databases_are_isolated() {
+        let first = TestDatabase::builder().sql(SCHEMA).open().unwrap();
+        first.execute("INSERT INTO users (name) VALUES ('Ada')", []).unwrap();
+
+        let second = TestDatabase::builder().sql(SCHEMA).open().unwrap();
+        assert_eq!(count(&second, "users"), 0);
+        assert!(TestDatabase::in_memory()
+            .unwrap()
+            .prepare("SELECT * FROM users")
+            .is_err());
+        assert_eq!(count(&first, "users"), 1);
+    }
+
+    #[test]
+    fn test_parallel_temp_files_are_isolated_and_removed() {
+        let tests: Vec<_> = (0..8)
+            .map(|i| {
+                thread::spawn(move || {
+                    let db = TestDatabase::builder().temp_file().sql(SCHEMA).open().unwrap();
+                    for _ in 0..=i {
+                        db.execute("INSERT INTO users (name) VALUES ('Ada')", []).unwrap();
+                    }
+                    assert_eq!(count(&db, "users"), i + 1);
+
+                    // Other connections see the same file
+                    let other = Connection::open(db.path().unwrap()).unwrap();
+                    let seen: i64 = other
+                        .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+                        .unwrap();
+                    assert_eq!(seen, i + 1);
+                    db.path().unwrap().to_path_buf()
+                })
+            })
+            .collect();
+
+        let mut paths: Vec<PathBuf> = tests.into_iter().map(|test| test.join().unwrap()).collect();
+        assert!(paths.iter().all(|path| !path.exists()));
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), 8);
+    }
+
+    #[test]
+    fn test_json_seed_defers_foreign_keys() {
+        let db = TestDatabase::builder()
+            .sql(
+                "PRAGMA foreign_keys = ON;
+                 CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, tags TEXT);
+                 CREATE TABLE posts (id INTEGER PRIMARY KEY,
+                                     user_id INTEGER NOT NULL REFERENCES users (id));",
+            )
+            .seed_json(
+                r#"{ "posts": [{ "id": 1, "user_id": 1 }],
+                     "users": [{ "id": 1, "name": "Ada", "tags": ["admin"] }] }"#,
+            )
+            .open()
+            .unwrap();
+
+        assert_eq!(count(&db, "posts"), 1);
+        let tags: String = db
+            .query_row("SELECT tags FROM users WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tags, r#"["admin"]"#);
+
+        let dangling = TestDatabase::builder()
+            .sql("PRAGMA foreign_keys = ON")
+            .sql("CREATE TABLE users (id INTEGER PRIMARY KEY)")
+            .sql("CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users)")
+            .seed_json(r#"{ "posts": [{ "id": 1, "user_id": 2 }] }"#)
+            .open();
+        assert!(matches!(dangling, Err(FixtureError::Sqlite(_))));
+    }
+
+    #[test]
+    fn test_invalid_fixtures_are_reported() {
+        let open = |json: &str| TestDatabase::builder().sql(SCHEMA).seed_json(json).open();
+
+        assert!(matches!(open("{"), Err(FixtureError::Json(_))));
+        assert!(matches!(open("[]"), Err(FixtureError::InvalidFixture(_))));
+        assert!(matches!(open(r#"{ "users": {} }"#), Err(FixtureError::InvalidFixture(_))));
+        assert!(matches!(open(r#"{ "users": [1] }"#), Err(FixtureError::InvalidFixture(_))));
+    }
+
+    #[test]
+    fn test_migration_files_run_in_version_order() {
+        let directory = temp_path().with_extension("migrations");
+        fs::create_dir(&directory).unwrap();
+        for (name, sql) in [
+            ("10_add_email.up.sql", "ALTER TABLE users ADD COLUMN email TEXT"),
+            ("10_add_email.down.sql", "ALTER TABLE users DROP COLUMN email"),
+            ("2_users.up.sql", SCHEMA),
+        ] {
+            fs::write(directory.join(name), sql).unwrap();
+        }
+
+        let db = TestDatabase::builder().migrations_dir(&directory).open();
+        fs::remove_dir_all(&directory).unwrap();
+
+        let db = db.unwrap();
+        db.execute("INSERT INTO users (name, email) VALUES ('Ada', 'ada@example.com')", [])
+            .unwrap();
+        assert_eq!(count(&db, "users"), 1);
+    }
+}